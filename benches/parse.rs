@@ -0,0 +1,118 @@
+//! Benchmarks for parsing events, and for converting them into owned events
+//!
+//! The events are parsed from synthetic buffers, in the format the kernel
+//! uses, so the numbers don't depend on how fast the file system is.
+
+use std::mem;
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use inotify::{EventMask, Events, NameInterner};
+
+/// The numbers of events per buffer, that each benchmark is run with
+///
+/// A read rarely returns a single event on a busy system. Comparing these
+/// shows how much a larger buffer helps, by spreading the fixed cost of each
+/// read across more events.
+const EVENTS_PER_BUFFER: [usize; 3] = [1, 16, 256];
+
+fn parse(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parse");
+
+    for n in EVENTS_PER_BUFFER {
+        let buffer = buffer(n, Some("app.log"));
+        group.throughput(Throughput::Elements(n as u64));
+
+        group.bench_with_input(BenchmarkId::new("iterate", n), &buffer, |b, buffer| {
+            b.iter(|| {
+                for event in Events::from_bytes(black_box(buffer)) {
+                    black_box(event);
+                }
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("checked", n), &buffer, |b, buffer| {
+            b.iter(|| {
+                for event in Events::from_bytes(black_box(buffer)).checked() {
+                    black_box(event.unwrap());
+                }
+            })
+        });
+    }
+
+    let buffer = buffer(1, None);
+    group.throughput(Throughput::Elements(1));
+    group.bench_function("peek", |b| {
+        b.iter(|| black_box(Events::from_bytes(black_box(&buffer)).peek()))
+    });
+
+    group.finish();
+}
+
+fn convert(c: &mut Criterion) {
+    let mut group = c.benchmark_group("convert");
+
+    for n in EVENTS_PER_BUFFER {
+        let buffer = buffer(n, Some("app.log"));
+        group.throughput(Throughput::Elements(n as u64));
+
+        group.bench_with_input(BenchmarkId::new("to_owned", n), &buffer, |b, buffer| {
+            b.iter(|| {
+                Events::from_bytes(black_box(buffer))
+                    .map(|event| event.to_owned())
+                    .collect::<Vec<_>>()
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("to_shared", n), &buffer, |b, buffer| {
+            b.iter(|| {
+                Events::from_bytes(black_box(buffer))
+                    .map(|event| event.to_shared())
+                    .collect::<Vec<_>>()
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("interned", n), &buffer, |b, buffer| {
+            let mut interner = NameInterner::new();
+            b.iter(|| {
+                Events::from_bytes(black_box(buffer))
+                    .map(|event| event.to_shared_with(&mut interner))
+                    .collect::<Vec<_>>()
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("buffered", n), &buffer, |b, buffer| {
+            b.iter(|| {
+                Events::from_bytes(black_box(buffer))
+                    .into_buffered()
+                    .collect::<Vec<_>>()
+            })
+        });
+    }
+
+    group.finish();
+}
+
+/// Builds a buffer of `n` events, in the format the kernel uses
+fn buffer(n: usize, name: Option<&str>) -> Vec<u8> {
+    // `wd`, `mask`, `cookie`, and `len` of `inotify_event`
+    let event_size = 4 * mem::size_of::<u32>();
+
+    let name = name.map_or(&[][..], str::as_bytes);
+    let len = if name.is_empty() {
+        0
+    } else {
+        // Leave room for at least one null byte, like the kernel does.
+        (name.len() / event_size + 1) * event_size
+    };
+
+    let mut buffer = Vec::new();
+    for i in 0..n {
+        buffer.extend_from_slice(&(i as i32 % 8 + 1).to_ne_bytes());
+        buffer.extend_from_slice(&EventMask::MODIFY.bits().to_ne_bytes());
+        buffer.extend_from_slice(&0u32.to_ne_bytes());
+        buffer.extend_from_slice(&(len as u32).to_ne_bytes());
+        buffer.extend_from_slice(name);
+        buffer.resize(buffer.len() + len - name.len(), 0);
+    }
+
+    buffer
+}
+
+criterion_group!(benches, parse, convert);
+criterion_main!(benches);
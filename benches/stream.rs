@@ -0,0 +1,61 @@
+//! Benchmarks for the poll loop of `EventStream`
+//!
+//! The events come from an `EventInjector`, which hands each event to the
+//! stream in a read of its own. That makes this the worst case for the fixed
+//! cost per read, which is what the poll loop adds on top of parsing.
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use futures_util::StreamExt;
+use inotify::{EventMask, EventStream};
+use tokio::runtime;
+
+/// The number of events that are pushed, and then taken out of the stream
+const EVENTS_PER_ITERATION: usize = 64;
+
+fn poll(c: &mut Criterion) {
+    let runtime = runtime::Builder::new_current_thread()
+        .enable_io()
+        .build()
+        .unwrap();
+    let _guard = runtime.enter();
+
+    let (mut stream, injector) = EventStream::with_injector().unwrap();
+    let wd = injector.add_watch("/tmp");
+
+    let mut group = c.benchmark_group("stream");
+    group.throughput(Throughput::Elements(EVENTS_PER_ITERATION as u64));
+
+    group.bench_function("next", |b| {
+        b.iter(|| {
+            for _ in 0..EVENTS_PER_ITERATION {
+                injector
+                    .push(&wd, EventMask::MODIFY, 0, Some("app.log".as_ref()))
+                    .unwrap();
+            }
+            runtime.block_on(async {
+                for _ in 0..EVENTS_PER_ITERATION {
+                    stream.next().await.unwrap().unwrap();
+                }
+            })
+        })
+    });
+    group.bench_function("next_borrowed", |b| {
+        b.iter(|| {
+            for _ in 0..EVENTS_PER_ITERATION {
+                injector
+                    .push(&wd, EventMask::MODIFY, 0, Some("app.log".as_ref()))
+                    .unwrap();
+            }
+            runtime.block_on(async {
+                for _ in 0..EVENTS_PER_ITERATION {
+                    stream.next_borrowed().await.unwrap().unwrap();
+                }
+            })
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, poll);
+criterion_main!(benches);
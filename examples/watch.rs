@@ -3,7 +3,7 @@ use std::env;
 use inotify::{EventMask, Inotify, WatchMask};
 
 fn main() {
-    let mut inotify = Inotify::init().expect("Failed to initialize inotify");
+    let inotify = Inotify::init().expect("Failed to initialize inotify");
 
     let current_dir = env::current_dir().expect("Failed to determine current directory");
 
@@ -0,0 +1,79 @@
+//! Parses arbitrary bytes as a buffer of events
+//!
+//! The kernel is trusted to return well-formed events, but `Events` also
+//! accepts buffers from elsewhere, through `Events::from_bytes`. Whatever is
+//! in the buffer, the checked iterator must not panic, must not read out of
+//! bounds, and must account for every byte it consumes.
+
+#![no_main]
+
+use std::os::unix::ffi::OsStrExt;
+
+use inotify::Events;
+use libfuzzer_sys::fuzz_target;
+
+/// The size of `inotify_event`, without the name
+const EVENT_SIZE: usize = 16;
+
+fuzz_target!(|data: &[u8]| {
+    let mut events = Events::from_bytes(data).checked();
+    let mut names = Vec::new();
+    let mut failed = false;
+
+    loop {
+        let (len, _) = events.size_hint();
+
+        let peeked = events.peek().map(|result| result.is_ok());
+        let event = match events.next() {
+            Some(event) => event,
+            None => {
+                assert_eq!(len, 0);
+                assert_eq!(peeked, None);
+                break;
+            }
+        };
+        assert_eq!(peeked, Some(event.is_ok()));
+
+        let event = match event {
+            Ok(event) => event,
+            Err(_) => {
+                failed = true;
+                // There's no way to find the next event after a malformed one.
+                assert!(events.next().is_none());
+                break;
+            }
+        };
+        assert!(len > 0);
+
+        if let Some(name) = event.name {
+            assert!(!name.is_empty());
+            assert!(!name.as_bytes().contains(&0));
+        }
+        names.push(event.name.map(|name| name.to_os_string()));
+    }
+
+    // The unchecked iterators must agree with the checked one, as long as
+    // there are no malformed events, which would make them panic.
+    if !failed {
+        let buffered = Events::from_bytes(data)
+            .into_buffered()
+            .map(|event| event.name.map(|name| name.to_os_string()))
+            .collect::<Vec<_>>();
+        assert_eq!(buffered, names);
+
+        let mut events = Events::from_bytes(data);
+        assert_eq!(events.len(), names.len());
+        for name in &names {
+            let before = events.consumed();
+            let event = events.next().unwrap();
+            assert_eq!(event.name.map(|name| name.to_os_string()), *name);
+
+            let step = events.consumed() - before;
+            assert!(step >= EVENT_SIZE);
+            assert!(step - EVENT_SIZE >= name.as_ref().map_or(0, |name| name.len()));
+            assert_eq!(events.consumed() + events.remaining_bytes().len(), data.len());
+        }
+        assert!(events.next().is_none());
+        assert_eq!(events.consumed(), data.len());
+    }
+});
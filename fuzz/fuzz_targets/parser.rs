@@ -0,0 +1,43 @@
+//! Feeds arbitrary bytes to `EventParser`, in arbitrary chunks
+//!
+//! Events can be split across chunks, so this exercises the code that waits
+//! for the rest of an event, in addition to the parsing itself.
+
+#![no_main]
+
+use inotify::EventParser;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let (chunk_size, data) = match data.split_first() {
+        Some((&chunk_size, data)) => (usize::from(chunk_size).max(1), data),
+        None => return,
+    };
+
+    let mut parser = EventParser::new();
+    let mut fed = 0;
+
+    for chunk in data.chunks(chunk_size) {
+        parser.feed(chunk);
+        fed += chunk.len();
+        assert!(parser.buffered_bytes() <= fed);
+
+        while let Some(result) = parser.next_event() {
+            let failed = match result {
+                Ok(event) => {
+                    if let Some(name) = event.name {
+                        assert!(!name.is_empty());
+                    }
+                    false
+                }
+                Err(_) => true,
+            };
+
+            if failed {
+                // The parser can't recover, so it drops what it has.
+                assert_eq!(parser.buffered_bytes(), 0);
+                break;
+            }
+        }
+    }
+});
@@ -0,0 +1,153 @@
+use std::{ffi::OsStr, ops::Range, os::unix::ffi::OsStrExt};
+
+use crate::events::Event;
+
+/// Storage for many events, without one allocation per name
+///
+/// Converting events into [`EventOwned`] allocates memory for every name.
+/// `EventArena` instead copies the names of all events into one shared
+/// buffer. Once the arena has grown to the size of a typical batch, and is
+/// reused by calling [`EventArena::clear`], storing events doesn't allocate
+/// at all.
+///
+/// # Examples
+///
+/// ```no_run
+/// use inotify::{EventArena, Inotify};
+///
+/// let inotify = Inotify::init()
+///     .expect("Failed to initialize an inotify instance");
+///
+/// let mut arena = EventArena::new();
+/// let mut buffer = [0; 4096];
+///
+/// loop {
+///     arena.clear();
+///
+///     let events = inotify.read_events_blocking(&mut buffer)
+///         .expect("Error while reading events");
+///     arena.extend(events);
+///
+///     // The buffer can be reused, while the events live on in the arena.
+///     for event in arena.iter() {
+///         // Handle event
+///     }
+/// }
+/// ```
+///
+/// [`EventOwned`]: crate::EventOwned
+#[derive(Debug, Default)]
+pub struct EventArena {
+    names: Vec<u8>,
+    events: Vec<Event<Range<usize>>>,
+}
+
+impl EventArena {
+    /// Creates an empty arena
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates an empty arena with room for some events and names
+    ///
+    /// `name_bytes` is the combined length of the names.
+    pub fn with_capacity(events: usize, name_bytes: usize) -> Self {
+        EventArena {
+            names: Vec::with_capacity(name_bytes),
+            events: Vec::with_capacity(events),
+        }
+    }
+
+    /// Copies an event into the arena
+    pub fn push<S>(&mut self, event: &Event<S>)
+    where
+        S: AsRef<OsStr>,
+    {
+        let names = &mut self.names;
+        let event = event.clone_with_name_ref().map_name(|name| {
+            let start = names.len();
+            names.extend_from_slice(name.as_bytes());
+            start..names.len()
+        });
+
+        self.events.push(event);
+    }
+
+    /// Returns the event at `index`
+    pub fn get(&self, index: usize) -> Option<Event<&OsStr>> {
+        self.events.get(index).map(|event| self.resolve(event))
+    }
+
+    /// Returns an iterator over all events in the arena
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = Event<&OsStr>> + '_ {
+        self.events.iter().map(move |event| self.resolve(event))
+    }
+
+    /// Returns the number of events in the arena
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    /// Indicates whether the arena contains no events
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// Removes all events, but keeps the allocated memory for reuse
+    pub fn clear(&mut self) {
+        self.names.clear();
+        self.events.clear();
+    }
+
+    fn resolve(&self, event: &Event<Range<usize>>) -> Event<&OsStr> {
+        event
+            .clone()
+            .map_name(|range| OsStr::from_bytes(&self.names[range]))
+    }
+}
+
+impl<S> Extend<Event<S>> for EventArena
+where
+    S: AsRef<OsStr>,
+{
+    fn extend<I>(&mut self, events: I)
+    where
+        I: IntoIterator<Item = Event<S>>,
+    {
+        for event in events {
+            self.push(&event);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::EventArena;
+    use crate::events::test_event;
+    use crate::EventMask;
+
+    #[test]
+    fn it_should_store_events_and_reuse_its_memory() {
+        let mut arena = EventArena::new();
+        arena.extend([
+            test_event(EventMask::CREATE, 0, Some("a")),
+            test_event(EventMask::DELETE_SELF, 0, None),
+            test_event(EventMask::MODIFY, 0, Some("bc")),
+        ]);
+
+        let events = arena.iter().collect::<Vec<_>>();
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[0].name, Some("a".as_ref()));
+        assert_eq!(events[1].name, None);
+        assert_eq!(events[2].mask, EventMask::MODIFY);
+        assert_eq!(events[2].name, Some("bc".as_ref()));
+
+        let capacity = arena.names.capacity();
+        arena.clear();
+        assert!(arena.is_empty());
+
+        arena.push(&test_event(EventMask::CREATE, 0, Some("d")));
+        assert_eq!(arena.names.capacity(), capacity);
+        assert_eq!(arena.get(0).unwrap().name, Some("d".as_ref()));
+    }
+}
@@ -0,0 +1,241 @@
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "stream")]
+use std::{
+    future::Future,
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+#[cfg(feature = "stream")]
+use futures_core::{FusedStream, Stream};
+#[cfg(feature = "stream")]
+use tokio::time::Sleep;
+
+use crate::events::EventOwned;
+
+/// Groups bursts of events into batches
+///
+/// Build systems and development servers usually want to react once per
+/// burst of changes, not once per event. `Batcher` collects events, until no
+/// further event has arrived for the configured quiet period, then returns
+/// all of them as one batch. To keep a steady stream of events from holding
+/// back a batch forever, a batch is also returned once its first event is
+/// older than the configured maximum delay.
+///
+/// Like [`Debouncer`], `Batcher` doesn't do any I/O, and doesn't look at the
+/// clock itself. Push all events into it using [`Batcher::push`], and take
+/// the batches out of it using [`Batcher::pop`]. If you're using an
+/// [`EventStream`], [`EventStream::batch`] does all of that for you.
+///
+/// [`Debouncer`]: crate::Debouncer
+/// [`EventStream`]: crate::EventStream
+/// [`EventStream::batch`]: crate::EventStream::batch
+#[derive(Debug)]
+pub struct Batcher {
+    quiet: Duration,
+    max_delay: Duration,
+    events: Vec<EventOwned>,
+    first: Option<Instant>,
+    last: Option<Instant>,
+}
+
+impl Batcher {
+    /// Creates a `Batcher`
+    ///
+    /// `quiet` is how long no further event must arrive, before a batch is
+    /// returned. `max_delay` is how long the first event of a batch is held
+    /// back at most.
+    pub fn new(quiet: Duration, max_delay: Duration) -> Self {
+        Batcher {
+            quiet,
+            max_delay,
+            events: Vec::new(),
+            first: None,
+            last: None,
+        }
+    }
+
+    /// Adds an event to the current batch
+    ///
+    /// `now` is the time at which the event was received.
+    pub fn push(&mut self, event: EventOwned, now: Instant) {
+        self.events.push(event);
+        self.first.get_or_insert(now);
+        self.last = Some(now);
+    }
+
+    /// Returns the current batch, if it is complete
+    ///
+    /// `now` is the current time.
+    pub fn pop(&mut self, now: Instant) -> Option<Vec<EventOwned>> {
+        if self.next_deadline()? > now {
+            return None;
+        }
+        self.flush()
+    }
+
+    /// Returns the current batch, without waiting for it to be complete
+    ///
+    /// Returns `None`, if there are no events. This is useful when shutting
+    /// down.
+    pub fn flush(&mut self) -> Option<Vec<EventOwned>> {
+        self.first = None;
+        self.last = None;
+
+        if self.events.is_empty() {
+            return None;
+        }
+        Some(std::mem::take(&mut self.events))
+    }
+
+    /// Returns the time at which the current batch is complete
+    ///
+    /// Returns `None`, if there are no events. The time moves further into
+    /// the future with each new event, until the maximum delay is reached.
+    pub fn next_deadline(&self) -> Option<Instant> {
+        let quiet = self.last? + self.quiet;
+        let max_delay = self.first? + self.max_delay;
+        Some(quiet.min(max_delay))
+    }
+
+    /// Indicates whether the batcher holds no events
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+}
+
+/// Stream adapter that groups bursts of events into batches
+///
+/// Returned by [`EventStream::batch`]. See [`Batcher`] for details. Once the
+/// underlying stream has ended, the last batch is returned right away.
+///
+/// [`EventStream::batch`]: crate::EventStream::batch
+#[cfg(feature = "stream")]
+#[derive(Debug)]
+pub struct Batched<S> {
+    inner: S,
+    inner_done: bool,
+    batcher: Batcher,
+    sleep: Option<Pin<Box<Sleep>>>,
+}
+
+#[cfg(feature = "stream")]
+impl<S> Batched<S> {
+    pub(crate) fn new(inner: S, quiet: Duration, max_delay: Duration) -> Self {
+        Batched {
+            inner,
+            inner_done: false,
+            batcher: Batcher::new(quiet, max_delay),
+            sleep: None,
+        }
+    }
+
+    /// Returns the underlying stream
+    ///
+    /// Events of the current batch are lost.
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+#[cfg(feature = "stream")]
+impl<S> Stream for Batched<S>
+where
+    S: Stream<Item = io::Result<EventOwned>> + Unpin,
+{
+    type Item = io::Result<Vec<EventOwned>>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let self_ = &mut *self;
+
+        loop {
+            while !self_.inner_done {
+                match Pin::new(&mut self_.inner).poll_next(cx) {
+                    Poll::Ready(Some(Ok(event))) => self_.batcher.push(event, Instant::now()),
+                    Poll::Ready(Some(Err(error))) => return Poll::Ready(Some(Err(error))),
+                    Poll::Ready(None) => self_.inner_done = true,
+                    Poll::Pending => break,
+                }
+            }
+
+            if self_.inner_done {
+                return Poll::Ready(self_.batcher.flush().map(Ok));
+            }
+
+            if let Some(batch) = self_.batcher.pop(Instant::now()) {
+                return Poll::Ready(Some(Ok(batch)));
+            }
+
+            let deadline = match self_.batcher.next_deadline() {
+                Some(deadline) => tokio::time::Instant::from_std(deadline),
+                None => return Poll::Pending,
+            };
+            let sleep = match &mut self_.sleep {
+                Some(sleep) => {
+                    sleep.as_mut().reset(deadline);
+                    sleep
+                }
+                None => self_
+                    .sleep
+                    .insert(Box::pin(tokio::time::sleep_until(deadline))),
+            };
+            if sleep.as_mut().poll(cx).is_pending() {
+                return Poll::Pending;
+            }
+        }
+    }
+}
+
+#[cfg(feature = "stream")]
+impl<S> FusedStream for Batched<S>
+where
+    S: Stream<Item = io::Result<EventOwned>> + Unpin,
+{
+    fn is_terminated(&self) -> bool {
+        self.inner_done && self.batcher.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::Batcher;
+    use crate::events::test_event;
+    use crate::EventMask;
+
+    #[test]
+    fn it_should_return_a_batch_once_events_stop() {
+        let now = Instant::now();
+        let quiet = Duration::from_secs(1);
+        let mut batcher = Batcher::new(quiet, quiet * 10);
+
+        batcher.push(test_event(EventMask::CREATE, 0, Some("a")), now);
+        batcher.push(test_event(EventMask::MODIFY, 0, Some("a")), now + quiet / 2);
+        assert_eq!(batcher.pop(now + quiet), None);
+        assert_eq!(batcher.next_deadline(), Some(now + quiet / 2 + quiet));
+
+        let batch = batcher.pop(now + quiet * 2).unwrap();
+        assert_eq!(batch.len(), 2);
+        assert!(batcher.is_empty());
+        assert_eq!(batcher.next_deadline(), None);
+    }
+
+    #[test]
+    fn it_should_not_hold_back_a_batch_beyond_the_maximum_delay() {
+        let now = Instant::now();
+        let quiet = Duration::from_secs(1);
+        let mut batcher = Batcher::new(quiet, quiet * 2);
+
+        for i in 0..4 {
+            batcher.push(
+                test_event(EventMask::MODIFY, 0, Some("a")),
+                now + quiet / 2 * i,
+            );
+        }
+        assert_eq!(batcher.next_deadline(), Some(now + quiet * 2));
+        assert_eq!(batcher.pop(now + quiet * 2).unwrap().len(), 4);
+    }
+}
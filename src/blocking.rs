@@ -0,0 +1,101 @@
+use std::{io, sync::Arc};
+
+use crate::events::{after_read, Event, EventOwned, Timestamp};
+use crate::fd_guard::{FdGuard, FdHandle};
+use crate::util::read_blocking;
+use crate::watches::Watches;
+use crate::Inotify;
+
+/// Blocking iterator over inotify events
+///
+/// Allows for iterating over events returned by
+/// [`Inotify::into_blocking_iter`]. Calls to [`Iterator::next`] block until an
+/// event is available. The iterator never returns `None`.
+#[derive(Debug)]
+pub struct BlockingIter<T> {
+    fd: Arc<FdGuard>,
+    buffer: T,
+    buffer_pos: usize,
+    unused_bytes: usize,
+    timestamp: Timestamp,
+    handle: FdHandle,
+}
+
+impl<T> BlockingIter<T>
+where
+    T: AsMut<[u8]> + AsRef<[u8]>,
+{
+    pub(crate) fn new(fd: Arc<FdGuard>, buffer: T) -> Self {
+        BlockingIter {
+            fd,
+            buffer,
+            buffer_pos: 0,
+            unused_bytes: 0,
+            timestamp: Timestamp::now(),
+            handle: FdHandle::default(),
+        }
+    }
+
+    /// Returns an instance of `Watches` to add and remove watches.
+    /// See [`Watches::add`] and [`Watches::remove`].
+    pub fn watches(&self) -> Watches {
+        Watches::new(self.fd.clone())
+    }
+
+    /// Consumes the `BlockingIter` instance and returns an `Inotify` using the
+    /// original file descriptor that was passed from `Inotify` to create the
+    /// `BlockingIter`.
+    ///
+    /// Any events that have already been read, but not yet returned from the
+    /// iterator, are lost.
+    pub fn into_inotify(self) -> Inotify {
+        Inotify::from_file_descriptor(self.fd)
+    }
+}
+
+impl<T> Iterator for BlockingIter<T>
+where
+    T: AsMut<[u8]> + AsRef<[u8]>,
+{
+    type Item = io::Result<EventOwned>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.unused_bytes == 0 {
+            // Nothing usable in buffer. Need to reset and fill buffer.
+            self.buffer_pos = 0;
+            self.unused_bytes = match read_blocking(**self.fd, self.buffer.as_mut()) {
+                Ok(num_bytes) => num_bytes,
+                Err(error) => return Some(Err(error)),
+            };
+            self.timestamp = Timestamp::now();
+            self.handle = FdHandle::new(Arc::downgrade(&self.fd));
+            after_read(
+                &self.fd,
+                &self.handle,
+                &self.buffer.as_ref()[..self.unused_bytes],
+                self.timestamp,
+            );
+        }
+
+        // We have bytes in the buffer. inotify doesn't put partial events in
+        // there, and we only take complete events out. That means we should
+        // have at least one event in there. If not, the rest of the buffer
+        // can't be trusted.
+        let result = Event::from_buffer(
+            self.handle.clone(),
+            &self.buffer.as_ref()[self.buffer_pos..self.buffer_pos + self.unused_bytes],
+            self.timestamp,
+        );
+        let (bytes_consumed, event) = match result {
+            Ok(result) => result,
+            Err(error) => {
+                self.unused_bytes = 0;
+                return Some(Err(error.into()));
+            }
+        };
+        self.buffer_pos += bytes_consumed;
+        self.unused_bytes -= bytes_consumed;
+
+        Some(Ok(event.to_owned()))
+    }
+}
@@ -0,0 +1,163 @@
+use std::{
+    collections::BTreeMap,
+    fmt, mem,
+    path::{Path, PathBuf},
+    sync::{Mutex, MutexGuard},
+};
+
+use inotify_sys as ffi;
+use libc::c_int;
+
+use crate::events::read_header;
+use crate::watches::WatchMask;
+
+/// The number of watches that `Debug` output lists, before truncating
+const MAX_DEBUG_ENTRIES: usize = 16;
+
+/// Keeps track of the path and mask of each watch
+///
+/// Disabled by default, as it costs memory and time for every watch. Lives in
+/// [`FdGuard`], so it sees watches that are added through any [`Watches`] of
+/// the same inotify instance. Watches that the kernel removes on its own are
+/// forgotten, once their [`EventMask::IGNORED`] event is read.
+///
+/// [`FdGuard`]: crate::fd_guard::FdGuard
+/// [`Watches`]: crate::Watches
+/// [`EventMask::IGNORED`]: crate::EventMask::IGNORED
+#[derive(Default)]
+pub(crate) struct Bookkeeping(Mutex<Option<BTreeMap<c_int, (PathBuf, WatchMask)>>>);
+
+impl Bookkeeping {
+    pub(crate) fn enable(&self) {
+        self.lock().get_or_insert_with(BTreeMap::new);
+    }
+
+    pub(crate) fn disable(&self) {
+        *self.lock() = None;
+    }
+
+    /// Called after [`Watches::add`] returned the watch `id`
+    ///
+    /// [`Watches::add`]: crate::Watches::add
+    pub(crate) fn watch_added(&self, id: c_int, path: &Path, mask: WatchMask) {
+        if let Some(watches) = self.lock().as_mut() {
+            let mask_add = mask.contains(WatchMask::MASK_ADD);
+            let mask = mask - WatchMask::MASK_ADD;
+
+            let entry = watches
+                .entry(id)
+                .or_insert_with(|| (PathBuf::new(), WatchMask::empty()));
+            entry.0 = path.to_owned();
+            entry.1 = if mask_add { entry.1 | mask } else { mask };
+        }
+    }
+
+    /// Called after [`Watches::remove`] removed the watch `id`
+    ///
+    /// [`Watches::remove`]: crate::Watches::remove
+    pub(crate) fn watch_removed(&self, id: c_int) {
+        if let Some(watches) = self.lock().as_mut() {
+            watches.remove(&id);
+        }
+    }
+
+    /// Forgets the watches that events in `buffer` report as removed
+    pub(crate) fn forget_ignored(&self, buffer: &[u8]) {
+        let mut watches = self.lock();
+        let watches = match watches.as_mut() {
+            Some(watches) => watches,
+            None => return,
+        };
+
+        let event_size = mem::size_of::<ffi::inotify_event>();
+        let mut pos = 0;
+        while buffer.len() - pos >= event_size {
+            let event = read_header(&buffer[pos..]).expect("Checked by loop condition");
+
+            if event.mask & ffi::IN_IGNORED != 0 {
+                watches.remove(&event.wd);
+            }
+            pos = pos.saturating_add(event_size + event.len as usize);
+        }
+    }
+
+    /// Adds the bookkept watches to the `Debug` output of a struct
+    pub(crate) fn debug_fields(&self, f: &mut fmt::DebugStruct<'_, '_>) {
+        let watches = self.lock();
+        let watches = match watches.as_ref() {
+            Some(watches) => watches,
+            None => return,
+        };
+
+        f.field("num_watches", &watches.len());
+        f.field("watches", &Table(watches));
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Option<BTreeMap<c_int, (PathBuf, WatchMask)>>> {
+        self.0.lock().unwrap_or_else(|error| error.into_inner())
+    }
+}
+
+impl fmt::Debug for Bookkeeping {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut f = f.debug_struct("Bookkeeping");
+        self.debug_fields(&mut f);
+        f.finish()
+    }
+}
+
+/// Formats watches as a map, truncated to [`MAX_DEBUG_ENTRIES`]
+struct Table<'a>(&'a BTreeMap<c_int, (PathBuf, WatchMask)>);
+
+impl fmt::Debug for Table<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut map = f.debug_map();
+
+        for (id, (path, mask)) in self.0.iter().take(MAX_DEBUG_ENTRIES) {
+            map.entry(id, &format_args!("{} ({})", path.display(), mask));
+        }
+        if self.0.len() > MAX_DEBUG_ENTRIES {
+            map.entry(
+                &format_args!(".."),
+                &format_args!("{} more", self.0.len() - MAX_DEBUG_ENTRIES),
+            );
+        }
+
+        map.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::Bookkeeping;
+    use crate::watches::WatchMask;
+
+    #[test]
+    fn it_should_track_masks_and_truncate_debug_output() {
+        let bookkeeping = Bookkeeping::default();
+        bookkeeping.watch_added(1, Path::new("/ignored"), WatchMask::MODIFY);
+        assert_eq!(format!("{:?}", bookkeeping), "Bookkeeping");
+
+        bookkeeping.enable();
+        bookkeeping.watch_added(1, Path::new("/a"), WatchMask::MODIFY);
+        bookkeeping.watch_added(1, Path::new("/a"), WatchMask::ATTRIB | WatchMask::MASK_ADD);
+        assert_eq!(
+            format!("{:?}", bookkeeping),
+            "Bookkeeping { num_watches: 1, watches: {1: /a (ATTRIB | MODIFY)} }"
+        );
+
+        for id in 2..=20 {
+            bookkeeping.watch_added(id, Path::new("/b"), WatchMask::CREATE);
+        }
+        bookkeeping.watch_removed(1);
+        let output = format!("{:?}", bookkeeping);
+        assert!(output.starts_with("Bookkeeping { num_watches: 19, watches: {2: /b (CREATE)"));
+        assert!(
+            output.ends_with("17: /b (CREATE), ..: 3 more} }"),
+            "{}",
+            output
+        );
+    }
+}
@@ -0,0 +1,203 @@
+use std::{
+    future, io,
+    pin::Pin,
+    sync::{Arc, Mutex, MutexGuard},
+    task::{Context, Poll},
+};
+
+use futures_core::Stream;
+use tokio::sync::mpsc;
+
+use crate::events::EventOwned;
+use crate::watches::WatchDescriptor;
+
+/// Distributes the events of one stream to multiple subscribers
+///
+/// Several components of an application might be interested in events from
+/// the same inotify instance. `Broadcaster` owns the stream, and sends each
+/// event to every [`Subscription`] that is interested in it. A subscription
+/// either receives all events ([`Broadcaster::subscribe`]), or only the events
+/// for a single watch ([`Broadcaster::subscribe_to`]).
+///
+/// The broadcaster doesn't do anything, until [`Broadcaster::run`] is polled.
+/// To subscribe after moving it into a task, get a [`BroadcastHandle`] first.
+///
+/// Each subscription has a channel with the capacity passed to
+/// [`Broadcaster::new`]. If it is full, the broadcaster waits until there is
+/// room, so a slow subscriber slows down everyone else.
+///
+/// # Examples
+///
+/// ```no_run
+/// use inotify::{Broadcaster, Inotify, WatchMask};
+///
+/// # async fn example() -> std::io::Result<()> {
+/// let inotify = Inotify::init()?;
+/// let config = inotify.watches().add("/etc/app", WatchMask::MODIFY)?;
+///
+/// let broadcaster = Broadcaster::new(inotify.into_event_stream_with_capacity(0)?, 16);
+/// let mut everything = broadcaster.subscribe();
+/// let mut config_changes = broadcaster.subscribe_to(config);
+/// tokio::spawn(broadcaster.run());
+///
+/// while let Some(_event) = config_changes.recv().await {
+///     // Reload config
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct Broadcaster<S> {
+    stream: S,
+    handle: BroadcastHandle,
+}
+
+impl<S> Broadcaster<S>
+where
+    S: Stream<Item = io::Result<EventOwned>> + Unpin,
+{
+    /// Creates a `Broadcaster` for `stream`
+    ///
+    /// `capacity` is the number of events that each subscription can hold.
+    pub fn new(stream: S, capacity: usize) -> Self {
+        Broadcaster {
+            stream,
+            handle: BroadcastHandle {
+                capacity,
+                subscribers: Arc::new(Mutex::new(Vec::new())),
+            },
+        }
+    }
+
+    /// Subscribes to all events
+    pub fn subscribe(&self) -> Subscription {
+        self.handle.subscribe()
+    }
+
+    /// Subscribes to the events for a single watch
+    ///
+    /// Queue overflow events are received too, as they affect all watches.
+    pub fn subscribe_to(&self, wd: WatchDescriptor) -> Subscription {
+        self.handle.subscribe_to(wd)
+    }
+
+    /// Returns a handle that can be used to subscribe from elsewhere
+    pub fn handle(&self) -> BroadcastHandle {
+        self.handle.clone()
+    }
+
+    /// Reads events from the stream and distributes them
+    ///
+    /// Returns, once the stream has ended, or returned an error. All
+    /// subscriptions end at that point.
+    pub async fn run(mut self) -> io::Result<()> {
+        let result = loop {
+            let event = match future::poll_fn(|cx| Pin::new(&mut self.stream).poll_next(cx)).await {
+                Some(Ok(event)) => event,
+                Some(Err(error)) => break Err(error),
+                None => break Ok(()),
+            };
+
+            // Don't hold the lock while sending, or subscribing would block
+            // while a subscriber is full.
+            let senders = self
+                .handle
+                .lock()
+                .iter()
+                .filter(|subscriber| subscriber.wants(&event))
+                .map(|subscriber| subscriber.sender.clone())
+                .collect::<Vec<_>>();
+
+            let mut closed = false;
+            for sender in senders {
+                closed |= sender.send(event.clone()).await.is_err();
+            }
+            if closed {
+                self.handle
+                    .lock()
+                    .retain(|subscriber| !subscriber.sender.is_closed());
+            }
+        };
+
+        self.handle.lock().clear();
+        result
+    }
+}
+
+/// Handle to subscribe to a [`Broadcaster`]
+///
+/// Returned by [`Broadcaster::handle`].
+#[derive(Clone, Debug)]
+pub struct BroadcastHandle {
+    capacity: usize,
+    subscribers: Arc<Mutex<Vec<Subscriber>>>,
+}
+
+impl BroadcastHandle {
+    /// Subscribes to all events
+    ///
+    /// See [`Broadcaster::subscribe`].
+    pub fn subscribe(&self) -> Subscription {
+        self.add_subscriber(None)
+    }
+
+    /// Subscribes to the events for a single watch
+    ///
+    /// See [`Broadcaster::subscribe_to`].
+    pub fn subscribe_to(&self, wd: WatchDescriptor) -> Subscription {
+        self.add_subscriber(Some(wd))
+    }
+
+    fn add_subscriber(&self, wd: Option<WatchDescriptor>) -> Subscription {
+        let (sender, receiver) = mpsc::channel(self.capacity.max(1));
+        self.lock().push(Subscriber { wd, sender });
+        Subscription { receiver }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Vec<Subscriber>> {
+        self.subscribers
+            .lock()
+            .unwrap_or_else(|error| error.into_inner())
+    }
+}
+
+#[derive(Debug)]
+struct Subscriber {
+    wd: Option<WatchDescriptor>,
+    sender: mpsc::Sender<EventOwned>,
+}
+
+impl Subscriber {
+    fn wants(&self, event: &EventOwned) -> bool {
+        match &self.wd {
+            Some(wd) => event.wd == *wd || event.is_queue_overflow(),
+            None => true,
+        }
+    }
+}
+
+/// Receives events from a [`Broadcaster`]
+///
+/// Can be used as a [`Stream`], or through [`Subscription::recv`]. Ends,
+/// once the broadcaster has stopped.
+#[derive(Debug)]
+pub struct Subscription {
+    receiver: mpsc::Receiver<EventOwned>,
+}
+
+impl Subscription {
+    /// Waits for the next event
+    ///
+    /// Returns `None`, once the broadcaster has stopped.
+    pub async fn recv(&mut self) -> Option<EventOwned> {
+        self.receiver.recv().await
+    }
+}
+
+impl Stream for Subscription {
+    type Item = EventOwned;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.receiver.poll_recv(cx)
+    }
+}
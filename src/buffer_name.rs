@@ -0,0 +1,132 @@
+use std::{
+    borrow::Borrow,
+    cmp::Ordering,
+    ffi::{OsStr, OsString},
+    fmt,
+    hash::{Hash, Hasher},
+    ops::Deref,
+    os::unix::ffi::OsStrExt,
+    path::Path,
+    sync::Arc,
+};
+
+/// The name of an event, stored in a buffer that is shared between events
+///
+/// Used by [`EventBuffered`]. All events from the same read share one
+/// reference-counted copy of the buffer, so taking an event out of the buffer
+/// doesn't allocate, and neither does cloning it. The buffer is freed, once
+/// the last event that refers to it has been dropped. Keeping a single event
+/// around keeps the whole buffer alive, so convert events that are stored for
+/// a long time into [`EventOwned`].
+///
+/// Dereferences to [`OsStr`], and compares and hashes like one.
+///
+/// [`EventBuffered`]: crate::EventBuffered
+/// [`EventOwned`]: crate::EventOwned
+#[derive(Clone)]
+pub struct BufferName {
+    buffer: Arc<[u8]>,
+    start: usize,
+    end: usize,
+}
+
+impl BufferName {
+    /// Refers to the name at `start..end` in `buffer`
+    pub(crate) fn new(buffer: Arc<[u8]>, start: usize, end: usize) -> Self {
+        debug_assert!(start <= end && end <= buffer.len());
+        BufferName { buffer, start, end }
+    }
+
+    /// Returns the name
+    pub fn as_os_str(&self) -> &OsStr {
+        OsStr::from_bytes(&self.buffer[self.start..self.end])
+    }
+
+    /// Copies the name out of the shared buffer
+    pub fn to_os_string(&self) -> OsString {
+        self.as_os_str().to_os_string()
+    }
+}
+
+impl Deref for BufferName {
+    type Target = OsStr;
+
+    fn deref(&self) -> &Self::Target {
+        self.as_os_str()
+    }
+}
+
+impl AsRef<OsStr> for BufferName {
+    fn as_ref(&self) -> &OsStr {
+        self.as_os_str()
+    }
+}
+
+impl AsRef<Path> for BufferName {
+    fn as_ref(&self) -> &Path {
+        Path::new(self.as_os_str())
+    }
+}
+
+impl Borrow<OsStr> for BufferName {
+    fn borrow(&self) -> &OsStr {
+        self.as_os_str()
+    }
+}
+
+impl From<BufferName> for OsString {
+    fn from(name: BufferName) -> Self {
+        name.to_os_string()
+    }
+}
+
+impl fmt::Debug for BufferName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.as_os_str().fmt(f)
+    }
+}
+
+impl PartialEq for BufferName {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_os_str() == other.as_os_str()
+    }
+}
+
+impl Eq for BufferName {}
+
+impl PartialEq<OsStr> for BufferName {
+    fn eq(&self, other: &OsStr) -> bool {
+        self.as_os_str() == other
+    }
+}
+
+impl PartialEq<str> for BufferName {
+    fn eq(&self, other: &str) -> bool {
+        self.as_os_str() == other
+    }
+}
+
+impl PartialEq<&str> for BufferName {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_os_str() == *other
+    }
+}
+
+impl PartialOrd for BufferName {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for BufferName {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.as_os_str().cmp(other.as_os_str())
+    }
+}
+
+impl Hash for BufferName {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        // Must hash like `OsStr`, to be consistent with `Borrow<OsStr>`.
+        self.as_os_str().hash(state);
+    }
+}
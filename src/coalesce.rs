@@ -0,0 +1,248 @@
+use std::ffi::OsString;
+
+use crate::events::{EventMask, EventOwned};
+use crate::watches::WatchDescriptor;
+
+/// Merges events for the same file into a single summary
+///
+/// Push the events of a batch (for example, the events from one read) into
+/// the `Coalescer` using [`Coalescer::push`]. [`Coalescer::drain`] then
+/// returns one [`CoalescedEvent`] per watch descriptor and name, in the order
+/// in which the files first appeared in the batch. A file that has been
+/// created, written and closed, for example, is reported once, as
+/// [`Change::Created`].
+///
+/// If you're using an [`EventStream`], [`EventStream::read_coalesced`] does
+/// all of that for you.
+///
+/// # Examples
+///
+/// ```no_run
+/// use inotify::{Change, Coalescer, Inotify, WatchMask};
+///
+/// let inotify = Inotify::init()
+///     .expect("Failed to initialize an inotify instance");
+/// inotify.watches().add("/tmp", WatchMask::ALL_EVENTS)
+///     .expect("Failed to add watch");
+///
+/// let mut coalescer = Coalescer::new();
+/// let mut buffer = [0; 4096];
+///
+/// loop {
+///     let events = inotify.read_events_blocking(&mut buffer)
+///         .expect("Error while reading events");
+///     for event in events {
+///         coalescer.push(event.to_owned());
+///     }
+///
+///     for event in coalescer.drain() {
+///         if event.change == Change::Created {
+///             println!("Created: {:?}", event.name);
+///         }
+///     }
+/// }
+/// ```
+///
+/// [`EventStream`]: crate::EventStream
+/// [`EventStream::read_coalesced`]: crate::EventStream::read_coalesced
+#[derive(Debug, Default)]
+pub struct Coalescer {
+    entries: Vec<Entry>,
+}
+
+impl Coalescer {
+    /// Creates an empty `Coalescer`
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an event to the current batch
+    pub fn push(&mut self, event: EventOwned) {
+        let entry = self
+            .entries
+            .iter_mut()
+            .find(|entry| entry.wd == event.wd && entry.name == event.name);
+
+        let entry = match entry {
+            Some(entry) => entry,
+            None => {
+                self.entries.push(Entry {
+                    wd: event.wd.clone(),
+                    name: event.name.clone(),
+                    mask: EventMask::empty(),
+                    existed_before: None,
+                    exists_after: None,
+                });
+                self.entries.last_mut().unwrap()
+            }
+        };
+
+        entry.mask |= event.mask;
+
+        let exists = if event
+            .mask
+            .intersects(EventMask::CREATE | EventMask::MOVED_TO)
+        {
+            true
+        } else if event.mask.intersects(
+            EventMask::DELETE
+                | EventMask::MOVED_FROM
+                | EventMask::DELETE_SELF
+                | EventMask::MOVE_SELF,
+        ) {
+            false
+        } else {
+            return;
+        };
+
+        entry.existed_before.get_or_insert(!exists);
+        entry.exists_after = Some(exists);
+    }
+
+    /// Returns the summaries of the current batch, and starts a new one
+    pub fn drain(&mut self) -> impl Iterator<Item = CoalescedEvent> + '_ {
+        self.entries.drain(..).map(Entry::summarize)
+    }
+
+    /// Indicates whether no events have been pushed since the last drain
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+#[derive(Debug)]
+struct Entry {
+    wd: WatchDescriptor,
+    name: Option<OsString>,
+    mask: EventMask,
+    existed_before: Option<bool>,
+    exists_after: Option<bool>,
+}
+
+impl Entry {
+    fn summarize(self) -> CoalescedEvent {
+        let change = match (self.existed_before, self.exists_after) {
+            (Some(false), Some(true)) => Change::Created,
+            (Some(false), Some(false)) => Change::Transient,
+            (Some(true), Some(false)) => Change::Removed,
+            // The file has been replaced, which is a modification, as far as
+            // anyone looking at it before and after is concerned.
+            (Some(true), Some(true)) => Change::Modified,
+            _ if self
+                .mask
+                .intersects(EventMask::MODIFY | EventMask::ATTRIB | EventMask::CLOSE_WRITE) =>
+            {
+                Change::Modified
+            }
+            _ => Change::Other,
+        };
+
+        CoalescedEvent {
+            wd: self.wd,
+            name: self.name,
+            mask: self.mask,
+            change,
+        }
+    }
+}
+
+/// A summary of all events for a file within a batch
+///
+/// Returned by [`Coalescer::drain`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CoalescedEvent {
+    /// Identifies the watch the events were generated for
+    pub wd: WatchDescriptor,
+
+    /// The name of the file the events refer to
+    ///
+    /// `None`, if the events refer to the watched file or directory itself.
+    pub name: Option<OsString>,
+
+    /// The union of the masks of all events
+    pub mask: EventMask,
+
+    /// What happened to the file, overall
+    pub change: Change,
+}
+
+/// What happened to a file within a batch
+///
+/// See [`CoalescedEvent`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Change {
+    /// The file didn't exist before the batch, but does afterwards
+    Created,
+
+    /// The file exists before and after the batch, but has been changed
+    ///
+    /// This includes files that have been deleted and created again.
+    Modified,
+
+    /// The file existed before the batch, but doesn't afterwards
+    Removed,
+
+    /// The file has been created and removed within the batch
+    Transient,
+
+    /// The file has only been accessed, opened or closed without writing
+    ///
+    /// This also covers events that don't refer to a file, like queue
+    /// overflows.
+    Other,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Change, Coalescer};
+    use crate::events::test_event;
+    use crate::fd_guard::FdHandle;
+    use crate::EventMask;
+
+    #[test]
+    fn it_should_summarize_events_per_file() {
+        let mut coalescer = Coalescer::new();
+
+        coalescer.push(test_event(EventMask::CREATE, 0, Some("a")));
+        coalescer.push(test_event(EventMask::MODIFY, 0, Some("b")));
+        coalescer.push(test_event(EventMask::MODIFY, 0, Some("a")));
+        coalescer.push(test_event(EventMask::CLOSE_WRITE, 0, Some("a")));
+        coalescer.push(test_event(EventMask::DELETE, 0, Some("c")));
+        coalescer.push(test_event(EventMask::MOVED_TO, 1, Some("d")));
+        coalescer.push(test_event(EventMask::DELETE, 0, Some("d")));
+        coalescer.push(test_event(EventMask::OPEN, 0, Some("e")));
+
+        let changes = coalescer
+            .drain()
+            .map(|event| (event.name.unwrap().into_string().unwrap(), event.change))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            changes,
+            vec![
+                ("a".to_string(), Change::Created),
+                ("b".to_string(), Change::Modified),
+                ("c".to_string(), Change::Removed),
+                ("d".to_string(), Change::Transient),
+                ("e".to_string(), Change::Other),
+            ]
+        );
+        assert!(coalescer.is_empty());
+    }
+
+    #[test]
+    fn it_should_keep_files_of_different_instances_apart() {
+        let mut coalescer = Coalescer::new();
+
+        let mut other = test_event(EventMask::DELETE, 0, Some("a"));
+        // Doesn't belong to the instance of the other events.
+        other.wd.fd = FdHandle::default();
+        coalescer.push(test_event(EventMask::CREATE, 0, Some("a")));
+        coalescer.push(other);
+
+        let changes = coalescer
+            .drain()
+            .map(|event| event.change)
+            .collect::<Vec<_>>();
+        assert_eq!(changes, vec![Change::Created, Change::Removed]);
+    }
+}
@@ -0,0 +1,88 @@
+use std::{io, sync::Weak};
+
+use bytes::{Buf, BytesMut};
+use tokio_util::codec::Decoder;
+
+use crate::events::{name_len, Event, EventMask, EventOwned, ParseError, Timestamp};
+use crate::fd_guard::{FdGuard, FdHandle};
+use crate::parser::MAX_NAME_LEN;
+
+/// Decodes inotify events from raw bytes
+///
+/// Implements [`Decoder`], so it can be used with [`FramedRead`], for example
+/// to read events from something other than the inotify file descriptor
+/// itself, like a pipe or socket that events are forwarded through. Events
+/// may be split across reads.
+///
+/// [`FramedRead`]: tokio_util::codec::FramedRead
+#[derive(Debug)]
+pub struct InotifyCodec {
+    fd: FdHandle,
+}
+
+impl InotifyCodec {
+    /// Creates a codec that isn't associated with any inotify instance
+    ///
+    /// The [`WatchDescriptor`]s of the decoded events won't be equal to the
+    /// ones returned by [`Watches::add`]. Use [`Inotify::codec`], if that's
+    /// needed.
+    ///
+    /// [`WatchDescriptor`]: crate::WatchDescriptor
+    /// [`Watches::add`]: crate::Watches::add
+    /// [`Inotify::codec`]: crate::Inotify::codec
+    pub fn new() -> Self {
+        Self::with_fd(Weak::new())
+    }
+
+    pub(crate) fn with_fd(fd: Weak<FdGuard>) -> Self {
+        InotifyCodec {
+            fd: FdHandle::new(fd),
+        }
+    }
+}
+
+impl Default for InotifyCodec {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Decoder for InotifyCodec {
+    type Item = EventOwned;
+    type Error = io::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> io::Result<Option<Self::Item>> {
+        // Check the length of the name first, so we don't wait forever for a
+        // name that's never going to arrive.
+        if let Some(len) = name_len(src) {
+            if len > MAX_NAME_LEN {
+                src.clear();
+                return Err(ParseError::NameTooLong { len }.into());
+            }
+        }
+
+        let (bytes_consumed, event) =
+            match Event::from_buffer(self.fd.clone(), src, Timestamp::now()) {
+                Ok((bytes_consumed, event)) => (bytes_consumed, event.to_owned()),
+                // The rest of the event hasn't been read yet.
+                Err(_) => return Ok(None),
+            };
+        src.advance(bytes_consumed);
+
+        if event.is_queue_overflow() {
+            if let Some(fd) = self.fd.upgrade() {
+                fd.overflow_hook.fire();
+            }
+        }
+        if let Some(fd) = self.fd.upgrade() {
+            fd.oneshots.deliver(&event);
+        }
+        if event.mask.contains(EventMask::IGNORED) {
+            if let Some(fd) = self.fd.upgrade() {
+                fd.bookkeeping.watch_removed(event.wd.id);
+            }
+        }
+
+        Ok(Some(event))
+    }
+}
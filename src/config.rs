@@ -0,0 +1,204 @@
+use std::{
+    ffi::OsString,
+    io,
+    os::unix::io::AsRawFd,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use crate::events::EventMask;
+use crate::inotify::Inotify;
+use crate::util::{get_buffer_size_for_events, poll_readable};
+use crate::watches::WatchMask;
+
+/// Notifies about changes to a configuration file
+///
+/// Watching a configuration file directly is surprisingly hard to get right.
+/// Editors often save files by writing a new file and renaming it over the
+/// old one, other tools delete the file and create it again, and a single
+/// save usually results in many events. `ConfigWatcher` takes care of all of
+/// that: It watches the directory containing the file, and returns a single
+/// [`ConfigChange`], once the file has been changed, replaced or removed, and
+/// no further changes happened for a short quiet period.
+///
+/// `ConfigWatcher` uses an [`Inotify`] instance of its own, and blocks while
+/// waiting for changes.
+///
+/// # Examples
+///
+/// ```no_run
+/// use inotify::{ConfigChange, ConfigWatcher};
+///
+/// let watcher = ConfigWatcher::new("/etc/app/config.toml")
+///     .expect("Failed to watch config");
+///
+/// for change in watcher {
+///     match change.expect("Error while watching config") {
+///         ConfigChange::Changed => println!("Reloading config"),
+///         ConfigChange::Removed => println!("Config removed, keeping the old one"),
+///     }
+/// }
+/// ```
+#[derive(Debug)]
+pub struct ConfigWatcher {
+    inotify: Inotify,
+    path: PathBuf,
+    name: OsString,
+    quiet: Duration,
+    buffer: Vec<u8>,
+    /// Whether the directory is still watched
+    watching: bool,
+}
+
+impl ConfigWatcher {
+    /// Starts watching the configuration file at `path`
+    ///
+    /// The file doesn't need to exist yet. The quiet period defaults to 100
+    /// milliseconds.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`io::Error`] with [`ErrorKind`]`::InvalidInput`, if `path`
+    /// doesn't have a file name. Otherwise, returns the error from
+    /// initializing inotify or from watching the directory.
+    ///
+    /// [`ErrorKind`]: std::io::ErrorKind
+    pub fn new<P>(path: P) -> io::Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref().to_path_buf();
+        let name = path
+            .file_name()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Path has no file name"))?
+            .to_os_string();
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+
+        let inotify = Inotify::init()?;
+        inotify.watches().add(
+            dir,
+            WatchMask::CREATE
+                | WatchMask::MODIFY
+                | WatchMask::CLOSE_WRITE
+                | WatchMask::MOVE
+                | WatchMask::DELETE,
+        )?;
+
+        Ok(ConfigWatcher {
+            inotify,
+            path,
+            name,
+            quiet: Duration::from_millis(100),
+            buffer: vec![0; get_buffer_size_for_events(16)],
+            watching: true,
+        })
+    }
+
+    /// Configures how long no further change must happen before notifying
+    pub fn quiet_period(mut self, quiet: Duration) -> Self {
+        self.quiet = quiet;
+        self
+    }
+
+    /// Returns the path of the watched file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Waits for the next change to the configuration file
+    ///
+    /// If the directory containing the file is removed, or its filesystem is
+    /// unmounted, that is reported as a change, usually as
+    /// [`ConfigChange::Removed`]. The directory isn't watched anymore
+    /// afterwards.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error from waiting for or reading events, or from looking
+    /// up the file afterwards. Returns an error with [`ErrorKind`]`::NotFound`,
+    /// once the directory isn't watched anymore.
+    ///
+    /// [`ErrorKind`]: std::io::ErrorKind
+    pub fn wait(&mut self) -> io::Result<ConfigChange> {
+        if !self.watching {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "Directory of the configuration file is no longer watched",
+            ));
+        }
+
+        let mut last_change = None;
+
+        loop {
+            let timeout = match last_change {
+                Some(last_change) => {
+                    let elapsed = Instant::now().saturating_duration_since(last_change);
+                    match self.quiet.checked_sub(elapsed) {
+                        Some(remaining) if !remaining.is_zero() => Some(remaining),
+                        _ => break,
+                    }
+                }
+                None => None,
+            };
+
+            let [readable] = poll_readable([self.inotify.fd.as_raw_fd()], timeout)?;
+            if readable && self.read_changes()? {
+                last_change = Some(Instant::now());
+            }
+        }
+
+        match self.path.symlink_metadata() {
+            Ok(_) => Ok(ConfigChange::Changed),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(ConfigChange::Removed),
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Reads all available events; returns whether any concerned the file
+    fn read_changes(&mut self) -> io::Result<bool> {
+        let events = match self.inotify.read_events(&mut self.buffer) {
+            Ok(events) => events,
+            Err(error) if error.kind() == io::ErrorKind::WouldBlock => return Ok(false),
+            Err(error) => return Err(error),
+        };
+
+        let mut changed = false;
+        for event in events {
+            // The directory has been removed or unmounted, which takes the
+            // file with it.
+            if event.mask.contains(EventMask::IGNORED) {
+                self.watching = false;
+                changed = true;
+            }
+
+            // After an overflow, it's unknown what happened to the file.
+            changed |= event.is_queue_overflow() || event.name == Some(self.name.as_os_str());
+        }
+
+        Ok(changed)
+    }
+}
+
+impl Iterator for ConfigWatcher {
+    type Item = io::Result<ConfigChange>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if !self.watching {
+            return None;
+        }
+        Some(self.wait())
+    }
+}
+
+/// A change to a file watched by a [`ConfigWatcher`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ConfigChange {
+    /// The file has been written to, replaced, or created
+    Changed,
+
+    /// The file has been removed
+    Removed,
+}
@@ -0,0 +1,285 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+#[cfg(feature = "stream")]
+use std::{
+    future::Future,
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+#[cfg(feature = "stream")]
+use futures_core::{FusedStream, Stream};
+#[cfg(feature = "stream")]
+use tokio::time::Sleep;
+
+use crate::events::EventOwned;
+
+/// Suppresses bursts of events for the same file
+///
+/// Editors and compilers tend to generate many events in quick succession,
+/// for example a series of [`MODIFY`] events while a file is being written.
+/// `Debouncer` holds back events, until no further event for the same watch
+/// descriptor and name has arrived for the configured quiet period. Only the
+/// last event of each burst is returned then.
+///
+/// Events are returned in the order in which their quiet periods end. Queue
+/// overflow events are never held back.
+///
+/// Like [`RenameTracker`], `Debouncer` doesn't do any I/O, and doesn't look at
+/// the clock itself. Push all events into it using [`Debouncer::push`], and
+/// take the results out of it using [`Debouncer::pop`]. If you're using an
+/// [`EventStream`], [`EventStream::debounce`] does all of that for you.
+///
+/// [`MODIFY`]: crate::EventMask::MODIFY
+/// [`RenameTracker`]: crate::RenameTracker
+/// [`EventStream`]: crate::EventStream
+/// [`EventStream::debounce`]: crate::EventStream::debounce
+#[derive(Debug)]
+pub struct Debouncer {
+    quiet: Duration,
+    ready: VecDeque<EventOwned>,
+    pending: VecDeque<Pending>,
+}
+
+impl Debouncer {
+    /// Creates a `Debouncer`
+    ///
+    /// `quiet` is how long no further event for a file must arrive, before
+    /// its last event is returned.
+    pub fn new(quiet: Duration) -> Self {
+        Debouncer {
+            quiet,
+            ready: VecDeque::new(),
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// Passes an event to the debouncer
+    ///
+    /// `now` is the time at which the event was received. If an event for the
+    /// same file is still held back, it is replaced by this one.
+    pub fn push(&mut self, event: EventOwned, now: Instant) {
+        if event.is_queue_overflow() {
+            self.ready.push_back(event);
+            return;
+        }
+
+        let previous = self
+            .pending
+            .iter()
+            .position(|pending| pending.event.wd == event.wd && pending.event.name == event.name);
+        if let Some(index) = previous {
+            self.pending.remove(index);
+        }
+
+        // Times are passed in order, so the queue stays sorted by deadline.
+        self.pending.push_back(Pending {
+            event,
+            deadline: now + self.quiet,
+        });
+    }
+
+    /// Returns the next event, if its quiet period has ended
+    ///
+    /// `now` is the current time.
+    pub fn pop(&mut self, now: Instant) -> Option<EventOwned> {
+        if let Some(event) = self.ready.pop_front() {
+            return Some(event);
+        }
+
+        if self.pending.front()?.deadline > now {
+            return None;
+        }
+        self.pending.pop_front().map(|pending| pending.event)
+    }
+
+    /// Returns all remaining events, without waiting for quiet periods to end
+    ///
+    /// This is useful when shutting down.
+    pub fn flush(&mut self) -> impl Iterator<Item = EventOwned> + '_ {
+        self.ready
+            .drain(..)
+            .chain(self.pending.drain(..).map(|pending| pending.event))
+    }
+
+    /// Returns the time at which the next held back event becomes ready
+    ///
+    /// Returns `None`, if no event is held back. If an event is ready already,
+    /// the returned time is in the past.
+    pub fn next_deadline(&self) -> Option<Instant> {
+        if !self.ready.is_empty() {
+            return Some(Instant::now());
+        }
+
+        self.pending.front().map(|pending| pending.deadline)
+    }
+
+    /// Indicates whether the debouncer holds no events
+    pub fn is_empty(&self) -> bool {
+        self.ready.is_empty() && self.pending.is_empty()
+    }
+}
+
+#[derive(Debug)]
+struct Pending {
+    event: EventOwned,
+    deadline: Instant,
+}
+
+/// Stream adapter that suppresses bursts of events for the same file
+///
+/// Returned by [`EventStream::debounce`]. See [`Debouncer`] for details. Once
+/// the underlying stream has ended, all held back events are returned right
+/// away.
+///
+/// [`EventStream::debounce`]: crate::EventStream::debounce
+#[cfg(feature = "stream")]
+#[derive(Debug)]
+pub struct Debounced<S> {
+    inner: S,
+    inner_done: bool,
+    debouncer: Debouncer,
+    sleep: Option<Pin<Box<Sleep>>>,
+}
+
+#[cfg(feature = "stream")]
+impl<S> Debounced<S> {
+    pub(crate) fn new(inner: S, quiet: Duration) -> Self {
+        Debounced {
+            inner,
+            inner_done: false,
+            debouncer: Debouncer::new(quiet),
+            sleep: None,
+        }
+    }
+
+    /// Returns the underlying stream
+    ///
+    /// Events that are still held back are lost.
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+#[cfg(feature = "stream")]
+impl<S> Stream for Debounced<S>
+where
+    S: Stream<Item = io::Result<EventOwned>> + Unpin,
+{
+    type Item = io::Result<EventOwned>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let self_ = &mut *self;
+
+        loop {
+            while !self_.inner_done {
+                match Pin::new(&mut self_.inner).poll_next(cx) {
+                    Poll::Ready(Some(Ok(event))) => self_.debouncer.push(event, Instant::now()),
+                    Poll::Ready(Some(Err(error))) => return Poll::Ready(Some(Err(error))),
+                    Poll::Ready(None) => self_.inner_done = true,
+                    Poll::Pending => break,
+                }
+            }
+
+            if self_.inner_done {
+                return Poll::Ready(self_.debouncer.flush().next().map(Ok));
+            }
+
+            if let Some(event) = self_.debouncer.pop(Instant::now()) {
+                return Poll::Ready(Some(Ok(event)));
+            }
+
+            let deadline = match self_.debouncer.next_deadline() {
+                Some(deadline) => tokio::time::Instant::from_std(deadline),
+                None => return Poll::Pending,
+            };
+            let sleep = match &mut self_.sleep {
+                Some(sleep) => {
+                    sleep.as_mut().reset(deadline);
+                    sleep
+                }
+                None => self_
+                    .sleep
+                    .insert(Box::pin(tokio::time::sleep_until(deadline))),
+            };
+            if sleep.as_mut().poll(cx).is_pending() {
+                return Poll::Pending;
+            }
+        }
+    }
+}
+
+#[cfg(feature = "stream")]
+impl<S> FusedStream for Debounced<S>
+where
+    S: Stream<Item = io::Result<EventOwned>> + Unpin,
+{
+    fn is_terminated(&self) -> bool {
+        self.inner_done && self.debouncer.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::Debouncer;
+    use crate::events::test_event;
+    use crate::fd_guard::FdHandle;
+    use crate::EventMask;
+
+    #[test]
+    fn it_should_return_the_last_event_of_a_burst() {
+        let now = Instant::now();
+        let quiet = Duration::from_secs(1);
+        let mut debouncer = Debouncer::new(quiet);
+
+        debouncer.push(test_event(EventMask::MODIFY, 0, Some("a")), now);
+        debouncer.push(test_event(EventMask::MODIFY, 0, Some("b")), now);
+        debouncer.push(
+            test_event(EventMask::CLOSE_WRITE, 0, Some("a")),
+            now + quiet / 2,
+        );
+        assert_eq!(debouncer.pop(now + quiet / 2), None);
+
+        let event = debouncer.pop(now + quiet).unwrap();
+        assert_eq!(event.name.as_deref(), Some("b".as_ref()));
+        assert_eq!(debouncer.pop(now + quiet), None);
+        assert_eq!(debouncer.next_deadline(), Some(now + quiet / 2 + quiet));
+
+        let event = debouncer.pop(now + quiet * 2).unwrap();
+        assert_eq!(event.mask, EventMask::CLOSE_WRITE);
+        assert!(debouncer.is_empty());
+    }
+
+    #[test]
+    fn it_should_not_hold_back_queue_overflows() {
+        let now = Instant::now();
+        let mut debouncer = Debouncer::new(Duration::from_secs(1));
+
+        debouncer.push(test_event(EventMask::MODIFY, 0, Some("a")), now);
+        debouncer.push(test_event(EventMask::Q_OVERFLOW, 0, None), now);
+
+        let event = debouncer.pop(now).unwrap();
+        assert_eq!(event.mask, EventMask::Q_OVERFLOW);
+        assert_eq!(debouncer.flush().count(), 1);
+    }
+
+    #[test]
+    fn it_should_keep_files_of_different_instances_apart() {
+        let now = Instant::now();
+        let mut debouncer = Debouncer::new(Duration::from_secs(1));
+
+        let mut other = test_event(EventMask::MODIFY, 0, Some("a"));
+        // Doesn't belong to the instance of the other event.
+        other.wd.fd = FdHandle::default();
+        debouncer.push(test_event(EventMask::MODIFY, 0, Some("a")), now);
+        debouncer.push(other, now);
+
+        assert_eq!(debouncer.flush().count(), 2);
+    }
+}
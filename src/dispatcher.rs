@@ -0,0 +1,315 @@
+use std::{
+    io,
+    os::unix::io::{AsRawFd, FromRawFd, OwnedFd},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc,
+    },
+    thread,
+};
+
+#[cfg(feature = "crossbeam")]
+use std::sync::Mutex;
+
+use crate::events::{EventOwned, Events};
+use crate::fd_guard::FdGuard;
+use crate::util::{poll_readable, read};
+use crate::watches::Watches;
+
+/// Size of the buffer the dispatcher thread reads events into
+const BUFFER_SIZE: usize = 4096;
+
+/// Handle to a dispatcher thread
+///
+/// Returned by [`Inotify::spawn_dispatcher`], together with the receiving end
+/// of the channel the dispatcher thread sends events to.
+///
+/// The dispatcher thread keeps running until [`DispatcherHandle::stop`] is
+/// called, the handle is dropped, the receiver is dropped, or reading events
+/// fails.
+///
+/// [`Inotify::spawn_dispatcher`]: crate::Inotify::spawn_dispatcher
+#[derive(Debug)]
+pub struct DispatcherHandle {
+    fd: Arc<FdGuard>,
+    stop: Arc<StopSignal>,
+    thread: Option<thread::JoinHandle<io::Result<()>>>,
+}
+
+impl DispatcherHandle {
+    /// Spawns a thread that reads events from `fd` and passes them to `sink`
+    pub(crate) fn spawn<S>(fd: Arc<FdGuard>, mut sink: S) -> io::Result<Self>
+    where
+        S: Sink,
+    {
+        let stop = Arc::new(StopSignal::new()?);
+
+        let thread = {
+            let fd = fd.clone();
+            let stop = stop.clone();
+
+            thread::Builder::new()
+                .name("inotify-dispatcher".into())
+                .spawn(move || dispatch(&fd, &stop, &mut sink))?
+        };
+
+        Ok(DispatcherHandle {
+            fd,
+            stop,
+            thread: Some(thread),
+        })
+    }
+
+    /// Returns an instance of `Watches` to add and remove watches.
+    /// See [`Watches::add`] and [`Watches::remove`].
+    pub fn watches(&self) -> Watches {
+        Watches::new(self.fd.clone())
+    }
+
+    /// Indicates whether the dispatcher thread has stopped
+    ///
+    /// This is the case, if reading events failed, or if the receiving end of
+    /// the channel has been dropped.
+    pub fn is_finished(&self) -> bool {
+        self.thread
+            .as_ref()
+            .map_or(true, |thread| thread.is_finished())
+    }
+
+    /// Stops the dispatcher thread and waits for it to finish
+    ///
+    /// Events that have already been sent remain available from the channel.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error that caused the dispatcher thread to stop, if it
+    /// stopped because of an error before this method was called.
+    pub fn stop(mut self) -> io::Result<()> {
+        self.stop.signal()?;
+
+        match self.thread.take() {
+            Some(thread) => thread.join().unwrap_or_else(|_| {
+                Err(io::Error::new(
+                    io::ErrorKind::Other,
+                    "inotify dispatcher thread panicked",
+                ))
+            }),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for DispatcherHandle {
+    fn drop(&mut self) {
+        // Tell the thread to stop, but don't wait for it. There's nothing we
+        // could do about an error here anyway.
+        let _ = self.stop.signal();
+    }
+}
+
+/// Destination for events read by a dispatcher thread
+pub(crate) trait Sink: Send + 'static {
+    /// Passes an event on
+    ///
+    /// Returns `Ok(false)`, if the receiving side is gone and the dispatcher
+    /// should stop. Implementations that block should return `Ok(false)` when
+    /// `stop` has been signaled.
+    fn send(&mut self, event: EventOwned, stop: &StopSignal) -> io::Result<bool>;
+}
+
+impl Sink for mpsc::Sender<EventOwned> {
+    fn send(&mut self, event: EventOwned, _: &StopSignal) -> io::Result<bool> {
+        Ok(mpsc::Sender::send(self, event).is_ok())
+    }
+}
+
+/// Defines what a dispatcher does, if its channel is full
+///
+/// Used by [`Inotify::spawn_crossbeam_dispatcher`].
+///
+/// [`Inotify::spawn_crossbeam_dispatcher`]: crate::Inotify::spawn_crossbeam_dispatcher
+#[cfg(feature = "crossbeam")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FullChannelPolicy {
+    /// Wait until the receiving side makes room
+    ///
+    /// While waiting, no further events are read. If the kernel's event queue
+    /// overflows in the meantime, an event with [`EventMask::Q_OVERFLOW`] is
+    /// delivered later.
+    ///
+    /// [`EventMask::Q_OVERFLOW`]: crate::EventMask::Q_OVERFLOW
+    Block,
+
+    /// Remove the oldest event from the channel to make room for the new one
+    ///
+    /// Since the dispatcher needs to keep a receiver for this, dropping all
+    /// other receivers doesn't stop the dispatcher thread. Use
+    /// [`DispatcherHandle::stop`] instead.
+    DropOldest,
+
+    /// Stop the dispatcher thread with an error
+    ///
+    /// The error is returned from [`DispatcherHandle::stop`].
+    Error,
+}
+
+#[cfg(feature = "crossbeam")]
+pub(crate) struct CrossbeamSink {
+    pub(crate) sender: crossbeam_channel::Sender<EventOwned>,
+    pub(crate) receiver: Option<crossbeam_channel::Receiver<EventOwned>>,
+    pub(crate) policy: FullChannelPolicy,
+}
+
+#[cfg(feature = "crossbeam")]
+impl Sink for CrossbeamSink {
+    fn send(&mut self, mut event: EventOwned, stop: &StopSignal) -> io::Result<bool> {
+        use crossbeam_channel::TrySendError;
+
+        match self.policy {
+            FullChannelPolicy::Block => crossbeam_channel::select! {
+                send(self.sender, event) -> result => Ok(result.is_ok()),
+                recv(stop.disconnected()) -> _ => Ok(false),
+            },
+            FullChannelPolicy::DropOldest => loop {
+                if stop.is_signaled() {
+                    return Ok(false);
+                }
+                match self.sender.try_send(event) {
+                    Ok(()) => return Ok(true),
+                    Err(TrySendError::Full(e)) => {
+                        if let Some(receiver) = &self.receiver {
+                            let _ = receiver.try_recv();
+                        }
+                        event = e;
+                    }
+                    Err(TrySendError::Disconnected(_)) => return Ok(false),
+                }
+            },
+            FullChannelPolicy::Error => match self.sender.try_send(event) {
+                Ok(()) => Ok(true),
+                Err(TrySendError::Full(_)) => Err(io::Error::new(
+                    io::ErrorKind::Other,
+                    "inotify dispatcher channel is full",
+                )),
+                Err(TrySendError::Disconnected(_)) => Ok(false),
+            },
+        }
+    }
+}
+
+fn dispatch<S>(fd: &Arc<FdGuard>, stop: &StopSignal, sink: &mut S) -> io::Result<()>
+where
+    S: Sink,
+{
+    let mut buffer = [0; BUFFER_SIZE];
+
+    loop {
+        let [stop_requested, readable] = poll_readable([stop.as_raw_fd(), fd.as_raw_fd()], None)?;
+        if stop_requested {
+            return Ok(());
+        }
+        if !readable {
+            continue;
+        }
+
+        let num_bytes = match read(fd.as_raw_fd(), &mut buffer) {
+            Ok(num_bytes) => num_bytes,
+            Err(error) if error.kind() == io::ErrorKind::WouldBlock => continue,
+            Err(error) => return Err(error),
+        };
+
+        for event in Events::new(Arc::downgrade(fd), &buffer, num_bytes).checked() {
+            if !sink.send(event?.to_owned(), stop)? {
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Wakes up a thread that is waiting in [`poll_readable`]
+///
+/// Wraps an `eventfd`, which becomes readable once [`StopSignal::signal`] has
+/// been called.
+#[derive(Debug)]
+pub(crate) struct StopSignal {
+    fd: OwnedFd,
+    signaled: AtomicBool,
+    /// Dropped when signaled, to wake up threads blocked on a channel
+    #[cfg(feature = "crossbeam")]
+    waker: Mutex<Option<crossbeam_channel::Sender<()>>>,
+    #[cfg(feature = "crossbeam")]
+    disconnected: crossbeam_channel::Receiver<()>,
+}
+
+impl StopSignal {
+    pub(crate) fn new() -> io::Result<Self> {
+        let fd = unsafe { libc::eventfd(0, libc::EFD_CLOEXEC | libc::EFD_NONBLOCK) };
+        if fd == -1 {
+            return Err(io::Error::last_os_error());
+        }
+
+        #[cfg(feature = "crossbeam")]
+        let (waker, disconnected) = crossbeam_channel::bounded(0);
+
+        Ok(StopSignal {
+            fd: unsafe { OwnedFd::from_raw_fd(fd) },
+            signaled: AtomicBool::new(false),
+            #[cfg(feature = "crossbeam")]
+            waker: Mutex::new(Some(waker)),
+            #[cfg(feature = "crossbeam")]
+            disconnected,
+        })
+    }
+
+    /// Returns a receiver that becomes disconnected once signaled
+    ///
+    /// Nothing is ever sent over it. Selecting on it lets a thread that waits
+    /// for a channel notice the signal, which it couldn't with the `eventfd`.
+    #[cfg(feature = "crossbeam")]
+    pub(crate) fn disconnected(&self) -> &crossbeam_channel::Receiver<()> {
+        &self.disconnected
+    }
+
+    /// Indicates whether [`StopSignal::signal`] has been called
+    #[cfg_attr(not(feature = "crossbeam"), allow(dead_code))]
+    pub(crate) fn is_signaled(&self) -> bool {
+        self.signaled.load(Ordering::Acquire)
+    }
+
+    pub(crate) fn signal(&self) -> io::Result<()> {
+        self.signaled.store(true, Ordering::Release);
+        #[cfg(feature = "crossbeam")]
+        self.waker
+            .lock()
+            .unwrap_or_else(|error| error.into_inner())
+            .take();
+
+        let value: u64 = 1;
+        let result = unsafe {
+            libc::write(
+                self.fd.as_raw_fd(),
+                &value as *const u64 as *const libc::c_void,
+                std::mem::size_of::<u64>(),
+            )
+        };
+
+        match result {
+            -1 => {
+                let error = io::Error::last_os_error();
+                // The counter is saturated, which means the signal has been
+                // sent many times before. That's fine.
+                if error.kind() == io::ErrorKind::WouldBlock {
+                    return Ok(());
+                }
+                Err(error)
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl AsRawFd for StopSignal {
+    fn as_raw_fd(&self) -> std::os::unix::io::RawFd {
+        self.fd.as_raw_fd()
+    }
+}
@@ -0,0 +1,110 @@
+use std::{
+    io,
+    os::unix::io::BorrowedFd,
+    task::{Context, Poll},
+};
+
+#[cfg(feature = "async-io")]
+use async_io::Async;
+#[cfg(any(feature = "stream", feature = "async-io"))]
+use futures_core::ready;
+#[cfg(any(feature = "stream", feature = "async-io"))]
+use std::os::unix::io::AsRawFd;
+#[cfg(feature = "stream")]
+use std::os::unix::io::RawFd;
+#[cfg(feature = "stream")]
+use tokio::io::unix::AsyncFd;
+
+/// Connects an [`EventStream`] to an async runtime
+///
+/// An `IoDriver` registers the inotify file descriptor with a reactor, and
+/// wakes the task polling the stream, once the file descriptor has become
+/// readable. `TokioDriver` is used by default, if the `stream` feature is
+/// enabled. Other runtimes can be supported by implementing this trait, and
+/// passing the implementation to [`Inotify::into_event_stream_with_driver`].
+///
+/// [`EventStream`]: crate::EventStream
+/// [`Inotify::into_event_stream_with_driver`]: crate::Inotify::into_event_stream_with_driver
+pub trait IoDriver: Sized {
+    /// Registers the inotify file descriptor with the reactor
+    ///
+    /// The file descriptor is non-blocking, and stays open for as long as the
+    /// driver exists.
+    fn register(fd: BorrowedFd<'_>) -> io::Result<Self>;
+
+    /// Calls `read`, once the file descriptor is readable
+    ///
+    /// `read` returns an error with [`io::ErrorKind::WouldBlock`], if the
+    /// file descriptor turns out not to be readable after all. The driver is
+    /// then expected to wait for readiness again.
+    fn poll_read<F>(&mut self, cx: &mut Context<'_>, read: F) -> Poll<io::Result<usize>>
+    where
+        F: FnMut() -> io::Result<usize>;
+}
+
+/// Drives an [`EventStream`] using tokio
+///
+/// This is the default driver. The stream must be polled from within a tokio
+/// runtime.
+///
+/// [`EventStream`]: crate::EventStream
+#[cfg(feature = "stream")]
+#[derive(Debug)]
+pub struct TokioDriver(AsyncFd<RawFd>);
+
+#[cfg(feature = "stream")]
+impl IoDriver for TokioDriver {
+    fn register(fd: BorrowedFd<'_>) -> io::Result<Self> {
+        AsyncFd::new(fd.as_raw_fd()).map(TokioDriver)
+    }
+
+    fn poll_read<F>(&mut self, cx: &mut Context<'_>, mut read: F) -> Poll<io::Result<usize>>
+    where
+        F: FnMut() -> io::Result<usize>,
+    {
+        loop {
+            let mut guard = ready!(self.0.poll_read_ready(cx))?;
+
+            // If the read would block, `try_io` clears the readiness, so the
+            // next call to `poll_read_ready` registers the waker and returns
+            // `Poll::Pending`, unless new events arrived in the meantime.
+            match guard.try_io(|_| read()) {
+                Ok(result) => return Poll::Ready(result),
+                Err(_would_block) => continue,
+            }
+        }
+    }
+}
+
+/// Drives an [`EventStream`] using async-io
+///
+/// This works with any executor, including the one from smol.
+///
+/// [`EventStream`]: crate::EventStream
+#[cfg(feature = "async-io")]
+#[derive(Debug)]
+pub struct AsyncIoDriver(Async<BorrowedFd<'static>>);
+
+#[cfg(feature = "async-io")]
+impl IoDriver for AsyncIoDriver {
+    fn register(fd: BorrowedFd<'_>) -> io::Result<Self> {
+        // The file descriptor stays open for as long as the driver exists,
+        // as required by the trait.
+        let fd = unsafe { BorrowedFd::borrow_raw(fd.as_raw_fd()) };
+        Async::new(fd).map(AsyncIoDriver)
+    }
+
+    fn poll_read<F>(&mut self, cx: &mut Context<'_>, mut read: F) -> Poll<io::Result<usize>>
+    where
+        F: FnMut() -> io::Result<usize>,
+    {
+        loop {
+            match read() {
+                Err(error) if error.kind() == io::ErrorKind::WouldBlock => {
+                    ready!(self.0.poll_readable(cx))?;
+                }
+                result => return Poll::Ready(result),
+            }
+        }
+    }
+}
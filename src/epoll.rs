@@ -0,0 +1,98 @@
+use std::{
+    io,
+    os::unix::io::{AsRawFd, FromRawFd, OwnedFd, RawFd},
+    time::Duration,
+};
+
+use libc::{c_int, epoll_event};
+
+/// A minimal wrapper around an epoll instance
+#[derive(Debug)]
+pub struct Epoll {
+    fd: OwnedFd,
+}
+
+impl Epoll {
+    /// Creates a new epoll instance by calling [`epoll_create1`]
+    ///
+    /// [`epoll_create1`]: libc::epoll_create1
+    pub fn new() -> io::Result<Self> {
+        let fd = unsafe { libc::epoll_create1(libc::EPOLL_CLOEXEC) };
+        if fd == -1 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(Epoll {
+            fd: unsafe { OwnedFd::from_raw_fd(fd) },
+        })
+    }
+
+    /// Registers `fd` for readability, identified by `token`
+    pub fn add(&self, fd: RawFd, token: u64) -> io::Result<()> {
+        add(self.fd.as_raw_fd(), fd, token)
+    }
+
+    /// Deregisters `fd`
+    pub fn delete(&self, fd: RawFd) -> io::Result<()> {
+        delete(self.fd.as_raw_fd(), fd)
+    }
+
+    /// Waits for registered file descriptors to become readable
+    ///
+    /// Returns the number of entries of `events` that have been filled in. A
+    /// `timeout` of `None` waits indefinitely.
+    pub fn wait(&self, events: &mut [epoll_event], timeout: Option<Duration>) -> io::Result<usize> {
+        let timeout = match timeout {
+            Some(timeout) => timeout.as_millis().min(c_int::MAX as u128) as c_int,
+            None => -1,
+        };
+        let max_events = events.len().min(c_int::MAX as usize) as c_int;
+
+        loop {
+            let result = unsafe {
+                libc::epoll_wait(
+                    self.fd.as_raw_fd(),
+                    events.as_mut_ptr(),
+                    max_events,
+                    timeout,
+                )
+            };
+
+            match result {
+                -1 => {
+                    let error = io::Error::last_os_error();
+                    if error.kind() == io::ErrorKind::Interrupted {
+                        continue;
+                    }
+                    return Err(error);
+                }
+                n => return Ok(n as usize),
+            }
+        }
+    }
+}
+
+/// Registers `fd` with the epoll instance `epfd`, for readability
+pub fn add(epfd: RawFd, fd: RawFd, token: u64) -> io::Result<()> {
+    let mut event = epoll_event {
+        events: libc::EPOLLIN as u32,
+        u64: token,
+    };
+
+    match unsafe { libc::epoll_ctl(epfd, libc::EPOLL_CTL_ADD, fd, &mut event) } {
+        -1 => Err(io::Error::last_os_error()),
+        _ => Ok(()),
+    }
+}
+
+/// Deregisters `fd` from the epoll instance `epfd`
+pub fn delete(epfd: RawFd, fd: RawFd) -> io::Result<()> {
+    // Kernels before 2.6.9 require a non-null pointer here, even though it is
+    // ignored.
+    let mut event = epoll_event { events: 0, u64: 0 };
+
+    match unsafe { libc::epoll_ctl(epfd, libc::EPOLL_CTL_DEL, fd, &mut event) } {
+        -1 => Err(io::Error::last_os_error()),
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,218 @@
+use std::{error, fmt, io};
+
+use crate::events::ParseError;
+use crate::mask_format::ParseMaskError;
+use crate::watches::AddWatchError;
+
+/// An error that occurred while using inotify
+///
+/// Most of the API of this crate returns [`io::Error`], to stay close to the
+/// underlying system calls. `Error` wraps those errors, and the more specific
+/// error types of this crate, into a single type that keeps track of what
+/// failed. Use it as the error type of code that does several things with
+/// inotify, and propagate errors using `?`.
+///
+/// Can be converted back into an [`io::Error`] of the same [`ErrorKind`].
+///
+/// # Examples
+///
+/// ```
+/// use inotify::{Error, Inotify, WatchMask};
+///
+/// fn watch(path: &str) -> Result<Inotify, Error> {
+///     let inotify = Inotify::init().map_err(Error::Init)?;
+///     inotify.watches().try_add(path, WatchMask::MODIFY)?;
+///     Ok(inotify)
+/// }
+///
+/// match watch("/does/not/exist") {
+///     Err(error @ Error::AddWatch(_)) => println!("{}", error),
+///     result => { result.expect("Unexpected error"); }
+/// }
+/// ```
+///
+/// [`ErrorKind`]: std::io::ErrorKind
+#[derive(Debug)]
+pub enum Error {
+    /// Initializing an inotify instance failed
+    Init(io::Error),
+
+    /// Reading events failed
+    Read(io::Error),
+
+    /// Adding a watch failed
+    AddWatch(AddWatchError),
+
+    /// Removing a watch failed
+    RemoveWatch(io::Error),
+
+    /// Events could not be parsed
+    Parse(ParseError),
+
+    /// A mask could not be parsed
+    ParseMask(ParseMaskError),
+
+    /// The inotify instance has been closed
+    ///
+    /// Converting an [`io::Error`] results in this variant, if the error is
+    /// `EBADF`. This happens when an instance is used after it has been
+    /// closed, for example through a [`CloseHandle`](crate::CloseHandle).
+    Closed,
+
+    /// Any other I/O error
+    Io(io::Error),
+}
+
+impl Error {
+    /// Returns the kind of the [`io::Error`] this error converts into
+    pub fn kind(&self) -> io::ErrorKind {
+        match self {
+            Error::Init(error)
+            | Error::Read(error)
+            | Error::RemoveWatch(error)
+            | Error::Io(error) => error.kind(),
+            Error::AddWatch(AddWatchError::Other(error)) => error.kind(),
+            Error::AddWatch(error) => match error.raw_os_error() {
+                Some(errno) => io::Error::from_raw_os_error(errno).kind(),
+                None => io::ErrorKind::Other,
+            },
+            Error::Parse(_) => io::ErrorKind::InvalidData,
+            Error::ParseMask(_) => io::ErrorKind::InvalidInput,
+            Error::Closed => io::Error::from_raw_os_error(libc::EBADF).kind(),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Init(error) => write!(f, "failed to initialize inotify: {}", error),
+            Error::Read(error) => write!(f, "failed to read inotify events: {}", error),
+            Error::AddWatch(error) => error.fmt(f),
+            Error::RemoveWatch(error) => write!(f, "failed to remove watch: {}", error),
+            Error::Parse(error) => write!(f, "failed to parse inotify events: {}", error),
+            Error::ParseMask(error) => write!(f, "failed to parse mask: {}", error),
+            Error::Closed => write!(f, "inotify instance has been closed"),
+            Error::Io(error) => error.fmt(f),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Error::Init(error) | Error::Read(error) | Error::RemoveWatch(error) => Some(error),
+            Error::AddWatch(error) => Some(error),
+            Error::Parse(error) => Some(error),
+            Error::ParseMask(error) => Some(error),
+            Error::Closed => None,
+            Error::Io(error) => error.source(),
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        // Keep the specific errors of this crate, if they have been converted
+        // into an `io::Error` before.
+        if error
+            .get_ref()
+            .is_some_and(|inner| inner.is::<ParseError>())
+        {
+            let inner = error.into_inner().expect("Checked above");
+            return Error::Parse(*inner.downcast().expect("Checked above"));
+        }
+        if error
+            .get_ref()
+            .is_some_and(|inner| inner.is::<AddWatchError>())
+        {
+            let inner = error.into_inner().expect("Checked above");
+            return Error::AddWatch(*inner.downcast().expect("Checked above"));
+        }
+
+        if error.raw_os_error() == Some(libc::EBADF) {
+            return Error::Closed;
+        }
+
+        Error::Io(error)
+    }
+}
+
+impl From<AddWatchError> for Error {
+    fn from(error: AddWatchError) -> Self {
+        Error::AddWatch(error)
+    }
+}
+
+impl From<ParseError> for Error {
+    fn from(error: ParseError) -> Self {
+        Error::Parse(error)
+    }
+}
+
+impl From<ParseMaskError> for Error {
+    fn from(error: ParseMaskError) -> Self {
+        Error::ParseMask(error)
+    }
+}
+
+impl From<Error> for io::Error {
+    fn from(error: Error) -> Self {
+        match error {
+            Error::Init(error)
+            | Error::Read(error)
+            | Error::RemoveWatch(error)
+            | Error::Io(error) => error,
+            Error::AddWatch(error) => error.into(),
+            Error::Closed => io::Error::from_raw_os_error(libc::EBADF),
+            error => io::Error::new(error.kind(), error),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use super::Error;
+    use crate::events::ParseError;
+    use crate::watches::AddWatchError;
+
+    #[test]
+    fn it_should_recover_errors_of_this_crate_from_io_errors() {
+        let error: io::Error = ParseError::TruncatedEvent { available: 3 }.into();
+        assert!(matches!(
+            Error::from(error),
+            Error::Parse(ParseError::TruncatedEvent { available: 3 })
+        ));
+
+        let error: io::Error = AddWatchError::WatchLimitReached { limit: None }.into();
+        assert!(matches!(
+            Error::from(error),
+            Error::AddWatch(AddWatchError::WatchLimitReached { limit: None })
+        ));
+
+        let error = io::Error::from_raw_os_error(libc::EBADF);
+        assert!(matches!(Error::from(error), Error::Closed));
+
+        let error = io::Error::from(io::ErrorKind::WouldBlock);
+        assert!(matches!(Error::from(error), Error::Io(_)));
+    }
+
+    #[test]
+    fn it_should_convert_into_io_errors_of_the_same_kind() {
+        let errors = [
+            Error::Read(io::Error::from(io::ErrorKind::WouldBlock)),
+            Error::AddWatch(AddWatchError::PathNotFound {
+                path: "/missing".into(),
+            }),
+            Error::Parse(ParseError::NameTooLong { len: 4096 }),
+            Error::Closed,
+        ];
+
+        for error in errors {
+            let kind = error.kind();
+            assert_eq!(io::Error::from(error).kind(), kind);
+        }
+    }
+}
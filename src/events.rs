@@ -1,14 +1,24 @@
 use std::{
+    convert::TryInto,
+    error::Error,
     ffi::{OsStr, OsString},
-    mem,
-    os::unix::ffi::OsStrExt,
-    sync::Weak,
+    fmt,
+    hash::{Hash, Hasher},
+    io, mem,
+    os::{raw::c_int, unix::ffi::OsStrExt},
+    sync::{Arc, Weak},
 };
 
+#[cfg(feature = "timestamps")]
+use std::time::{Instant, SystemTime};
+
 use inotify_sys as ffi;
 
-use crate::fd_guard::FdGuard;
-use crate::watches::WatchDescriptor;
+use crate::buffer_name::BufferName;
+use crate::fd_guard::{FdGuard, FdHandle};
+use crate::inline_name::InlineName;
+use crate::intern::NamePolicy;
+use crate::watches::{WatchDescriptor, Watches};
 
 /// Iterator over inotify events
 ///
@@ -19,19 +29,185 @@ use crate::watches::WatchDescriptor;
 /// [`Inotify::read_events`]: crate::Inotify::read_events
 #[derive(Debug)]
 pub struct Events<'a> {
-    fd: Weak<FdGuard>,
+    fd: FdHandle,
     buffer: &'a [u8],
     num_bytes: usize,
     pos: usize,
+    timestamp: Timestamp,
+
+    /// The number of complete events that haven't been returned yet
+    num_events: usize,
+
+    /// Whether there are bytes after the complete events
+    malformed: bool,
 }
 
 impl<'a> Events<'a> {
+    /// Creates an iterator over events that have just been read into `buffer`
     pub(crate) fn new(fd: Weak<FdGuard>, buffer: &'a [u8], num_bytes: usize) -> Self {
+        Events::with_handle(FdHandle::new(fd), buffer, num_bytes)
+    }
+
+    /// Like [`Events::new`], but the events share an existing handle
+    pub(crate) fn with_handle(fd: FdHandle, buffer: &'a [u8], num_bytes: usize) -> Self {
+        let timestamp = Timestamp::now();
+        if let Some(guard) = fd.upgrade() {
+            after_read(&guard, &fd, &buffer[..num_bytes], timestamp);
+        }
+
+        let scan = scan(&buffer[..num_bytes]);
+        Events::from_scan(fd, buffer, num_bytes, scan, timestamp)
+    }
+
+    /// Like [`Events::with_handle`], but for events that haven't just been
+    /// read from the instance of `fd`
+    ///
+    /// Leaves the instance alone, so its bookkeeping, oneshot watches and
+    /// overflow hook don't see the events.
+    pub(crate) fn replayed(fd: FdHandle, buffer: &'a [u8], num_bytes: usize) -> Self {
+        let scan = scan(&buffer[..num_bytes]);
+        Events::from_scan(fd, buffer, num_bytes, scan, Timestamp::now())
+    }
+
+    fn from_scan(
+        fd: FdHandle,
+        buffer: &'a [u8],
+        num_bytes: usize,
+        scan: Scan,
+        timestamp: Timestamp,
+    ) -> Self {
         Events {
             fd,
             buffer,
             num_bytes,
             pos: 0,
+            timestamp,
+            num_events: scan.num_events,
+            malformed: scan.malformed,
+        }
+    }
+
+    /// Creates an iterator over events in a caller-provided buffer
+    ///
+    /// This is useful, if you read from the inotify file descriptor yourself,
+    /// for example from a custom event loop, or if the file descriptor was
+    /// passed in from elsewhere. `bytes` must start at the beginning of an
+    /// event. Since the contents of `bytes` can't be trusted, consider calling
+    /// [`Events::checked`] on the returned iterator.
+    ///
+    /// The [`WatchDescriptor`]s of the returned events are not associated with
+    /// any [`Inotify`] instance, so they won't be equal to the ones returned by
+    /// [`Watches::add`]. Use [`Inotify::events_from_bytes`], if that's needed.
+    ///
+    /// [`Inotify`]: crate::Inotify
+    /// [`Inotify::events_from_bytes`]: crate::Inotify::events_from_bytes
+    /// [`Watches::add`]: crate::Watches::add
+    pub fn from_bytes(bytes: &'a [u8]) -> Self {
+        Events::new(Weak::new(), bytes, bytes.len())
+    }
+}
+
+impl<'a> Events<'a> {
+    /// Converts this iterator into one that reports malformed events
+    ///
+    /// [`Events`] panics, if the buffer contains data that can't be parsed as
+    /// an inotify event. This should never happen with data read from the
+    /// kernel, but the returned [`CheckedEvents`] iterator yields a
+    /// [`ParseError`] instead, for those who want to be sure.
+    pub fn checked(self) -> CheckedEvents<'a> {
+        CheckedEvents { events: self }
+    }
+
+    /// Converts this iterator into one that yields [`TypedEvent`]s
+    pub fn typed(self) -> TypedEvents<'a> {
+        TypedEvents { events: self }
+    }
+
+    /// Converts this iterator into one that yields [`EventBuffered`]s
+    ///
+    /// Copies the events that haven't been returned yet into a single shared
+    /// buffer, which the names of all returned events refer to. That's one
+    /// allocation per read, instead of one per event, as with
+    /// [`Event::to_owned`]. See [`BufferName`] for details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use inotify::{EventBuffered, Inotify};
+    ///
+    /// let inotify = Inotify::init()
+    ///     .expect("Failed to initialize an inotify instance");
+    ///
+    /// let mut buffer = [0; 4096];
+    /// let events: Vec<EventBuffered> = match inotify.read_events(&mut buffer) {
+    ///     Ok(events) => events.into_buffered().collect(),
+    ///     Err(_) => Vec::new(),
+    /// };
+    /// ```
+    pub fn into_buffered(self) -> BufferedEvents {
+        BufferedEvents {
+            fd: self.fd.clone(),
+            buffer: Arc::from(self.remaining_bytes()),
+            pos: 0,
+            timestamp: self.timestamp,
+            num_events: self.num_events,
+        }
+    }
+
+    /// Returns the next event without consuming it
+    ///
+    /// The next call to [`Iterator::next`] returns the same event.
+    ///
+    /// # Panics
+    ///
+    /// Panics, if the next event can't be parsed, just like
+    /// [`Iterator::next`] does. Use [`CheckedEvents::peek`] to avoid that.
+    pub fn peek(&self) -> Option<Event<&'a OsStr>> {
+        self.try_peek().map(|result| {
+            result
+                .map(|(_, event)| event)
+                .unwrap_or_else(|error| panic!("Failed to parse inotify event: {}", error))
+        })
+    }
+
+    /// Returns the bytes of the events that haven't been returned yet
+    ///
+    /// The returned slice starts at the beginning of the next event. It can be
+    /// passed to [`Events::from_bytes`], to continue parsing elsewhere.
+    pub fn remaining_bytes(&self) -> &'a [u8] {
+        &self.buffer[self.pos..self.num_bytes]
+    }
+
+    /// Returns the number of bytes of the events that have been returned
+    pub fn consumed(&self) -> usize {
+        self.pos
+    }
+
+    fn try_peek(&self) -> Option<Result<(usize, Event<&'a OsStr>), ParseError>> {
+        if self.pos >= self.num_bytes {
+            return None;
+        }
+
+        Some(Event::from_buffer(
+            self.fd.clone(),
+            &self.buffer[self.pos..self.num_bytes],
+            self.timestamp,
+        ))
+    }
+
+    fn try_next(&mut self) -> Option<Result<Event<&'a OsStr>, ParseError>> {
+        match self.try_peek()? {
+            Ok((step, event)) => {
+                self.pos += step;
+                self.num_events -= 1;
+                Some(Ok(event))
+            }
+            Err(error) => {
+                // There's no way to find the next event after a malformed one.
+                self.pos = self.num_bytes;
+                self.malformed = false;
+                Some(Err(error))
+            }
         }
     }
 }
@@ -40,17 +216,330 @@ impl<'a> Iterator for Events<'a> {
     type Item = Event<&'a OsStr>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.pos < self.num_bytes {
-            let (step, event) = Event::from_buffer(self.fd.clone(), &self.buffer[self.pos..]);
-            self.pos += step;
+        self.try_next().map(|result| {
+            result.unwrap_or_else(|error| panic!("Failed to parse inotify event: {}", error))
+        })
+    }
 
-            Some(event)
-        } else {
-            None
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.num_events, Some(self.num_events))
+    }
+}
+
+impl ExactSizeIterator for Events<'_> {}
+
+/// Summary of the events in a buffer
+pub(crate) struct Scan {
+    /// The number of complete events
+    pub(crate) num_events: usize,
+
+    /// Whether there are bytes left after the complete events, which means
+    /// the buffer contains a malformed event
+    pub(crate) malformed: bool,
+
+    /// Whether one of the events signals a queue overflow
+    pub(crate) overflow: bool,
+}
+
+/// Looks at the events in `buffer`, without fully parsing them
+pub(crate) fn scan(buffer: &[u8]) -> Scan {
+    scan_with(buffer, |_| {})
+}
+
+/// Like [`scan`], but also passes the header of each event to `f`
+fn scan_with<F>(buffer: &[u8], mut f: F) -> Scan
+where
+    F: FnMut(&ffi::inotify_event),
+{
+    let event_size = mem::size_of::<ffi::inotify_event>();
+
+    let mut pos = 0;
+    let mut num_events = 0;
+    let mut overflow = false;
+
+    while buffer.len() - pos >= event_size {
+        // We've made sure that there are enough bytes for an `inotify_event`.
+        let event = read_header(&buffer[pos..]).expect("Checked above");
+
+        let size = event_size.saturating_add(event.len as usize);
+        if buffer.len() - pos < size {
+            break;
+        }
+
+        overflow |= event.mask & ffi::IN_Q_OVERFLOW != 0;
+        f(&event);
+
+        pos += size;
+        num_events += 1;
+    }
+
+    Scan {
+        num_events,
+        malformed: pos < buffer.len(),
+        overflow,
+    }
+}
+
+/// Logs events that signal conditions an application might not notice
+#[cfg(feature = "log")]
+fn log_event(event: &ffi::inotify_event) {
+    if event.mask & ffi::IN_Q_OVERFLOW != 0 {
+        log::warn!("inotify event queue overflowed, events have been lost");
+    }
+    if event.mask & ffi::IN_UNMOUNT != 0 {
+        log::warn!(
+            "File system of inotify watch {} has been unmounted, watch removed",
+            event.wd
+        );
+    } else if event.mask & ffi::IN_IGNORED != 0 {
+        log::debug!("inotify watch {} has been removed", event.wd);
+    }
+}
+
+/// Calls the overflow hook of `fd`, if `buffer` contains an overflow event
+///
+/// Also lets the bookkeeping of `fd` forget the watches that have been removed,
+/// and delivers the events that oneshot watches are waiting for. `handle` and
+/// `timestamp` are the ones of the read. With the `log` feature, this is where
+/// notable events are logged, so each read is only logged once.
+pub(crate) fn after_read(fd: &FdGuard, handle: &FdHandle, buffer: &[u8], timestamp: Timestamp) {
+    #[cfg(feature = "log")]
+    let scan = scan_with(buffer, log_event);
+    #[cfg(not(feature = "log"))]
+    let scan = scan(buffer);
+
+    fd.bookkeeping.forget_ignored(buffer);
+    fd.oneshots.deliver_all(handle, buffer, timestamp);
+    if scan.overflow {
+        fd.overflow_hook.fire();
+    }
+}
+
+// `read_header` relies on `inotify_event` consisting of four 32-bit fields,
+// without any padding.
+const _: () = assert!(mem::size_of::<ffi::inotify_event>() == 4 * mem::size_of::<u32>());
+
+/// Reads the `inotify_event` at the beginning of `buffer`
+///
+/// Returns `None`, if `buffer` doesn't contain a complete `inotify_event`.
+/// The fields are copied out of the buffer one by one, in native byte order,
+/// so the buffer doesn't need to be aligned, and no `unsafe` is required.
+pub(crate) fn read_header(buffer: &[u8]) -> Option<ffi::inotify_event> {
+    let header = buffer.get(..mem::size_of::<ffi::inotify_event>())?;
+    let field = |index: usize| -> [u8; 4] {
+        header[index * 4..(index + 1) * 4]
+            .try_into()
+            .expect("Slice has the size of a field")
+    };
+
+    Some(ffi::inotify_event {
+        wd: c_int::from_ne_bytes(field(0)),
+        mask: u32::from_ne_bytes(field(1)),
+        cookie: u32::from_ne_bytes(field(2)),
+        len: u32::from_ne_bytes(field(3)),
+    })
+}
+
+/// Reads the length of the name from the event at the beginning of `buffer`
+///
+/// Returns `None`, if `buffer` doesn't contain a complete `inotify_event`.
+pub(crate) fn name_len(buffer: &[u8]) -> Option<usize> {
+    read_header(buffer).map(|event| event.len as usize)
+}
+
+/// Appends an event to `buffer`, in the format the kernel uses
+///
+/// The name is padded with null bytes, like the kernel does.
+pub(crate) fn encode(
+    wd: c_int,
+    mask: EventMask,
+    cookie: u32,
+    name: Option<&OsStr>,
+    buffer: &mut Vec<u8>,
+) {
+    let event_size = mem::size_of::<ffi::inotify_event>();
+    let name = name.map_or(&[][..], |name| name.as_bytes());
+    let len = if name.is_empty() {
+        0
+    } else {
+        // Leave room for at least one null byte, and align the next event.
+        (name.len() / event_size + 1) * event_size
+    };
+
+    buffer.extend_from_slice(&wd.to_ne_bytes());
+    buffer.extend_from_slice(&mask.bits().to_ne_bytes());
+    buffer.extend_from_slice(&cookie.to_ne_bytes());
+    buffer.extend_from_slice(&(len as u32).to_ne_bytes());
+    buffer.extend_from_slice(name);
+    buffer.resize(buffer.len() + len - name.len(), 0);
+}
+
+/// Iterator over inotify events that reports malformed events
+///
+/// Returned by [`Events::checked`]. Yields a [`ParseError`] instead of
+/// panicking, if the buffer contains data that isn't a valid event. After an
+/// error, the iterator ends.
+#[derive(Debug)]
+pub struct CheckedEvents<'a> {
+    events: Events<'a>,
+}
+
+impl<'a> CheckedEvents<'a> {
+    /// Returns the next event without consuming it
+    ///
+    /// The next call to [`Iterator::next`] returns the same result.
+    pub fn peek(&self) -> Option<Result<Event<&'a OsStr>, ParseError>> {
+        self.events
+            .try_peek()
+            .map(|result| result.map(|(_, event)| event))
+    }
+}
+
+impl<'a> Iterator for CheckedEvents<'a> {
+    type Item = Result<Event<&'a OsStr>, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.events.try_next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.events.num_events + self.events.malformed as usize;
+        (len, Some(len))
+    }
+}
+
+impl ExactSizeIterator for CheckedEvents<'_> {}
+
+/// Iterator over inotify events, with parsed event masks
+///
+/// Returned by [`Events::typed`]. Queue overflows are yielded as
+/// [`TypedItem::QueueOverflow`]. Yields an [`EventMaskParseError`] for events
+/// whose mask can't be parsed.
+#[derive(Debug)]
+pub struct TypedEvents<'a> {
+    events: Events<'a>,
+}
+
+impl<'a> Iterator for TypedEvents<'a> {
+    type Item = Result<TypedItem<&'a OsStr>, EventMaskParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.events.next().map(Event::into_typed_item)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.events.size_hint()
+    }
+}
+
+impl ExactSizeIterator for TypedEvents<'_> {}
+
+/// Iterator over inotify events that share a buffer
+///
+/// Returned by [`Events::into_buffered`]. Doesn't borrow the buffer that the
+/// events have been read into, so it can be moved elsewhere. Like [`Events`],
+/// this panics, if the buffer contains data that can't be parsed.
+#[derive(Debug)]
+pub struct BufferedEvents {
+    fd: FdHandle,
+    buffer: Arc<[u8]>,
+    pos: usize,
+    timestamp: Timestamp,
+    num_events: usize,
+}
+
+impl Iterator for BufferedEvents {
+    type Item = EventBuffered;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.pos >= self.buffer.len() {
+            return None;
+        }
+
+        let start = self.pos + mem::size_of::<ffi::inotify_event>();
+        let (step, event) =
+            Event::from_buffer(self.fd.clone(), &self.buffer[self.pos..], self.timestamp)
+                .unwrap_or_else(|error| panic!("Failed to parse inotify event: {}", error));
+        let event =
+            event.map_name(|name| BufferName::new(self.buffer.clone(), start, start + name.len()));
+
+        self.pos += step;
+        self.num_events = self.num_events.saturating_sub(1);
+        Some(event)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.num_events, Some(self.num_events))
+    }
+}
+
+impl ExactSizeIterator for BufferedEvents {}
+
+/// An error that occurred while parsing events from a buffer
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ParseError {
+    /// The buffer ends in the middle of an event
+    ///
+    /// `available` is the number of bytes left in the buffer, which is less
+    /// than the size of an `inotify_event` struct.
+    TruncatedEvent {
+        /// The number of bytes left in the buffer
+        available: usize,
+    },
+
+    /// The buffer ends in the middle of an event's name
+    TruncatedName {
+        /// The length of the name, according to the event
+        expected: usize,
+
+        /// The number of bytes left in the buffer after the event
+        available: usize,
+    },
+
+    /// The length of an event's name is larger than any valid name
+    ///
+    /// Only returned by [`EventParser`], which otherwise waits for the rest of
+    /// the name to be fed.
+    ///
+    /// [`EventParser`]: crate::EventParser
+    NameTooLong {
+        /// The length of the name, according to the event
+        len: usize,
+    },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::TruncatedEvent { available } => write!(
+                f,
+                "buffer ends in the middle of an event ({} bytes left, {} needed)",
+                available,
+                mem::size_of::<ffi::inotify_event>(),
+            ),
+            ParseError::TruncatedName {
+                expected,
+                available,
+            } => write!(
+                f,
+                "buffer ends in the middle of an event name ({} bytes left, {} needed)",
+                available, expected,
+            ),
+            ParseError::NameTooLong { len } => {
+                write!(f, "event name is too long ({} bytes)", len)
+            }
         }
     }
 }
 
+impl Error for ParseError {}
+
+impl From<ParseError> for io::Error {
+    fn from(error: ParseError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, error)
+    }
+}
+
 /// An inotify event
 ///
 /// A file system event that describes a change that the user previously
@@ -76,6 +565,9 @@ pub struct Event<S> {
     pub wd: WatchDescriptor,
 
     /// Indicates what kind of event this is
+    ///
+    /// Bits that newer kernels might set, but that [`EventMask`] doesn't know
+    /// about, are retained. Use [`Event::raw_mask`] to inspect them.
     pub mask: EventMask,
 
     /// Connects related events to each other
@@ -94,12 +586,22 @@ pub struct Event<S> {
     /// watched directory. If the event concerns a file or directory that is
     /// watched directly, `name` will be `None`.
     pub name: Option<S>,
+
+    #[cfg(feature = "timestamps")]
+    received_at: Timestamp,
 }
 
 impl<'a> Event<&'a OsStr> {
-    fn new(fd: Weak<FdGuard>, event: &ffi::inotify_event, name: &'a OsStr) -> Self {
-        let mask = EventMask::from_bits(event.mask)
-            .expect("Failed to convert event mask. This indicates a bug.");
+    #[cfg_attr(not(feature = "timestamps"), allow(unused_variables))]
+    fn new(
+        fd: FdHandle,
+        event: &ffi::inotify_event,
+        name: &'a OsStr,
+        timestamp: Timestamp,
+    ) -> Self {
+        // Keep any bits we don't know about, instead of failing. Newer kernels
+        // might add new flags, and those shouldn't break existing code.
+        let mask = EventMask::from_bits_retain(event.mask);
 
         let wd = crate::WatchDescriptor { id: event.wd, fd };
 
@@ -110,42 +612,45 @@ impl<'a> Event<&'a OsStr> {
             mask,
             cookie: event.cookie,
             name,
+            #[cfg(feature = "timestamps")]
+            received_at: timestamp,
         }
     }
 
     /// Create an `Event` from a buffer
     ///
     /// Assumes that a full `inotify_event` plus its name is located at the
-    /// beginning of `buffer`.
+    /// beginning of `buffer`. Returns an error, if that's not the case.
     ///
     /// Returns the number of bytes used from the buffer, and the event.
-    ///
-    /// # Panics
-    ///
-    /// Panics if the buffer does not contain a full event, including its name.
-    pub(crate) fn from_buffer(fd: Weak<FdGuard>, buffer: &'a [u8]) -> (usize, Self) {
+    pub(crate) fn from_buffer(
+        fd: FdHandle,
+        buffer: &'a [u8],
+        timestamp: Timestamp,
+    ) -> Result<(usize, Self), ParseError> {
         let event_size = mem::size_of::<ffi::inotify_event>();
 
         // Make sure that the buffer is big enough to contain an event, without
         // the name. Otherwise we can't safely convert it to an `inotify_event`.
-        assert!(buffer.len() >= event_size);
-
-        let ffi_event_ptr = buffer.as_ptr() as *const ffi::inotify_event;
+        if buffer.len() < event_size {
+            return Err(ParseError::TruncatedEvent {
+                available: buffer.len(),
+            });
+        }
 
-        // We have a pointer to an `inotify_event`, pointing to the beginning of
-        // `buffer`. Since we know, as per the assertion above, that there are
-        // enough bytes in the buffer for at least one event, we can safely
-        // read that `inotify_event`.
-        // We call `read_unaligned()` since the byte buffer has alignment 1
-        // and `inotify_event` has a higher alignment, so `*` cannot be used to dereference
-        // the unaligned pointer (undefined behavior).
-        let ffi_event = unsafe { ffi_event_ptr.read_unaligned() };
+        // We've made sure that there are enough bytes for an `inotify_event`.
+        let ffi_event = read_header(buffer).expect("Checked above");
 
         // The name's length is given by `event.len`. There should always be
         // enough bytes left in the buffer to fit the name. Let's make sure that
         // is the case.
         let bytes_left_in_buffer = buffer.len() - event_size;
-        assert!(bytes_left_in_buffer >= ffi_event.len as usize);
+        if bytes_left_in_buffer < ffi_event.len as usize {
+            return Err(ParseError::TruncatedName {
+                expected: ffi_event.len as usize,
+                available: bytes_left_in_buffer,
+            });
+        }
 
         // Directly after the event struct should be a name, if there's one
         // associated with the event. Let's make a new slice that starts with
@@ -163,9 +668,9 @@ impl<'a> Event<&'a OsStr> {
         // least one result, even if the original slice contains no '\0'.
         let name = name.splitn(2, |b| b == &0u8).next().unwrap();
 
-        let event = Event::new(fd, &ffi_event, OsStr::from_bytes(name));
+        let event = Event::new(fd, &ffi_event, OsStr::from_bytes(name), timestamp);
 
-        (bytes_consumed, event)
+        Ok((bytes_consumed, event))
     }
 
     /// Returns an owned copy of the event.
@@ -183,13 +688,405 @@ impl<'a> Event<&'a OsStr> {
             mask: self.mask,
             cookie: self.cookie,
             name: self.name.map(OsStr::to_os_string),
+            #[cfg(feature = "timestamps")]
+            received_at: self.received_at,
+        }
+    }
+
+    /// Returns a copy of the event, with a name that is cheap to clone
+    ///
+    /// The name is allocated once. Clones of the returned event share it,
+    /// which makes them cheap to hand out to multiple consumers.
+    #[must_use = "cloning is often expensive and is not expected to have side effects"]
+    pub fn to_shared(&self) -> EventShared {
+        Event {
+            wd: self.wd.clone(),
+            mask: self.mask,
+            cookie: self.cookie,
+            name: self.name.map(Arc::from),
+            #[cfg(feature = "timestamps")]
+            received_at: self.received_at,
+        }
+    }
+
+    /// Returns a copy of the event, with a name stored according to `policy`
+    ///
+    /// Like [`Event::to_shared`], but `policy` decides how the name is
+    /// allocated. Pass a [`NameInterner`] to share the allocation between
+    /// events with the same name.
+    ///
+    /// [`NameInterner`]: crate::NameInterner
+    #[must_use = "cloning is often expensive and is not expected to have side effects"]
+    pub fn to_shared_with(&self, policy: &mut impl NamePolicy) -> EventShared {
+        Event {
+            wd: self.wd.clone(),
+            mask: self.mask,
+            cookie: self.cookie,
+            name: self.name.map(|name| policy.name(name)),
+            #[cfg(feature = "timestamps")]
+            received_at: self.received_at,
+        }
+    }
+
+    /// Returns a copy of the event, with a name that is stored inline if short
+    ///
+    /// Most names fit into the event itself and don't need an allocation.
+    /// See [`InlineName`].
+    #[must_use = "cloning is often expensive and is not expected to have side effects"]
+    pub fn to_inline(&self) -> EventInline {
+        Event {
+            wd: self.wd.clone(),
+            mask: self.mask,
+            cookie: self.cookie,
+            name: self.name.map(InlineName::from),
+            #[cfg(feature = "timestamps")]
+            received_at: self.received_at,
         }
     }
 }
 
+impl EventOwned {
+    /// Creates an event that didn't come from the kernel
+    pub(crate) fn synthetic(wd: WatchDescriptor, mask: EventMask, name: Option<OsString>) -> Self {
+        Event {
+            wd,
+            mask,
+            cookie: 0,
+            name,
+            #[cfg(feature = "timestamps")]
+            received_at: Timestamp::now(),
+        }
+    }
+
+    /// Converts the event into one with a name that is cheap to clone
+    ///
+    /// See [`Event::to_shared`].
+    pub fn into_shared(self) -> EventShared {
+        Event {
+            wd: self.wd,
+            mask: self.mask,
+            cookie: self.cookie,
+            name: self.name.map(Arc::from),
+            #[cfg(feature = "timestamps")]
+            received_at: self.received_at,
+        }
+    }
+
+    /// Converts the event into one with a name stored according to `policy`
+    ///
+    /// See [`Event::to_shared_with`].
+    pub fn into_shared_with(self, policy: &mut impl NamePolicy) -> EventShared {
+        self.map_name(|name| policy.name(&name))
+    }
+
+    /// Converts the event into one with a name that is stored inline if short
+    ///
+    /// See [`Event::to_inline`]. Long names keep their existing allocation.
+    pub fn into_inline(self) -> EventInline {
+        self.map_name(InlineName::from)
+    }
+}
+
+impl<S> Event<S> {
+    /// Returns the event mask as it was received from the kernel
+    ///
+    /// This includes any bits that [`EventMask`] doesn't have a constant for.
+    pub fn raw_mask(&self) -> u32 {
+        self.mask.bits()
+    }
+
+    /// Returns a copy of the event that borrows the name
+    pub(crate) fn clone_with_name_ref(&self) -> Event<&OsStr>
+    where
+        S: AsRef<OsStr>,
+    {
+        Event {
+            wd: self.wd.clone(),
+            mask: self.mask,
+            cookie: self.cookie,
+            name: self.name.as_ref().map(AsRef::as_ref),
+            #[cfg(feature = "timestamps")]
+            received_at: self.received_at,
+        }
+    }
+
+    /// Returns a `Watches` instance for the inotify instance this event came from
+    ///
+    /// This allows adding and removing watches in response to an event, for
+    /// example to watch a newly created directory, without having to keep a
+    /// [`Watches`] around. Returns `None`, if the instance has been closed, or
+    /// if the event wasn't read from an instance.
+    pub fn watches(&self) -> Option<Watches> {
+        self.wd.fd.upgrade().map(Watches::new)
+    }
+
+    /// Converts the name of the event, keeping everything else
+    pub(crate) fn map_name<T>(self, f: impl FnOnce(S) -> T) -> Event<T> {
+        Event {
+            wd: self.wd,
+            mask: self.mask,
+            cookie: self.cookie,
+            name: self.name.map(f),
+            #[cfg(feature = "timestamps")]
+            received_at: self.received_at,
+        }
+    }
+
+    /// Parses the event mask
+    ///
+    /// Shorthand for calling [`EventMask::parse`] on [`Event::mask`].
+    pub fn parsed(&self) -> Result<ParsedEventMask, EventMaskParseError> {
+        self.mask.parse()
+    }
+
+    /// Converts the event into a [`TypedEvent`], by parsing its mask
+    ///
+    /// # Errors
+    ///
+    /// Returns the error from [`EventMask::parse`].
+    pub fn into_typed(self) -> Result<TypedEvent<S>, EventMaskParseError> {
+        let parsed = self.parsed()?;
+
+        Ok(TypedEvent {
+            wd: self.wd,
+            kind: parsed.kind,
+            subject: parsed.subject(),
+            flags: parsed.auxiliary_flags,
+            cookie: self.cookie,
+            name: self.name,
+            #[cfg(feature = "timestamps")]
+            received_at: self.received_at,
+        })
+    }
+
+    /// Indicates whether this event signals that the event queue overflowed
+    ///
+    /// If this is the case, events have been lost, and any state that was
+    /// built from previous events can't be trusted anymore. See
+    /// [`Inotify::set_overflow_hook`] for another way to notice this.
+    ///
+    /// [`Inotify::set_overflow_hook`]: crate::Inotify::set_overflow_hook
+    pub fn is_queue_overflow(&self) -> bool {
+        self.mask.contains(EventMask::Q_OVERFLOW)
+    }
+
+    /// Converts the event into a [`TypedItem`], by parsing its mask
+    ///
+    /// Unlike [`Event::into_typed`], this doesn't treat queue overflows as
+    /// errors.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`EventMaskParseError::TooManyBitsSet`], if more than one kind
+    /// of event is set in the mask.
+    pub fn into_typed_item(self) -> Result<TypedItem<S>, EventMaskParseError> {
+        if self.is_queue_overflow() {
+            return Ok(TypedItem::QueueOverflow);
+        }
+
+        self.into_typed().map(TypedItem::Event)
+    }
+
+    /// Returns the point in time at which the event was read from the kernel
+    ///
+    /// All events returned by the same read share the same timestamp.
+    #[cfg(feature = "timestamps")]
+    pub fn received_at(&self) -> Instant {
+        self.received_at.instant
+    }
+
+    /// Returns the system time at which the event was read from the kernel
+    ///
+    /// Unlike [`Event::received_at`], this is suitable for logging, but not
+    /// for measuring durations, as the system time can jump.
+    #[cfg(feature = "timestamps")]
+    pub fn received_at_system_time(&self) -> SystemTime {
+        self.received_at.system_time
+    }
+}
+
+/// Formats the event for logs, like `MODIFY (dir) cookie=0 name="foo" wd=3`
+impl<S> fmt::Display for Event<S>
+where
+    S: AsRef<OsStr>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut mask = self.mask;
+        mask.remove(EventMask::ISDIR);
+        write!(f, "{}", mask)?;
+
+        if self.mask.contains(EventMask::ISDIR) {
+            write!(f, " (dir)")?;
+        }
+
+        write!(f, " cookie={}", self.cookie)?;
+
+        if let Some(name) = &self.name {
+            write!(f, " name={:?}", name.as_ref())?;
+        }
+
+        write!(f, " wd={}", self.wd.id)
+    }
+}
+
+// Equality and hashing are implemented manually, so they only consider what
+// the kernel sent, and not any bookkeeping, like the time of the read.
+impl<S> PartialEq for Event<S>
+where
+    S: PartialEq,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.wd == other.wd
+            && self.mask == other.mask
+            && self.cookie == other.cookie
+            && self.name == other.name
+    }
+}
+
+impl<S> Eq for Event<S> where S: Eq {}
+
+impl<S> Hash for Event<S>
+where
+    S: Hash,
+{
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.wd.hash(state);
+        self.mask.hash(state);
+        self.cookie.hash(state);
+        self.name.hash(state);
+    }
+}
+
 /// An owned version of `Event`
 pub type EventOwned = Event<OsString>;
 
+/// An owned version of `Event`, whose name is cheap to clone
+///
+/// Created by [`Event::to_shared`] or [`Event::into_shared`].
+pub type EventShared = Event<Arc<OsStr>>;
+
+/// An owned version of `Event`, whose name refers to a shared read buffer
+///
+/// Created by [`Events::into_buffered`]. See [`BufferName`].
+pub type EventBuffered = Event<BufferName>;
+
+/// An owned version of `Event`, whose name is stored inline if short
+///
+/// Created by [`Event::to_inline`] or [`Event::into_inline`]. See
+/// [`InlineName`].
+pub type EventInline = Event<InlineName>;
+
+/// An inotify event with a parsed event mask
+///
+/// Like [`Event`], but instead of a raw [`EventMask`], it carries the
+/// [`EventKind`] and [`EventAuxiliaryFlags`]. Created by
+/// [`Event::into_typed`], or by iterating over [`Events::typed`].
+#[derive(Clone, Debug)]
+pub struct TypedEvent<S> {
+    /// Identifies the watch this event originates from
+    ///
+    /// See [`Event::wd`].
+    pub wd: WatchDescriptor,
+
+    /// The kind of event
+    ///
+    /// This is `None` for events that carry only auxiliary flags, like
+    /// [`EventAuxiliaryFlags::IGNORED`].
+    pub kind: Option<EventKind>,
+
+    /// Whether the event concerns a file or a directory
+    pub subject: EventSubject,
+
+    /// Additional information about the event
+    pub flags: EventAuxiliaryFlags,
+
+    /// Connects related events to each other
+    ///
+    /// See [`Event::cookie`].
+    pub cookie: u32,
+
+    /// The name of the file the event originates from
+    ///
+    /// See [`Event::name`].
+    pub name: Option<S>,
+
+    #[cfg(feature = "timestamps")]
+    received_at: Timestamp,
+}
+
+impl TypedEvent<&OsStr> {
+    /// Returns an owned copy of the event.
+    #[must_use = "cloning is often expensive and is not expected to have side effects"]
+    pub fn to_owned(&self) -> TypedEvent<OsString> {
+        TypedEvent {
+            wd: self.wd.clone(),
+            kind: self.kind,
+            subject: self.subject,
+            flags: self.flags,
+            cookie: self.cookie,
+            name: self.name.map(OsStr::to_os_string),
+            #[cfg(feature = "timestamps")]
+            received_at: self.received_at,
+        }
+    }
+}
+
+#[cfg(feature = "timestamps")]
+impl<S> TypedEvent<S> {
+    /// Returns the point in time at which the event was read from the kernel
+    ///
+    /// See [`Event::received_at`].
+    pub fn received_at(&self) -> Instant {
+        self.received_at.instant
+    }
+
+    /// Returns the system time at which the event was read from the kernel
+    ///
+    /// See [`Event::received_at_system_time`].
+    pub fn received_at_system_time(&self) -> SystemTime {
+        self.received_at.system_time
+    }
+}
+
+/// An item read from an inotify instance, with a parsed event mask
+///
+/// Created by [`Event::into_typed_item`], or by iterating over
+/// [`Events::typed`].
+#[derive(Clone, Debug)]
+pub enum TypedItem<S> {
+    /// A regular event
+    Event(TypedEvent<S>),
+
+    /// The event queue overflowed, and events have been lost
+    ///
+    /// Any state that was built from previous events can't be trusted
+    /// anymore, and needs to be rebuilt.
+    QueueOverflow,
+}
+
+/// The point in time at which a read from the kernel completed
+///
+/// Without the `timestamps` feature, this is empty and costs nothing.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Timestamp {
+    #[cfg(feature = "timestamps")]
+    instant: Instant,
+
+    #[cfg(feature = "timestamps")]
+    system_time: SystemTime,
+}
+
+impl Timestamp {
+    pub(crate) fn now() -> Self {
+        Timestamp {
+            #[cfg(feature = "timestamps")]
+            instant: Instant::now(),
+            #[cfg(feature = "timestamps")]
+            system_time: SystemTime::now(),
+        }
+    }
+}
+
 bitflags! {
     /// Indicates the type of an event
     ///
@@ -338,13 +1235,254 @@ impl EventMask {
     }
 }
 
+impl EventMask {
+    /// Parses the mask into an [`EventKind`] and [`EventAuxiliaryFlags`]
+    ///
+    /// # Errors
+    ///
+    /// Returns [`EventMaskParseError::QueueOverflow`], if this is a queue
+    /// overflow event, which doesn't relate to any watch. Returns
+    /// [`EventMaskParseError::TooManyBitsSet`], if more than one kind of event
+    /// is set in the mask, which the kernel never does.
+    pub fn parse(self) -> Result<ParsedEventMask, EventMaskParseError> {
+        ParsedEventMask::from_raw_event_mask(self.bits())
+    }
+}
+
+/// The kind of an inotify event
+///
+/// Each event carries at most one kind. See [`EventMask`] for a description
+/// of each kind.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum EventKind {
+    /// See [`EventMask::ACCESS`]
+    Access,
+
+    /// See [`EventMask::ATTRIB`]
+    Attrib,
+
+    /// See [`EventMask::CLOSE_WRITE`]
+    CloseWrite,
+
+    /// See [`EventMask::CLOSE_NOWRITE`]
+    CloseNowrite,
+
+    /// See [`EventMask::CREATE`]
+    Create,
+
+    /// See [`EventMask::DELETE`]
+    Delete,
+
+    /// See [`EventMask::DELETE_SELF`]
+    DeleteSelf,
+
+    /// See [`EventMask::MODIFY`]
+    Modify,
+
+    /// See [`EventMask::MOVE_SELF`]
+    MoveSelf,
+
+    /// See [`EventMask::MOVED_FROM`]
+    MovedFrom,
+
+    /// See [`EventMask::MOVED_TO`]
+    MovedTo,
+
+    /// See [`EventMask::OPEN`]
+    Open,
+}
+
+impl EventKind {
+    const BITMASK: u32 = ffi::IN_ACCESS
+        | ffi::IN_ATTRIB
+        | ffi::IN_CLOSE_WRITE
+        | ffi::IN_CLOSE_NOWRITE
+        | ffi::IN_CREATE
+        | ffi::IN_DELETE
+        | ffi::IN_DELETE_SELF
+        | ffi::IN_MODIFY
+        | ffi::IN_MOVE_SELF
+        | ffi::IN_MOVED_FROM
+        | ffi::IN_MOVED_TO
+        | ffi::IN_OPEN;
+
+    fn from_raw_event_mask(mask: u32) -> Result<Option<Self>, EventMaskParseError> {
+        let kind = match mask & Self::BITMASK {
+            0 => return Ok(None),
+            ffi::IN_ACCESS => EventKind::Access,
+            ffi::IN_ATTRIB => EventKind::Attrib,
+            ffi::IN_CLOSE_WRITE => EventKind::CloseWrite,
+            ffi::IN_CLOSE_NOWRITE => EventKind::CloseNowrite,
+            ffi::IN_CREATE => EventKind::Create,
+            ffi::IN_DELETE => EventKind::Delete,
+            ffi::IN_DELETE_SELF => EventKind::DeleteSelf,
+            ffi::IN_MODIFY => EventKind::Modify,
+            ffi::IN_MOVE_SELF => EventKind::MoveSelf,
+            ffi::IN_MOVED_FROM => EventKind::MovedFrom,
+            ffi::IN_MOVED_TO => EventKind::MovedTo,
+            ffi::IN_OPEN => EventKind::Open,
+            _ => return Err(EventMaskParseError::TooManyBitsSet(mask)),
+        };
+
+        Ok(Some(kind))
+    }
+
+    /// Returns the bit that represents this kind in event and watch masks
+    pub(crate) fn bit(self) -> u32 {
+        match self {
+            EventKind::Access => ffi::IN_ACCESS,
+            EventKind::Attrib => ffi::IN_ATTRIB,
+            EventKind::CloseWrite => ffi::IN_CLOSE_WRITE,
+            EventKind::CloseNowrite => ffi::IN_CLOSE_NOWRITE,
+            EventKind::Create => ffi::IN_CREATE,
+            EventKind::Delete => ffi::IN_DELETE,
+            EventKind::DeleteSelf => ffi::IN_DELETE_SELF,
+            EventKind::Modify => ffi::IN_MODIFY,
+            EventKind::MoveSelf => ffi::IN_MOVE_SELF,
+            EventKind::MovedFrom => ffi::IN_MOVED_FROM,
+            EventKind::MovedTo => ffi::IN_MOVED_TO,
+            EventKind::Open => ffi::IN_OPEN,
+        }
+    }
+}
+
+impl From<EventKind> for EventMask {
+    fn from(kind: EventKind) -> Self {
+        EventMask::from_bits_retain(kind.bit())
+    }
+}
+
+bitflags! {
+    /// Auxiliary flags of an inotify event
+    ///
+    /// Unlike [`EventKind`], any number of these can be set on an event.
+    #[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Clone, Copy)]
+    pub struct EventAuxiliaryFlags: u32 {
+        /// See [`EventMask::IGNORED`]
+        const IGNORED = ffi::IN_IGNORED;
+
+        /// See [`EventMask::ISDIR`]
+        const ISDIR = ffi::IN_ISDIR;
+
+        /// See [`EventMask::UNMOUNT`]
+        const UNMOUNT = ffi::IN_UNMOUNT;
+    }
+}
+
+/// An event mask, parsed into its kind and auxiliary flags
+///
+/// Returned by [`EventMask::parse`] and [`Event::parsed`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ParsedEventMask {
+    /// The kind of event, if any
+    pub kind: Option<EventKind>,
+
+    /// The auxiliary flags of the event
+    pub auxiliary_flags: EventAuxiliaryFlags,
+}
+
+impl ParsedEventMask {
+    /// Parses a raw event mask, as received from the kernel
+    ///
+    /// See [`EventMask::parse`].
+    pub fn from_raw_event_mask(mask: u32) -> Result<Self, EventMaskParseError> {
+        if mask & ffi::IN_Q_OVERFLOW != 0 {
+            return Err(EventMaskParseError::QueueOverflow);
+        }
+
+        Ok(ParsedEventMask {
+            kind: EventKind::from_raw_event_mask(mask)?,
+            auxiliary_flags: EventAuxiliaryFlags::from_bits_truncate(mask),
+        })
+    }
+
+    /// Returns whether the event concerns a file or a directory
+    ///
+    /// This is derived from [`EventAuxiliaryFlags::ISDIR`].
+    pub fn subject(&self) -> EventSubject {
+        if self.auxiliary_flags.contains(EventAuxiliaryFlags::ISDIR) {
+            EventSubject::Directory
+        } else {
+            EventSubject::File
+        }
+    }
+}
+
+/// The subject of an event
+///
+/// Returned by [`ParsedEventMask::subject`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum EventSubject {
+    /// The event concerns a file, or anything else that isn't a directory
+    File,
+
+    /// The event concerns a directory
+    Directory,
+}
+
+/// An error that occurred while parsing an event mask
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum EventMaskParseError {
+    /// More than one kind of event is set in the mask
+    ///
+    /// Contains the raw mask.
+    TooManyBitsSet(u32),
+
+    /// The event queue overflowed
+    ///
+    /// This is not a problem with the mask itself, but queue overflow events
+    /// don't have a kind or relate to any watch. Events have presumably been
+    /// lost.
+    QueueOverflow,
+}
+
+impl fmt::Display for EventMaskParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EventMaskParseError::TooManyBitsSet(mask) => {
+                write!(f, "more than one event kind is set in mask {:#x}", mask)
+            }
+            EventMaskParseError::QueueOverflow => write!(f, "the event queue overflowed"),
+        }
+    }
+}
+
+impl Error for EventMaskParseError {}
+
+impl From<EventMaskParseError> for io::Error {
+    fn from(error: EventMaskParseError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, error)
+    }
+}
+
+/// Creates an event for use in tests
+#[cfg(test)]
+pub(crate) fn test_event(mask: EventMask, cookie: u32, name: Option<&str>) -> EventOwned {
+    Event {
+        wd: WatchDescriptor {
+            id: 1,
+            fd: FdHandle::for_tests(),
+        },
+        mask,
+        cookie,
+        name: name.map(Into::into),
+        #[cfg(feature = "timestamps")]
+        received_at: Timestamp::now(),
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use std::{io::prelude::*, mem, slice, sync};
+    use std::{ffi::OsStr, io::prelude::*, mem, os::unix::io::FromRawFd, slice, sync};
 
     use inotify_sys as ffi;
 
-    use super::Event;
+    use super::{
+        encode, read_header, Event, EventAuxiliaryFlags, EventKind, EventMask, EventMaskParseError,
+        EventSubject, Events, ParseError, Timestamp,
+    };
+    use crate::events::test_event;
+    use crate::fd_guard::{FdGuard, FdHandle};
 
     #[test]
     fn from_buffer_should_not_mistake_next_event_for_name_of_previous_event() {
@@ -369,7 +1507,215 @@ mod tests {
 
         // Now create the event and verify that the name is actually `None`, as
         // dictated by the value `len` above.
-        let (_, event) = Event::from_buffer(sync::Weak::new(), &buffer);
+        let (_, event) =
+            Event::from_buffer(FdHandle::default(), &buffer, Timestamp::now()).unwrap();
         assert_eq!(event.name, None);
     }
+
+    #[test]
+    fn peek_should_not_consume_the_event() {
+        let mut buffer = Vec::new();
+        for mask in [ffi::IN_MOVED_FROM, ffi::IN_MOVED_TO] {
+            let event = ffi::inotify_event {
+                wd: 0,
+                mask,
+                cookie: 1,
+                len: 0,
+            };
+            let event = unsafe {
+                slice::from_raw_parts(&event as *const _ as *const u8, mem::size_of_val(&event))
+            };
+            buffer.extend_from_slice(event);
+        }
+
+        let mut events = Events::from_bytes(&buffer);
+        assert_eq!(events.peek().unwrap().mask, EventMask::MOVED_FROM);
+        assert_eq!(events.next().unwrap().mask, EventMask::MOVED_FROM);
+        assert_eq!(events.consumed(), buffer.len() / 2);
+        assert_eq!(events.remaining_bytes(), &buffer[buffer.len() / 2..]);
+        assert_eq!(events.peek().unwrap().mask, EventMask::MOVED_TO);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events.next().unwrap().mask, EventMask::MOVED_TO);
+        assert!(events.peek().is_none());
+    }
+
+    #[test]
+    fn checked_events_should_report_truncated_names_instead_of_panicking() {
+        let event = ffi::inotify_event {
+            wd: 0,
+            mask: 0,
+            cookie: 0,
+            len: 16, // name doesn't fit into the buffer below
+        };
+        let buffer = unsafe {
+            slice::from_raw_parts(&event as *const _ as *const u8, mem::size_of_val(&event))
+        };
+
+        let mut events = Events::new(sync::Weak::new(), buffer, buffer.len()).checked();
+        assert_eq!(events.len(), 1);
+        assert!(matches!(
+            events.peek(),
+            Some(Err(ParseError::TruncatedName { .. }))
+        ));
+        assert!(matches!(
+            events.next(),
+            Some(Err(ParseError::TruncatedName {
+                expected: 16,
+                available: 0,
+            }))
+        ));
+        assert!(events.next().is_none());
+    }
+
+    #[test]
+    fn events_should_be_compared_by_their_contents() {
+        let mut buffer = [0u8; 1024];
+
+        let event = ffi::inotify_event {
+            wd: 1,
+            mask: ffi::IN_CREATE,
+            cookie: 0,
+            len: 8,
+        };
+        let event = unsafe {
+            slice::from_raw_parts(&event as *const _ as *const u8, mem::size_of_val(&event))
+        };
+        (&mut buffer[..]).write_all(event).unwrap();
+        (&mut buffer[event.len()..])
+            .write_all(b"file\0\0\0\0")
+            .unwrap();
+
+        // Watch descriptors are only equal, if they belong to the same
+        // instance. They stay equal after that instance has been closed.
+        let fd = sync::Arc::new(unsafe { FdGuard::from_raw_fd(-1) });
+        fd.should_not_close();
+
+        let (_, a) = Event::from_buffer(
+            FdHandle::new(sync::Arc::downgrade(&fd)),
+            &buffer,
+            Timestamp::now(),
+        )
+        .unwrap();
+        let (_, b) = Event::from_buffer(
+            FdHandle::new(sync::Arc::downgrade(&fd)),
+            &buffer,
+            Timestamp::now(),
+        )
+        .unwrap();
+        assert_eq!(a, b);
+        assert_eq!(a.to_owned(), b.to_owned());
+
+        assert_eq!(a.to_shared(), b.to_owned().into_shared());
+
+        let mut c = a.to_owned();
+        c.name = Some("other".into());
+        assert_ne!(a.to_owned(), c);
+    }
+
+    #[test]
+    fn from_buffer_should_retain_unknown_mask_bits() {
+        let unknown_bit = 0x0001_0000;
+
+        let event = ffi::inotify_event {
+            wd: 0,
+            mask: ffi::IN_MODIFY | unknown_bit,
+            cookie: 0,
+            len: 0,
+        };
+        let buffer = unsafe {
+            slice::from_raw_parts(&event as *const _ as *const u8, mem::size_of_val(&event))
+        };
+
+        let (_, event) = Event::from_buffer(FdHandle::default(), buffer, Timestamp::now()).unwrap();
+        assert!(event.mask.contains(EventMask::MODIFY));
+        assert_eq!(event.raw_mask(), ffi::IN_MODIFY | unknown_bit);
+    }
+
+    #[test]
+    fn parse_should_separate_kind_and_auxiliary_flags() {
+        let parsed = (EventMask::CREATE | EventMask::ISDIR).parse().unwrap();
+        assert_eq!(parsed.kind, Some(EventKind::Create));
+        assert_eq!(parsed.auxiliary_flags, EventAuxiliaryFlags::ISDIR);
+        assert_eq!(parsed.subject(), EventSubject::Directory);
+
+        let parsed = EventMask::IGNORED.parse().unwrap();
+        assert_eq!(parsed.kind, None);
+        assert_eq!(parsed.subject(), EventSubject::File);
+        assert_eq!(parsed.auxiliary_flags, EventAuxiliaryFlags::IGNORED);
+    }
+
+    #[test]
+    fn event_kinds_should_convert_back_into_masks() {
+        let mask = EventMask::MOVED_FROM | EventMask::ISDIR;
+        let kind = mask.parse().unwrap().kind.unwrap();
+        assert_eq!(EventMask::from(kind), EventMask::MOVED_FROM);
+    }
+
+    #[test]
+    fn read_header_should_handle_unaligned_and_short_buffers() {
+        let mut buffer = vec![0xff];
+        encode(
+            7,
+            EventMask::CREATE,
+            42,
+            Some(OsStr::new("file")),
+            &mut buffer,
+        );
+
+        // The event starts at an odd offset, so it is never aligned.
+        let event = read_header(&buffer[1..]).unwrap();
+        assert_eq!(event.wd, 7);
+        assert_eq!(event.mask, EventMask::CREATE.bits());
+        assert_eq!(event.cookie, 42);
+        assert_eq!(
+            event.len as usize,
+            buffer.len() - 1 - mem::size_of_val(&event)
+        );
+
+        assert!(read_header(&buffer[1..mem::size_of_val(&event)]).is_none());
+    }
+
+    #[test]
+    fn events_from_one_read_should_share_a_handle() {
+        let fd = sync::Arc::new(unsafe { FdGuard::from_raw_fd(-1) });
+        fd.should_not_close();
+
+        let mut buffer = Vec::new();
+        encode(1, EventMask::MODIFY, 0, None, &mut buffer);
+        encode(2, EventMask::MODIFY, 0, None, &mut buffer);
+
+        let events = Events::new(sync::Arc::downgrade(&fd), &buffer, buffer.len());
+        let events = events.collect::<Vec<_>>();
+        assert_eq!(sync::Arc::weak_count(&fd), 1);
+        assert!(events.iter().all(|event| event.watches().is_some()));
+
+        drop(events);
+        assert_eq!(sync::Arc::weak_count(&fd), 0);
+    }
+
+    #[test]
+    fn events_should_be_displayed_in_a_compact_form() {
+        let event = test_event(EventMask::MODIFY | EventMask::ISDIR, 0, Some("foo.txt"));
+        assert_eq!(
+            event.to_string(),
+            r#"MODIFY (dir) cookie=0 name="foo.txt" wd=1"#
+        );
+
+        let event = test_event(EventMask::MOVED_FROM, 5, None);
+        assert_eq!(event.to_string(), "MOVED_FROM cookie=5 wd=1");
+    }
+
+    #[test]
+    fn parse_should_reject_invalid_masks() {
+        assert_eq!(
+            EventMask::Q_OVERFLOW.parse(),
+            Err(EventMaskParseError::QueueOverflow)
+        );
+
+        let mask = EventMask::CREATE | EventMask::DELETE;
+        assert_eq!(
+            mask.parse(),
+            Err(EventMaskParseError::TooManyBitsSet(mask.bits()))
+        );
+    }
 }
@@ -0,0 +1,549 @@
+use std::{
+    fs, io, mem,
+    os::unix::io::{AsFd, AsRawFd, BorrowedFd, FromRawFd, OwnedFd, RawFd},
+    path::PathBuf,
+    ptr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    vec,
+};
+
+use super::fid::Fid;
+
+bitflags! {
+    /// Indicates what kind of event has occurred
+    ///
+    /// See [`MarkMask`] for a description of the events. A single event can
+    /// have several of them set, if the kernel merged events for the same
+    /// file.
+    ///
+    /// [`MarkMask`]: super::MarkMask
+    #[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Clone, Copy)]
+    pub struct EventMask: u64 {
+        /// See [`MarkMask::ACCESS`](super::MarkMask::ACCESS)
+        const ACCESS = libc::FAN_ACCESS;
+
+        /// See [`MarkMask::MODIFY`](super::MarkMask::MODIFY)
+        const MODIFY = libc::FAN_MODIFY;
+
+        /// See [`MarkMask::ATTRIB`](super::MarkMask::ATTRIB)
+        const ATTRIB = libc::FAN_ATTRIB;
+
+        /// See [`MarkMask::CLOSE_WRITE`](super::MarkMask::CLOSE_WRITE)
+        const CLOSE_WRITE = libc::FAN_CLOSE_WRITE;
+
+        /// See [`MarkMask::CLOSE_NOWRITE`](super::MarkMask::CLOSE_NOWRITE)
+        const CLOSE_NOWRITE = libc::FAN_CLOSE_NOWRITE;
+
+        /// See [`MarkMask::OPEN`](super::MarkMask::OPEN)
+        const OPEN = libc::FAN_OPEN;
+
+        /// See [`MarkMask::OPEN_EXEC`](super::MarkMask::OPEN_EXEC)
+        const OPEN_EXEC = libc::FAN_OPEN_EXEC;
+
+        /// See [`MarkMask::MOVED_FROM`](super::MarkMask::MOVED_FROM)
+        const MOVED_FROM = libc::FAN_MOVED_FROM;
+
+        /// See [`MarkMask::MOVED_TO`](super::MarkMask::MOVED_TO)
+        const MOVED_TO = libc::FAN_MOVED_TO;
+
+        /// See [`MarkMask::CREATE`](super::MarkMask::CREATE)
+        const CREATE = libc::FAN_CREATE;
+
+        /// See [`MarkMask::DELETE`](super::MarkMask::DELETE)
+        const DELETE = libc::FAN_DELETE;
+
+        /// See [`MarkMask::DELETE_SELF`](super::MarkMask::DELETE_SELF)
+        const DELETE_SELF = libc::FAN_DELETE_SELF;
+
+        /// See [`MarkMask::MOVE_SELF`](super::MarkMask::MOVE_SELF)
+        const MOVE_SELF = libc::FAN_MOVE_SELF;
+
+        /// See [`MarkMask::RENAME`](super::MarkMask::RENAME)
+        const RENAME = libc::FAN_RENAME;
+
+        /// See [`MarkMask::OPEN_PERM`](super::MarkMask::OPEN_PERM)
+        const OPEN_PERM = libc::FAN_OPEN_PERM;
+
+        /// See [`MarkMask::ACCESS_PERM`](super::MarkMask::ACCESS_PERM)
+        const ACCESS_PERM = libc::FAN_ACCESS_PERM;
+
+        /// See [`MarkMask::OPEN_EXEC_PERM`](super::MarkMask::OPEN_EXEC_PERM)
+        const OPEN_EXEC_PERM = libc::FAN_OPEN_EXEC_PERM;
+
+        /// The event queue overflowed
+        ///
+        /// Events have been lost. Such an event doesn't carry a file
+        /// descriptor.
+        ///
+        /// See [`libc::FAN_Q_OVERFLOW`].
+        const Q_OVERFLOW = libc::FAN_Q_OVERFLOW;
+
+        /// A file system error was detected
+        ///
+        /// See [`libc::FAN_FS_ERROR`].
+        const FS_ERROR = libc::FAN_FS_ERROR;
+
+        /// The event is about a directory
+        ///
+        /// See [`libc::FAN_ONDIR`].
+        const ONDIR = libc::FAN_ONDIR;
+    }
+}
+
+/// A fanotify event
+///
+/// Unlike an inotify event, a fanotify event doesn't carry the name of the
+/// file it is about. It carries an open file descriptor for it instead, which
+/// is closed when the event is dropped. Instances that report file
+/// identifiers carry those instead of a file descriptor.
+///
+/// A permission request that is dropped without having been answered using
+/// [`Fanotify::respond`] is answered with the default response of the
+/// instance, so the process that caused it doesn't stay blocked. See
+/// [`Fanotify::set_default_response`].
+///
+/// [`Fanotify::respond`]: super::Fanotify::respond
+/// [`Fanotify::set_default_response`]: super::Fanotify::set_default_response
+#[derive(Debug)]
+pub struct Event {
+    /// Indicates what kind of event this is
+    pub mask: EventMask,
+
+    /// The ID of the process that caused the event
+    ///
+    /// This is the ID of the thread instead, if
+    /// [`InitFlags::REPORT_TID`](super::InitFlags::REPORT_TID) has been
+    /// passed.
+    pub pid: i32,
+
+    fd: Option<OwnedFd>,
+    pidfd: Option<OwnedFd>,
+    fids: Vec<Fid>,
+    request: Option<Request>,
+}
+
+impl Event {
+    /// Returns the file descriptor for the file the event is about
+    ///
+    /// Returns `None` for queue overflow events.
+    pub fn fd(&self) -> Option<BorrowedFd<'_>> {
+        self.fd.as_ref().map(AsFd::as_fd)
+    }
+
+    /// Takes the file descriptor out of the event
+    ///
+    /// Use this to keep the file open after the event has been dropped. A
+    /// permission request that hasn't been answered yet is answered with the
+    /// default response first.
+    pub fn into_fd(mut self) -> Option<OwnedFd> {
+        self.answer_default();
+        self.fd.take()
+    }
+
+    /// Returns a process file descriptor for the process that caused the event
+    ///
+    /// Only available if the instance has been initialized with
+    /// [`InitFlags::REPORT_PIDFD`]. Unlike [`Event::pid`], it can't refer to
+    /// another process that reused the ID after the original one exited.
+    /// `None`, if the process has exited before the event was read.
+    ///
+    /// [`InitFlags::REPORT_PIDFD`]: super::InitFlags::REPORT_PIDFD
+    pub fn pidfd(&self) -> Option<BorrowedFd<'_>> {
+        self.pidfd.as_ref().map(AsFd::as_fd)
+    }
+
+    /// Returns the real user ID of the process that caused the event
+    ///
+    /// fanotify doesn't report user IDs, so this is looked up in
+    /// `/proc/<pid>/status`. The process might have exited since the event
+    /// occurred, and its ID might have been reused by another process. If
+    /// the instance reports process file descriptors, this returns an error
+    /// in that case.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error from reading the status of the process. Returns an
+    /// error with [`ErrorKind::NotFound`], if the process has exited.
+    ///
+    /// [`ErrorKind::NotFound`]: std::io::ErrorKind::NotFound
+    pub fn uid(&self) -> io::Result<u32> {
+        let status = fs::read_to_string(format!("/proc/{}/status", self.pid))?;
+
+        // Make sure the status we read is from the process that caused the
+        // event, and not from one that reused its ID.
+        if let Some(pidfd) = &self.pidfd {
+            let result = unsafe {
+                libc::syscall(
+                    libc::SYS_pidfd_send_signal,
+                    pidfd.as_raw_fd(),
+                    0,
+                    ptr::null::<libc::siginfo_t>(),
+                    0,
+                )
+            };
+            if result == -1 {
+                return Err(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    "Process has exited",
+                ));
+            }
+        }
+
+        status
+            .lines()
+            .find_map(|line| line.strip_prefix("Uid:"))
+            .and_then(|uids| uids.split_whitespace().next())
+            .and_then(|uid| uid.parse().ok())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Invalid process status"))
+    }
+
+    /// Returns the file identifiers of the event
+    ///
+    /// Empty, unless the instance has been initialized with one of the
+    /// `REPORT_*FID*` flags of [`InitFlags`]. Rename events carry one for the
+    /// old and one for the new directory entry.
+    ///
+    /// [`InitFlags`]: super::InitFlags
+    pub fn fids(&self) -> &[Fid] {
+        &self.fids
+    }
+
+    /// Returns the path of the file the event is about
+    ///
+    /// The path is looked up using the file descriptor. The file might have
+    /// been moved or deleted since the event occurred.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error with [`ErrorKind::NotFound`], if the event doesn't
+    /// carry a file descriptor, or the error from looking up the path.
+    ///
+    /// [`ErrorKind::NotFound`]: std::io::ErrorKind::NotFound
+    pub fn path(&self) -> io::Result<PathBuf> {
+        let fd = self.fd.as_ref().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, "Event has no file descriptor")
+        })?;
+
+        fs::read_link(format!("/proc/self/fd/{}", fd.as_raw_fd()))
+    }
+
+    /// Indicates whether the event queue overflowed
+    pub fn is_queue_overflow(&self) -> bool {
+        self.mask.contains(EventMask::Q_OVERFLOW)
+    }
+
+    /// Indicates whether the event is a permission request
+    ///
+    /// Permission requests must be answered using [`Fanotify::respond`].
+    ///
+    /// [`Fanotify::respond`]: super::Fanotify::respond
+    pub fn is_permission_request(&self) -> bool {
+        self.mask
+            .intersects(EventMask::OPEN_PERM | EventMask::ACCESS_PERM | EventMask::OPEN_EXEC_PERM)
+    }
+
+    /// Answers the permission request with the default response, unless it
+    /// has been answered already
+    fn answer_default(&self) {
+        if let (Some(request), Some(fd)) = (&self.request, &self.fd) {
+            if !request.answered.swap(true, Ordering::AcqRel) {
+                // There's nobody to report an error to.
+                let _ = write_response(
+                    request.fanotify.as_raw_fd(),
+                    fd.as_raw_fd(),
+                    request.default,
+                );
+            }
+        }
+    }
+}
+
+impl Drop for Event {
+    fn drop(&mut self) {
+        self.answer_default();
+    }
+}
+
+/// A permission request that still needs to be answered
+#[derive(Debug)]
+struct Request {
+    fanotify: Arc<OwnedFd>,
+    default: Response,
+    answered: AtomicBool,
+}
+
+/// The answer to a permission request
+///
+/// Passed to [`Fanotify::respond`].
+///
+/// [`Fanotify::respond`]: super::Fanotify::respond
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Response {
+    /// Allow the access
+    ///
+    /// See [`libc::FAN_ALLOW`].
+    Allow,
+
+    /// Deny the access
+    ///
+    /// The process that requested it gets an `EPERM` error.
+    ///
+    /// See [`libc::FAN_DENY`].
+    Deny,
+}
+
+/// Answers the permission request `event` on the fanotify instance `fd`
+pub(crate) fn respond(fd: RawFd, event: &Event, response: Response) -> io::Result<()> {
+    let event_fd = event.fd.as_ref().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Event is not a permission request",
+        )
+    })?;
+
+    write_response(fd, event_fd.as_raw_fd(), response)?;
+    if let Some(request) = &event.request {
+        request.answered.store(true, Ordering::Release);
+    }
+
+    Ok(())
+}
+
+fn write_response(fd: RawFd, event_fd: RawFd, response: Response) -> io::Result<()> {
+    let response = libc::fanotify_response {
+        fd: event_fd,
+        response: match response {
+            Response::Allow => libc::FAN_ALLOW,
+            Response::Deny => libc::FAN_DENY,
+        },
+    };
+    let num_bytes = unsafe {
+        libc::write(
+            fd,
+            &response as *const _ as *const libc::c_void,
+            mem::size_of::<libc::fanotify_response>(),
+        )
+    };
+    if num_bytes == -1 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(())
+}
+
+/// Iterator over fanotify events
+///
+/// Returned by [`Fanotify::read_events_blocking`] and
+/// [`Fanotify::read_events`]. Events that are not consumed are dropped along
+/// with the iterator, which closes their file descriptors, and answers their
+/// permission requests with the default response.
+///
+/// [`Fanotify::read_events_blocking`]: super::Fanotify::read_events_blocking
+/// [`Fanotify::read_events`]: super::Fanotify::read_events
+#[derive(Debug)]
+pub struct Events {
+    events: vec::IntoIter<Event>,
+}
+
+impl Events {
+    /// Parses the events that have been read into `bytes` from `fanotify`
+    ///
+    /// Takes ownership of the file descriptors of all events. Permission
+    /// requests are answered with `default`, if they are dropped without an
+    /// answer, including when parsing fails.
+    pub(crate) fn parse(
+        bytes: &[u8],
+        fanotify: &Arc<OwnedFd>,
+        default: Response,
+    ) -> io::Result<Self> {
+        let mut events = Vec::new();
+        let mut error = None;
+        let mut offset = 0;
+
+        while bytes.len() - offset >= mem::size_of::<libc::fanotify_event_metadata>() {
+            // The buffer has no particular alignment.
+            let metadata = unsafe {
+                ptr::read_unaligned(bytes[offset..].as_ptr() as *const libc::fanotify_event_metadata)
+            };
+            let event_len = metadata.event_len as usize;
+            let metadata_len = metadata.metadata_len as usize;
+
+            // Without valid metadata, there's no telling where the next event
+            // starts, or whether the file descriptor is one.
+            if metadata.vers != libc::FANOTIFY_METADATA_VERSION
+                || metadata_len < mem::size_of::<libc::fanotify_event_metadata>()
+                || event_len < metadata_len
+                || event_len > bytes.len() - offset
+            {
+                return Err(unsupported_format());
+            }
+
+            // Create the event, even if its info records can't be parsed, so
+            // the file descriptor is closed, and a permission request
+            // answered.
+            let mut event = Event {
+                mask: EventMask::from_bits_retain(metadata.mask),
+                pid: metadata.pid,
+                fd: match metadata.fd {
+                    libc::FAN_NOFD => None,
+                    fd => Some(unsafe { OwnedFd::from_raw_fd(fd) }),
+                },
+                pidfd: None,
+                fids: Vec::new(),
+                request: None,
+            };
+            if event.is_permission_request() {
+                event.request = Some(Request {
+                    fanotify: fanotify.clone(),
+                    default,
+                    answered: AtomicBool::new(false),
+                });
+            }
+
+            let info = &bytes[offset + metadata_len..offset + event_len];
+            if let Err(err) = parse_info(&mut event, info) {
+                error.get_or_insert(err);
+            }
+
+            events.push(event);
+
+            offset += event_len;
+        }
+
+        // Dropping the events answers their permission requests.
+        if let Some(error) = error {
+            return Err(error);
+        }
+
+        Ok(Events {
+            events: events.into_iter(),
+        })
+    }
+}
+
+/// Parses the info records of `event`
+fn parse_info(event: &mut Event, mut info: &[u8]) -> io::Result<()> {
+    while !info.is_empty() {
+        let header_len = mem::size_of::<libc::fanotify_event_info_header>();
+        if info.len() < header_len {
+            return Err(unsupported_format());
+        }
+        let len = u16::from_ne_bytes([info[2], info[3]]) as usize;
+        if len < header_len || len > info.len() {
+            return Err(unsupported_format());
+        }
+
+        let (record, rest) = info.split_at(len);
+        if record[0] == libc::FAN_EVENT_INFO_TYPE_PIDFD {
+            if record.len() < header_len + 4 {
+                return Err(unsupported_format());
+            }
+            let mut fd = [0; 4];
+            fd.copy_from_slice(&record[header_len..header_len + 4]);
+
+            // Negative values indicate that there is no process file
+            // descriptor, for example because the process has exited.
+            event.pidfd = match i32::from_ne_bytes(fd) {
+                fd if fd < 0 => None,
+                fd => Some(unsafe { OwnedFd::from_raw_fd(fd) }),
+            };
+        } else if let Some(fid) = Fid::parse(record)? {
+            event.fids.push(fid);
+        }
+        info = rest;
+    }
+
+    Ok(())
+}
+
+fn unsupported_format() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        "Unsupported fanotify event format",
+    )
+}
+
+impl Iterator for Events {
+    type Item = Event;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.events.next()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs::File,
+        io::{self, Read},
+        mem,
+        os::unix::io::{FromRawFd, IntoRawFd, OwnedFd},
+        ptr, slice,
+        sync::Arc,
+    };
+
+    use super::{Events, Response};
+
+    fn event(mask: u64, fd: i32, info: &[u8]) -> Vec<u8> {
+        let metadata_len = mem::size_of::<libc::fanotify_event_metadata>();
+        let mut metadata: libc::fanotify_event_metadata = unsafe { mem::zeroed() };
+        metadata.event_len = (metadata_len + info.len()) as u32;
+        metadata.vers = libc::FANOTIFY_METADATA_VERSION;
+        metadata.metadata_len = metadata_len as u16;
+        metadata.mask = mask;
+        metadata.fd = fd;
+        metadata.pid = 1;
+
+        let mut bytes =
+            unsafe { slice::from_raw_parts(ptr::addr_of!(metadata) as *const u8, metadata_len) }
+                .to_vec();
+        bytes.extend_from_slice(info);
+        bytes
+    }
+
+    #[test]
+    fn parse_should_answer_requests_after_a_corrupt_event() {
+        let mut fds = [0; 2];
+        assert_eq!(unsafe { libc::pipe(fds.as_mut_ptr()) }, 0);
+        let mut responses = unsafe { File::from_raw_fd(fds[0]) };
+        let fanotify = Arc::new(unsafe { OwnedFd::from_raw_fd(fds[1]) });
+
+        let first = File::open("/dev/null").unwrap().into_raw_fd();
+        let second = File::open("/dev/null").unwrap().into_raw_fd();
+        let third = File::open("/dev/null").unwrap().into_raw_fd();
+
+        let mut bytes = event(libc::FAN_OPEN_PERM, first, &[]);
+        // The length in the info header is shorter than the header itself.
+        bytes.extend(event(libc::FAN_OPEN_PERM, second, &[0, 0, 2, 0]));
+        bytes.extend(event(libc::FAN_OPEN_PERM, third, &[]));
+
+        let error = Events::parse(&bytes, &fanotify, Response::Deny).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        drop(fanotify);
+        let mut answers = Vec::new();
+        responses.read_to_end(&mut answers).unwrap();
+
+        let len = mem::size_of::<libc::fanotify_response>();
+        let answers = answers
+            .chunks(len)
+            .map(|answer| {
+                let answer = unsafe {
+                    ptr::read_unaligned(answer.as_ptr() as *const libc::fanotify_response)
+                };
+                (answer.fd, answer.response)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            answers,
+            vec![
+                (first, libc::FAN_DENY),
+                (second, libc::FAN_DENY),
+                (third, libc::FAN_DENY),
+            ]
+        );
+    }
+}
@@ -0,0 +1,313 @@
+use std::{
+    ffi::{OsStr, OsString},
+    fs, io, mem,
+    os::unix::{
+        ffi::OsStrExt,
+        io::{AsRawFd, BorrowedFd, FromRawFd, OwnedFd},
+    },
+    path::PathBuf,
+    ptr,
+};
+
+/// Size of the header of an info record
+const HEADER_LEN: usize = mem::size_of::<libc::fanotify_event_info_header>();
+
+/// Size of the part of a file identifier record that precedes the handle
+const FID_LEN: usize = mem::size_of::<libc::fanotify_event_info_fid>();
+
+/// Size of the part of a file handle that precedes its bytes
+const HANDLE_LEN: usize = mem::size_of::<libc::file_handle>();
+
+/// Indicates what a file identifier refers to
+///
+/// See [`Fid`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FidKind {
+    /// The file or directory the event is about
+    ///
+    /// See [`libc::FAN_EVENT_INFO_TYPE_FID`].
+    Fid,
+
+    /// The directory that contains the file or directory the event is about
+    ///
+    /// See [`libc::FAN_EVENT_INFO_TYPE_DFID`].
+    Dfid,
+
+    /// The directory that contains the entry the event is about, and the
+    /// entry's name
+    ///
+    /// See [`libc::FAN_EVENT_INFO_TYPE_DFID_NAME`].
+    DfidName,
+
+    /// The directory and name an entry has been moved from
+    ///
+    /// See [`libc::FAN_EVENT_INFO_TYPE_OLD_DFID_NAME`].
+    OldDfidName,
+
+    /// The directory and name an entry has been moved to
+    ///
+    /// See [`libc::FAN_EVENT_INFO_TYPE_NEW_DFID_NAME`].
+    NewDfidName,
+}
+
+impl FidKind {
+    fn from_info_type(info_type: u8) -> Option<Self> {
+        match info_type {
+            libc::FAN_EVENT_INFO_TYPE_FID => Some(FidKind::Fid),
+            libc::FAN_EVENT_INFO_TYPE_DFID => Some(FidKind::Dfid),
+            libc::FAN_EVENT_INFO_TYPE_DFID_NAME => Some(FidKind::DfidName),
+            libc::FAN_EVENT_INFO_TYPE_OLD_DFID_NAME => Some(FidKind::OldDfidName),
+            libc::FAN_EVENT_INFO_TYPE_NEW_DFID_NAME => Some(FidKind::NewDfidName),
+            _ => None,
+        }
+    }
+
+    fn has_name(self) -> bool {
+        matches!(
+            self,
+            FidKind::DfidName | FidKind::OldDfidName | FidKind::NewDfidName
+        )
+    }
+}
+
+/// A file identifier
+///
+/// Instances that have been initialized with one of the `REPORT_*FID*` flags
+/// of [`InitFlags`] identify files using file handles, instead of open file
+/// descriptors. Unlike file descriptors, file handles are also available for
+/// files that have been deleted, which makes events like
+/// [`EventMask::CREATE`] and [`EventMask::DELETE`] possible.
+///
+/// [`InitFlags`]: super::InitFlags
+/// [`EventMask::CREATE`]: super::EventMask::CREATE
+/// [`EventMask::DELETE`]: super::EventMask::DELETE
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Fid {
+    /// Indicates what the file identifier refers to
+    pub kind: FidKind,
+
+    /// The ID of the file system that contains the file
+    pub fsid: [i32; 2],
+
+    /// The file handle
+    pub handle: FileHandle,
+
+    /// The name of the directory entry
+    ///
+    /// Only set for the kinds that carry a name, and only if the entry
+    /// has one. It is `None` if the event is about the directory itself.
+    pub name: Option<OsString>,
+}
+
+impl Fid {
+    /// Parses an info record, if it is a file identifier
+    ///
+    /// `bytes` contains the whole record, including its header. Returns
+    /// `Ok(None)` for other kinds of records.
+    pub(crate) fn parse(bytes: &[u8]) -> io::Result<Option<Self>> {
+        let kind = match FidKind::from_info_type(bytes[0]) {
+            Some(kind) => kind,
+            None => return Ok(None),
+        };
+
+        if bytes.len() < FID_LEN + HANDLE_LEN {
+            return Err(invalid_data());
+        }
+        let fsid = [read_i32(bytes, HEADER_LEN), read_i32(bytes, HEADER_LEN + 4)];
+
+        let handle_bytes = read_i32(bytes, FID_LEN) as u32 as usize;
+        let handle_type = read_i32(bytes, FID_LEN + 4);
+        let handle_start = FID_LEN + HANDLE_LEN;
+        let handle_end = handle_start
+            .checked_add(handle_bytes)
+            .filter(|&end| end <= bytes.len())
+            .ok_or_else(invalid_data)?;
+
+        let name = if kind.has_name() {
+            let name = &bytes[handle_end..];
+            let name = &name[..name.iter().position(|&b| b == 0).unwrap_or(name.len())];
+
+            // A name of "." stands for the directory itself.
+            if name.is_empty() || name == b"." {
+                None
+            } else {
+                Some(OsStr::from_bytes(name).to_owned())
+            }
+        } else {
+            None
+        };
+
+        Ok(Some(Fid {
+            kind,
+            fsid,
+            handle: FileHandle {
+                handle_type,
+                bytes: bytes[handle_start..handle_end].to_vec(),
+            },
+            name,
+        }))
+    }
+
+    /// Returns the path of the file the identifier refers to
+    ///
+    /// Resolves the handle using [`FileHandle::path`], then appends the name,
+    /// if there is one.
+    ///
+    /// # Errors
+    ///
+    /// See [`FileHandle::path`].
+    pub fn path(&self, mount_fd: BorrowedFd<'_>) -> io::Result<PathBuf> {
+        let mut path = self.handle.path(mount_fd)?;
+        if let Some(name) = &self.name {
+            path.push(name);
+        }
+
+        Ok(path)
+    }
+}
+
+/// A file handle
+///
+/// Identifies a file within a file system. See [`Fid`].
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct FileHandle {
+    handle_type: i32,
+    bytes: Vec<u8>,
+}
+
+impl FileHandle {
+    /// Returns the type of the file handle
+    ///
+    /// The type is specific to the file system.
+    pub fn handle_type(&self) -> i32 {
+        self.handle_type
+    }
+
+    /// Returns the opaque bytes of the file handle
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Opens the file the handle refers to
+    ///
+    /// `mount_fd` can be any open file or directory on the file system the
+    /// handle belongs to. `flags` are passed on to [`open_by_handle_at`];
+    /// [`O_CLOEXEC`] is always added.
+    ///
+    /// # Errors
+    ///
+    /// Directly returns the error from the call to [`open_by_handle_at`].
+    /// Without the `CAP_DAC_READ_SEARCH` capability, this is an error with
+    /// [`ErrorKind::PermissionDenied`]. If the file has been deleted in the
+    /// meantime, the error is `ESTALE`.
+    ///
+    /// [`open_by_handle_at`]: libc::open_by_handle_at
+    /// [`O_CLOEXEC`]: libc::O_CLOEXEC
+    /// [`ErrorKind::PermissionDenied`]: std::io::ErrorKind::PermissionDenied
+    pub fn open(&self, mount_fd: BorrowedFd<'_>, flags: i32) -> io::Result<OwnedFd> {
+        // `file_handle` has to be aligned, so build it in a buffer of words.
+        let len = HANDLE_LEN + self.bytes.len();
+        let mut buffer = vec![0u32; (len + 3) / 4];
+        let handle = buffer.as_mut_ptr() as *mut libc::file_handle;
+
+        let fd = unsafe {
+            (*handle).handle_bytes = self.bytes.len() as libc::c_uint;
+            (*handle).handle_type = self.handle_type;
+            ptr::copy_nonoverlapping(
+                self.bytes.as_ptr(),
+                (handle as *mut u8).add(HANDLE_LEN),
+                self.bytes.len(),
+            );
+
+            libc::open_by_handle_at(mount_fd.as_raw_fd(), handle, flags | libc::O_CLOEXEC)
+        };
+        if fd == -1 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(unsafe { OwnedFd::from_raw_fd(fd) })
+    }
+
+    /// Returns the path of the file the handle refers to
+    ///
+    /// Opens the file using [`FileHandle::open`] with [`O_PATH`], then looks
+    /// up the path of the file descriptor.
+    ///
+    /// # Errors
+    ///
+    /// See [`FileHandle::open`].
+    ///
+    /// [`O_PATH`]: libc::O_PATH
+    pub fn path(&self, mount_fd: BorrowedFd<'_>) -> io::Result<PathBuf> {
+        let fd = self.open(mount_fd, libc::O_PATH)?;
+        fs::read_link(format!("/proc/self/fd/{}", fd.as_raw_fd()))
+    }
+}
+
+fn read_i32(bytes: &[u8], offset: usize) -> i32 {
+    let mut value = [0; 4];
+    value.copy_from_slice(&bytes[offset..offset + 4]);
+    i32::from_ne_bytes(value)
+}
+
+fn invalid_data() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        "Invalid fanotify file identifier",
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::OsString;
+
+    use super::{Fid, FidKind};
+
+    fn record(info_type: u8, handle: &[u8], name: &[u8]) -> Vec<u8> {
+        let mut bytes = vec![info_type, 0, 0, 0];
+        bytes.extend_from_slice(&1i32.to_ne_bytes());
+        bytes.extend_from_slice(&2i32.to_ne_bytes());
+        bytes.extend_from_slice(&(handle.len() as u32).to_ne_bytes());
+        bytes.extend_from_slice(&7i32.to_ne_bytes());
+        bytes.extend_from_slice(handle);
+        bytes.extend_from_slice(name);
+
+        let len = bytes.len() as u16;
+        bytes[2..4].copy_from_slice(&len.to_ne_bytes());
+        bytes
+    }
+
+    #[test]
+    fn parse_should_read_handle_and_name() {
+        let bytes = record(
+            libc::FAN_EVENT_INFO_TYPE_DFID_NAME,
+            &[1, 2, 3],
+            b"file\0\0\0",
+        );
+        let fid = Fid::parse(&bytes).unwrap().unwrap();
+
+        assert_eq!(fid.kind, FidKind::DfidName);
+        assert_eq!(fid.fsid, [1, 2]);
+        assert_eq!(fid.handle.handle_type(), 7);
+        assert_eq!(fid.handle.as_bytes(), &[1, 2, 3]);
+        assert_eq!(fid.name, Some(OsString::from("file")));
+
+        let bytes = record(libc::FAN_EVENT_INFO_TYPE_FID, &[1, 2, 3], b"\0");
+        let fid = Fid::parse(&bytes).unwrap().unwrap();
+        assert_eq!(fid.kind, FidKind::Fid);
+        assert_eq!(fid.name, None);
+    }
+
+    #[test]
+    fn parse_should_reject_truncated_handles() {
+        let mut bytes = record(libc::FAN_EVENT_INFO_TYPE_FID, &[1, 2, 3], b"");
+        bytes.truncate(bytes.len() - 1);
+
+        assert!(Fid::parse(&bytes).is_err());
+        assert!(
+            Fid::parse(&record(libc::FAN_EVENT_INFO_TYPE_PIDFD, &[], b""))
+                .unwrap()
+                .is_none()
+        );
+    }
+}
@@ -0,0 +1,392 @@
+use std::{
+    ffi::CString,
+    io,
+    os::unix::{
+        ffi::OsStrExt,
+        io::{AsRawFd, OwnedFd},
+    },
+    path::Path,
+    sync::Arc,
+};
+
+bitflags! {
+    /// Describes which events a mark is for
+    ///
+    /// Passed to [`Marks::add`], [`Marks::remove`], their variants for
+    /// mounts and file systems, and [`Marks::ignore`].
+    #[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Clone, Copy)]
+    pub struct MarkMask: u64 {
+        /// File was accessed
+        ///
+        /// See [`libc::FAN_ACCESS`].
+        const ACCESS = libc::FAN_ACCESS;
+
+        /// File was modified
+        ///
+        /// See [`libc::FAN_MODIFY`].
+        const MODIFY = libc::FAN_MODIFY;
+
+        /// Metadata changed
+        ///
+        /// See [`libc::FAN_ATTRIB`].
+        const ATTRIB = libc::FAN_ATTRIB;
+
+        /// File opened for writing was closed
+        ///
+        /// See [`libc::FAN_CLOSE_WRITE`].
+        const CLOSE_WRITE = libc::FAN_CLOSE_WRITE;
+
+        /// File or directory not opened for writing was closed
+        ///
+        /// See [`libc::FAN_CLOSE_NOWRITE`].
+        const CLOSE_NOWRITE = libc::FAN_CLOSE_NOWRITE;
+
+        /// File or directory was opened
+        ///
+        /// See [`libc::FAN_OPEN`].
+        const OPEN = libc::FAN_OPEN;
+
+        /// File was opened for execution
+        ///
+        /// See [`libc::FAN_OPEN_EXEC`].
+        const OPEN_EXEC = libc::FAN_OPEN_EXEC;
+
+        /// File or directory was moved out of a marked directory
+        ///
+        /// See [`libc::FAN_MOVED_FROM`].
+        const MOVED_FROM = libc::FAN_MOVED_FROM;
+
+        /// File or directory was moved into a marked directory
+        ///
+        /// See [`libc::FAN_MOVED_TO`].
+        const MOVED_TO = libc::FAN_MOVED_TO;
+
+        /// File or directory was created in a marked directory
+        ///
+        /// See [`libc::FAN_CREATE`].
+        const CREATE = libc::FAN_CREATE;
+
+        /// File or directory was deleted from a marked directory
+        ///
+        /// See [`libc::FAN_DELETE`].
+        const DELETE = libc::FAN_DELETE;
+
+        /// Marked file or directory was deleted
+        ///
+        /// See [`libc::FAN_DELETE_SELF`].
+        const DELETE_SELF = libc::FAN_DELETE_SELF;
+
+        /// Marked file or directory was moved
+        ///
+        /// See [`libc::FAN_MOVE_SELF`].
+        const MOVE_SELF = libc::FAN_MOVE_SELF;
+
+        /// File or directory was renamed within a marked directory
+        ///
+        /// See [`libc::FAN_RENAME`].
+        const RENAME = libc::FAN_RENAME;
+
+        /// Permission to open a file or directory is requested
+        ///
+        /// The process that opens the file is blocked, until the request has
+        /// been answered using [`Fanotify::respond`]. Requires an instance
+        /// that has been initialized with
+        /// [`InitFlags::CLASS_CONTENT`] or
+        /// [`InitFlags::CLASS_PRE_CONTENT`].
+        ///
+        /// See [`libc::FAN_OPEN_PERM`].
+        ///
+        /// [`Fanotify::respond`]: super::Fanotify::respond
+        /// [`InitFlags::CLASS_CONTENT`]: super::InitFlags::CLASS_CONTENT
+        /// [`InitFlags::CLASS_PRE_CONTENT`]: super::InitFlags::CLASS_PRE_CONTENT
+        const OPEN_PERM = libc::FAN_OPEN_PERM;
+
+        /// Permission to read a file or directory is requested
+        ///
+        /// See [`OPEN_PERM`](Self::OPEN_PERM) and [`libc::FAN_ACCESS_PERM`].
+        const ACCESS_PERM = libc::FAN_ACCESS_PERM;
+
+        /// Permission to open a file for execution is requested
+        ///
+        /// See [`OPEN_PERM`](Self::OPEN_PERM) and
+        /// [`libc::FAN_OPEN_EXEC_PERM`].
+        const OPEN_EXEC_PERM = libc::FAN_OPEN_EXEC_PERM;
+
+        /// Also report events for the children of a marked directory
+        ///
+        /// See [`libc::FAN_EVENT_ON_CHILD`].
+        const EVENT_ON_CHILD = libc::FAN_EVENT_ON_CHILD;
+
+        /// Also report events for directories
+        ///
+        /// See [`libc::FAN_ONDIR`].
+        const ONDIR = libc::FAN_ONDIR;
+
+        /// Combination of [`CLOSE_WRITE`](Self::CLOSE_WRITE) and
+        /// [`CLOSE_NOWRITE`](Self::CLOSE_NOWRITE)
+        ///
+        /// See [`libc::FAN_CLOSE`].
+        const CLOSE = libc::FAN_CLOSE;
+
+        /// Combination of [`MOVED_FROM`](Self::MOVED_FROM) and
+        /// [`MOVED_TO`](Self::MOVED_TO)
+        ///
+        /// See [`libc::FAN_MOVE`].
+        const MOVE = libc::FAN_MOVE;
+    }
+}
+
+/// Interface for adding and removing marks
+///
+/// Returned by [`Fanotify::marks`]. Can be cloned, and used while events are
+/// being read.
+///
+/// [`Fanotify::marks`]: super::Fanotify::marks
+#[derive(Clone, Debug)]
+pub struct Marks {
+    fd: Arc<OwnedFd>,
+}
+
+impl Marks {
+    pub(crate) fn new(fd: Arc<OwnedFd>) -> Self {
+        Marks { fd }
+    }
+
+    /// Adds a mark for the file or directory at `path`
+    ///
+    /// If the file or directory has been marked already, `mask` is added to
+    /// the events of the existing mark.
+    ///
+    /// # Errors
+    ///
+    /// Directly returns the error from the call to [`fanotify_mark`].
+    ///
+    /// [`fanotify_mark`]: libc::fanotify_mark
+    pub fn add<P>(&self, path: P, mask: MarkMask) -> io::Result<()>
+    where
+        P: AsRef<Path>,
+    {
+        self.mark(libc::FAN_MARK_ADD, mask, path.as_ref())
+    }
+
+    /// Removes events from the mark for the file or directory at `path`
+    ///
+    /// The mark is removed, once no events are left.
+    ///
+    /// # Errors
+    ///
+    /// Directly returns the error from the call to [`fanotify_mark`].
+    ///
+    /// [`fanotify_mark`]: libc::fanotify_mark
+    pub fn remove<P>(&self, path: P, mask: MarkMask) -> io::Result<()>
+    where
+        P: AsRef<Path>,
+    {
+        self.mark(libc::FAN_MARK_REMOVE, mask, path.as_ref())
+    }
+
+    /// Adds a mark for the mount that contains `path`
+    ///
+    /// A mount mark covers all files and directories on the mount, no matter
+    /// how many there are. This is much cheaper than watching a large tree
+    /// with inotify, which requires a watch per directory.
+    ///
+    /// Events that concern directory entries, like
+    /// [`MarkMask::CREATE`] or [`MarkMask::DELETE`], are not supported for
+    /// mount marks.
+    ///
+    /// # Errors
+    ///
+    /// Directly returns the error from the call to [`fanotify_mark`].
+    ///
+    /// [`fanotify_mark`]: libc::fanotify_mark
+    pub fn add_mount<P>(&self, path: P, mask: MarkMask) -> io::Result<()>
+    where
+        P: AsRef<Path>,
+    {
+        self.mark(
+            libc::FAN_MARK_ADD | libc::FAN_MARK_MOUNT,
+            mask,
+            path.as_ref(),
+        )
+    }
+
+    /// Removes events from the mark for the mount that contains `path`
+    ///
+    /// # Errors
+    ///
+    /// Directly returns the error from the call to [`fanotify_mark`].
+    ///
+    /// [`fanotify_mark`]: libc::fanotify_mark
+    pub fn remove_mount<P>(&self, path: P, mask: MarkMask) -> io::Result<()>
+    where
+        P: AsRef<Path>,
+    {
+        self.mark(
+            libc::FAN_MARK_REMOVE | libc::FAN_MARK_MOUNT,
+            mask,
+            path.as_ref(),
+        )
+    }
+
+    /// Adds a mark for the file system that contains `path`
+    ///
+    /// Like a mount mark, but covers all mounts of the file system. Events
+    /// that concern directory entries require an instance that reports file
+    /// identifiers.
+    ///
+    /// # Errors
+    ///
+    /// Directly returns the error from the call to [`fanotify_mark`].
+    ///
+    /// [`fanotify_mark`]: libc::fanotify_mark
+    pub fn add_filesystem<P>(&self, path: P, mask: MarkMask) -> io::Result<()>
+    where
+        P: AsRef<Path>,
+    {
+        self.mark(
+            libc::FAN_MARK_ADD | libc::FAN_MARK_FILESYSTEM,
+            mask,
+            path.as_ref(),
+        )
+    }
+
+    /// Removes events from the mark for the file system that contains `path`
+    ///
+    /// # Errors
+    ///
+    /// Directly returns the error from the call to [`fanotify_mark`].
+    ///
+    /// [`fanotify_mark`]: libc::fanotify_mark
+    pub fn remove_filesystem<P>(&self, path: P, mask: MarkMask) -> io::Result<()>
+    where
+        P: AsRef<Path>,
+    {
+        self.mark(
+            libc::FAN_MARK_REMOVE | libc::FAN_MARK_FILESYSTEM,
+            mask,
+            path.as_ref(),
+        )
+    }
+
+    /// Ignores events for the file or directory at `path`
+    ///
+    /// Events in `mask` are not reported for `path`, even if they are covered
+    /// by another mark, like a mount mark. Use this to exclude noisy files or
+    /// directories from a mount or file system mark. To also ignore events for
+    /// the children of a directory, include [`MarkMask::EVENT_ON_CHILD`] in
+    /// `mask`. To ignore events for the directory itself, include
+    /// [`MarkMask::ONDIR`].
+    ///
+    /// The ignore mark is kept when the file is modified. On kernels older than
+    /// 6.0, this falls back to the legacy ignored mask, which doesn't support
+    /// [`MarkMask::EVENT_ON_CHILD`] and [`MarkMask::ONDIR`].
+    ///
+    /// # Errors
+    ///
+    /// Directly returns the error from the call to [`fanotify_mark`].
+    ///
+    /// [`fanotify_mark`]: libc::fanotify_mark
+    pub fn ignore<P>(&self, path: P, mask: MarkMask) -> io::Result<()>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+
+        match self.mark(libc::FAN_MARK_ADD | libc::FAN_MARK_IGNORE_SURV, mask, path) {
+            Err(error) if error.raw_os_error() == Some(libc::EINVAL) => self.mark(
+                libc::FAN_MARK_ADD
+                    | libc::FAN_MARK_IGNORED_MASK
+                    | libc::FAN_MARK_IGNORED_SURV_MODIFY,
+                mask - (MarkMask::EVENT_ON_CHILD | MarkMask::ONDIR),
+                path,
+            ),
+            result => result,
+        }
+    }
+
+    /// Stops ignoring events for the file or directory at `path`
+    ///
+    /// Undoes [`Marks::ignore`] for the events in `mask`.
+    ///
+    /// # Errors
+    ///
+    /// Directly returns the error from the call to [`fanotify_mark`].
+    ///
+    /// [`fanotify_mark`]: libc::fanotify_mark
+    pub fn unignore<P>(&self, path: P, mask: MarkMask) -> io::Result<()>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+
+        match self.mark(libc::FAN_MARK_REMOVE | libc::FAN_MARK_IGNORE, mask, path) {
+            Err(error) if error.raw_os_error() == Some(libc::EINVAL) => self.mark(
+                libc::FAN_MARK_REMOVE | libc::FAN_MARK_IGNORED_MASK,
+                mask - (MarkMask::EVENT_ON_CHILD | MarkMask::ONDIR),
+                path,
+            ),
+            result => result,
+        }
+    }
+
+    /// Removes all marks for files and directories
+    ///
+    /// # Errors
+    ///
+    /// Directly returns the error from the call to [`fanotify_mark`].
+    ///
+    /// [`fanotify_mark`]: libc::fanotify_mark
+    pub fn flush(&self) -> io::Result<()> {
+        self.mark(libc::FAN_MARK_FLUSH, MarkMask::empty(), Path::new("/"))
+    }
+
+    /// Removes all mount marks
+    ///
+    /// # Errors
+    ///
+    /// Directly returns the error from the call to [`fanotify_mark`].
+    ///
+    /// [`fanotify_mark`]: libc::fanotify_mark
+    pub fn flush_mounts(&self) -> io::Result<()> {
+        self.mark(
+            libc::FAN_MARK_FLUSH | libc::FAN_MARK_MOUNT,
+            MarkMask::empty(),
+            Path::new("/"),
+        )
+    }
+
+    /// Removes all file system marks
+    ///
+    /// # Errors
+    ///
+    /// Directly returns the error from the call to [`fanotify_mark`].
+    ///
+    /// [`fanotify_mark`]: libc::fanotify_mark
+    pub fn flush_filesystems(&self) -> io::Result<()> {
+        self.mark(
+            libc::FAN_MARK_FLUSH | libc::FAN_MARK_FILESYSTEM,
+            MarkMask::empty(),
+            Path::new("/"),
+        )
+    }
+
+    fn mark(&self, flags: u32, mask: MarkMask, path: &Path) -> io::Result<()> {
+        let path = CString::new(path.as_os_str().as_bytes())?;
+
+        let result = unsafe {
+            libc::fanotify_mark(
+                self.fd.as_raw_fd(),
+                flags,
+                mask.bits(),
+                libc::AT_FDCWD,
+                path.as_ptr(),
+            )
+        };
+        if result == -1 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(())
+    }
+}
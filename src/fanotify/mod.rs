@@ -0,0 +1,291 @@
+//! Wrapper for the fanotify API
+//!
+//! fanotify is the other file system notification API of the Linux kernel.
+//! Unlike inotify, it reports which process caused an event, and it hands out
+//! an open file descriptor for the file an event is about. Most of its
+//! features require the `CAP_SYS_ADMIN` capability.
+//!
+//! The API of this module mirrors the one for inotify. [`Fanotify`] is the
+//! main entry point. Marks, the fanotify equivalent of watches, are added and
+//! removed through [`Marks`].
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use inotify::fanotify::{Fanotify, InitFlags, MarkMask};
+//!
+//! let fanotify = Fanotify::init(InitFlags::CLASS_NOTIF)
+//!     .expect("Failed to initialize a fanotify instance");
+//!
+//! fanotify.marks().add("/tmp/file", MarkMask::MODIFY)
+//!     .expect("Failed to add mark");
+//!
+//! let mut buffer = [0; 4096];
+//! let events = fanotify.read_events_blocking(&mut buffer)
+//!     .expect("Error while reading events");
+//!
+//! for event in events {
+//!     println!("{:?} modified by process {}", event.path(), event.pid);
+//! }
+//! ```
+
+use std::{
+    io,
+    os::unix::io::{AsFd, AsRawFd, BorrowedFd, FromRawFd, OwnedFd, RawFd},
+    sync::Arc,
+};
+
+use self::events::respond;
+use crate::util::{read, read_blocking};
+
+mod events;
+mod fid;
+mod marks;
+#[cfg(feature = "stream")]
+mod stream;
+
+pub use self::events::{Event, EventMask, Events, Response};
+pub use self::fid::{Fid, FidKind, FileHandle};
+pub use self::marks::{MarkMask, Marks};
+#[cfg(feature = "stream")]
+pub use self::stream::EventStream;
+
+bitflags! {
+    /// Flags for initializing a fanotify instance
+    ///
+    /// Passed to [`Fanotify::init`]. Exactly one of the `CLASS_*` flags should
+    /// be set.
+    #[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Clone, Copy)]
+    pub struct InitFlags: u32 {
+        /// Receive events after files have been accessed
+        ///
+        /// See [`libc::FAN_CLASS_NOTIF`].
+        const CLASS_NOTIF = libc::FAN_CLASS_NOTIF;
+
+        /// Receive events when a file's final content is available
+        ///
+        /// Meant for tools that scan files before they are accessed.
+        ///
+        /// See [`libc::FAN_CLASS_CONTENT`].
+        const CLASS_CONTENT = libc::FAN_CLASS_CONTENT;
+
+        /// Receive events before a file's final content is available
+        ///
+        /// Meant for tools like hierarchical storage managers, that provide
+        /// the content of files.
+        ///
+        /// See [`libc::FAN_CLASS_PRE_CONTENT`].
+        const CLASS_PRE_CONTENT = libc::FAN_CLASS_PRE_CONTENT;
+
+        /// Don't limit the number of events in the queue
+        ///
+        /// See [`libc::FAN_UNLIMITED_QUEUE`].
+        const UNLIMITED_QUEUE = libc::FAN_UNLIMITED_QUEUE;
+
+        /// Don't limit the number of marks
+        ///
+        /// See [`libc::FAN_UNLIMITED_MARKS`].
+        const UNLIMITED_MARKS = libc::FAN_UNLIMITED_MARKS;
+
+        /// Report thread IDs instead of process IDs
+        ///
+        /// See [`libc::FAN_REPORT_TID`].
+        const REPORT_TID = libc::FAN_REPORT_TID;
+
+        /// Report a process file descriptor for the process that caused an
+        /// event
+        ///
+        /// See [`Event::pidfd`] and [`libc::FAN_REPORT_PIDFD`].
+        const REPORT_PIDFD = libc::FAN_REPORT_PIDFD;
+
+        /// Identify files by file handles instead of file descriptors
+        ///
+        /// Events carry a [`FidKind::Fid`] identifier instead of a file
+        /// descriptor. Required for events that concern directory entries,
+        /// like [`MarkMask::CREATE`], on file system marks.
+        ///
+        /// See [`libc::FAN_REPORT_FID`].
+        const REPORT_FID = libc::FAN_REPORT_FID;
+
+        /// Report the directory that contains the file
+        ///
+        /// Events carry a [`FidKind::Dfid`] identifier.
+        ///
+        /// See [`libc::FAN_REPORT_DIR_FID`].
+        const REPORT_DIR_FID = libc::FAN_REPORT_DIR_FID;
+
+        /// Report the name of the directory entry
+        ///
+        /// Requires [`REPORT_DIR_FID`](Self::REPORT_DIR_FID).
+        ///
+        /// See [`libc::FAN_REPORT_NAME`].
+        const REPORT_NAME = libc::FAN_REPORT_NAME;
+
+        /// Report the file handle of the file that entry events are about
+        ///
+        /// Requires [`REPORT_DFID_NAME`](Self::REPORT_DFID_NAME) and
+        /// [`REPORT_FID`](Self::REPORT_FID).
+        ///
+        /// See [`libc::FAN_REPORT_TARGET_FID`].
+        const REPORT_TARGET_FID = libc::FAN_REPORT_TARGET_FID;
+
+        /// Combination of [`REPORT_DIR_FID`](Self::REPORT_DIR_FID) and
+        /// [`REPORT_NAME`](Self::REPORT_NAME)
+        ///
+        /// Events carry a [`FidKind::DfidName`] identifier, which can be
+        /// resolved to the path of the file using [`Fid::path`].
+        ///
+        /// See [`libc::FAN_REPORT_DFID_NAME`].
+        const REPORT_DFID_NAME = libc::FAN_REPORT_DFID_NAME;
+    }
+}
+
+/// A fanotify instance
+///
+/// This is the main entry point into the fanotify API. Create an instance
+/// using [`Fanotify::init`], add marks using [`Fanotify::marks`], and read
+/// events using [`Fanotify::read_events_blocking`] or
+/// [`Fanotify::read_events`].
+#[derive(Debug)]
+pub struct Fanotify {
+    fd: Arc<OwnedFd>,
+    default_response: Response,
+}
+
+impl Fanotify {
+    /// Creates a fanotify instance
+    ///
+    /// Initializes a fanotify instance by calling [`fanotify_init`]. Like
+    /// [`Inotify::init`], this always passes [`FAN_CLOEXEC`] and
+    /// [`FAN_NONBLOCK`].
+    ///
+    /// The file descriptors that events carry are opened read-only.
+    ///
+    /// # Errors
+    ///
+    /// Directly returns the error from the call to [`fanotify_init`]. Without
+    /// the `CAP_SYS_ADMIN` capability, this is an error with
+    /// [`ErrorKind::PermissionDenied`] for most flags.
+    ///
+    /// [`fanotify_init`]: libc::fanotify_init
+    /// [`Inotify::init`]: crate::Inotify::init
+    /// [`FAN_CLOEXEC`]: libc::FAN_CLOEXEC
+    /// [`FAN_NONBLOCK`]: libc::FAN_NONBLOCK
+    /// [`ErrorKind::PermissionDenied`]: std::io::ErrorKind::PermissionDenied
+    pub fn init(flags: InitFlags) -> io::Result<Fanotify> {
+        let fd = unsafe {
+            libc::fanotify_init(
+                flags.bits() | libc::FAN_CLOEXEC | libc::FAN_NONBLOCK,
+                (libc::O_RDONLY | libc::O_CLOEXEC | libc::O_LARGEFILE) as u32,
+            )
+        };
+        if fd == -1 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(Fanotify {
+            fd: Arc::new(unsafe { OwnedFd::from_raw_fd(fd) }),
+            default_response: Response::Allow,
+        })
+    }
+
+    /// Sets the answer to permission requests that are dropped unanswered
+    ///
+    /// A permission request blocks the process that caused it, until it has
+    /// been answered. To make sure that happens, an [`Event`] answers its
+    /// permission request with this response when it is dropped, unless it
+    /// has been answered using [`Fanotify::respond`] before. This includes
+    /// events that are dropped because reading fails partway through a
+    /// buffer. Defaults to [`Response::Allow`].
+    ///
+    /// Only affects events that are read after this has been called.
+    pub fn set_default_response(&mut self, response: Response) {
+        self.default_response = response;
+    }
+
+    /// Returns an instance of `Marks` to add and remove marks
+    pub fn marks(&self) -> Marks {
+        Marks::new(self.fd.clone())
+    }
+
+    /// Waits until events are available, then returns them
+    ///
+    /// Works like [`Inotify::read_events_blocking`]. Each event carries an
+    /// open file descriptor, which is closed when the event is dropped.
+    ///
+    /// # Errors
+    ///
+    /// Directly returns the error from reading, or an error with
+    /// [`ErrorKind::InvalidData`], if the kernel uses an event format that
+    /// isn't supported.
+    ///
+    /// [`Inotify::read_events_blocking`]: crate::Inotify::read_events_blocking
+    /// [`ErrorKind::InvalidData`]: std::io::ErrorKind::InvalidData
+    pub fn read_events_blocking(&self, buffer: &mut [u8]) -> io::Result<Events> {
+        let num_bytes = read_blocking(self.fd.as_raw_fd(), buffer)?;
+        Events::parse(&buffer[..num_bytes], &self.fd, self.default_response)
+    }
+
+    /// Returns one buffer's worth of available events
+    ///
+    /// Works like [`Inotify::read_events`]. Returns an error with
+    /// [`ErrorKind::WouldBlock`], if no events are available.
+    ///
+    /// # Errors
+    ///
+    /// See [`Fanotify::read_events_blocking`].
+    ///
+    /// [`Inotify::read_events`]: crate::Inotify::read_events
+    /// [`ErrorKind::WouldBlock`]: std::io::ErrorKind::WouldBlock
+    pub fn read_events(&self, buffer: &mut [u8]) -> io::Result<Events> {
+        let num_bytes = read(self.fd.as_raw_fd(), buffer)?;
+        Events::parse(&buffer[..num_bytes], &self.fd, self.default_response)
+    }
+
+    /// Answers a permission request
+    ///
+    /// Permission requests are events for the `*_PERM` flags of
+    /// [`MarkMask`]. The process that caused the event is blocked until the
+    /// request has been answered, so every permission request must be
+    /// answered exactly once. Keep the event around until then, as its file
+    /// descriptor identifies the request. Requests that are dropped without
+    /// an answer are answered with the default response, see
+    /// [`Fanotify::set_default_response`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error with [`ErrorKind::InvalidInput`], if `event` doesn't
+    /// carry a file descriptor, or the error from writing the response.
+    ///
+    /// [`ErrorKind::InvalidInput`]: std::io::ErrorKind::InvalidInput
+    pub fn respond(&self, event: &Event, response: Response) -> io::Result<()> {
+        respond(self.fd.as_raw_fd(), event, response)
+    }
+
+    /// Creates a stream of the events of this instance
+    ///
+    /// `buffer` is used to read events, and should be able to hold at least
+    /// one event. The stream must be polled from within a tokio runtime.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error from registering the instance with the runtime.
+    #[cfg(feature = "stream")]
+    pub fn into_event_stream(self, buffer: Vec<u8>) -> io::Result<EventStream> {
+        EventStream::new(self.fd, buffer, self.default_response)
+    }
+}
+
+impl AsRawFd for Fanotify {
+    #[inline]
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd.as_raw_fd()
+    }
+}
+
+impl AsFd for Fanotify {
+    #[inline]
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.fd.as_fd()
+    }
+}
@@ -0,0 +1,87 @@
+use std::{
+    io,
+    os::unix::io::{AsFd, AsRawFd, OwnedFd},
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    vec,
+};
+
+use futures_core::{ready, Stream};
+
+use super::events::{respond, Event, Events, Response};
+use super::marks::Marks;
+use crate::driver::{IoDriver, TokioDriver};
+use crate::util::read;
+
+/// Stream of fanotify events
+///
+/// Returned by [`Fanotify::into_event_stream`]. Must be polled from within a
+/// tokio runtime.
+///
+/// [`Fanotify::into_event_stream`]: super::Fanotify::into_event_stream
+#[derive(Debug)]
+pub struct EventStream {
+    // Declared before `fd`, so the driver is dropped before the file
+    // descriptor is closed.
+    driver: TokioDriver,
+    fd: Arc<OwnedFd>,
+    buffer: Vec<u8>,
+    default_response: Response,
+    pending: vec::IntoIter<Event>,
+}
+
+impl EventStream {
+    pub(crate) fn new(
+        fd: Arc<OwnedFd>,
+        buffer: Vec<u8>,
+        default_response: Response,
+    ) -> io::Result<Self> {
+        Ok(EventStream {
+            driver: TokioDriver::register(fd.as_fd())?,
+            fd,
+            buffer,
+            default_response,
+            pending: Vec::new().into_iter(),
+        })
+    }
+
+    /// Returns an instance of `Marks` to add and remove marks
+    pub fn marks(&self) -> Marks {
+        Marks::new(self.fd.clone())
+    }
+
+    /// Answers a permission request
+    ///
+    /// See [`Fanotify::respond`].
+    ///
+    /// [`Fanotify::respond`]: super::Fanotify::respond
+    pub fn respond(&self, event: &Event, response: Response) -> io::Result<()> {
+        respond(self.fd.as_raw_fd(), event, response)
+    }
+}
+
+impl Stream for EventStream {
+    type Item = io::Result<Event>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let self_ = self.get_mut();
+
+        loop {
+            if let Some(event) = self_.pending.next() {
+                return Poll::Ready(Some(Ok(event)));
+            }
+
+            let fd = self_.fd.as_raw_fd();
+            let buffer = &mut self_.buffer;
+            let num_bytes = ready!(self_.driver.poll_read(cx, || read(fd, buffer)))?;
+
+            let events = Events::parse(
+                &self_.buffer[..num_bytes],
+                &self_.fd,
+                self_.default_response,
+            )?;
+            self_.pending = events.collect::<Vec<_>>().into_iter();
+        }
+    }
+}
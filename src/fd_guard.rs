@@ -1,16 +1,38 @@
 use std::{
+    fmt,
     ops::Deref,
     os::unix::io::{AsFd, AsRawFd, BorrowedFd, FromRawFd, IntoRawFd, RawFd},
-    sync::atomic::{AtomicBool, Ordering},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex, Weak,
+    },
 };
 
 use inotify_sys as ffi;
 
+use crate::bookkeeping::Bookkeeping;
+use crate::oneshot::Oneshots;
+use crate::usage::UsageHook;
+
 /// A RAII guard around a `RawFd` that closes it automatically on drop.
 #[derive(Debug)]
 pub struct FdGuard {
     pub(crate) fd: RawFd,
+    pub(crate) instance: u64,
     pub(crate) close_on_drop: AtomicBool,
+    pub(crate) overflow_hook: OverflowHook,
+    pub(crate) usage_hook: UsageHook,
+    pub(crate) bookkeeping: Bookkeeping,
+    pub(crate) oneshots: Oneshots,
+}
+
+/// The instance id that the next `FdGuard` gets
+///
+/// Starts at 1, so 0 can stand for "no instance" in [`FdHandle`].
+static NEXT_INSTANCE: AtomicU64 = AtomicU64::new(1);
+
+fn next_instance() -> u64 {
+    NEXT_INSTANCE.fetch_add(1, Ordering::Relaxed)
 }
 
 impl FdGuard {
@@ -51,7 +73,12 @@ impl FromRawFd for FdGuard {
     unsafe fn from_raw_fd(fd: RawFd) -> Self {
         FdGuard {
             fd,
+            instance: next_instance(),
             close_on_drop: AtomicBool::new(true),
+            overflow_hook: OverflowHook::default(),
+            usage_hook: UsageHook::default(),
+            bookkeeping: Bookkeeping::default(),
+            oneshots: Oneshots::default(),
         }
     }
 }
@@ -81,3 +108,113 @@ impl PartialEq for FdGuard {
         self.fd == other.fd
     }
 }
+
+/// Callback that is called when the event queue has overflowed
+///
+/// Lives in [`FdGuard`], so it is shared by everything that reads from the
+/// same inotify instance.
+#[derive(Default)]
+pub(crate) struct OverflowHook(Mutex<Option<Arc<dyn Fn() + Send + Sync>>>);
+
+impl OverflowHook {
+    pub(crate) fn set(&self, hook: Option<Arc<dyn Fn() + Send + Sync>>) {
+        *self.0.lock().unwrap_or_else(|error| error.into_inner()) = hook;
+    }
+
+    pub(crate) fn fire(&self) {
+        // Don't hold the lock while calling the hook, so the hook can replace
+        // itself.
+        let hook = self
+            .0
+            .lock()
+            .unwrap_or_else(|error| error.into_inner())
+            .clone();
+
+        if let Some(hook) = hook {
+            hook();
+        }
+    }
+}
+
+impl fmt::Debug for OverflowHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let is_set = self.0.lock().is_ok_and(|hook| hook.is_some());
+
+        f.debug_struct("OverflowHook")
+            .field("is_set", &is_set)
+            .finish()
+    }
+}
+
+/// A handle to an inotify instance, shared by the events of a single read
+///
+/// Every [`WatchDescriptor`] refers back to its inotify instance. If each of
+/// them held its own `Weak<FdGuard>`, every event would touch the reference
+/// count of the instance, which all threads that read from it share. Instead,
+/// all events from one read share a handle, so cloning it only touches a
+/// reference count that is local to that read. Handles that don't refer to
+/// any instance don't allocate.
+///
+/// The handle also caches the id of the instance, which, unlike the file
+/// descriptor, is never reused. Comparing two handles only compares those ids,
+/// without touching the weak pointer.
+///
+/// [`WatchDescriptor`]: crate::WatchDescriptor
+#[derive(Clone, Default)]
+pub(crate) struct FdHandle {
+    instance: u64,
+    fd: Option<Arc<Weak<FdGuard>>>,
+}
+
+impl FdHandle {
+    pub(crate) fn new(fd: Weak<FdGuard>) -> Self {
+        let instance = match fd.upgrade() {
+            Some(guard) => guard.instance,
+            None => return FdHandle::default(),
+        };
+
+        FdHandle {
+            instance,
+            fd: Some(Arc::new(fd)),
+        }
+    }
+
+    /// Creates a handle with a fresh instance id, that doesn't refer to a file
+    /// descriptor
+    ///
+    /// Used for watch descriptors that have been received from elsewhere.
+    /// They are equal to each other, but not to those of any real instance.
+    #[cfg(feature = "wire")]
+    pub(crate) fn detached() -> Self {
+        FdHandle {
+            instance: next_instance(),
+            fd: None,
+        }
+    }
+
+    /// Creates a handle that all events created in tests share, so their
+    /// watch descriptors are equal
+    #[cfg(test)]
+    pub(crate) fn for_tests() -> Self {
+        FdHandle {
+            instance: u64::MAX,
+            fd: None,
+        }
+    }
+
+    /// Returns the id of the instance, or 0, if the handle doesn't refer to one
+    pub(crate) fn instance(&self) -> u64 {
+        self.instance
+    }
+
+    pub(crate) fn upgrade(&self) -> Option<Arc<FdGuard>> {
+        self.fd.as_ref().and_then(|fd| fd.upgrade())
+    }
+}
+
+impl fmt::Debug for FdHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Same as `Weak`, which this used to be.
+        write!(f, "(Weak)")
+    }
+}
@@ -0,0 +1,81 @@
+use std::ops::{Deref, DerefMut};
+
+use crate::util::INOTIFY_EVENT_SIZE;
+
+/// A statically sized buffer for reading events
+///
+/// Wraps an array of `N` bytes, which is aligned for `inotify_event`. Can be
+/// used wherever a buffer is expected, without allocating or computing its
+/// size at runtime. Use [`get_buffer_size_for_events`] to pick `N`.
+///
+/// `N` must be large enough to hold at least one event of maximum size.
+/// Smaller sizes are rejected at compile time.
+///
+/// # Examples
+///
+/// ```
+/// use inotify::{get_buffer_size_for_events, FixedEventBuffer, Inotify};
+///
+/// let inotify = Inotify::init()
+///     .expect("Failed to initialize an inotify instance");
+///
+/// let mut buffer = FixedEventBuffer::<{ get_buffer_size_for_events(16) }>::new();
+/// # let _ = || {
+/// let events = inotify.read_events_blocking(&mut buffer)
+///     .expect("Error while reading events");
+/// # };
+/// ```
+///
+/// [`get_buffer_size_for_events`]: crate::get_buffer_size_for_events
+#[derive(Clone, Debug)]
+#[repr(C, align(4))]
+pub struct FixedEventBuffer<const N: usize> {
+    bytes: [u8; N],
+}
+
+impl<const N: usize> FixedEventBuffer<N> {
+    const CAN_HOLD_EVENT: () = assert!(
+        N >= INOTIFY_EVENT_SIZE,
+        "FixedEventBuffer must be able to hold at least one event"
+    );
+
+    /// Creates a zeroed buffer
+    pub const fn new() -> Self {
+        #[allow(clippy::let_unit_value)]
+        let () = Self::CAN_HOLD_EVENT;
+
+        FixedEventBuffer { bytes: [0; N] }
+    }
+}
+
+impl<const N: usize> Default for FixedEventBuffer<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> Deref for FixedEventBuffer<N> {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        &self.bytes
+    }
+}
+
+impl<const N: usize> DerefMut for FixedEventBuffer<N> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.bytes
+    }
+}
+
+impl<const N: usize> AsRef<[u8]> for FixedEventBuffer<N> {
+    fn as_ref(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+impl<const N: usize> AsMut<[u8]> for FixedEventBuffer<N> {
+    fn as_mut(&mut self) -> &mut [u8] {
+        &mut self.bytes
+    }
+}
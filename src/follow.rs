@@ -0,0 +1,306 @@
+use std::{
+    collections::VecDeque,
+    ffi::OsString,
+    fs::File,
+    io::{self, Read, Seek, SeekFrom},
+    os::unix::fs::MetadataExt,
+    path::{Path, PathBuf},
+};
+
+use crate::events::{EventMask, EventOwned};
+use crate::inotify::Inotify;
+use crate::util::get_buffer_size_for_events;
+use crate::watches::{WatchDescriptor, WatchMask};
+
+/// Follows a file by name, like `tail -F`
+///
+/// `FileFollower` watches a file, and returns the data that is appended to
+/// it. If the file is rotated (moved away and replaced by a new one), or
+/// deleted and created again, the follower switches over to the new file, and
+/// continues to read it from the start. If the file is truncated, reading
+/// continues from its new end.
+///
+/// To notice rotation, the follower watches the directory containing the file
+/// as well. It uses an [`Inotify`] instance of its own, and blocks while
+/// waiting for events.
+///
+/// # Examples
+///
+/// ```no_run
+/// use inotify::{FileFollower, FollowEvent};
+///
+/// let mut follower = FileFollower::new("/var/log/syslog")
+///     .expect("Failed to follow file");
+///
+/// loop {
+///     match follower.next_event().expect("Error while following file") {
+///         FollowEvent::Appended(data) => print!("{}", String::from_utf8_lossy(&data)),
+///         FollowEvent::Truncated => eprintln!("syslog: file truncated"),
+///         event => eprintln!("syslog: {:?}", event),
+///     }
+/// }
+/// ```
+#[derive(Debug)]
+pub struct FileFollower {
+    inotify: Inotify,
+    path: PathBuf,
+    name: OsString,
+    dir_wd: WatchDescriptor,
+    file: Option<Followed>,
+    pending: VecDeque<FollowEvent>,
+    buffer: Vec<u8>,
+}
+
+impl FileFollower {
+    /// Starts following the file at `path`
+    ///
+    /// Data that is in the file already is skipped. If the file doesn't exist
+    /// yet, the follower waits for it to be created.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`io::Error`] with [`ErrorKind`]`::InvalidInput`, if `path`
+    /// doesn't have a file name. Otherwise, returns the error from
+    /// initializing inotify, adding the watches, or opening the file.
+    ///
+    /// [`ErrorKind`]: std::io::ErrorKind
+    pub fn new<P>(path: P) -> io::Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref().to_path_buf();
+        let name = path
+            .file_name()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Path has no file name"))?
+            .to_os_string();
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+
+        let inotify = Inotify::init()?;
+        let dir_wd = inotify.watches().add(
+            dir,
+            WatchMask::CREATE | WatchMask::MOVED_TO | WatchMask::DELETE,
+        )?;
+
+        let mut follower = FileFollower {
+            inotify,
+            path,
+            name,
+            dir_wd,
+            file: None,
+            pending: VecDeque::new(),
+            buffer: vec![0; get_buffer_size_for_events(16)],
+        };
+        if follower.attach()? {
+            let file = follower.file.as_mut().unwrap();
+            file.position = file.file.seek(SeekFrom::End(0))?;
+        }
+
+        Ok(follower)
+    }
+
+    /// Returns the path of the followed file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Waits for the next change to the followed file
+    ///
+    /// # Errors
+    ///
+    /// Returns the error from reading events or from the file. The follower
+    /// can be used again afterwards.
+    pub fn next_event(&mut self) -> io::Result<FollowEvent> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return Ok(event);
+            }
+
+            let events = self
+                .inotify
+                .read_events_blocking(&mut self.buffer)?
+                .map(|event| event.to_owned())
+                .collect::<Vec<_>>();
+            for event in events {
+                self.handle(event)?;
+            }
+        }
+    }
+
+    fn handle(&mut self, event: EventOwned) -> io::Result<()> {
+        if event.is_queue_overflow() {
+            // Anything might have happened, so check everything.
+            if self.file.is_some() {
+                self.read_appended()?;
+            }
+            return self.reattach();
+        }
+
+        if event.wd == self.dir_wd {
+            if event.name.as_ref() != Some(&self.name) {
+                return Ok(());
+            }
+
+            // The file watch only reports the deletion once the file is
+            // closed, and the follower keeps it open.
+            if event.mask.contains(EventMask::DELETE) && self.is_deleted()? {
+                self.detach()?;
+                self.pending.push_back(FollowEvent::Removed);
+            }
+            return self.reattach();
+        }
+
+        let is_followed = self
+            .file
+            .as_ref()
+            .is_some_and(|followed| followed.wd == event.wd);
+        if !is_followed {
+            // Left over from a file that has been replaced.
+            return Ok(());
+        }
+
+        if event.mask.contains(EventMask::MODIFY) {
+            self.read_appended()?;
+        }
+        if event
+            .mask
+            .intersects(EventMask::MOVE_SELF | EventMask::DELETE_SELF)
+        {
+            self.detach()?;
+            self.pending.push_back(FollowEvent::Removed);
+        }
+
+        Ok(())
+    }
+
+    /// Switches over to the file at the path, if it's not the followed one
+    fn reattach(&mut self) -> io::Result<()> {
+        let ino = match self.path.metadata() {
+            Ok(metadata) => metadata.ino(),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(error) => return Err(error),
+        };
+        if self.file.as_ref().map(|followed| followed.ino) == Some(ino) {
+            return Ok(());
+        }
+
+        if self.file.is_some() {
+            self.detach()?;
+        }
+        if self.attach()? {
+            self.pending.push_back(FollowEvent::Reopened);
+            self.read_appended()?;
+        }
+
+        Ok(())
+    }
+
+    /// Indicates whether the followed file has no name left
+    fn is_deleted(&self) -> io::Result<bool> {
+        match &self.file {
+            Some(followed) => Ok(followed.file.metadata()?.nlink() == 0),
+            None => Ok(false),
+        }
+    }
+
+    /// Opens the file and watches it; returns `false`, if it doesn't exist
+    fn attach(&mut self) -> io::Result<bool> {
+        // Watch first, so no modification is missed between opening and
+        // watching.
+        let wd = match self.inotify.watches().add(
+            &self.path,
+            WatchMask::MODIFY | WatchMask::MOVE_SELF | WatchMask::DELETE_SELF,
+        ) {
+            Ok(wd) => wd,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(false),
+            Err(error) => return Err(error),
+        };
+        let file = match File::open(&self.path) {
+            Ok(file) => file,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(false),
+            Err(error) => return Err(error),
+        };
+        let ino = file.metadata()?.ino();
+
+        self.file = Some(Followed {
+            file,
+            wd,
+            ino,
+            position: 0,
+        });
+        Ok(true)
+    }
+
+    /// Reads what's left of the followed file, and stops following it
+    fn detach(&mut self) -> io::Result<()> {
+        self.read_appended()?;
+
+        if let Some(followed) = self.file.take() {
+            // The watch is gone already, if the file has been deleted.
+            let _ = self.inotify.watches().remove(followed.wd);
+        }
+
+        Ok(())
+    }
+
+    fn read_appended(&mut self) -> io::Result<()> {
+        let followed = match &mut self.file {
+            Some(followed) => followed,
+            None => return Ok(()),
+        };
+
+        if followed.file.metadata()?.len() < followed.position {
+            followed.position = followed.file.seek(SeekFrom::Start(0))?;
+            self.pending.push_back(FollowEvent::Truncated);
+        }
+
+        let mut data = Vec::new();
+        followed.position += followed.file.read_to_end(&mut data)? as u64;
+        if !data.is_empty() {
+            self.pending.push_back(FollowEvent::Appended(data));
+        }
+
+        Ok(())
+    }
+}
+
+impl Iterator for FileFollower {
+    type Item = io::Result<FollowEvent>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.next_event())
+    }
+}
+
+#[derive(Debug)]
+struct Followed {
+    file: File,
+    wd: WatchDescriptor,
+    ino: u64,
+    position: u64,
+}
+
+/// A change to a file followed by a [`FileFollower`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FollowEvent {
+    /// Data has been appended to the file
+    Appended(Vec<u8>),
+
+    /// The file has been truncated
+    ///
+    /// Data that is written afterwards is returned as [`FollowEvent::Appended`]
+    /// again.
+    Truncated,
+
+    /// The file has been moved away or deleted
+    ///
+    /// The follower waits for a new file to be created at the path.
+    Removed,
+
+    /// A new file has been created at the path, or moved there
+    ///
+    /// The follower continues with the new file, starting at its beginning.
+    Reopened,
+}
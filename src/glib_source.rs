@@ -0,0 +1,49 @@
+use std::{ffi::OsStr, io, os::unix::io::AsRawFd};
+
+use glib::{ControlFlow, IOCondition, SourceId};
+
+use crate::events::Event;
+use crate::util::get_buffer_size_for_events;
+use crate::Inotify;
+
+impl Inotify {
+    /// Calls `callback` for each event, from the default GLib main context
+    ///
+    /// This is the equivalent of calling `g_io_add_watch` or `g_unix_fd_add`
+    /// on the inotify file descriptor in C. The instance is consumed, and
+    /// closed once the source has been removed. The source is removed, if
+    /// `callback` returns [`ControlFlow::Break`], if reading fails, or if it
+    /// is removed using the returned [`SourceId`].
+    ///
+    /// Add watches before calling this, or use [`Inotify::watches`] to get a
+    /// handle for adding them later.
+    ///
+    /// # Panics
+    ///
+    /// Panics, if the default main context is owned by another thread.
+    pub fn add_glib_watch<F>(self, mut callback: F) -> SourceId
+    where
+        F: FnMut(Event<&OsStr>) -> ControlFlow + 'static,
+    {
+        let fd = self.fd.as_raw_fd();
+        let mut buffer = vec![0; get_buffer_size_for_events(16)];
+
+        glib::unix_fd_add_local(fd, IOCondition::IN, move |_, _| {
+            let events = match self.read_events(&mut buffer) {
+                Ok(events) => events,
+                Err(error) if error.kind() == io::ErrorKind::WouldBlock => {
+                    return ControlFlow::Continue;
+                }
+                Err(_) => return ControlFlow::Break,
+            };
+
+            for event in events {
+                if callback(event) == ControlFlow::Break {
+                    return ControlFlow::Break;
+                }
+            }
+
+            ControlFlow::Continue
+        })
+    }
+}
@@ -0,0 +1,107 @@
+use std::path::Path;
+
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+
+/// Patterns for files and directories that should not be watched
+///
+/// The patterns follow the rules of `.gitignore` files, as far as they apply
+/// here: A pattern that doesn't contain a `/` matches a file or directory of
+/// that name anywhere in the tree, while a pattern that does is matched
+/// against the path relative to the root of the tree. A leading `/` is
+/// ignored, and so is a trailing one. `*` doesn't match `/`, but `**` does.
+/// Negated patterns are not supported.
+///
+/// Anything below an ignored directory is ignored too. Pass
+/// [`IgnoreRules::filter`] to [`Watches::add_recursive`], so ignored
+/// directories are not watched, and use [`IgnoreRules::is_ignored`] to filter
+/// out events for ignored files.
+///
+/// # Examples
+///
+/// ```no_run
+/// use inotify::{IgnoreRules, Inotify, WatchMask};
+///
+/// let rules = IgnoreRules::new(["target", ".git", "node_modules", "*.swp"])
+///     .expect("Invalid pattern");
+///
+/// let inotify = Inotify::init()
+///     .expect("Failed to initialize an inotify instance");
+/// inotify.watches()
+///     .add_recursive("/home/user/project", WatchMask::MODIFY, rules.filter())
+///     .expect("Failed to add watches");
+/// ```
+///
+/// [`Watches::add_recursive`]: crate::Watches::add_recursive
+#[derive(Clone, Debug)]
+pub struct IgnoreRules {
+    set: GlobSet,
+}
+
+impl IgnoreRules {
+    /// Creates `IgnoreRules` from a list of patterns
+    ///
+    /// # Errors
+    ///
+    /// Returns an error, if one of the patterns is invalid.
+    pub fn new<I, S>(patterns: I) -> Result<Self, globset::Error>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut builder = GlobSetBuilder::new();
+
+        for pattern in patterns {
+            let pattern = pattern.as_ref().trim_end_matches('/');
+            let pattern = match pattern.strip_prefix('/') {
+                Some(anchored) => anchored.to_string(),
+                None if pattern.contains('/') => pattern.to_string(),
+                None => format!("**/{}", pattern),
+            };
+
+            builder.add(GlobBuilder::new(&pattern).literal_separator(true).build()?);
+        }
+
+        Ok(IgnoreRules {
+            set: builder.build()?,
+        })
+    }
+
+    /// Indicates whether `path` is ignored
+    ///
+    /// `path` is relative to the root of the tree. It is ignored, if it or
+    /// any of its ancestors matches one of the patterns.
+    pub fn is_ignored(&self, path: &Path) -> bool {
+        path.ancestors()
+            .take_while(|ancestor| !ancestor.as_os_str().is_empty())
+            .any(|ancestor| self.set.is_match(ancestor))
+    }
+
+    /// Returns a filter for [`Watches::add_recursive`]
+    ///
+    /// [`Watches::add_recursive`]: crate::Watches::add_recursive
+    pub fn filter(&self) -> impl FnMut(&Path) -> bool + '_ {
+        move |path| !self.is_ignored(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::IgnoreRules;
+
+    #[test]
+    fn it_should_ignore_matching_paths_and_everything_below_them() {
+        let rules = IgnoreRules::new(["target/", ".git", "/docs/build", "*.swp"]).unwrap();
+
+        assert!(rules.is_ignored(Path::new("target")));
+        assert!(rules.is_ignored(Path::new("crates/a/target/debug")));
+        assert!(rules.is_ignored(Path::new(".git/objects")));
+        assert!(rules.is_ignored(Path::new("docs/build/index.html")));
+        assert!(rules.is_ignored(Path::new("src/.main.rs.swp")));
+
+        assert!(!rules.is_ignored(Path::new("src/target.rs")));
+        assert!(!rules.is_ignored(Path::new("crates/docs/build")));
+        assert!(!rules.is_ignored(Path::new("src/main.rs")));
+    }
+}
@@ -0,0 +1,200 @@
+use std::{
+    borrow::Borrow,
+    cmp::Ordering,
+    ffi::{OsStr, OsString},
+    fmt,
+    hash::{Hash, Hasher},
+    ops::Deref,
+    os::unix::ffi::OsStrExt,
+    path::Path,
+};
+
+/// The number of bytes a name can have, to be stored inline
+///
+/// Chosen so an `InlineName` takes up 32 bytes, which is not much more than
+/// the 24 bytes of an `OsString`, before counting the allocation.
+const INLINE_CAPACITY: usize = 30;
+
+/// The name of an event, stored without allocating, if it is short
+///
+/// Used by [`EventInline`]. Names of up to 30 bytes, which covers most file
+/// names, are stored inside the `InlineName` itself. Longer names are
+/// allocated, just like an [`OsString`]. Applications that keep many events
+/// around save an allocation per event that way, and keep the names close to
+/// the rest of the event in memory.
+///
+/// Dereferences to [`OsStr`], and compares and hashes like one.
+///
+/// [`EventInline`]: crate::EventInline
+#[derive(Clone)]
+pub struct InlineName(Repr);
+
+#[derive(Clone)]
+enum Repr {
+    Inline {
+        len: u8,
+        bytes: [u8; INLINE_CAPACITY],
+    },
+    Heap(Box<OsStr>),
+}
+
+impl InlineName {
+    /// Returns the name
+    pub fn as_os_str(&self) -> &OsStr {
+        match &self.0 {
+            Repr::Inline { len, bytes } => OsStr::from_bytes(&bytes[..usize::from(*len)]),
+            Repr::Heap(name) => name,
+        }
+    }
+
+    /// Indicates whether the name is stored inline, without an allocation
+    pub fn is_inline(&self) -> bool {
+        matches!(self.0, Repr::Inline { .. })
+    }
+}
+
+impl From<&OsStr> for InlineName {
+    fn from(name: &OsStr) -> Self {
+        let name = name.as_bytes();
+        if name.len() > INLINE_CAPACITY {
+            return InlineName(Repr::Heap(OsStr::from_bytes(name).into()));
+        }
+
+        let mut bytes = [0; INLINE_CAPACITY];
+        bytes[..name.len()].copy_from_slice(name);
+
+        InlineName(Repr::Inline {
+            len: name.len() as u8,
+            bytes,
+        })
+    }
+}
+
+impl From<OsString> for InlineName {
+    fn from(name: OsString) -> Self {
+        if name.len() > INLINE_CAPACITY {
+            // Reuse the existing allocation.
+            return InlineName(Repr::Heap(name.into_boxed_os_str()));
+        }
+
+        InlineName::from(name.as_os_str())
+    }
+}
+
+impl From<InlineName> for OsString {
+    fn from(name: InlineName) -> Self {
+        match name.0 {
+            Repr::Inline { .. } => name.as_os_str().to_os_string(),
+            Repr::Heap(name) => name.into_os_string(),
+        }
+    }
+}
+
+impl Deref for InlineName {
+    type Target = OsStr;
+
+    fn deref(&self) -> &Self::Target {
+        self.as_os_str()
+    }
+}
+
+impl AsRef<OsStr> for InlineName {
+    fn as_ref(&self) -> &OsStr {
+        self.as_os_str()
+    }
+}
+
+impl AsRef<Path> for InlineName {
+    fn as_ref(&self) -> &Path {
+        Path::new(self.as_os_str())
+    }
+}
+
+impl Borrow<OsStr> for InlineName {
+    fn borrow(&self) -> &OsStr {
+        self.as_os_str()
+    }
+}
+
+impl fmt::Debug for InlineName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.as_os_str().fmt(f)
+    }
+}
+
+impl PartialEq for InlineName {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_os_str() == other.as_os_str()
+    }
+}
+
+impl Eq for InlineName {}
+
+impl PartialEq<OsStr> for InlineName {
+    fn eq(&self, other: &OsStr) -> bool {
+        self.as_os_str() == other
+    }
+}
+
+impl PartialEq<str> for InlineName {
+    fn eq(&self, other: &str) -> bool {
+        self.as_os_str() == other
+    }
+}
+
+impl PartialEq<&str> for InlineName {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_os_str() == *other
+    }
+}
+
+impl PartialOrd for InlineName {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for InlineName {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.as_os_str().cmp(other.as_os_str())
+    }
+}
+
+impl Hash for InlineName {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        // Must hash like `OsStr`, to be consistent with `Borrow<OsStr>`.
+        self.as_os_str().hash(state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        ffi::{OsStr, OsString},
+        mem,
+    };
+
+    use super::{InlineName, INLINE_CAPACITY};
+
+    #[test]
+    fn short_names_should_be_stored_inline() {
+        assert_eq!(mem::size_of::<InlineName>(), 32);
+
+        let name = InlineName::from(OsStr::new("app.log"));
+        assert!(name.is_inline());
+        assert_eq!(name, "app.log");
+
+        let longest = "a".repeat(INLINE_CAPACITY);
+        assert!(InlineName::from(OsStr::new(&longest)).is_inline());
+    }
+
+    #[test]
+    fn long_names_should_be_allocated() {
+        let long = "a".repeat(INLINE_CAPACITY + 1);
+
+        let name = InlineName::from(OsString::from(&long));
+        assert!(!name.is_inline());
+        assert_eq!(name, InlineName::from(OsStr::new(&long)));
+        assert_eq!(OsString::from(name), OsString::from(long));
+    }
+}
@@ -1,19 +1,36 @@
 use std::{
-    io,
+    fmt, io,
     os::unix::io::{AsFd, AsRawFd, BorrowedFd, FromRawFd, IntoRawFd, OwnedFd, RawFd},
     path::Path,
-    sync::{atomic::AtomicBool, Arc},
+    sync::{mpsc, Arc},
 };
 
 use inotify_sys as ffi;
-use libc::{fcntl, F_GETFL, F_SETFL, O_NONBLOCK};
 
-use crate::events::Events;
+use crate::blocking::BlockingIter;
+#[cfg(feature = "codec")]
+use crate::codec::InotifyCodec;
+use crate::dispatcher::DispatcherHandle;
+#[cfg(feature = "crossbeam")]
+use crate::dispatcher::{CrossbeamSink, FullChannelPolicy};
+use crate::epoll;
+use crate::events::{EventOwned, Events};
 use crate::fd_guard::FdGuard;
-use crate::util::read_into_buffer;
+use crate::fixed_buffer::FixedEventBuffer;
+use crate::namespace::NamespaceWatches;
+use crate::parser::EventParser;
+use crate::queue::{queued_bytes, QueueMonitor};
+#[cfg(feature = "systemd")]
+use crate::systemd;
+use crate::usage::WatchUsage;
+use crate::util::{
+    get_buffer_size_for_events, read, read_blocking, read_drained, read_drained_blocking,
+};
 use crate::watches::{WatchDescriptor, WatchMask, Watches};
 
-#[cfg(feature = "stream")]
+#[cfg(feature = "stream-core")]
+use crate::driver::IoDriver;
+#[cfg(feature = "stream-core")]
 use crate::stream::EventStream;
 
 /// Idiomatic Rust wrapper around Linux's inotify API
@@ -22,13 +39,16 @@ use crate::stream::EventStream;
 /// adhere to the underlying inotify API closely, while making access to it
 /// safe and convenient.
 ///
+/// All methods that read events take `&self`, so an `Inotify` instance can be
+/// shared between threads by putting it into an [`Arc`], without wrapping it
+/// in a `Mutex`.
+///
 /// Please refer to the [top-level documentation] for further details and a
 /// usage example.
 ///
 /// [top-level documentation]: crate
-#[derive(Debug)]
 pub struct Inotify {
-    fd: Arc<FdGuard>,
+    pub(crate) fd: Arc<FdGuard>,
 }
 
 impl Inotify {
@@ -41,6 +61,8 @@ impl Inotify {
     /// inappropriate in the context of this wrapper:
     ///
     /// - [`IN_CLOEXEC`] prevents leaking file descriptors to other processes.
+    ///   If you want to pass the instance to a child process, use
+    ///   [`Inotify::set_cloexec`] or [`Inotify::into_inheritable_fd`].
     /// - [`IN_NONBLOCK`] controls the blocking behavior of the inotify API,
     ///   which is entirely managed by this wrapper.
     ///
@@ -86,19 +108,124 @@ impl Inotify {
         }
 
         Ok(Inotify {
-            fd: Arc::new(FdGuard {
-                fd,
-                close_on_drop: AtomicBool::new(true),
-            }),
+            fd: Arc::new(unsafe { FdGuard::from_raw_fd(fd) }),
         })
     }
 
+    /// Adopts an inotify instance passed by systemd
+    ///
+    /// Takes the first inotify file descriptor passed via the `LISTEN_FDS`
+    /// protocol, which systemd uses both for socket activation and to pass
+    /// back file descriptors from its file descriptor store. This allows a
+    /// daemon to keep its watches across restarts, by storing the instance
+    /// with `FDSTORE=1` before exiting, and adopting it here after the
+    /// restart.
+    ///
+    /// The file descriptor is checked to actually refer to an inotify
+    /// instance, and is switched to non-blocking and close-on-exec mode, as
+    /// [`Inotify::init`] would have done. Each passed file descriptor is only
+    /// ever returned once.
+    ///
+    /// See [sd_listen_fds(3)] for details on the protocol.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error with [`ErrorKind::NotFound`], if systemd didn't pass
+    /// any inotify file descriptors to this process (or all of them have
+    /// already been adopted). Returns the error from the call to [`fcntl`], if
+    /// the file descriptor flags can't be set.
+    ///
+    /// [sd_listen_fds(3)]: https://www.freedesktop.org/software/systemd/man/sd_listen_fds.html
+    /// [`ErrorKind::NotFound`]: std::io::ErrorKind::NotFound
+    /// [`fcntl`]: libc::fcntl
+    #[cfg(feature = "systemd")]
+    pub fn from_systemd_fd() -> io::Result<Inotify> {
+        Self::from_adopted_fd(systemd::take_inotify_fd(None)?)
+    }
+
+    /// Adopts a named inotify instance passed by systemd
+    ///
+    /// Like [`Inotify::from_systemd_fd`], but only considers the file
+    /// descriptor called `name`, as set by `FileDescriptorName=` or by
+    /// `FDNAME=` when the file descriptor was stored.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error with [`ErrorKind::NotFound`], if no file descriptor
+    /// called `name` has been passed, and an error with
+    /// [`ErrorKind::InvalidInput`], if it isn't an inotify instance.
+    ///
+    /// [`ErrorKind::NotFound`]: std::io::ErrorKind::NotFound
+    /// [`ErrorKind::InvalidInput`]: std::io::ErrorKind::InvalidInput
+    #[cfg(feature = "systemd")]
+    pub fn from_systemd_fd_named(name: &str) -> io::Result<Inotify> {
+        Self::from_adopted_fd(systemd::take_inotify_fd(Some(name))?)
+    }
+
+    /// Sets up a file descriptor from another source the way `init` would
+    #[cfg(feature = "systemd")]
+    fn from_adopted_fd(fd: OwnedFd) -> io::Result<Inotify> {
+        let inotify = Inotify::from(fd);
+
+        let flags = match unsafe { libc::fcntl(**inotify.fd, libc::F_GETFL) } {
+            -1 => return Err(io::Error::last_os_error()),
+            flags => flags,
+        };
+        if unsafe { libc::fcntl(**inotify.fd, libc::F_SETFL, flags | libc::O_NONBLOCK) } == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        inotify.set_cloexec(true)?;
+
+        Ok(inotify)
+    }
+
     /// Gets an interface that allows adding and removing watches.
     /// See [`Watches::add`] and [`Watches::remove`].
     pub fn watches(&self) -> Watches {
         Watches::new(self.fd.clone())
     }
 
+    /// Gets an interface that adds watches inside another mount namespace
+    ///
+    /// `ns` refers to the mount namespace. It can be a file descriptor for
+    /// `/proc/<pid>/ns/mnt`, or a process file descriptor, in which case the
+    /// mount namespace of that process is used. Events are read from this
+    /// instance, as usual. See [`NamespaceWatches`] for details.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error from starting the helper thread, or from moving it
+    /// into the namespace. Without the `CAP_SYS_ADMIN` capability, the latter
+    /// is an error with [`ErrorKind::PermissionDenied`].
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::fs::File;
+    ///
+    /// use inotify::{Inotify, WatchMask};
+    ///
+    /// let inotify = Inotify::init()
+    ///     .expect("Failed to initialize an inotify instance");
+    ///
+    /// // The mount namespace of a container's init process
+    /// let ns = File::open("/proc/1234/ns/mnt")
+    ///     .expect("Failed to open namespace");
+    ///
+    /// let mut watches = inotify.watches_in_namespace(ns)
+    ///     .expect("Failed to enter namespace");
+    /// watches.add("/etc/hosts", WatchMask::MODIFY)
+    ///     .expect("Failed to add watch");
+    /// ```
+    ///
+    /// [`ErrorKind::PermissionDenied`]: std::io::ErrorKind::PermissionDenied
+    pub fn watches_in_namespace<N>(&self, ns: N) -> io::Result<NamespaceWatches>
+    where
+        N: Into<OwnedFd>,
+    {
+        NamespaceWatches::new(self.watches(), ns.into())
+    }
+
     /// Deprecated: use `Inotify.watches().add()` instead
     #[deprecated = "use `Inotify.watches().add()` instead"]
     pub fn add_watch<P>(&mut self, path: P, mask: WatchMask) -> io::Result<WatchDescriptor>
@@ -122,28 +249,15 @@ impl Inotify {
     /// This method calls [`Inotify::read_events`] internally and behaves
     /// essentially the same, apart from the blocking behavior. Please refer to
     /// the documentation of [`Inotify::read_events`] for more information.
-    pub fn read_events_blocking<'a>(&mut self, buffer: &'a mut [u8]) -> io::Result<Events<'a>> {
-        unsafe {
-            let res = fcntl(**self.fd, F_GETFL);
-            if res == -1 {
-                return Err(io::Error::last_os_error());
-            }
-            if fcntl(**self.fd, F_SETFL, res & !O_NONBLOCK) == -1 {
-                return Err(io::Error::last_os_error());
-            }
-        };
-        let result = self.read_events(buffer);
-        unsafe {
-            let res = fcntl(**self.fd, F_GETFL);
-            if res == -1 {
-                return Err(io::Error::last_os_error());
-            }
-            if fcntl(**self.fd, F_SETFL, res | O_NONBLOCK) == -1 {
-                return Err(io::Error::last_os_error());
-            }
-        };
-
-        result
+    ///
+    /// The inotify file descriptor itself stays in non-blocking mode while this
+    /// method waits. This means it is safe to call this method from multiple
+    /// threads at the same time, for example if the [`Inotify`] instance is
+    /// shared using an [`Arc`]. If another thread takes the available events
+    /// first, this method simply goes back to waiting.
+    pub fn read_events_blocking<'a>(&self, buffer: &'a mut [u8]) -> io::Result<Events<'a>> {
+        let num_bytes = read_blocking(**self.fd, buffer)?;
+        Ok(Events::new(Arc::downgrade(&self.fd), buffer, num_bytes))
     }
 
     /// Returns one buffer's worth of available events
@@ -160,6 +274,10 @@ impl Inotify {
     /// The `buffer` argument, as the name indicates, is used as a buffer for
     /// the inotify events. Its contents may be overwritten.
     ///
+    /// This method only requires a shared reference, as every call brings its
+    /// own buffer. If multiple threads read from the same instance, each event
+    /// is returned to exactly one of them.
+    ///
     /// # Errors
     ///
     /// This function directly returns all errors from the call to [`read`].
@@ -176,7 +294,7 @@ impl Inotify {
     /// use inotify::Inotify;
     /// use std::io::ErrorKind;
     ///
-    /// let mut inotify = Inotify::init()
+    /// let inotify = Inotify::init()
     ///     .expect("Failed to initialize an inotify instance");
     ///
     /// let mut buffer = [0; 1024];
@@ -197,48 +315,272 @@ impl Inotify {
     /// [`read`]: libc::read
     /// [`ErrorKind::UnexpectedEof`]: std::io::ErrorKind::UnexpectedEof
     /// [`ErrorKind::InvalidInput`]: std::io::ErrorKind::InvalidInput
-    pub fn read_events<'a>(&mut self, buffer: &'a mut [u8]) -> io::Result<Events<'a>> {
-        let num_bytes = read_into_buffer(**self.fd, buffer);
-
-        let num_bytes = match num_bytes {
-            0 => {
-                return Err(io::Error::new(
-                    io::ErrorKind::UnexpectedEof,
-                    "`read` return `0`, signaling end-of-file",
-                ));
-            }
-            -1 => {
-                let error = io::Error::last_os_error();
-                return Err(error);
-            }
-            _ if num_bytes < 0 => {
-                panic!(
-                    "{} {} {} {} {} {}",
-                    "Unexpected return value from `read`. Received a negative",
-                    "value that was not `-1`. According to the `read` man page",
-                    "this shouldn't happen, as either `-1` is returned on",
-                    "error, `0` on end-of-file, or a positive value for the",
-                    "number of bytes read. Returned value:",
-                    num_bytes,
-                );
-            }
-            _ => {
-                // The value returned by `read` should be `isize`. Let's quickly
-                // verify this with the following assignment, so we can be sure
-                // our cast below is valid.
-                let num_bytes: isize = num_bytes;
-
-                // The type returned by `read` is `isize`, and we've ruled out
-                // all negative values with the match arms above. This means we
-                // can safely cast to `usize`.
-                debug_assert!(num_bytes > 0);
-                num_bytes as usize
-            }
-        };
+    pub fn read_events<'a>(&self, buffer: &'a mut [u8]) -> io::Result<Events<'a>> {
+        let num_bytes = read(**self.fd, buffer)?;
+        Ok(Events::new(Arc::downgrade(&self.fd), buffer, num_bytes))
+    }
 
+    /// Returns all available events, reading until none are left
+    ///
+    /// Like [`Inotify::read_events`], but keeps reading into the rest of
+    /// `buffer`, until a read would block or `buffer` is full. Events that
+    /// arrive while the first batch is being read are picked up in the same
+    /// call, which saves system calls and wakeups, if a busy set of watches
+    /// is read from an event loop. A larger buffer makes this more effective.
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors of [`Inotify::read_events`], if the first read
+    /// fails. Errors of later reads end the loop, and are returned by the
+    /// next call instead.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use inotify::Inotify;
+    /// use std::io::ErrorKind;
+    ///
+    /// let inotify = Inotify::init()
+    ///     .expect("Failed to initialize an inotify instance");
+    ///
+    /// let mut buffer = vec![0; 64 * 1024];
+    /// match inotify.drain_events(&mut buffer) {
+    ///     Ok(events) => {
+    ///         for event in events {
+    ///             // Handle event
+    ///         }
+    ///     }
+    ///     Err(error) if error.kind() == ErrorKind::WouldBlock => {}
+    ///     Err(error) => panic!("Error while reading events: {}", error),
+    /// }
+    /// ```
+    pub fn drain_events<'a>(&self, buffer: &'a mut [u8]) -> io::Result<Events<'a>> {
+        let num_bytes = read_drained(**self.fd, buffer)?;
         Ok(Events::new(Arc::downgrade(&self.fd), buffer, num_bytes))
     }
 
+    /// Waits for events, then returns all available events
+    ///
+    /// The blocking version of [`Inotify::drain_events`]. Waits like
+    /// [`Inotify::read_events_blocking`].
+    pub fn drain_events_blocking<'a>(&self, buffer: &'a mut [u8]) -> io::Result<Events<'a>> {
+        let num_bytes = read_drained_blocking(**self.fd, buffer)?;
+        Ok(Events::new(Arc::downgrade(&self.fd), buffer, num_bytes))
+    }
+
+    /// Appends one buffer's worth of available events to `events`
+    ///
+    /// Like [`Inotify::read_events`], but reads into a buffer on the stack, and
+    /// appends owned copies of the events to `events`. Reusing the same `Vec`
+    /// across calls reuses its capacity, so a long-running collector only
+    /// allocates for the names of the events.
+    ///
+    /// Returns the number of events that were appended.
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors of [`Inotify::read_events`]. `events` is left
+    /// untouched in that case.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use inotify::Inotify;
+    /// use std::io::ErrorKind;
+    ///
+    /// let inotify = Inotify::init()
+    ///     .expect("Failed to initialize an inotify instance");
+    ///
+    /// let mut events = Vec::new();
+    /// match inotify.read_events_into(&mut events) {
+    ///     Ok(_) => {
+    ///         for event in events.drain(..) {
+    ///             // Handle event
+    ///         }
+    ///     }
+    ///     Err(error) if error.kind() == ErrorKind::WouldBlock => {}
+    ///     Err(error) => panic!("Error while reading events: {}", error),
+    /// }
+    /// ```
+    pub fn read_events_into(&self, events: &mut Vec<EventOwned>) -> io::Result<usize> {
+        let mut buffer = FixedEventBuffer::<{ get_buffer_size_for_events(16) }>::new();
+
+        let len = events.len();
+        events.extend(self.read_events(&mut buffer)?.map(|event| event.to_owned()));
+
+        Ok(events.len() - len)
+    }
+
+    /// Returns an iterator over events in a caller-provided buffer
+    ///
+    /// Like [`Events::from_bytes`], but the returned events' watch descriptors
+    /// are associated with this instance, as if they had been returned from
+    /// [`Inotify::read_events`]. Use this, if you read from this instance's
+    /// file descriptor yourself.
+    pub fn events_from_bytes<'a>(&self, bytes: &'a [u8]) -> Events<'a> {
+        Events::new(Arc::downgrade(&self.fd), bytes, bytes.len())
+    }
+
+    /// Sets a callback that is called when the event queue has overflowed
+    ///
+    /// If the application doesn't read events quickly enough, the kernel's
+    /// event queue overflows, and events are lost. The kernel then adds an
+    /// event with [`EventMask::Q_OVERFLOW`] to the queue. The callback is
+    /// called when a read returns such an event, before the events are passed
+    /// on. This makes it hard to miss, and is a good place to trigger any
+    /// resynchronization logic.
+    ///
+    /// The callback is shared with everything created from this instance,
+    /// like a [`BlockingIter`] or an `EventStream`, and is called from whichever
+    /// thread reads the overflow. It replaces any previously set callback.
+    ///
+    /// [`EventMask::Q_OVERFLOW`]: crate::EventMask::Q_OVERFLOW
+    pub fn set_overflow_hook<F>(&self, hook: F)
+    where
+        F: Fn() + Send + Sync + 'static,
+    {
+        self.fd.overflow_hook.set(Some(Arc::new(hook)));
+    }
+
+    /// Removes the callback set by [`Inotify::set_overflow_hook`]
+    pub fn clear_overflow_hook(&self) {
+        self.fd.overflow_hook.set(None);
+    }
+
+    /// Starts keeping track of the path and mask of each watch
+    ///
+    /// Once enabled, the `Debug` output of this instance and of its
+    /// [`Watches`] includes the number of watches and a table of their watch
+    /// descriptors, paths and masks. That table is truncated, if there are
+    /// many watches. This is meant as a debugging aid, and costs some memory
+    /// and time for each watch that is added.
+    ///
+    /// Only watches that are added after this call are tracked. Watches are
+    /// forgotten when they are removed, or when an event with
+    /// [`EventMask::IGNORED`] is read for them. The paths are the ones that
+    /// were passed to [`Watches::add`], so they don't reflect later renames.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use inotify::{Inotify, WatchMask};
+    ///
+    /// let inotify = Inotify::init()
+    ///     .expect("Failed to initialize an inotify instance");
+    /// inotify.enable_bookkeeping();
+    ///
+    /// inotify.watches().add("/tmp", WatchMask::CREATE)
+    ///     .expect("Failed to add watch");
+    ///
+    /// // Prints something like:
+    /// // Inotify { fd: 3, num_watches: 1, watches: {1: /tmp (CREATE)} }
+    /// println!("{:?}", inotify);
+    /// ```
+    ///
+    /// [`EventMask::IGNORED`]: crate::EventMask::IGNORED
+    pub fn enable_bookkeeping(&self) {
+        self.fd.bookkeeping.enable();
+    }
+
+    /// Stops the bookkeeping started by [`Inotify::enable_bookkeeping`]
+    ///
+    /// Forgets all watches that have been tracked so far.
+    pub fn disable_bookkeeping(&self) {
+        self.fd.bookkeeping.disable();
+    }
+
+    /// Returns the number of bytes of events that are waiting to be read
+    ///
+    /// Uses the `FIONREAD` ioctl. See [`Inotify::queue_monitor`], to find out
+    /// whether the queue is about to overflow.
+    ///
+    /// # Errors
+    ///
+    /// Directly returns the error from the ioctl.
+    pub fn queued_bytes(&self) -> io::Result<usize> {
+        queued_bytes(**self.fd)
+    }
+
+    /// Creates a monitor that reports when the event queue is getting full
+    ///
+    /// The monitor reports the queue, once the estimated number of queued
+    /// events reaches `percent` percent of [`max_queued_events`]. See
+    /// [`QueueMonitor`] for details.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error from reading the limit.
+    ///
+    /// [`max_queued_events`]: crate::max_queued_events
+    pub fn queue_monitor(&self, percent: u8) -> io::Result<QueueMonitor> {
+        QueueMonitor::new(self.fd.clone(), percent)
+    }
+
+    /// Sets a callback that is called when watch usage crosses a threshold
+    ///
+    /// Adding a watch fails with `ENOSPC`, once all instances of a user have
+    /// [`max_user_watches`] watches. To notice this before it happens, the
+    /// callback is called when a watch has been added, and this instance has
+    /// at least `percent` percent of that limit.
+    ///
+    /// The callback is called once, and again only after the number of
+    /// watches has dropped below the threshold. It is shared with everything
+    /// created from this instance, and is called from whichever thread adds
+    /// the watch. It replaces any previously set callback.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error from reading the limit, or from reading the current
+    /// number of watches.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use inotify::Inotify;
+    ///
+    /// let inotify = Inotify::init()
+    ///     .expect("Failed to initialize an inotify instance");
+    ///
+    /// inotify
+    ///     .set_usage_hook(80, |usage| {
+    ///         eprintln!(
+    ///             "Using {} of {} inotify watches",
+    ///             usage.watches, usage.limit,
+    ///         );
+    ///     })
+    ///     .expect("Failed to set usage hook");
+    /// ```
+    ///
+    /// [`max_user_watches`]: crate::max_user_watches
+    pub fn set_usage_hook<F>(&self, percent: u8, hook: F) -> io::Result<()>
+    where
+        F: Fn(WatchUsage) + Send + Sync + 'static,
+    {
+        self.fd.usage_hook.set(**self.fd, percent, Arc::new(hook))
+    }
+
+    /// Removes the callback set by [`Inotify::set_usage_hook`]
+    pub fn clear_usage_hook(&self) {
+        self.fd.usage_hook.clear();
+    }
+
+    /// Returns an [`EventParser`] for data read from this instance
+    ///
+    /// The watch descriptors of the parsed events are associated with this
+    /// instance, as if they had been returned from [`Inotify::read_events`].
+    pub fn event_parser(&self) -> EventParser {
+        EventParser::with_fd(Arc::downgrade(&self.fd))
+    }
+
+    /// Returns an [`InotifyCodec`] for data read from this instance
+    ///
+    /// The watch descriptors of the decoded events are associated with this
+    /// instance, as if they had been returned from [`Inotify::read_events`].
+    #[cfg(feature = "codec")]
+    pub fn codec(&self) -> InotifyCodec {
+        InotifyCodec::with_fd(Arc::downgrade(&self.fd))
+    }
+
     /// Deprecated: use `into_event_stream()` instead, which enforces a single `Stream` and predictable reads.
     /// Using this method to create multiple `EventStream` instances from one `Inotify` is unsupported,
     /// as they will contend over one event source and each produce unpredictable stream contents.
@@ -265,14 +607,321 @@ impl Inotify {
         EventStream::new(self.fd, buffer)
     }
 
+    /// Create a stream which collects events, using an internal buffer
+    ///
+    /// Like [`Inotify::into_event_stream`], but allocates a buffer of
+    /// `capacity` bytes, instead of requiring the caller to provide one. If
+    /// `capacity` is too small to hold the largest possible event, a buffer
+    /// that can is allocated instead. Passing `0` is therefore a simple way to
+    /// get a working stream.
+    ///
+    /// Since the stream owns its buffer, it grows the buffer if the kernel
+    /// reports it to be too small for the next event, instead of returning an
+    /// error. This can happen on file systems that allow names longer than
+    /// assumed by [`get_buffer_size_for_events`].
+    ///
+    /// Consider [`get_buffer_size_for_events`] to choose a capacity.
+    ///
+    /// [`get_buffer_size_for_events`]: crate::get_buffer_size_for_events
+    #[cfg(feature = "stream")]
+    pub fn into_event_stream_with_capacity(
+        self,
+        capacity: usize,
+    ) -> io::Result<EventStream<Vec<u8>>> {
+        let capacity = capacity.max(get_buffer_size_for_events(1));
+        self.into_event_stream(vec![0; capacity])
+            .map(EventStream::with_growable_buffer)
+    }
+
+    /// Create a stream which collects events, driven by a custom [`IoDriver`]
+    ///
+    /// Like [`Inotify::into_event_stream`], but allows for using a runtime
+    /// other than tokio, for example with `AsyncIoDriver`, which is available
+    /// with the `async-io` feature.
+    #[cfg(feature = "stream-core")]
+    pub fn into_event_stream_with_driver<D, T>(self, buffer: T) -> io::Result<EventStream<T, D>>
+    where
+        D: IoDriver,
+        T: AsMut<[u8]> + AsRef<[u8]>,
+    {
+        EventStream::new(self.fd, buffer)
+    }
+
     /// Creates an `Inotify` instance using the file descriptor which was originally
     /// initialized in `Inotify::init`. This is intended to be used to transform an
     /// `EventStream` back into an `Inotify`. Do not attempt to clone `Inotify` with this.
-    #[cfg(feature = "stream")]
     pub(crate) fn from_file_descriptor(fd: Arc<FdGuard>) -> Self {
         Inotify { fd }
     }
 
+    /// Create a blocking iterator which collects events. Consumes the `Inotify`
+    /// instance.
+    ///
+    /// Returns an [`Iterator`] over all events that are available. Whenever no
+    /// more events are left in `buffer`, [`BlockingIter::next`] blocks until
+    /// the next events become available. This iterator never ends.
+    ///
+    /// This replaces the common pattern of calling
+    /// [`Inotify::read_events_blocking`] in a loop, and then looping over the
+    /// returned events.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use inotify::{Inotify, WatchMask};
+    ///
+    /// let inotify = Inotify::init()
+    ///     .expect("Failed to initialize an inotify instance");
+    ///
+    /// inotify.watches().add("/tmp", WatchMask::CREATE)
+    ///     .expect("Failed to add watch");
+    ///
+    /// for event in inotify.into_blocking_iter([0; 1024]) {
+    ///     let event = event.expect("Error while reading events");
+    ///     // Handle event
+    /// }
+    /// ```
+    ///
+    /// [`BlockingIter::next`]: Iterator::next
+    pub fn into_blocking_iter<T>(self, buffer: T) -> BlockingIter<T>
+    where
+        T: AsMut<[u8]> + AsRef<[u8]>,
+    {
+        BlockingIter::new(self.fd, buffer)
+    }
+
+    /// Spawns a thread that forwards events over a channel. Consumes the
+    /// `Inotify` instance.
+    ///
+    /// The spawned thread waits for events and sends them over a
+    /// [`std::sync::mpsc`] channel, whose receiving end is returned. Watches
+    /// can still be added and removed through [`DispatcherHandle::watches`].
+    ///
+    /// The thread stops when [`DispatcherHandle::stop`] is called, when the
+    /// [`DispatcherHandle`] or the [`Receiver`] is dropped, or when reading
+    /// events fails. In the latter case, [`DispatcherHandle::stop`] returns
+    /// the error.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error, if the resources for stopping the thread can't be
+    /// created, or if the thread can't be spawned.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use inotify::{Inotify, WatchMask};
+    ///
+    /// let inotify = Inotify::init()
+    ///     .expect("Failed to initialize an inotify instance");
+    ///
+    /// inotify.watches().add("/tmp", WatchMask::CREATE)
+    ///     .expect("Failed to add watch");
+    ///
+    /// let (events, dispatcher) = inotify.spawn_dispatcher()
+    ///     .expect("Failed to spawn dispatcher");
+    ///
+    /// for event in events.iter().take(10) {
+    ///     // Handle event
+    /// }
+    ///
+    /// dispatcher.stop()
+    ///     .expect("Error while reading events");
+    /// ```
+    ///
+    /// [`Receiver`]: std::sync::mpsc::Receiver
+    pub fn spawn_dispatcher(self) -> io::Result<(mpsc::Receiver<EventOwned>, DispatcherHandle)> {
+        let (sender, receiver) = mpsc::channel();
+        let handle = DispatcherHandle::spawn(self.fd, sender)?;
+
+        Ok((receiver, handle))
+    }
+
+    /// Spawns a thread that forwards events into a bounded crossbeam channel.
+    /// Consumes the `Inotify` instance.
+    ///
+    /// Works like [`Inotify::spawn_dispatcher`], but sends events over a
+    /// [`crossbeam_channel`] channel that holds at most `capacity` events.
+    /// What happens when the channel is full is defined by `policy`.
+    ///
+    /// Unlike the channels from [`std::sync::mpsc`], the returned receiver can
+    /// be cloned, to distribute events between multiple consumers.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`io::Error`] with [`ErrorKind`]`::InvalidInput`, if
+    /// `capacity` is zero. Otherwise, returns an error, if the resources for
+    /// stopping the thread can't be created, or if the thread can't be
+    /// spawned.
+    ///
+    /// [`ErrorKind`]: std::io::ErrorKind
+    #[cfg(feature = "crossbeam")]
+    pub fn spawn_crossbeam_dispatcher(
+        self,
+        capacity: usize,
+        policy: FullChannelPolicy,
+    ) -> io::Result<(crossbeam_channel::Receiver<EventOwned>, DispatcherHandle)> {
+        // A zero-capacity channel is never ready for `try_send`, and there'd
+        // be no oldest event to drop.
+        if capacity == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "dispatcher channel capacity must not be zero",
+            ));
+        }
+
+        let (sender, receiver) = crossbeam_channel::bounded(capacity);
+        let sink = CrossbeamSink {
+            sender,
+            receiver: match policy {
+                FullChannelPolicy::DropOldest => Some(receiver.clone()),
+                _ => None,
+            },
+            policy,
+        };
+        let handle = DispatcherHandle::spawn(self.fd, sink)?;
+
+        Ok((receiver, handle))
+    }
+
+    /// Registers this instance with an epoll instance
+    ///
+    /// Adds the inotify file descriptor to the interest list of `epfd`, so it
+    /// is reported as readable (`EPOLLIN`) when events are available. Reported
+    /// readiness carries `token` in its `u64` field.
+    ///
+    /// The registration is level-triggered. After being woken up, call
+    /// [`Inotify::read_events`] until it returns
+    /// [`ErrorKind::WouldBlock`], or at least once per wakeup.
+    ///
+    /// # Errors
+    ///
+    /// Directly returns the error from the call to [`epoll_ctl`].
+    ///
+    /// [`ErrorKind::WouldBlock`]: std::io::ErrorKind::WouldBlock
+    /// [`epoll_ctl`]: libc::epoll_ctl
+    pub fn register_epoll<F>(&self, epfd: F, token: u64) -> io::Result<()>
+    where
+        F: AsFd,
+    {
+        epoll::add(epfd.as_fd().as_raw_fd(), **self.fd, token)
+    }
+
+    /// Removes this instance from an epoll instance
+    ///
+    /// Reverses [`Inotify::register_epoll`]. The inotify file descriptor is
+    /// also removed from all epoll instances automatically, when it is closed.
+    ///
+    /// # Errors
+    ///
+    /// Directly returns the error from the call to [`epoll_ctl`].
+    ///
+    /// [`epoll_ctl`]: libc::epoll_ctl
+    pub fn deregister_epoll<F>(&self, epfd: F) -> io::Result<()>
+    where
+        F: AsFd,
+    {
+        epoll::delete(epfd.as_fd().as_raw_fd(), **self.fd)
+    }
+
+    /// Indicates whether the close-on-exec flag is set
+    ///
+    /// Instances created by [`Inotify::init`] have this flag set, which means
+    /// the inotify file descriptor is not inherited by programs executed via
+    /// `exec`.
+    ///
+    /// # Errors
+    ///
+    /// Directly returns the error from the call to [`fcntl`].
+    ///
+    /// [`fcntl`]: libc::fcntl
+    pub fn is_cloexec(&self) -> io::Result<bool> {
+        match unsafe { libc::fcntl(**self.fd, libc::F_GETFD) } {
+            -1 => Err(io::Error::last_os_error()),
+            flags => Ok(flags & libc::FD_CLOEXEC != 0),
+        }
+    }
+
+    /// Sets or clears the close-on-exec flag
+    ///
+    /// Clearing the flag allows the inotify file descriptor to be inherited by
+    /// programs executed via `exec`, for example a worker process started by a
+    /// supervisor. The child can pick it up using [`FromRawFd::from_raw_fd`],
+    /// once it knows the file descriptor number (from a command line argument
+    /// or an environment variable, for example).
+    ///
+    /// Please note that the flag applies to the file descriptor, and that all
+    /// processes that are spawned while the flag is cleared will inherit it.
+    /// Consider [`Inotify::into_inheritable_fd`], if the instance is meant to
+    /// be used by a child process exclusively.
+    ///
+    /// # Errors
+    ///
+    /// Directly returns the error from the calls to [`fcntl`].
+    ///
+    /// [`fcntl`]: libc::fcntl
+    pub fn set_cloexec(&self, cloexec: bool) -> io::Result<()> {
+        let flags = match unsafe { libc::fcntl(**self.fd, libc::F_GETFD) } {
+            -1 => return Err(io::Error::last_os_error()),
+            flags => flags,
+        };
+
+        let flags = if cloexec {
+            flags | libc::FD_CLOEXEC
+        } else {
+            flags & !libc::FD_CLOEXEC
+        };
+
+        match unsafe { libc::fcntl(**self.fd, libc::F_SETFD, flags) } {
+            -1 => Err(io::Error::last_os_error()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Converts the instance into a file descriptor that child processes
+    /// inherit
+    ///
+    /// Clears the close-on-exec flag (see [`Inotify::set_cloexec`]) and
+    /// returns the file descriptor. Keep the [`OwnedFd`] alive until the child
+    /// process has been spawned, and drop it afterwards, so the child is the
+    /// only remaining user of the instance.
+    ///
+    /// The file descriptor stays in non-blocking mode, which is what
+    /// [`Inotify`] expects when the child process turns it back into an
+    /// instance using [`FromRawFd::from_raw_fd`]. All existing watches are
+    /// preserved.
+    ///
+    /// # Errors
+    ///
+    /// Directly returns the error from the calls to [`fcntl`]. The instance is
+    /// closed in that case.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::{os::unix::io::AsRawFd, process::Command};
+    ///
+    /// use inotify::Inotify;
+    ///
+    /// let inotify = Inotify::init()
+    ///     .expect("Failed to initialize an inotify instance");
+    ///
+    /// let fd = inotify.into_inheritable_fd()
+    ///     .expect("Failed to clear close-on-exec flag");
+    ///
+    /// let child = Command::new("worker")
+    ///     .arg(fd.as_raw_fd().to_string())
+    ///     .spawn()
+    ///     .expect("Failed to spawn worker");
+    ///
+    /// // The child has its own copy of the file descriptor now.
+    /// drop(fd);
+    /// ```
+    pub fn into_inheritable_fd(self) -> io::Result<OwnedFd> {
+        self.set_cloexec(false)?;
+        Ok(self.into())
+    }
+
     /// Closes the inotify instance
     ///
     /// Closes the file descriptor referring to the inotify instance. The user
@@ -311,6 +960,15 @@ impl Inotify {
     }
 }
 
+impl fmt::Debug for Inotify {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut f = f.debug_struct("Inotify");
+        f.field("fd", &**self.fd);
+        self.fd.bookkeeping.debug_fields(&mut f);
+        f.finish()
+    }
+}
+
 impl AsRawFd for Inotify {
     #[inline]
     fn as_raw_fd(&self) -> RawFd {
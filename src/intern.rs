@@ -0,0 +1,170 @@
+use std::{collections::HashSet, ffi::OsStr, sync::Arc};
+
+/// Decides how event names are stored, when events are converted
+///
+/// Passed to [`Event::to_shared_with`]. The default is [`AllocateNames`],
+/// which allocates every name separately, just like [`Event::to_shared`].
+/// [`NameInterner`] instead returns the same allocation for equal names.
+///
+/// Closures that take an `&OsStr` and return an `Arc<OsStr>` implement this
+/// trait as well.
+///
+/// [`Event::to_shared_with`]: crate::Event::to_shared_with
+/// [`Event::to_shared`]: crate::Event::to_shared
+pub trait NamePolicy {
+    /// Returns the name to store in the converted event
+    fn name(&mut self, name: &OsStr) -> Arc<OsStr>;
+}
+
+impl<F> NamePolicy for F
+where
+    F: FnMut(&OsStr) -> Arc<OsStr>,
+{
+    fn name(&mut self, name: &OsStr) -> Arc<OsStr> {
+        self(name)
+    }
+}
+
+/// Allocates every event name separately
+///
+/// See [`NamePolicy`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct AllocateNames;
+
+impl NamePolicy for AllocateNames {
+    fn name(&mut self, name: &OsStr) -> Arc<OsStr> {
+        Arc::from(name)
+    }
+}
+
+/// Returns the same allocation for names that have been seen before
+///
+/// If the same few files keep changing, like a log file that is appended to,
+/// most events have a name that has been seen before. Applications that keep
+/// many events around then store many copies of the same names. With a
+/// `NameInterner`, those events share a single allocation per name.
+///
+/// The interner keeps every name it has returned. Call
+/// [`NameInterner::purge`] from time to time to drop names that are no
+/// longer used, or set a limit with [`NameInterner::with_limit`].
+///
+/// # Examples
+///
+/// ```
+/// use inotify::{Inotify, NameInterner};
+///
+/// let inotify = Inotify::init()
+///     .expect("Failed to initialize an inotify instance");
+/// let mut interner = NameInterner::new();
+///
+/// let mut buffer = [0; 1024];
+/// if let Ok(events) = inotify.read_events(&mut buffer) {
+///     let events: Vec<_> = events
+///         .map(|event| event.to_shared_with(&mut interner))
+///         .collect();
+/// }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct NameInterner {
+    names: HashSet<Arc<OsStr>>,
+    limit: Option<usize>,
+}
+
+impl NameInterner {
+    /// Creates an interner without a limit
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates an interner that keeps at most `limit` names
+    ///
+    /// Once the limit has been reached, names that are no longer used are
+    /// dropped. If all names are still in use, new names are allocated
+    /// separately, without being kept.
+    pub fn with_limit(limit: usize) -> Self {
+        NameInterner {
+            names: HashSet::new(),
+            limit: Some(limit),
+        }
+    }
+
+    /// Returns the number of names that are kept
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    /// Indicates whether no names are kept
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    /// Drops the names that are only kept by the interner itself
+    pub fn purge(&mut self) {
+        self.names.retain(|name| Arc::strong_count(name) > 1);
+    }
+
+    /// Drops all names
+    pub fn clear(&mut self) {
+        self.names.clear();
+    }
+
+    fn is_full(&self) -> bool {
+        self.limit.is_some_and(|limit| self.names.len() >= limit)
+    }
+}
+
+impl NamePolicy for NameInterner {
+    fn name(&mut self, name: &OsStr) -> Arc<OsStr> {
+        if let Some(interned) = self.names.get(name) {
+            return interned.clone();
+        }
+
+        if self.is_full() {
+            self.purge();
+        }
+
+        let name: Arc<OsStr> = Arc::from(name);
+        if !self.is_full() {
+            self.names.insert(name.clone());
+        }
+
+        name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{ffi::OsStr, sync::Arc};
+
+    use super::{NameInterner, NamePolicy};
+
+    #[test]
+    fn interner_should_return_the_same_allocation_for_equal_names() {
+        let mut interner = NameInterner::new();
+
+        let a = interner.name(OsStr::new("app.log"));
+        let b = interner.name(OsStr::new("app.log"));
+        assert!(Arc::ptr_eq(&a, &b));
+        assert_eq!(interner.len(), 1);
+
+        drop((a, b));
+        interner.purge();
+        assert!(interner.is_empty());
+    }
+
+    #[test]
+    fn interner_should_stop_keeping_names_at_the_limit() {
+        let mut interner = NameInterner::with_limit(1);
+
+        let a = interner.name(OsStr::new("a"));
+        let b = interner.name(OsStr::new("b"));
+        assert_eq!(&*b, OsStr::new("b"));
+        assert_eq!(interner.len(), 1);
+
+        // Once `a` is no longer used, it makes room for other names.
+        drop(a);
+        let b_2 = interner.name(OsStr::new("b"));
+        assert!(!Arc::ptr_eq(&b, &b_2));
+        assert!(Arc::ptr_eq(&b_2, &interner.name(OsStr::new("b"))));
+    }
+}
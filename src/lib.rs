@@ -18,7 +18,7 @@
 //!     WatchMask,
 //! };
 //!
-//! let mut inotify = Inotify::init()
+//! let inotify = Inotify::init()
 //!     .expect("Error while initializing inotify instance");
 //!
 //! # // Create a temporary file, so `Watches::add` won't return an error.
@@ -82,19 +82,162 @@
 #[macro_use]
 extern crate bitflags;
 
+mod arena;
+mod batch;
+mod blocking;
+mod bookkeeping;
+#[cfg(feature = "stream")]
+mod broadcast;
+mod buffer_name;
+mod coalesce;
+#[cfg(feature = "codec")]
+mod codec;
+mod config;
+mod debounce;
+mod dispatcher;
+mod epoll;
+mod error;
 mod events;
+#[cfg(feature = "fanotify")]
+pub mod fanotify;
 mod fd_guard;
+mod fixed_buffer;
+mod follow;
+#[cfg(feature = "glib")]
+mod glib_source;
+#[cfg(feature = "globset")]
+mod ignore;
+mod inline_name;
 mod inotify;
+mod intern;
+#[cfg(feature = "clap")]
+mod mask_clap;
+mod mask_format;
+#[cfg(feature = "serde")]
+mod mask_serde;
+mod mock;
+mod namespace;
+#[cfg(feature = "notify-types")]
+mod notify_compat;
+mod oneshot;
+mod parser;
+mod path_watch;
+mod probe;
+mod queue;
+mod rate;
+mod record;
+mod remount;
+mod rename;
+mod resolve;
+mod resync;
+#[cfg(feature = "stream-core")]
+mod router;
+mod save;
+mod scan;
+#[cfg(feature = "stream")]
+mod service;
+mod set;
+mod shared;
+mod source;
+#[cfg(feature = "systemd")]
+mod systemd;
+#[cfg(feature = "test-util")]
+mod test_util;
+mod unchanged;
+mod usage;
+#[cfg(feature = "camino")]
+mod utf8;
 mod util;
+#[cfg(feature = "stream")]
+mod watch_file;
+mod watcher;
 mod watches;
+#[cfg(feature = "wire")]
+mod wire;
 
-#[cfg(feature = "stream")]
+#[cfg(feature = "stream-core")]
+mod driver;
+#[cfg(feature = "stream-core")]
 mod stream;
 
-pub use crate::events::{Event, EventMask, EventOwned, Events};
+pub use crate::arena::EventArena;
+pub use crate::batch::Batcher;
+pub use crate::blocking::BlockingIter;
+pub use crate::buffer_name::BufferName;
+pub use crate::coalesce::{Change, CoalescedEvent, Coalescer};
+pub use crate::config::{ConfigChange, ConfigWatcher};
+pub use crate::debounce::Debouncer;
+pub use crate::dispatcher::DispatcherHandle;
+pub use crate::error::Error;
+pub use crate::events::{
+    BufferedEvents, CheckedEvents, Event, EventAuxiliaryFlags, EventBuffered, EventInline,
+    EventKind, EventMask, EventMaskParseError, EventOwned, EventShared, EventSubject, Events,
+    ParseError, ParsedEventMask, TypedEvent, TypedEvents, TypedItem,
+};
+pub use crate::fixed_buffer::FixedEventBuffer;
+pub use crate::follow::{FileFollower, FollowEvent};
+pub use crate::inline_name::InlineName;
 pub use crate::inotify::Inotify;
-pub use crate::util::{get_absolute_path_buffer_size, get_buffer_size};
-pub use crate::watches::{WatchDescriptor, WatchMask, Watches};
+pub use crate::intern::{AllocateNames, NameInterner, NamePolicy};
+pub use crate::mask_format::ParseMaskError;
+pub use crate::mock::MockInotify;
+pub use crate::namespace::NamespaceWatches;
+pub use crate::oneshot::{OneshotEvent, WaitFor};
+pub use crate::parser::EventParser;
+pub use crate::path_watch::{PathEvent, PathWatcher};
+pub use crate::probe::{kernel_supports, Feature};
+pub use crate::queue::{QueueLevel, QueueMonitor};
+pub use crate::rate::{RateItem, RateLimiter};
+pub use crate::record::{Recorder, Replayer};
+pub use crate::remount::{RemountEvent, RemountWatcher};
+pub use crate::rename::{RenameItem, RenameTracker};
+pub use crate::resolve::{PathItem, PathResolver};
+pub use crate::resync::Resync;
+pub use crate::save::{SaveDetector, SaveItem};
+pub use crate::scan::Existing;
+pub use crate::set::{InotifySet, InstanceKey, SetEvents};
+pub use crate::shared::{Registration, SharedInotify};
+pub use crate::source::EventSource;
+pub use crate::unchanged::{Compare, UnchangedFilter};
+pub use crate::usage::{user_watch_usage, WatchUsage};
+pub use crate::util::{
+    get_absolute_path_buffer_size, get_buffer_size, get_buffer_size_for_events,
+    get_buffer_size_for_path, max_queued_events, max_user_instances, max_user_watches,
+};
+pub use crate::watcher::Watcher;
+pub use crate::watches::{
+    AddWatchError, WatchDescriptor, WatchEvents, WatchFlags, WatchMask, Watches,
+};
 
 #[cfg(feature = "stream")]
-pub use self::stream::EventStream;
+pub use self::batch::Batched;
+#[cfg(feature = "stream")]
+pub use self::broadcast::{BroadcastHandle, Broadcaster, Subscription};
+#[cfg(feature = "codec")]
+pub use self::codec::InotifyCodec;
+#[cfg(feature = "stream")]
+pub use self::debounce::Debounced;
+#[cfg(feature = "async-io")]
+pub use self::driver::AsyncIoDriver;
+#[cfg(feature = "stream-core")]
+pub use self::driver::IoDriver;
+#[cfg(feature = "stream")]
+pub use self::driver::TokioDriver;
+#[cfg(feature = "globset")]
+pub use self::ignore::IgnoreRules;
+#[cfg(feature = "stream-core")]
+pub use self::router::{WatchRouter, WatchStream};
+#[cfg(feature = "stream")]
+pub use self::service::{ServiceEvents, ServiceHandle, WatcherService};
+#[cfg(feature = "stream-core")]
+pub use self::stream::{CloseHandle, EventStream, TypedEventStream};
+#[cfg(feature = "stream")]
+pub use self::watch_file::{watch_file, FileEvents};
+#[cfg(feature = "crossbeam")]
+pub use crate::dispatcher::FullChannelPolicy;
+#[cfg(feature = "clap")]
+pub use crate::mask_clap::WatchMaskParser;
+#[cfg(feature = "test-util")]
+pub use crate::test_util::EventInjector;
+#[cfg(feature = "wire")]
+pub use crate::wire::{WireItem, WireReader, WireWriter};
@@ -0,0 +1,115 @@
+use std::ffi::OsStr;
+
+use bitflags::Flags;
+use clap::{
+    builder::{PossibleValue, TypedValueParser, ValueParserFactory},
+    error::ErrorKind,
+    Arg, Command, Error,
+};
+
+use crate::mask_format::parse_mask;
+use crate::watches::WatchMask;
+
+/// Parses command-line arguments into a [`WatchMask`]
+///
+/// Accepts flag names separated by `,` or `|`, like `create,modify,close_write`,
+/// which matches the `--event` option of `inotifywait`. Flag names are parsed
+/// like by [`WatchMask::from_str`](std::str::FromStr::from_str).
+///
+/// Available with the `clap` feature. Arguments of type [`WatchMask`] use this
+/// parser automatically, or it can be passed to [`Arg::value_parser`]
+/// explicitly.
+///
+/// # Examples
+///
+/// ```
+/// use clap::{Arg, Command};
+/// use inotify::{WatchMask, WatchMaskParser};
+///
+/// let command = Command::new("watch")
+///     .arg(Arg::new("events").long("events").value_parser(WatchMaskParser));
+///
+/// let matches = command.get_matches_from(["watch", "--events", "create,modify"]);
+/// assert_eq!(
+///     matches.get_one::<WatchMask>("events"),
+///     Some(&(WatchMask::CREATE | WatchMask::MODIFY)),
+/// );
+/// ```
+#[derive(Clone, Copy, Debug, Default)]
+pub struct WatchMaskParser;
+
+impl TypedValueParser for WatchMaskParser {
+    type Value = WatchMask;
+
+    fn parse_ref(
+        &self,
+        cmd: &Command,
+        arg: Option<&Arg>,
+        value: &OsStr,
+    ) -> Result<Self::Value, Error> {
+        let value = value
+            .to_str()
+            .ok_or_else(|| Error::new(ErrorKind::InvalidUtf8).with_cmd(cmd))?;
+
+        parse_mask(&value.replace(',', "|")).map_err(|error| {
+            let arg = arg.map_or_else(|| "...".to_owned(), ToString::to_string);
+            Error::raw(
+                ErrorKind::InvalidValue,
+                format!("invalid value '{}' for '{}': {}\n", value, arg, error),
+            )
+            .with_cmd(cmd)
+        })
+    }
+
+    fn possible_values(&self) -> Option<Box<dyn Iterator<Item = PossibleValue> + '_>> {
+        let names = WatchMask::FLAGS
+            .iter()
+            .map(|flag| PossibleValue::new(flag.name().to_ascii_lowercase()));
+
+        Some(Box::new(names))
+    }
+}
+
+impl ValueParserFactory for WatchMask {
+    type Parser = WatchMaskParser;
+
+    fn value_parser() -> Self::Parser {
+        WatchMaskParser
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::{error::ErrorKind, value_parser, Arg, Command};
+
+    use crate::WatchMask;
+
+    fn command() -> Command {
+        Command::new("watch").arg(
+            Arg::new("events")
+                .long("events")
+                .value_parser(value_parser!(WatchMask)),
+        )
+    }
+
+    #[test]
+    fn masks_should_be_parsed_from_arguments() {
+        let matches = command()
+            .try_get_matches_from(["watch", "--events", "create,modify,close_write"])
+            .unwrap();
+
+        assert_eq!(
+            matches.get_one::<WatchMask>("events"),
+            Some(&(WatchMask::CREATE | WatchMask::MODIFY | WatchMask::CLOSE_WRITE))
+        );
+    }
+
+    #[test]
+    fn unknown_flags_should_be_rejected() {
+        let error = command()
+            .try_get_matches_from(["watch", "--events", "create,foo"])
+            .unwrap_err();
+
+        assert_eq!(error.kind(), ErrorKind::InvalidValue);
+    }
+}
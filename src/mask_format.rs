@@ -0,0 +1,201 @@
+use std::{error::Error, fmt, str::FromStr};
+
+use bitflags::Flags;
+
+use crate::events::EventMask;
+use crate::watches::{WatchEvents, WatchFlags, WatchMask};
+
+/// Formats a mask as flag names, separated by `" | "`
+///
+/// Bits that have no name are written as a hexadecimal number at the end.
+fn format_mask<F>(mask: &F, f: &mut fmt::Formatter<'_>) -> fmt::Result
+where
+    F: Flags,
+    F::Bits: bitflags::parser::WriteHex,
+{
+    bitflags::parser::to_writer(mask, f)
+}
+
+/// Parses a mask from flag names, separated by `|`
+///
+/// Flag names are matched case-insensitively, and may be prefixed with `IN_`,
+/// like the constants in the C API. Hexadecimal numbers prefixed with `0x` are
+/// accepted too. An empty string results in an empty mask.
+pub(crate) fn parse_mask<F>(s: &str) -> Result<F, ParseMaskError>
+where
+    F: Flags<Bits = u32>,
+{
+    let mut mask = F::empty();
+
+    if s.trim().is_empty() {
+        return Ok(mask);
+    }
+
+    for part in s.split('|') {
+        let part = part.trim();
+
+        let flag = if let Some(hex) = part.strip_prefix("0x").or_else(|| part.strip_prefix("0X")) {
+            u32::from_str_radix(hex, 16)
+                .map(F::from_bits_retain)
+                .map_err(|_| ParseMaskError::new(part))?
+        } else {
+            let name = if part.len() > 3 && part[..3].eq_ignore_ascii_case("IN_") {
+                &part[3..]
+            } else {
+                part
+            };
+
+            F::FLAGS
+                .iter()
+                .find(|flag| flag.name().eq_ignore_ascii_case(name))
+                .map(|flag| F::from_bits_retain(flag.value().bits()))
+                .ok_or_else(|| ParseMaskError::new(part))?
+        };
+
+        mask.insert(flag);
+    }
+
+    Ok(mask)
+}
+
+/// An error that occurred while parsing a [`WatchMask`] or [`EventMask`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseMaskError {
+    flag: String,
+}
+
+impl ParseMaskError {
+    fn new(flag: &str) -> Self {
+        ParseMaskError {
+            flag: flag.to_owned(),
+        }
+    }
+
+    /// Returns the part of the input that could not be parsed
+    pub fn flag(&self) -> &str {
+        &self.flag
+    }
+}
+
+impl fmt::Display for ParseMaskError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.flag.is_empty() {
+            write!(f, "empty flag in mask")
+        } else {
+            write!(f, "unknown flag `{}` in mask", self.flag)
+        }
+    }
+}
+
+impl Error for ParseMaskError {}
+
+impl fmt::Display for WatchMask {
+    /// Formats the mask as flag names, like `CREATE | MODIFY`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        format_mask(self, f)
+    }
+}
+
+impl FromStr for WatchMask {
+    type Err = ParseMaskError;
+
+    /// Parses flag names separated by `|`, like `create | modify`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_mask(s)
+    }
+}
+
+impl fmt::Display for WatchEvents {
+    /// Formats the events as flag names, like `CREATE | MODIFY`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        format_mask(self, f)
+    }
+}
+
+impl FromStr for WatchEvents {
+    type Err = ParseMaskError;
+
+    /// Parses flag names separated by `|`, like `create | modify`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_mask(s)
+    }
+}
+
+impl fmt::Display for WatchFlags {
+    /// Formats the flags as flag names, like `ONESHOT | ONLYDIR`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        format_mask(self, f)
+    }
+}
+
+impl FromStr for WatchFlags {
+    type Err = ParseMaskError;
+
+    /// Parses flag names separated by `|`, like `oneshot | onlydir`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_mask(s)
+    }
+}
+
+impl fmt::Display for EventMask {
+    /// Formats the mask as flag names, like `CREATE | ISDIR`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        format_mask(self, f)
+    }
+}
+
+impl FromStr for EventMask {
+    type Err = ParseMaskError;
+
+    /// Parses flag names separated by `|`, like `create | isdir`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_mask(s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{EventMask, WatchEvents, WatchFlags, WatchMask};
+
+    #[test]
+    fn masks_should_round_trip_through_strings() {
+        let mask = WatchMask::CREATE | WatchMask::MODIFY | WatchMask::DONT_FOLLOW;
+        assert_eq!(mask.to_string(), "CREATE | MODIFY | DONT_FOLLOW");
+        assert_eq!(mask.to_string().parse::<WatchMask>(), Ok(mask));
+
+        let mask = EventMask::from_bits_retain(EventMask::CREATE.bits() | 0x0000_1000);
+        assert_eq!(mask.to_string(), "CREATE | 0x1000");
+        assert_eq!(mask.to_string().parse::<EventMask>(), Ok(mask));
+    }
+
+    #[test]
+    fn parse_should_be_lenient_about_case_prefixes_and_whitespace() {
+        assert_eq!(
+            "create|IN_MODIFY |  Close_Write".parse::<WatchMask>(),
+            Ok(WatchMask::CREATE | WatchMask::MODIFY | WatchMask::CLOSE_WRITE)
+        );
+        assert_eq!("move".parse::<WatchMask>(), Ok(WatchMask::MOVE));
+        assert_eq!("".parse::<WatchMask>(), Ok(WatchMask::empty()));
+    }
+
+    #[test]
+    fn parse_should_reject_unknown_flags() {
+        let error = "CREATE | CRATE".parse::<WatchMask>().unwrap_err();
+        assert_eq!(error.flag(), "CRATE");
+
+        let error = "CREATE |".parse::<WatchMask>().unwrap_err();
+        assert_eq!(error.flag(), "");
+    }
+
+    #[test]
+    fn events_and_flags_should_only_parse_their_own_names() {
+        assert_eq!("create".parse::<WatchEvents>(), Ok(WatchEvents::CREATE));
+        assert_eq!("oneshot".parse::<WatchFlags>(), Ok(WatchFlags::ONESHOT));
+
+        assert_eq!(
+            "oneshot".parse::<WatchEvents>().unwrap_err().flag(),
+            "oneshot"
+        );
+        assert_eq!("create".parse::<WatchFlags>().unwrap_err().flag(), "create");
+    }
+}
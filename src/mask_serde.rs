@@ -0,0 +1,113 @@
+use std::fmt;
+
+use serde::{
+    de::{self, SeqAccess, Visitor},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+use crate::mask_format::parse_mask;
+use crate::watches::WatchMask;
+
+/// Serializes the mask as flag names, like `"CREATE | MODIFY"`
+impl Serialize for WatchMask {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+/// Deserializes the mask from flag names
+///
+/// Accepts either a string of flag names separated by `|`, like
+/// `"CREATE|MODIFY"`, or a sequence of flag names, like
+/// `["CREATE", "MODIFY"]`. Flag names are parsed like by
+/// [`WatchMask::from_str`](std::str::FromStr::from_str).
+impl<'de> Deserialize<'de> for WatchMask {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(WatchMaskVisitor)
+    }
+}
+
+struct WatchMaskVisitor;
+
+impl<'de> Visitor<'de> for WatchMaskVisitor {
+    type Value = WatchMask;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "flag names, as a string separated by `|`, or a sequence")
+    }
+
+    fn visit_str<E>(self, s: &str) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        parse_mask(s).map_err(E::custom)
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut mask = WatchMask::empty();
+
+        while let Some(flag) = seq.next_element::<String>()? {
+            // A single flag must not be empty, or contain more than one flag.
+            if flag.trim().is_empty() || flag.contains('|') {
+                return Err(de::Error::invalid_value(de::Unexpected::Str(&flag), &self));
+            }
+
+            mask.insert(parse_mask(&flag).map_err(de::Error::custom)?);
+        }
+
+        Ok(mask)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{
+        de::{
+            value::{Error, SeqDeserializer, StrDeserializer},
+            IntoDeserializer,
+        },
+        Deserialize,
+    };
+
+    use crate::WatchMask;
+
+    fn from_str(s: &str) -> Result<WatchMask, Error> {
+        let deserializer: StrDeserializer<Error> = s.into_deserializer();
+        WatchMask::deserialize(deserializer)
+    }
+
+    fn from_seq(flags: &[&'static str]) -> Result<WatchMask, Error> {
+        let deserializer: SeqDeserializer<_, Error> = flags.to_vec().into_deserializer();
+        WatchMask::deserialize(deserializer)
+    }
+
+    #[test]
+    fn masks_should_be_deserialized_from_strings() {
+        assert_eq!(
+            from_str("CREATE|MODIFY").unwrap(),
+            WatchMask::CREATE | WatchMask::MODIFY
+        );
+        assert_eq!(from_str("close_write").unwrap(), WatchMask::CLOSE_WRITE);
+        assert!(from_str("CREATE|FOO").is_err());
+    }
+
+    #[test]
+    fn masks_should_be_deserialized_from_sequences() {
+        assert_eq!(
+            from_seq(&["CREATE", "IN_MODIFY"]).unwrap(),
+            WatchMask::CREATE | WatchMask::MODIFY
+        );
+        assert_eq!(from_seq(&[]).unwrap(), WatchMask::empty());
+        assert!(from_seq(&["CREATE", ""]).is_err());
+        assert!(from_seq(&["CREATE|MODIFY"]).is_err());
+    }
+}
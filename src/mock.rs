@@ -0,0 +1,203 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    ffi::OsStr,
+    io, mem,
+    os::{raw::c_int, unix::io::FromRawFd},
+    path::{Path, PathBuf},
+    sync::{Arc, Condvar, Mutex, MutexGuard},
+};
+
+use inotify_sys as ffi;
+
+use crate::events::{encode, EventMask, Events};
+use crate::fd_guard::{FdGuard, FdHandle};
+use crate::source::EventSource;
+use crate::watches::WatchDescriptor;
+
+/// An [`EventSource`] whose events are pushed into it by the caller
+///
+/// This is meant for testing code that handles events, without having to
+/// touch the file system and wait for the kernel. Instead of watching files,
+/// get watch descriptors using [`MockInotify::add_watch`], and push events
+/// for them using [`MockInotify::push`]. They are returned from the next
+/// read, in the order in which they were pushed.
+///
+/// `MockInotify` can be shared between threads. If no events are available,
+/// [`EventSource::read_events_blocking`] waits until another thread pushes
+/// one.
+///
+/// See [`EventSource`] for an example.
+#[derive(Debug)]
+pub struct MockInotify {
+    fd: Arc<FdGuard>,
+    state: Mutex<State>,
+    pushed: Condvar,
+}
+
+impl MockInotify {
+    /// Creates a `MockInotify` without any watches or events
+    ///
+    /// # Errors
+    ///
+    /// Each instance has a file descriptor, to make its watch descriptors
+    /// distinct from those of other instances. Returns the error from
+    /// creating it.
+    pub fn new() -> io::Result<Self> {
+        let fd = unsafe { libc::eventfd(0, libc::EFD_CLOEXEC | libc::EFD_NONBLOCK) };
+        if fd == -1 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(MockInotify {
+            fd: Arc::new(unsafe { FdGuard::from_raw_fd(fd) }),
+            state: Mutex::new(State::default()),
+            pushed: Condvar::new(),
+        })
+    }
+
+    /// Returns a watch descriptor for `path`
+    ///
+    /// Nothing is watched. Like [`Watches::add`], this returns the same watch
+    /// descriptor, if it is called for the same path again.
+    ///
+    /// [`Watches::add`]: crate::Watches::add
+    pub fn add_watch<P>(&self, path: P) -> WatchDescriptor
+    where
+        P: AsRef<Path>,
+    {
+        let mut state = self.lock();
+
+        let next_id = state.watches.len() as c_int + 1;
+        let id = *state
+            .watches
+            .entry(path.as_ref().to_path_buf())
+            .or_insert(next_id);
+
+        WatchDescriptor {
+            id,
+            fd: FdHandle::new(Arc::downgrade(&self.fd)),
+        }
+    }
+
+    /// Adds an event, to be returned from the next read
+    ///
+    /// `wd` should have been returned from [`MockInotify::add_watch`], but
+    /// that isn't checked.
+    pub fn push(&self, wd: &WatchDescriptor, mask: EventMask, cookie: u32, name: Option<&OsStr>) {
+        let mut bytes = Vec::new();
+        encode(wd.id, mask, cookie, name, &mut bytes);
+
+        self.lock().queue.push_back(bytes);
+        self.pushed.notify_all();
+    }
+
+    /// Adds a queue overflow event, to be returned from the next read
+    pub fn push_overflow(&self) {
+        let mut bytes = Vec::new();
+        encode(-1, EventMask::Q_OVERFLOW, 0, None, &mut bytes);
+
+        self.lock().queue.push_back(bytes);
+        self.pushed.notify_all();
+    }
+
+    /// Indicates whether all pushed events have been read
+    pub fn is_empty(&self) -> bool {
+        self.lock().queue.is_empty()
+    }
+
+    fn read<'a>(
+        &self,
+        mut state: MutexGuard<'_, State>,
+        buffer: &'a mut [u8],
+    ) -> io::Result<Events<'a>> {
+        let mut num_bytes = 0;
+
+        while let Some(event) = state.queue.front() {
+            if buffer.len() - num_bytes < event.len() {
+                break;
+            }
+
+            buffer[num_bytes..num_bytes + event.len()].copy_from_slice(event);
+            num_bytes += event.len();
+            state.queue.pop_front();
+        }
+
+        if num_bytes == 0 {
+            // Just like the kernel does, if the buffer is too small for the
+            // next event.
+            let min_size = mem::size_of::<ffi::inotify_event>();
+            if buffer.len() < min_size || !state.queue.is_empty() {
+                return Err(io::Error::from(io::ErrorKind::InvalidInput));
+            }
+            return Err(io::Error::from(io::ErrorKind::WouldBlock));
+        }
+
+        Ok(Events::new(Arc::downgrade(&self.fd), buffer, num_bytes))
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|error| error.into_inner())
+    }
+}
+
+impl EventSource for MockInotify {
+    fn read_events<'a>(&self, buffer: &'a mut [u8]) -> io::Result<Events<'a>> {
+        self.read(self.lock(), buffer)
+    }
+
+    fn read_events_blocking<'a>(&self, buffer: &'a mut [u8]) -> io::Result<Events<'a>> {
+        let mut state = self.lock();
+        while state.queue.is_empty() {
+            state = self
+                .pushed
+                .wait(state)
+                .unwrap_or_else(|error| error.into_inner());
+        }
+
+        self.read(state, buffer)
+    }
+}
+
+#[derive(Debug, Default)]
+struct State {
+    watches: HashMap<PathBuf, c_int>,
+    queue: VecDeque<Vec<u8>>,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::OsStr;
+
+    use super::MockInotify;
+    use crate::{EventMask, EventSource};
+
+    #[test]
+    fn it_should_return_pushed_events() {
+        let mock = MockInotify::new().unwrap();
+        let wd = mock.add_watch("/tmp");
+        assert_eq!(mock.add_watch("/tmp"), wd);
+        assert_ne!(mock.add_watch("/var"), wd);
+
+        mock.push(
+            &wd,
+            EventMask::CREATE,
+            0,
+            Some(OsStr::new("a-rather-long-file-name")),
+        );
+        mock.push_overflow();
+
+        let mut buffer = [0; 1024];
+        let events = mock.read_events(&mut buffer).unwrap().collect::<Vec<_>>();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].wd, wd);
+        assert_eq!(events[0].mask, EventMask::CREATE);
+        assert_eq!(events[0].name, Some(OsStr::new("a-rather-long-file-name")));
+        assert!(events[1].is_queue_overflow());
+
+        assert!(mock.is_empty());
+        assert_eq!(
+            mock.read_events(&mut buffer).unwrap_err().kind(),
+            std::io::ErrorKind::WouldBlock
+        );
+    }
+}
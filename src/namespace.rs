@@ -0,0 +1,122 @@
+use std::{
+    io,
+    os::unix::io::{AsRawFd, OwnedFd},
+    path::{Path, PathBuf},
+    sync::mpsc,
+    thread,
+};
+
+use crate::watches::{WatchDescriptor, WatchMask, Watches};
+
+/// Interface for adding watches inside another mount namespace
+///
+/// inotify resolves the path of a watch in the mount namespace of the thread
+/// that adds it. Once added, events for the watch are read from the inotify
+/// instance like any other, no matter which namespace it belongs to. This
+/// makes it possible to watch paths as a container sees them, without
+/// bind-mounting them into the namespace of the host.
+///
+/// `NamespaceWatches` starts a helper thread, which moves into the other
+/// mount namespace and adds watches on behalf of its caller. The thread exits
+/// once `NamespaceWatches` is dropped.
+///
+/// Returned by [`Inotify::watches_in_namespace`].
+///
+/// [`Inotify::watches_in_namespace`]: crate::Inotify::watches_in_namespace
+#[derive(Debug)]
+pub struct NamespaceWatches {
+    watches: Watches,
+    requests: mpsc::Sender<(PathBuf, WatchMask)>,
+    replies: mpsc::Receiver<io::Result<WatchDescriptor>>,
+}
+
+impl NamespaceWatches {
+    /// Starts the helper thread and moves it into the namespace `ns`
+    pub(crate) fn new(watches: Watches, ns: OwnedFd) -> io::Result<Self> {
+        let (request_tx, request_rx) = mpsc::channel::<(PathBuf, WatchMask)>();
+        let (reply_tx, reply_rx) = mpsc::channel();
+        let (setup_tx, setup_rx) = mpsc::channel();
+
+        let mut thread_watches = watches.clone();
+        thread::Builder::new()
+            .name("inotify-namespace".into())
+            .spawn(move || {
+                let result = enter(&ns);
+                let failed = result.is_err();
+                let _ = setup_tx.send(result);
+                if failed {
+                    return;
+                }
+
+                for (path, mask) in request_rx {
+                    if reply_tx.send(thread_watches.add(path, mask)).is_err() {
+                        break;
+                    }
+                }
+            })?;
+
+        setup_rx.recv().unwrap_or_else(|_| {
+            Err(io::Error::new(
+                io::ErrorKind::Other,
+                "Namespace thread panicked",
+            ))
+        })?;
+
+        Ok(NamespaceWatches {
+            watches,
+            requests: request_tx,
+            replies: reply_rx,
+        })
+    }
+
+    /// Adds or updates a watch for the given path
+    ///
+    /// Works like [`Watches::add`], except that `path` is resolved in the
+    /// other mount namespace.
+    ///
+    /// # Errors
+    ///
+    /// Directly returns the error from the call to [`inotify_add_watch`].
+    /// Returns an error with [`ErrorKind::BrokenPipe`], if the helper thread
+    /// is no longer running.
+    ///
+    /// [`inotify_add_watch`]: inotify_sys::inotify_add_watch
+    /// [`ErrorKind::BrokenPipe`]: std::io::ErrorKind::BrokenPipe
+    pub fn add<P>(&mut self, path: P, mask: WatchMask) -> io::Result<WatchDescriptor>
+    where
+        P: AsRef<Path>,
+    {
+        let stopped = || io::Error::new(io::ErrorKind::BrokenPipe, "Namespace thread stopped");
+
+        self.requests
+            .send((path.as_ref().to_owned(), mask))
+            .map_err(|_| stopped())?;
+        self.replies.recv().map_err(|_| stopped())?
+    }
+
+    /// Stops watching a file
+    ///
+    /// See [`Watches::remove`]. Removing a watch doesn't involve a path, so
+    /// this doesn't need the helper thread.
+    ///
+    /// # Errors
+    ///
+    /// See [`Watches::remove`].
+    pub fn remove(&mut self, wd: WatchDescriptor) -> io::Result<()> {
+        self.watches.remove(wd)
+    }
+}
+
+/// Moves the current thread into the mount namespace `ns`
+fn enter(ns: &OwnedFd) -> io::Result<()> {
+    // A thread can only change its mount namespace, if it doesn't share its
+    // file system information with other threads.
+    if unsafe { libc::unshare(libc::CLONE_FS) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    if unsafe { libc::setns(ns.as_raw_fd(), libc::CLONE_NEWNS) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(())
+}
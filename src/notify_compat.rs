@@ -0,0 +1,163 @@
+use std::{convert::TryFrom, ffi::OsStr, path::Path};
+
+use notify_types::event::{
+    AccessKind, AccessMode, CreateKind, DataChange, EventKind as NotifyEventKind, Flag,
+    MetadataKind, ModifyKind, RemoveKind, RenameMode,
+};
+
+use crate::events::{Event, EventKind, EventMask, EventMaskParseError, EventOwned, EventSubject};
+
+/// Maps an event mask to the event kind of the `notify` crate
+///
+/// The mapping follows the one that `notify` uses for its own inotify
+/// backend. Queue overflows map to [`NotifyEventKind::Other`], and so do
+/// events that carry only auxiliary flags, like [`EventMask::IGNORED`].
+impl TryFrom<EventMask> for NotifyEventKind {
+    type Error = EventMaskParseError;
+
+    fn try_from(mask: EventMask) -> Result<Self, Self::Error> {
+        if mask.contains(EventMask::Q_OVERFLOW) {
+            return Ok(NotifyEventKind::Other);
+        }
+
+        let parsed = mask.parse()?;
+        let subject = parsed.subject();
+
+        let kind = match parsed.kind {
+            Some(EventKind::Access) => NotifyEventKind::Access(AccessKind::Read),
+            Some(EventKind::Attrib) => {
+                NotifyEventKind::Modify(ModifyKind::Metadata(MetadataKind::Any))
+            }
+            Some(EventKind::CloseWrite) => {
+                NotifyEventKind::Access(AccessKind::Close(AccessMode::Write))
+            }
+            Some(EventKind::CloseNowrite) => {
+                NotifyEventKind::Access(AccessKind::Close(AccessMode::Read))
+            }
+            Some(EventKind::Create) => NotifyEventKind::Create(match subject {
+                EventSubject::File => CreateKind::File,
+                EventSubject::Directory => CreateKind::Folder,
+            }),
+            Some(EventKind::Delete) | Some(EventKind::DeleteSelf) => {
+                NotifyEventKind::Remove(match subject {
+                    EventSubject::File => RemoveKind::File,
+                    EventSubject::Directory => RemoveKind::Folder,
+                })
+            }
+            Some(EventKind::Modify) => NotifyEventKind::Modify(ModifyKind::Data(DataChange::Any)),
+            Some(EventKind::MoveSelf) | Some(EventKind::MovedFrom) => {
+                NotifyEventKind::Modify(ModifyKind::Name(RenameMode::From))
+            }
+            Some(EventKind::MovedTo) => NotifyEventKind::Modify(ModifyKind::Name(RenameMode::To)),
+            Some(EventKind::Open) => NotifyEventKind::Access(AccessKind::Open(AccessMode::Any)),
+            None => NotifyEventKind::Other,
+        };
+
+        Ok(kind)
+    }
+}
+
+impl<S> Event<S>
+where
+    S: AsRef<OsStr>,
+{
+    /// Converts the event into an event of the `notify` crate
+    ///
+    /// `watch_path` is the path that was passed to [`Watches::add`], when the
+    /// watch that produced this event was added. It is joined with the event's
+    /// name, if any, to form the path of the returned event.
+    ///
+    /// The cookie of the event becomes the tracker of the returned event, so
+    /// both halves of a rename can be connected. Queue overflows are flagged
+    /// with [`Flag::Rescan`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error, if the event mask can't be parsed. See
+    /// [`EventMask::parse`].
+    ///
+    /// [`Watches::add`]: crate::Watches::add
+    pub fn to_notify_event(
+        &self,
+        watch_path: &Path,
+    ) -> Result<notify_types::event::Event, EventMaskParseError> {
+        let path = match &self.name {
+            Some(name) => watch_path.join(name.as_ref()),
+            None => watch_path.to_path_buf(),
+        };
+
+        let event = notify_event(self.mask, self.cookie)?;
+        if self.mask.contains(EventMask::Q_OVERFLOW) {
+            // Overflows aren't about any particular path.
+            return Ok(event);
+        }
+
+        Ok(event.add_path(path))
+    }
+}
+
+/// Converts the event into an event of the `notify` crate
+///
+/// Since an [`Event`] doesn't know the path of its watch, the path of the
+/// returned event is just the event's name, or missing, if the event has no
+/// name. Use [`Event::to_notify_event`] to get full paths.
+impl TryFrom<EventOwned> for notify_types::event::Event {
+    type Error = EventMaskParseError;
+
+    fn try_from(event: EventOwned) -> Result<Self, Self::Error> {
+        let notify_event = notify_event(event.mask, event.cookie)?;
+        Ok(notify_event.add_some_path(event.name.map(Into::into)))
+    }
+}
+
+fn notify_event(
+    mask: EventMask,
+    cookie: u32,
+) -> Result<notify_types::event::Event, EventMaskParseError> {
+    let mut event = notify_types::event::Event::new(NotifyEventKind::try_from(mask)?);
+
+    if mask.contains(EventMask::Q_OVERFLOW) {
+        event = event.set_flag(Flag::Rescan);
+    }
+    if cookie != 0 {
+        event = event.set_tracker(cookie as usize);
+    }
+
+    Ok(event)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{convert::TryFrom, path::Path};
+
+    use notify_types::event::{CreateKind, EventKind, ModifyKind, RenameMode};
+
+    use crate::events::test_event;
+    use crate::EventMask;
+
+    #[test]
+    fn it_should_convert_events_with_their_paths() {
+        let event = test_event(EventMask::CREATE | EventMask::ISDIR, 0, Some("dir"));
+        let event = event.to_notify_event(Path::new("/watched")).unwrap();
+
+        assert_eq!(event.kind, EventKind::Create(CreateKind::Folder));
+        assert_eq!(event.paths, vec![Path::new("/watched/dir")]);
+        assert_eq!(event.tracker(), None);
+    }
+
+    #[test]
+    fn it_should_keep_rename_cookies_and_flag_overflows() {
+        let event = test_event(EventMask::MOVED_TO, 3, Some("file"));
+        let event = notify_types::event::Event::try_from(event).unwrap();
+        assert_eq!(
+            event.kind,
+            EventKind::Modify(ModifyKind::Name(RenameMode::To))
+        );
+        assert_eq!(event.paths, vec![Path::new("file")]);
+        assert_eq!(event.tracker(), Some(3));
+
+        let event = test_event(EventMask::Q_OVERFLOW, 0, None);
+        let event = notify_types::event::Event::try_from(event).unwrap();
+        assert!(event.need_rescan());
+    }
+}
@@ -0,0 +1,427 @@
+use std::{
+    collections::HashMap,
+    ffi::OsStr,
+    fmt,
+    future::Future,
+    io, mem,
+    pin::Pin,
+    sync::{
+        mpsc::{RecvError, TryRecvError},
+        Arc, Condvar, Mutex, MutexGuard,
+    },
+    task::{Context, Poll, Waker},
+};
+
+use libc::c_int;
+
+use crate::events::{Event, EventMask, EventOwned, Timestamp};
+use crate::fd_guard::FdHandle;
+use crate::watches::WatchDescriptor;
+
+/// The event of a watch added by [`Watches::add_oneshot`]
+///
+/// Resolves with the first event that is read for the watch. Events are only
+/// read when something reads from the inotify instance, for example an
+/// [`EventStream`], or another thread that calls
+/// [`Inotify::read_events_blocking`]. The event is still returned from that
+/// read too.
+///
+/// Can be waited for using [`OneshotEvent::recv`], or polled using
+/// [`OneshotEvent::try_recv`]. It is also a [`Future`]. All of these return
+/// an error, if the watch is removed using [`Watches::remove`], if another
+/// `OneshotEvent` is created for the same watch, or if the inotify instance
+/// is closed, before an event has been read. They also return an error, if
+/// the event queue overflows before then, as the event might have been lost.
+///
+/// [`Watches::add_oneshot`]: crate::Watches::add_oneshot
+/// [`Watches::remove`]: crate::Watches::remove
+/// [`EventStream`]: crate::EventStream
+/// [`Inotify::read_events_blocking`]: crate::Inotify::read_events_blocking
+pub struct OneshotEvent {
+    wd: WatchDescriptor,
+    slot: Arc<Slot>,
+}
+
+impl OneshotEvent {
+    /// Returns the watch that this is the event of
+    pub fn wd(&self) -> &WatchDescriptor {
+        &self.wd
+    }
+
+    /// Blocks the current thread until the event has been read
+    ///
+    /// # Errors
+    ///
+    /// Returns an error, if no event is going to arrive anymore.
+    pub fn recv(self) -> Result<EventOwned, RecvError> {
+        let mut state = self.slot.lock();
+        loop {
+            match mem::replace(&mut *state, State::Taken) {
+                State::Pending(_) => {
+                    *state = State::Pending(None);
+                    state = self
+                        .slot
+                        .ready
+                        .wait(state)
+                        .unwrap_or_else(|error| error.into_inner());
+                }
+                State::Ready(event) => return Ok(event),
+                State::Closed | State::Overflowed | State::Taken => return Err(RecvError),
+            }
+        }
+    }
+
+    /// Returns the event, if it has been read already
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TryRecvError::Empty`], if the event hasn't been read yet, or
+    /// [`TryRecvError::Disconnected`], if no event is going to arrive anymore.
+    pub fn try_recv(&mut self) -> Result<EventOwned, TryRecvError> {
+        let mut state = self.slot.lock();
+        match mem::replace(&mut *state, State::Taken) {
+            State::Pending(waker) => {
+                *state = State::Pending(waker);
+                Err(TryRecvError::Empty)
+            }
+            State::Ready(event) => Ok(event),
+            State::Closed | State::Overflowed | State::Taken => Err(TryRecvError::Disconnected),
+        }
+    }
+}
+
+impl OneshotEvent {
+    fn poll_event(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<EventOwned>> {
+        let mut state = self.slot.lock();
+        match mem::replace(&mut *state, State::Taken) {
+            State::Pending(_) => {
+                *state = State::Pending(Some(cx.waker().clone()));
+                Poll::Pending
+            }
+            State::Ready(event) => Poll::Ready(Ok(event)),
+            State::Overflowed => Poll::Ready(Err(io::Error::new(
+                io::ErrorKind::Other,
+                "inotify event queue overflowed, the event might have been lost",
+            ))),
+            State::Closed | State::Taken => Poll::Ready(Err(removed())),
+        }
+    }
+}
+
+impl Future for OneshotEvent {
+    type Output = Result<EventOwned, RecvError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.poll_event(cx).map_err(|_| RecvError)
+    }
+}
+
+/// Resolves with the first matching event of a watch
+///
+/// Returned by [`Watches::wait_for`]. Unlike awaiting a [`OneshotEvent`], this
+/// treats the removal of the watch as an error, and tells why no event is
+/// going to arrive.
+///
+/// [`Watches::wait_for`]: crate::Watches::wait_for
+#[derive(Debug)]
+pub struct WaitFor {
+    oneshot: OneshotEvent,
+}
+
+impl WaitFor {
+    pub(crate) fn new(oneshot: OneshotEvent) -> Self {
+        WaitFor { oneshot }
+    }
+}
+
+impl Future for WaitFor {
+    type Output = io::Result<EventOwned>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.oneshot.poll_event(cx).map(|result| {
+            result.and_then(|event| {
+                if event.mask.contains(EventMask::IGNORED) {
+                    return Err(removed());
+                }
+                Ok(event)
+            })
+        })
+    }
+}
+
+fn removed() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::NotFound,
+        "Watch was removed before a matching event occurred",
+    )
+}
+
+impl fmt::Debug for OneshotEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OneshotEvent")
+            .field("wd", &self.wd)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The watches that a [`OneshotEvent`] is waiting for
+///
+/// Lives in [`FdGuard`], so events are delivered, no matter which reader of
+/// the instance reads them.
+///
+/// [`FdGuard`]: crate::fd_guard::FdGuard
+#[derive(Default)]
+pub(crate) struct Oneshots(Mutex<Waiting>);
+
+#[derive(Default)]
+struct Waiting {
+    slots: HashMap<c_int, Arc<Slot>>,
+
+    /// The number of oneshot watches that are being added
+    adding: usize,
+
+    /// The events that have been read while watches were being added
+    early: Vec<EventOwned>,
+}
+
+impl Oneshots {
+    fn lock(&self) -> OneshotsGuard<'_> {
+        OneshotsGuard(self.0.lock().unwrap_or_else(|error| error.into_inner()))
+    }
+
+    /// Starts adding a oneshot watch
+    ///
+    /// Events that are read until [`Adding::finish`] is called are kept, so
+    /// the event of the new watch can't be read before it is waited for. The
+    /// lock isn't held in the meantime, as adding a watch calls the usage
+    /// hook, which might add oneshot watches itself.
+    pub(crate) fn start_adding(&self) -> Adding<'_> {
+        let mut oneshots = self.lock();
+        oneshots.0.adding += 1;
+
+        Adding {
+            oneshots: self,
+            start: oneshots.0.early.len(),
+        }
+    }
+
+    /// Delivers the events in `buffer` to the watches that wait for them
+    pub(crate) fn deliver_all(&self, fd: &FdHandle, buffer: &[u8], timestamp: Timestamp) {
+        let mut oneshots = self.lock();
+        if oneshots.0.slots.is_empty() && oneshots.0.adding == 0 {
+            return;
+        }
+
+        let mut pos = 0;
+        while let Ok((bytes_consumed, event)) =
+            Event::from_buffer(fd.clone(), &buffer[pos..], timestamp)
+        {
+            oneshots.deliver(&event);
+            pos += bytes_consumed;
+        }
+    }
+
+    /// Delivers `event` to the watch that waits for it, if any
+    pub(crate) fn deliver<S>(&self, event: &Event<S>)
+    where
+        S: AsRef<OsStr> + Clone,
+    {
+        self.lock().deliver(event);
+    }
+
+    /// Called after [`Watches::remove`] removed the watch `id`
+    ///
+    /// [`Watches::remove`]: crate::Watches::remove
+    pub(crate) fn watch_removed(&self, id: c_int) {
+        if let Some(slot) = self.lock().0.slots.remove(&id) {
+            slot.close();
+        }
+    }
+}
+
+impl Drop for Oneshots {
+    fn drop(&mut self) {
+        for (_, slot) in self.lock().0.slots.drain() {
+            slot.close();
+        }
+    }
+}
+
+impl fmt::Debug for Oneshots {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut ids = self.lock().0.slots.keys().copied().collect::<Vec<_>>();
+        ids.sort_unstable();
+
+        f.debug_tuple("Oneshots").field(&ids).finish()
+    }
+}
+
+/// A oneshot watch that is being added
+///
+/// Returned by [`Oneshots::start_adding`]. Stops keeping events for it when
+/// dropped, for example because adding the watch failed.
+pub(crate) struct Adding<'a> {
+    oneshots: &'a Oneshots,
+
+    /// The number of early events that had been kept before
+    start: usize,
+}
+
+impl Adding<'_> {
+    /// Returns a [`OneshotEvent`] for the watch `wd`, which has been added
+    pub(crate) fn finish(self, wd: WatchDescriptor) -> OneshotEvent {
+        let mut oneshots = self.oneshots.lock();
+        let event = oneshots.wait_for(wd);
+
+        // The event might have been read already.
+        let early = oneshots.0.early[self.start..]
+            .iter()
+            .find(|early| early.is_queue_overflow() || early.wd.id == event.wd.id)
+            .cloned();
+        if let Some(early) = early {
+            if let Some(slot) = oneshots.0.slots.remove(&event.wd.id) {
+                slot.set(if early.is_queue_overflow() {
+                    State::Overflowed
+                } else {
+                    State::Ready(early)
+                });
+            }
+        }
+
+        event
+    }
+}
+
+impl Drop for Adding<'_> {
+    fn drop(&mut self) {
+        let mut oneshots = self.oneshots.lock();
+        oneshots.0.adding -= 1;
+        if oneshots.0.adding == 0 {
+            oneshots.0.early.clear();
+        }
+    }
+}
+
+struct OneshotsGuard<'a>(MutexGuard<'a, Waiting>);
+
+impl OneshotsGuard<'_> {
+    /// Returns a [`OneshotEvent`] for the watch `wd`
+    ///
+    /// A previous `OneshotEvent` for the same watch is closed.
+    fn wait_for(&mut self, wd: WatchDescriptor) -> OneshotEvent {
+        let slot = Arc::new(Slot {
+            state: Mutex::new(State::Pending(None)),
+            ready: Condvar::new(),
+        });
+
+        if let Some(previous) = self.0.slots.insert(wd.id, slot.clone()) {
+            previous.close();
+        }
+
+        OneshotEvent { wd, slot }
+    }
+
+    fn deliver<S>(&mut self, event: &Event<S>)
+    where
+        S: AsRef<OsStr> + Clone,
+    {
+        if self.0.adding > 0 {
+            let event = event.clone().map_name(|name| name.as_ref().to_os_string());
+            self.0.early.push(event);
+        }
+
+        // Events after the overflow have been lost, and the awaited ones
+        // might have been among them.
+        if event.is_queue_overflow() {
+            for (_, slot) in self.0.slots.drain() {
+                slot.set(State::Overflowed);
+            }
+            return;
+        }
+
+        if let Some(slot) = self.0.slots.remove(&event.wd.id) {
+            let event = event.clone().map_name(|name| name.as_ref().to_os_string());
+            slot.set(State::Ready(event));
+        }
+    }
+}
+
+struct Slot {
+    state: Mutex<State>,
+    ready: Condvar,
+}
+
+impl Slot {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|error| error.into_inner())
+    }
+
+    fn set(&self, new: State) {
+        let waker = match mem::replace(&mut *self.lock(), new) {
+            State::Pending(waker) => waker,
+            _ => None,
+        };
+
+        self.ready.notify_all();
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+
+    fn close(&self) {
+        self.set(State::Closed);
+    }
+}
+
+enum State {
+    /// No event has been read yet. Contains the waker of the last poll.
+    Pending(Option<Waker>),
+    Ready(EventOwned),
+    Closed,
+    /// The event queue overflowed before the event has been read
+    Overflowed,
+    Taken,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io, sync::mpsc::TryRecvError};
+
+    use futures_util::FutureExt;
+
+    use super::{Oneshots, WaitFor};
+    use crate::events::test_event;
+    use crate::EventMask;
+
+    #[test]
+    fn overflows_should_fail_waiting_oneshots() {
+        let oneshots = Oneshots::default();
+        let mut event = oneshots
+            .lock()
+            .wait_for(test_event(EventMask::MODIFY, 0, None).wd);
+        assert_eq!(event.try_recv(), Err(TryRecvError::Empty));
+
+        let mut overflow = test_event(EventMask::Q_OVERFLOW, 0, None);
+        overflow.wd.id = -1;
+        oneshots.deliver(&overflow);
+
+        let error = WaitFor::new(event).now_or_never().unwrap().unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::Other);
+    }
+
+    #[test]
+    fn events_read_while_adding_should_be_delivered() {
+        let oneshots = Oneshots::default();
+        let event = test_event(EventMask::MODIFY, 0, None);
+
+        let adding = oneshots.start_adding();
+        oneshots.deliver(&event);
+        let mut oneshot = adding.finish(event.wd.clone());
+        assert_eq!(oneshot.try_recv().unwrap().mask, EventMask::MODIFY);
+
+        // Events are only kept while watches are being added.
+        oneshots.deliver(&event);
+        let mut oneshot = oneshots.start_adding().finish(event.wd.clone());
+        assert_eq!(oneshot.try_recv(), Err(TryRecvError::Empty));
+    }
+}
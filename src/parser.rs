@@ -0,0 +1,217 @@
+use std::{ffi::OsStr, sync::Weak};
+
+use crate::events::{name_len, Event, EventMask, ParseError, Timestamp};
+use crate::fd_guard::{FdGuard, FdHandle};
+
+/// The maximum length of an event name the parser will wait for
+///
+/// The kernel never sends names longer than `NAME_MAX`, plus padding. Anything
+/// beyond `PATH_MAX` means the data is garbage, and waiting for more of it
+/// would never end.
+pub(crate) const MAX_NAME_LEN: usize = libc::PATH_MAX as usize;
+
+/// Incremental parser for inotify events
+///
+/// Unlike [`Events`], which requires a buffer of complete events, the parser
+/// accepts data in pieces of any size, as passed to [`EventParser::feed`]. If
+/// an event is split across pieces, it is returned by
+/// [`EventParser::next_event`] once the rest of it has been fed.
+///
+/// The parser doesn't do any I/O itself. This makes it useful for reading the
+/// inotify file descriptor in unusual ways, for replaying recorded events, and
+/// for testing.
+///
+/// # Examples
+///
+/// ```
+/// use inotify::EventParser;
+///
+/// let mut parser = EventParser::new();
+///
+/// // Normally, the data would come from an inotify file descriptor.
+/// let data = [0; 16];
+/// parser.feed(&data[..10]);
+/// assert!(parser.next_event().is_none());
+///
+/// parser.feed(&data[10..]);
+/// assert!(parser.next_event().is_some());
+/// ```
+///
+/// [`Events`]: crate::Events
+#[derive(Debug)]
+pub struct EventParser {
+    fd: FdHandle,
+    buffer: Vec<u8>,
+    pos: usize,
+    timestamp: Timestamp,
+}
+
+impl EventParser {
+    /// Creates a parser that isn't associated with any inotify instance
+    ///
+    /// The [`WatchDescriptor`]s of the parsed events won't be equal to the
+    /// ones returned by [`Watches::add`]. Use [`Inotify::event_parser`], if
+    /// that's needed.
+    ///
+    /// [`WatchDescriptor`]: crate::WatchDescriptor
+    /// [`Watches::add`]: crate::Watches::add
+    /// [`Inotify::event_parser`]: crate::Inotify::event_parser
+    pub fn new() -> Self {
+        Self::with_fd(Weak::new())
+    }
+
+    pub(crate) fn with_fd(fd: Weak<FdGuard>) -> Self {
+        EventParser {
+            fd: FdHandle::new(fd),
+            buffer: Vec::new(),
+            pos: 0,
+            timestamp: Timestamp::now(),
+        }
+    }
+
+    /// Appends data to the parser's internal buffer
+    ///
+    /// `bytes` doesn't need to contain complete events. Incomplete events are
+    /// kept until the rest of them is fed.
+    pub fn feed(&mut self, bytes: &[u8]) {
+        // Get rid of the events that have already been returned, so the buffer
+        // doesn't grow forever.
+        self.buffer.drain(..self.pos);
+        self.pos = 0;
+
+        self.buffer.extend_from_slice(bytes);
+        self.timestamp = Timestamp::now();
+    }
+
+    /// Returns the next complete event, if one is available
+    ///
+    /// Returns `None`, if the data fed so far doesn't contain another complete
+    /// event.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ParseError::NameTooLong`], if the data can't be inotify
+    /// events. All buffered data is discarded in that case, as there's no way
+    /// to find the start of the next event.
+    pub fn next_event(&mut self) -> Option<Result<Event<&OsStr>, ParseError>> {
+        if self.pos == self.buffer.len() {
+            return None;
+        }
+
+        // Check the length of the name first, so we don't wait forever for a
+        // name that's never going to arrive.
+        if let Some(len) = name_len(&self.buffer[self.pos..]) {
+            if len > MAX_NAME_LEN {
+                self.buffer.clear();
+                self.pos = 0;
+
+                return Some(Err(ParseError::NameTooLong { len }));
+            }
+        }
+
+        match Event::from_buffer(self.fd.clone(), &self.buffer[self.pos..], self.timestamp) {
+            Ok((bytes_consumed, event)) => {
+                self.pos += bytes_consumed;
+
+                if event.is_queue_overflow() {
+                    if let Some(fd) = self.fd.upgrade() {
+                        fd.overflow_hook.fire();
+                    }
+                }
+                if let Some(fd) = self.fd.upgrade() {
+                    fd.oneshots.deliver(&event);
+                }
+                if event.mask.contains(EventMask::IGNORED) {
+                    if let Some(fd) = self.fd.upgrade() {
+                        fd.bookkeeping.watch_removed(event.wd.id);
+                    }
+                }
+
+                Some(Ok(event))
+            }
+            // The rest of the event hasn't been fed yet.
+            Err(_) => None,
+        }
+    }
+
+    /// Returns the number of bytes that have been fed, but not yet returned
+    /// as part of an event
+    pub fn buffered_bytes(&self) -> usize {
+        self.buffer.len() - self.pos
+    }
+}
+
+impl Default for EventParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{mem, slice};
+
+    use inotify_sys as ffi;
+
+    use super::EventParser;
+    use crate::{EventMask, ParseError};
+
+    fn event_bytes(mask: u32, name: &[u8; 16]) -> Vec<u8> {
+        let event = ffi::inotify_event {
+            wd: 1,
+            mask,
+            cookie: 0,
+            len: name.len() as u32,
+        };
+        let event = unsafe {
+            slice::from_raw_parts(&event as *const _ as *const u8, mem::size_of_val(&event))
+        };
+
+        let mut bytes = event.to_vec();
+        bytes.extend_from_slice(name);
+        bytes
+    }
+
+    #[test]
+    fn it_should_parse_events_split_across_feeds() {
+        let mut data = event_bytes(ffi::IN_CREATE, b"a\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0");
+        data.extend(event_bytes(
+            ffi::IN_DELETE,
+            b"b\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0",
+        ));
+
+        let mut parser = EventParser::new();
+        let mut events = Vec::new();
+
+        for byte in &data {
+            parser.feed(slice::from_ref(byte));
+            while let Some(event) = parser.next_event() {
+                events.push(event.unwrap().to_owned());
+            }
+        }
+
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].mask, EventMask::CREATE);
+        assert_eq!(events[0].name.as_deref(), Some("a".as_ref()));
+        assert_eq!(events[1].mask, EventMask::DELETE);
+        assert_eq!(events[1].name.as_deref(), Some("b".as_ref()));
+        assert_eq!(parser.buffered_bytes(), 0);
+    }
+
+    #[test]
+    fn it_should_reject_impossible_name_lengths() {
+        let mut data = event_bytes(ffi::IN_CREATE, &[0; 16]);
+        data[12..16].copy_from_slice(&u32::MAX.to_ne_bytes());
+
+        let mut parser = EventParser::new();
+        parser.feed(&data);
+
+        assert_eq!(
+            parser.next_event().map(|result| result.map(|_| ())),
+            Some(Err(ParseError::NameTooLong {
+                len: u32::MAX as usize
+            }))
+        );
+        assert_eq!(parser.buffered_bytes(), 0);
+    }
+}
@@ -0,0 +1,261 @@
+use std::{
+    collections::VecDeque,
+    io,
+    path::{Path, PathBuf},
+};
+
+use crate::events::{EventMask, EventOwned};
+use crate::inotify::Inotify;
+use crate::util::get_buffer_size_for_events;
+use crate::watches::{WatchDescriptor, WatchMask};
+
+/// Watches a path that might not exist yet
+///
+/// inotify can only watch files and directories that exist. `PathWatcher`
+/// watches a path regardless: While the path doesn't exist, it watches the
+/// deepest ancestor of it that does, and walks down as the missing
+/// directories are created. Once the path exists, it is watched with the
+/// configured mask, and [`PathEvent::Attached`] is returned. If the path is
+/// removed or moved away, [`PathEvent::Detached`] is returned, and the
+/// watcher walks back up, to wait for it to appear again.
+///
+/// `PathWatcher` uses an [`Inotify`] instance of its own, and blocks while
+/// waiting for events. Only the ancestor that is currently watched and the
+/// path itself are watched, so moving away a directory further up is not
+/// noticed.
+///
+/// # Examples
+///
+/// ```no_run
+/// use inotify::{PathEvent, PathWatcher, WatchMask};
+///
+/// let mut watcher = PathWatcher::new("/run/app/sockets/control", WatchMask::ATTRIB)
+///     .expect("Failed to watch path");
+///
+/// loop {
+///     match watcher.next_event().expect("Error while watching path") {
+///         PathEvent::Attached => println!("Socket available"),
+///         PathEvent::Detached => println!("Socket gone"),
+///         PathEvent::Event(event) => println!("{:?}", event),
+///     }
+/// }
+/// ```
+#[derive(Debug)]
+pub struct PathWatcher {
+    inotify: Inotify,
+    path: PathBuf,
+    mask: WatchMask,
+    ancestors: Vec<PathBuf>,
+    watched: Option<Watched>,
+    pending: VecDeque<PathEvent>,
+    buffer: Vec<u8>,
+}
+
+impl PathWatcher {
+    /// Starts watching `path` with `mask`
+    ///
+    /// If the path exists already, [`PathEvent::Attached`] is the first item
+    /// returned.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error from initializing inotify, or from adding a watch.
+    pub fn new<P>(path: P, mask: WatchMask) -> io::Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref().to_path_buf();
+
+        let mut ancestors = path
+            .ancestors()
+            .skip(1)
+            .map(|ancestor| {
+                if ancestor.as_os_str().is_empty() {
+                    Path::new(".").to_path_buf()
+                } else {
+                    ancestor.to_path_buf()
+                }
+            })
+            .collect::<Vec<_>>();
+        ancestors.reverse();
+
+        let mut watcher = PathWatcher {
+            inotify: Inotify::init()?,
+            path,
+            mask,
+            ancestors,
+            watched: None,
+            pending: VecDeque::new(),
+            buffer: vec![0; get_buffer_size_for_events(16)],
+        };
+        watcher.settle()?;
+
+        Ok(watcher)
+    }
+
+    /// Returns the watched path
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Indicates whether the path exists and is being watched
+    pub fn is_attached(&self) -> bool {
+        self.watched
+            .as_ref()
+            .is_some_and(|watched| watched.depth == self.ancestors.len())
+    }
+
+    /// Waits for the next event
+    ///
+    /// # Errors
+    ///
+    /// Returns the error from reading events, or from adding a watch. The
+    /// watcher can be used again afterwards.
+    pub fn next_event(&mut self) -> io::Result<PathEvent> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return Ok(event);
+            }
+
+            let events = self
+                .inotify
+                .read_events_blocking(&mut self.buffer)?
+                .map(|event| event.to_owned())
+                .collect::<Vec<_>>();
+            for event in events {
+                self.handle(event)?;
+            }
+        }
+    }
+
+    fn handle(&mut self, event: EventOwned) -> io::Result<()> {
+        if event.is_queue_overflow() {
+            if self.is_attached() {
+                self.pending.push_back(PathEvent::Event(event));
+            }
+            return self.settle();
+        }
+
+        let watched = match &self.watched {
+            Some(watched) if watched.wd == event.wd => watched,
+            // Left over from a watch that has been removed.
+            _ => return Ok(()),
+        };
+
+        let gone = event
+            .mask
+            .intersects(EventMask::DELETE_SELF | EventMask::MOVE_SELF | EventMask::IGNORED);
+
+        if watched.depth == self.ancestors.len() {
+            if gone {
+                self.detach();
+                return self.settle();
+            }
+            self.pending.push_back(PathEvent::Event(event));
+            return Ok(());
+        }
+
+        let next = self.component(watched.depth + 1);
+        if gone || event.name.as_deref() == next.file_name() {
+            self.settle()?;
+        }
+
+        Ok(())
+    }
+
+    /// Watches the deepest existing ancestor, or the path itself
+    fn settle(&mut self) -> io::Result<()> {
+        loop {
+            let depth = (0..=self.ancestors.len())
+                .rev()
+                .find(|&depth| self.component(depth).exists())
+                .unwrap_or(0);
+
+            let (path, mask) = if depth == self.ancestors.len() {
+                (
+                    self.path.clone(),
+                    self.mask | WatchMask::DELETE_SELF | WatchMask::MOVE_SELF,
+                )
+            } else {
+                (
+                    self.component(depth).to_path_buf(),
+                    WatchMask::CREATE
+                        | WatchMask::MOVED_TO
+                        | WatchMask::DELETE_SELF
+                        | WatchMask::MOVE_SELF
+                        | WatchMask::ONLYDIR,
+                )
+            };
+
+            if self
+                .watched
+                .as_ref()
+                .is_some_and(|watched| watched.depth == depth && watched.path == path)
+            {
+                return Ok(());
+            }
+
+            if self.is_attached() {
+                self.detach();
+            } else if let Some(watched) = self.watched.take() {
+                let _ = self.inotify.watches().remove(watched.wd);
+            }
+
+            let wd = match self.inotify.watches().add(&path, mask) {
+                Ok(wd) => wd,
+                // Removed in the meantime, so walk up again.
+                Err(error) if error.kind() == io::ErrorKind::NotFound => continue,
+                Err(error) => return Err(error),
+            };
+            self.watched = Some(Watched { wd, depth, path });
+
+            if depth == self.ancestors.len() {
+                self.pending.push_back(PathEvent::Attached);
+                return Ok(());
+            }
+
+            // The next component might have been created before the watch
+            // was added. Loop to check that.
+        }
+    }
+
+    fn detach(&mut self) {
+        if let Some(watched) = self.watched.take() {
+            // The watch is gone already, if the path has been deleted.
+            let _ = self.inotify.watches().remove(watched.wd);
+            self.pending.push_back(PathEvent::Detached);
+        }
+    }
+
+    fn component(&self, depth: usize) -> &Path {
+        self.ancestors.get(depth).unwrap_or(&self.path)
+    }
+}
+
+impl Iterator for PathWatcher {
+    type Item = io::Result<PathEvent>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.next_event())
+    }
+}
+
+#[derive(Debug)]
+struct Watched {
+    wd: WatchDescriptor,
+    depth: usize,
+    path: PathBuf,
+}
+
+/// An item returned by [`PathWatcher`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PathEvent {
+    /// The path exists now, and is being watched
+    Attached,
+
+    /// The path has been removed or moved away
+    Detached,
+
+    /// An event for the path
+    Event(EventOwned),
+}
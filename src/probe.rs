@@ -0,0 +1,205 @@
+use std::{
+    ffi::CStr,
+    io, mem,
+    os::unix::io::{AsRawFd, FromRawFd, OwnedFd},
+};
+
+use inotify_sys as ffi;
+use libc::c_char;
+
+/// The path that is used for trial system calls, as a C string
+const ROOT: &[u8] = b"/\0";
+
+/// A kernel feature that can be detected at runtime
+///
+/// Passed to [`kernel_supports`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Feature {
+    /// [`WatchMask::MASK_CREATE`](crate::WatchMask::MASK_CREATE)
+    ///
+    /// Available since Linux 4.18.
+    MaskCreate,
+
+    /// [`WatchMask::EXCL_UNLINK`](crate::WatchMask::EXCL_UNLINK)
+    ///
+    /// Available since Linux 2.6.36.
+    ExclUnlink,
+
+    /// The fanotify API
+    ///
+    /// Available since Linux 2.6.37.
+    Fanotify,
+
+    /// fanotify instances that report file identifiers
+    ///
+    /// Available since Linux 5.1.
+    FanotifyReportFid,
+
+    /// fanotify instances that report directory identifiers and names
+    ///
+    /// Available since Linux 5.9.
+    FanotifyReportDfidName,
+
+    /// fanotify instances that report process file descriptors
+    ///
+    /// Available since Linux 5.15.
+    FanotifyReportPidfd,
+
+    /// fanotify ignore marks that apply to directories and their children
+    ///
+    /// Available since Linux 6.0. Older kernels only support the legacy
+    /// ignored mask.
+    FanotifyMarkIgnore,
+}
+
+/// Detects whether the running kernel supports a feature
+///
+/// Applications that must run on older kernels can use this to select their
+/// behavior at runtime, instead of failing with `EINVAL`. Most features are
+/// detected by trying them out, as distributions backport features to older
+/// kernel versions. Results are not cached.
+///
+/// # Errors
+///
+/// Returns the error from the trial system calls, if it doesn't indicate
+/// whether the feature is supported. Most fanotify features require the
+/// `CAP_SYS_ADMIN` capability. Without it, this returns an error with
+/// [`ErrorKind::PermissionDenied`] for them.
+///
+/// # Examples
+///
+/// ```
+/// use inotify::{kernel_supports, Feature, WatchMask};
+///
+/// let mut mask = WatchMask::MODIFY;
+/// if kernel_supports(Feature::MaskCreate).unwrap_or(false) {
+///     mask |= WatchMask::MASK_CREATE;
+/// }
+/// ```
+///
+/// [`ErrorKind::PermissionDenied`]: std::io::ErrorKind::PermissionDenied
+pub fn kernel_supports(feature: Feature) -> io::Result<bool> {
+    match feature {
+        Feature::MaskCreate => probe_mask_create(),
+        Feature::ExclUnlink => Ok(kernel_version()? >= (2, 6, 36)),
+        Feature::Fanotify => probe_fanotify_init(libc::FAN_CLASS_NOTIF).map(|fd| fd.is_some()),
+        Feature::FanotifyReportFid => {
+            probe_fanotify_init(libc::FAN_CLASS_NOTIF | libc::FAN_REPORT_FID).map(|fd| fd.is_some())
+        }
+        Feature::FanotifyReportDfidName => {
+            probe_fanotify_init(libc::FAN_CLASS_NOTIF | libc::FAN_REPORT_DFID_NAME)
+                .map(|fd| fd.is_some())
+        }
+        Feature::FanotifyReportPidfd => {
+            probe_fanotify_init(libc::FAN_CLASS_NOTIF | libc::FAN_REPORT_PIDFD)
+                .map(|fd| fd.is_some())
+        }
+        Feature::FanotifyMarkIgnore => probe_fanotify_mark_ignore(),
+    }
+}
+
+/// Adds the same watch twice, which must fail, if `IN_MASK_CREATE` is known
+///
+/// Older kernels ignore unknown flags, as long as the mask contains at least
+/// one event.
+fn probe_mask_create() -> io::Result<bool> {
+    let fd = unsafe { ffi::inotify_init1(ffi::IN_CLOEXEC) };
+    if fd == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+
+    let mask = ffi::IN_ATTRIB | ffi::IN_MASK_CREATE;
+    for _ in 0..2 {
+        let wd =
+            unsafe { ffi::inotify_add_watch(fd.as_raw_fd(), ROOT.as_ptr() as *const c_char, mask) };
+        if wd == -1 {
+            let error = io::Error::last_os_error();
+            return match error.raw_os_error() {
+                Some(libc::EEXIST) => Ok(true),
+                _ => Err(error),
+            };
+        }
+    }
+
+    Ok(false)
+}
+
+/// Initializes a fanotify instance, returning `None` if `flags` are unknown
+fn probe_fanotify_init(flags: u32) -> io::Result<Option<OwnedFd>> {
+    let fd = unsafe { libc::fanotify_init(flags | libc::FAN_CLOEXEC, libc::O_RDONLY as u32) };
+    if fd == -1 {
+        let error = io::Error::last_os_error();
+        return match error.raw_os_error() {
+            Some(libc::EINVAL) | Some(libc::ENOSYS) => Ok(None),
+            _ => Err(error),
+        };
+    }
+
+    Ok(Some(unsafe { OwnedFd::from_raw_fd(fd) }))
+}
+
+fn probe_fanotify_mark_ignore() -> io::Result<bool> {
+    let fd = match probe_fanotify_init(libc::FAN_CLASS_NOTIF)? {
+        Some(fd) => fd,
+        None => return Ok(false),
+    };
+
+    let result = unsafe {
+        libc::fanotify_mark(
+            fd.as_raw_fd(),
+            libc::FAN_MARK_ADD | libc::FAN_MARK_IGNORE_SURV,
+            libc::FAN_OPEN,
+            libc::AT_FDCWD,
+            ROOT.as_ptr() as *const c_char,
+        )
+    };
+    if result == -1 {
+        let error = io::Error::last_os_error();
+        return match error.raw_os_error() {
+            Some(libc::EINVAL) => Ok(false),
+            _ => Err(error),
+        };
+    }
+
+    Ok(true)
+}
+
+/// Returns the version of the running kernel
+fn kernel_version() -> io::Result<(u32, u32, u32)> {
+    let mut uts: libc::utsname = unsafe { mem::zeroed() };
+    if unsafe { libc::uname(&mut uts) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+
+    let release = unsafe { CStr::from_ptr(uts.release.as_ptr()) };
+    parse_version(&release.to_string_lossy())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Failed to parse kernel version"))
+}
+
+/// Parses a kernel release like `6.1.0-13-amd64`
+fn parse_version(release: &str) -> Option<(u32, u32, u32)> {
+    let mut parts = release.split(|c: char| !c.is_ascii_digit());
+
+    let major = parts.next()?.parse().ok()?;
+    let minor = parts.next()?.parse().ok()?;
+    let patch = parts
+        .next()
+        .and_then(|patch| patch.parse().ok())
+        .unwrap_or(0);
+
+    Some((major, minor, patch))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_version;
+
+    #[test]
+    fn parse_version_should_handle_distribution_suffixes() {
+        assert_eq!(parse_version("6.1.0-13-amd64"), Some((6, 1, 0)));
+        assert_eq!(parse_version("5.14.0-362.el9.x86_64"), Some((5, 14, 0)));
+        assert_eq!(parse_version("6.8"), Some((6, 8, 0)));
+        assert_eq!(parse_version("linux"), None);
+    }
+}
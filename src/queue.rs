@@ -0,0 +1,158 @@
+use std::{io, mem, os::unix::io::RawFd, sync::Arc};
+
+use inotify_sys as ffi;
+use libc::c_int;
+
+use crate::fd_guard::FdGuard;
+use crate::util::max_queued_events;
+
+/// How full the event queue of an inotify instance is
+///
+/// Returned by [`QueueMonitor`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct QueueLevel {
+    /// The number of bytes that are waiting to be read
+    pub bytes: usize,
+
+    /// The maximum number of events in the queue
+    ///
+    /// See [`max_queued_events`](crate::max_queued_events).
+    pub limit: u64,
+}
+
+impl QueueLevel {
+    /// Estimates the number of queued events
+    ///
+    /// The kernel only reports the number of queued bytes. The size of an
+    /// event depends on the length of its name, so this assumes events
+    /// without a name, which results in an upper bound.
+    pub fn events(&self) -> usize {
+        self.bytes / mem::size_of::<ffi::inotify_event>()
+    }
+
+    /// Returns the share of the queue that is in use, in percent
+    ///
+    /// Based on [`QueueLevel::events`], so this is an upper bound as well.
+    pub fn percent(&self) -> f64 {
+        if self.limit == 0 {
+            return 100.0;
+        }
+
+        self.events() as f64 * 100.0 / self.limit as f64
+    }
+}
+
+/// Watches how full the event queue of an inotify instance is
+///
+/// Once the event queue is full, the kernel drops events and reports
+/// [`EventMask::Q_OVERFLOW`]. Events that have been lost can't be recovered.
+/// Sampling the fill level of the queue, for example after each read, gives
+/// an application the chance to shed load before that happens.
+///
+/// Returned by [`Inotify::queue_monitor`].
+///
+/// # Examples
+///
+/// ```
+/// use inotify::Inotify;
+///
+/// let inotify = Inotify::init()
+///     .expect("Failed to initialize an inotify instance");
+/// let mut monitor = inotify.queue_monitor(80)
+///     .expect("Failed to create queue monitor");
+///
+/// // Call this regularly, for example after reading events.
+/// if let Some(level) = monitor.check().expect("Failed to sample queue") {
+///     eprintln!("inotify queue is {:.0}% full", level.percent());
+/// }
+/// ```
+///
+/// [`EventMask::Q_OVERFLOW`]: crate::EventMask::Q_OVERFLOW
+/// [`Inotify::queue_monitor`]: crate::Inotify::queue_monitor
+#[derive(Debug)]
+pub struct QueueMonitor {
+    fd: Arc<FdGuard>,
+    limit: u64,
+    threshold: usize,
+    warned: bool,
+}
+
+impl QueueMonitor {
+    pub(crate) fn new(fd: Arc<FdGuard>, percent: u8) -> io::Result<Self> {
+        let limit = max_queued_events()?;
+
+        Ok(QueueMonitor {
+            fd,
+            limit,
+            threshold: (limit.saturating_mul(u64::from(percent)) / 100) as usize,
+            warned: false,
+        })
+    }
+
+    /// Returns the current fill level of the queue
+    ///
+    /// # Errors
+    ///
+    /// Returns the error from the `FIONREAD` ioctl.
+    pub fn sample(&self) -> io::Result<QueueLevel> {
+        Ok(QueueLevel {
+            bytes: queued_bytes(**self.fd)?,
+            limit: self.limit,
+        })
+    }
+
+    /// Returns the fill level, if the queue has become too full
+    ///
+    /// Returns `Some`, if the estimated number of events has reached the
+    /// threshold that has been passed to [`Inotify::queue_monitor`]. After
+    /// that, this returns `None`, until the queue has dropped below the
+    /// threshold again, so a full queue is only reported once.
+    ///
+    /// # Errors
+    ///
+    /// See [`QueueMonitor::sample`].
+    ///
+    /// [`Inotify::queue_monitor`]: crate::Inotify::queue_monitor
+    pub fn check(&mut self) -> io::Result<Option<QueueLevel>> {
+        let level = self.sample()?;
+
+        if level.events() < self.threshold {
+            self.warned = false;
+            return Ok(None);
+        }
+        if self.warned {
+            return Ok(None);
+        }
+
+        self.warned = true;
+        Ok(Some(level))
+    }
+}
+
+/// Returns the number of bytes that can be read from `fd`
+pub(crate) fn queued_bytes(fd: RawFd) -> io::Result<usize> {
+    let mut bytes: c_int = 0;
+
+    let result = unsafe { libc::ioctl(fd, libc::FIONREAD, &mut bytes) };
+    if result == -1 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(bytes as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::QueueLevel;
+
+    #[test]
+    fn percent_should_be_based_on_event_estimate() {
+        let level = QueueLevel {
+            bytes: 16 * 10,
+            limit: 100,
+        };
+
+        assert_eq!(level.events(), 10);
+        assert_eq!(level.percent(), 10.0);
+    }
+}
@@ -0,0 +1,305 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    ffi::OsString,
+    time::{Duration, Instant},
+};
+
+use crate::events::EventOwned;
+use crate::watches::WatchDescriptor;
+
+/// Limits the rate of events
+///
+/// Watching a busy directory, like a spool or a cache, can produce more
+/// events than the code handling them can keep up with. `RateLimiter` passes
+/// on at most a configured number of events per period, and drops the rest.
+/// Once the period ends, the dropped events are summarized by a
+/// [`RateItem::Skipped`], which contains the last dropped event and how many
+/// have been dropped.
+///
+/// By default, the limit applies to all events. Use
+/// [`RateLimiter::per_file`] to apply it to each watch descriptor and name
+/// separately instead. Queue overflow events are never dropped.
+///
+/// Like [`RenameTracker`], `RateLimiter` doesn't do any I/O, and doesn't look
+/// at the clock itself. Push all events into it using [`RateLimiter::push`],
+/// and take the results out of it using [`RateLimiter::pop`].
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::time::{Duration, Instant};
+///
+/// use inotify::{Inotify, RateItem, RateLimiter, WatchMask};
+///
+/// let inotify = Inotify::init()
+///     .expect("Failed to initialize an inotify instance");
+/// inotify.watches().add("/var/spool", WatchMask::CREATE | WatchMask::MODIFY)
+///     .expect("Failed to add watch");
+///
+/// let mut limiter = RateLimiter::new(100, Duration::from_secs(1)).per_file(true);
+/// let mut buffer = [0; 4096];
+///
+/// loop {
+///     let events = inotify.read_events_blocking(&mut buffer)
+///         .expect("Error while reading events");
+///     for event in events {
+///         limiter.push(event.to_owned(), Instant::now());
+///     }
+///
+///     while let Some(item) = limiter.pop(Instant::now()) {
+///         match item {
+///             RateItem::Event(event) => {
+///                 // Handle event
+///             }
+///             RateItem::Skipped { last, count } => {
+///                 println!("Skipped {} events for {:?}", count, last.name);
+///             }
+///         }
+///     }
+/// }
+/// ```
+///
+/// [`RenameTracker`]: crate::RenameTracker
+#[derive(Debug)]
+pub struct RateLimiter {
+    max: usize,
+    period: Duration,
+    per_file: bool,
+    windows: HashMap<Key, Window>,
+    ready: VecDeque<RateItem>,
+    skipped: u64,
+}
+
+impl RateLimiter {
+    /// Creates a `RateLimiter`
+    ///
+    /// At most `max` events are passed on per `period`. A period starts with
+    /// the first event after the previous one has ended.
+    pub fn new(max: usize, period: Duration) -> Self {
+        RateLimiter {
+            max,
+            period,
+            per_file: false,
+            windows: HashMap::new(),
+            ready: VecDeque::new(),
+            skipped: 0,
+        }
+    }
+
+    /// Applies the limit to each file separately
+    ///
+    /// Files are identified by the watch descriptor and name of their events.
+    pub fn per_file(mut self, enabled: bool) -> Self {
+        self.per_file = enabled;
+        self
+    }
+
+    /// Passes an event to the rate limiter
+    ///
+    /// `now` is the time at which the event was received.
+    pub fn push(&mut self, event: EventOwned, now: Instant) {
+        self.expire(now);
+
+        if event.is_queue_overflow() {
+            self.ready.push_back(RateItem::Event(event));
+            return;
+        }
+
+        let key = if self.per_file {
+            Some((event.wd.clone(), event.name.clone()))
+        } else {
+            None
+        };
+        let window = self.windows.entry(key).or_insert(Window {
+            end: now + self.period,
+            passed: 0,
+            skipped: None,
+        });
+
+        if window.passed < self.max {
+            window.passed += 1;
+            self.ready.push_back(RateItem::Event(event));
+            return;
+        }
+
+        let count = window.skipped.take().map_or(0, |(_, count)| count);
+        window.skipped = Some((event, count + 1));
+        self.skipped += 1;
+    }
+
+    /// Returns the next event or summary of skipped events
+    ///
+    /// `now` is the current time.
+    pub fn pop(&mut self, now: Instant) -> Option<RateItem> {
+        self.expire(now);
+        self.ready.pop_front()
+    }
+
+    /// Returns all remaining items, without waiting for periods to end
+    ///
+    /// This is useful when shutting down.
+    pub fn flush(&mut self) -> impl Iterator<Item = RateItem> + '_ {
+        let mut windows = self.windows.drain().map(|(_, window)| window).collect();
+        summarize(&mut windows, &mut self.ready);
+        self.ready.drain(..)
+    }
+
+    /// Returns the time at which the next summary becomes ready
+    ///
+    /// Returns `None`, if no events have been skipped in the current periods.
+    /// If an item is ready already, the returned time is in the past.
+    pub fn next_deadline(&self) -> Option<Instant> {
+        if !self.ready.is_empty() {
+            return Some(Instant::now());
+        }
+
+        self.windows
+            .values()
+            .filter(|window| window.skipped.is_some())
+            .map(|window| window.end)
+            .min()
+    }
+
+    /// Indicates whether the rate limiter holds no items
+    pub fn is_empty(&self) -> bool {
+        self.ready.is_empty() && self.windows.values().all(|window| window.skipped.is_none())
+    }
+
+    /// Returns how many events have been skipped in total
+    pub fn skipped(&self) -> u64 {
+        self.skipped
+    }
+
+    fn expire(&mut self, now: Instant) {
+        if self.windows.values().all(|window| window.end > now) {
+            return;
+        }
+
+        let mut expired = Vec::new();
+        self.windows.retain(|_, window| {
+            if window.end > now {
+                return true;
+            }
+            expired.push(Window {
+                end: window.end,
+                passed: window.passed,
+                skipped: window.skipped.take(),
+            });
+            false
+        });
+        summarize(&mut expired, &mut self.ready);
+    }
+}
+
+/// An item returned by [`RateLimiter`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RateItem {
+    /// An event that has been passed on
+    Event(EventOwned),
+
+    /// Events that have been skipped during a period
+    Skipped {
+        /// The last of the skipped events
+        last: EventOwned,
+
+        /// How many events have been skipped
+        count: usize,
+    },
+}
+
+/// Identifies what a limit applies to
+///
+/// `None`, if the limit applies to all events.
+type Key = Option<(WatchDescriptor, Option<OsString>)>;
+
+#[derive(Debug)]
+struct Window {
+    end: Instant,
+    passed: usize,
+    skipped: Option<(EventOwned, usize)>,
+}
+
+/// Adds summaries of the skipped events to `ready`, oldest first
+fn summarize(windows: &mut Vec<Window>, ready: &mut VecDeque<RateItem>) {
+    windows.sort_by_key(|window| window.end);
+    for window in windows.drain(..) {
+        if let Some((last, count)) = window.skipped {
+            ready.push_back(RateItem::Skipped { last, count });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::{RateItem, RateLimiter};
+    use crate::events::test_event;
+    use crate::fd_guard::FdHandle;
+    use crate::EventMask;
+
+    #[test]
+    fn it_should_summarize_skipped_events() {
+        let now = Instant::now();
+        let period = Duration::from_secs(1);
+        let mut limiter = RateLimiter::new(2, period);
+
+        for name in ["a", "b", "c", "d"] {
+            limiter.push(test_event(EventMask::MODIFY, 0, Some(name)), now);
+        }
+        limiter.push(test_event(EventMask::Q_OVERFLOW, 0, None), now);
+
+        let names = [limiter.pop(now), limiter.pop(now)].map(|item| match item {
+            Some(RateItem::Event(event)) => event.name,
+            item => panic!("Unexpected item: {:?}", item),
+        });
+        assert_eq!(names, [Some("a".into()), Some("b".into())]);
+        assert!(matches!(
+            limiter.pop(now),
+            Some(RateItem::Event(event)) if event.is_queue_overflow()
+        ));
+        assert_eq!(limiter.pop(now), None);
+        assert_eq!(limiter.next_deadline(), Some(now + period));
+
+        match limiter.pop(now + period) {
+            Some(RateItem::Skipped { last, count }) => {
+                assert_eq!(last.name, Some("d".into()));
+                assert_eq!(count, 2);
+            }
+            item => panic!("Unexpected item: {:?}", item),
+        }
+        assert_eq!(limiter.skipped(), 2);
+        assert!(limiter.is_empty());
+    }
+
+    #[test]
+    fn it_should_limit_each_file_separately() {
+        let now = Instant::now();
+        let mut limiter = RateLimiter::new(1, Duration::from_secs(1)).per_file(true);
+
+        for name in ["a", "a", "b"] {
+            limiter.push(test_event(EventMask::MODIFY, 0, Some(name)), now);
+        }
+
+        let items = limiter.flush().collect::<Vec<_>>();
+        assert_eq!(items.len(), 3);
+        assert!(matches!(&items[2], RateItem::Skipped { count: 1, .. }));
+        assert!(limiter.is_empty());
+    }
+
+    #[test]
+    fn it_should_limit_files_of_different_instances_separately() {
+        let now = Instant::now();
+        let mut limiter = RateLimiter::new(1, Duration::from_secs(1)).per_file(true);
+
+        let mut other = test_event(EventMask::MODIFY, 0, Some("a"));
+        // Doesn't belong to the instance of the other event.
+        other.wd.fd = FdHandle::default();
+        limiter.push(test_event(EventMask::MODIFY, 0, Some("a")), now);
+        limiter.push(other, now);
+
+        let items = limiter.flush().collect::<Vec<_>>();
+        assert_eq!(items.len(), 2);
+        assert!(items.iter().all(|item| matches!(item, RateItem::Event(_))));
+    }
+}
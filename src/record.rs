@@ -0,0 +1,358 @@
+use std::{
+    collections::VecDeque,
+    ffi::OsStr,
+    io::{self, Read, Write},
+    os::{raw::c_int, unix::ffi::OsStrExt},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+#[cfg(feature = "stream-core")]
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+#[cfg(feature = "stream-core")]
+use futures_core::Stream;
+
+use crate::events::{EventOwned, Events};
+use crate::fd_guard::FdHandle;
+use crate::inotify::Inotify;
+use crate::util::{read, read_blocking};
+use crate::watches::WatchDescriptor;
+
+/// Identifies a recording, and the version of its format
+const MAGIC: &[u8; 8] = b"INOTREC1";
+
+/// The maximum size of a record
+///
+/// Far larger than any read from the kernel, but small enough to be allocated
+/// without a second thought, when replaying a corrupted recording.
+const MAX_RECORD_LEN: usize = 16 * 1024 * 1024;
+
+const RECORD_WATCH: u8 = b'W';
+const RECORD_READ: u8 = b'R';
+
+/// Records the events read from an inotify instance
+///
+/// Reading events through a `Recorder` writes the raw bytes of each read to
+/// a [`Write`]r, for example a file. Together with the paths of the watches,
+/// recorded using [`Recorder::record_watch`], this is enough to reproduce the
+/// exact sequence of events later, using a [`Replayer`]. This is useful to
+/// capture the events that led to an incident in production, and turn them
+/// into a test.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::fs::File;
+///
+/// use inotify::{Inotify, Recorder, WatchMask};
+///
+/// let inotify = Inotify::init()
+///     .expect("Failed to initialize an inotify instance");
+/// let mut recorder = Recorder::new(File::create("events.rec").unwrap())
+///     .expect("Failed to start recording");
+///
+/// let wd = inotify.watches().add("/tmp", WatchMask::ALL_EVENTS)
+///     .expect("Failed to add watch");
+/// recorder.record_watch(&wd, "/tmp").expect("Failed to record watch");
+///
+/// let mut buffer = [0; 4096];
+/// loop {
+///     let events = recorder.read_events_blocking(&inotify, &mut buffer)
+///         .expect("Error while reading events");
+///     for event in events {
+///         // Handle event
+///     }
+/// }
+/// ```
+#[derive(Debug)]
+pub struct Recorder<W> {
+    writer: W,
+}
+
+impl<W> Recorder<W>
+where
+    W: Write,
+{
+    /// Starts a recording
+    ///
+    /// # Errors
+    ///
+    /// Returns the error from writing to `writer`.
+    pub fn new(mut writer: W) -> io::Result<Self> {
+        writer.write_all(MAGIC)?;
+        Ok(Recorder { writer })
+    }
+
+    /// Records which path a watch has been added for
+    ///
+    /// # Errors
+    ///
+    /// Returns the error from writing to the underlying writer.
+    pub fn record_watch<P>(&mut self, wd: &WatchDescriptor, path: P) -> io::Result<()>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref().as_os_str().as_bytes();
+
+        let mut payload = Vec::with_capacity(4 + path.len());
+        payload.extend_from_slice(&wd.id.to_le_bytes());
+        payload.extend_from_slice(path);
+
+        self.write_record(RECORD_WATCH, &payload)
+    }
+
+    /// Reads events like [`Inotify::read_events_blocking`], and records them
+    ///
+    /// # Errors
+    ///
+    /// Returns the error from reading events, or from writing to the
+    /// underlying writer.
+    pub fn read_events_blocking<'a>(
+        &mut self,
+        inotify: &Inotify,
+        buffer: &'a mut [u8],
+    ) -> io::Result<Events<'a>> {
+        let num_bytes = read_blocking(**inotify.fd, buffer)?;
+        self.write_record(RECORD_READ, &buffer[..num_bytes])?;
+        Ok(inotify.events_from_bytes(&buffer[..num_bytes]))
+    }
+
+    /// Reads events like [`Inotify::read_events`], and records them
+    ///
+    /// # Errors
+    ///
+    /// Returns the error from reading events, or from writing to the
+    /// underlying writer.
+    pub fn read_events<'a>(
+        &mut self,
+        inotify: &Inotify,
+        buffer: &'a mut [u8],
+    ) -> io::Result<Events<'a>> {
+        let num_bytes = read(**inotify.fd, buffer)?;
+        self.write_record(RECORD_READ, &buffer[..num_bytes])?;
+        Ok(inotify.events_from_bytes(&buffer[..num_bytes]))
+    }
+
+    /// Flushes the underlying writer, and returns it
+    ///
+    /// # Errors
+    ///
+    /// Returns the error from flushing the writer.
+    pub fn into_inner(mut self) -> io::Result<W> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+
+    fn write_record(&mut self, kind: u8, payload: &[u8]) -> io::Result<()> {
+        if payload.len() > MAX_RECORD_LEN {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Record too large",
+            ));
+        }
+        let len = payload.len() as u32;
+
+        self.writer.write_all(&[kind])?;
+        self.writer.write_all(&len.to_le_bytes())?;
+        self.writer.write_all(payload)
+    }
+}
+
+/// Replays events that have been recorded by a [`Recorder`]
+///
+/// The recorded reads are returned one by one from [`Replayer::next_events`],
+/// exactly as they have been read from the kernel. `Replayer` can also be
+/// used as an [`Iterator`], and, with the `stream-core` feature, as a
+/// [`Stream`] of events, which allows to pass it to the same code that
+/// handles an [`EventStream`].
+///
+/// The watch descriptors of the replayed events are associated with the
+/// [`Inotify`] instance that is passed to [`Replayer::new`]. Use
+/// [`Replayer::watches`] to find out which paths they refer to. Replaying
+/// doesn't otherwise affect the instance: Its watches, oneshot watches and
+/// overflow hook don't see the replayed events.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::fs::File;
+///
+/// use inotify::{Inotify, Replayer};
+///
+/// let inotify = Inotify::init()
+///     .expect("Failed to initialize an inotify instance");
+/// let mut replayer = Replayer::new(File::open("events.rec").unwrap(), &inotify)
+///     .expect("Failed to open recording");
+///
+/// while let Some(events) = replayer.next_events().expect("Error while replaying") {
+///     for event in events {
+///         // Handle event
+///     }
+/// }
+/// ```
+///
+/// [`Stream`]: futures_core::Stream
+/// [`EventStream`]: crate::EventStream
+#[derive(Debug)]
+pub struct Replayer<R> {
+    reader: R,
+    fd: FdHandle,
+    watches: Vec<(c_int, PathBuf)>,
+    buffer: Vec<u8>,
+    pending: VecDeque<EventOwned>,
+}
+
+impl<R> Replayer<R>
+where
+    R: Read,
+{
+    /// Opens a recording
+    ///
+    /// # Errors
+    ///
+    /// Returns the error from reading from `reader`, or an error with
+    /// [`ErrorKind::InvalidData`], if it doesn't contain a recording.
+    ///
+    /// [`ErrorKind::InvalidData`]: std::io::ErrorKind::InvalidData
+    pub fn new(mut reader: R, inotify: &Inotify) -> io::Result<Self> {
+        let mut magic = [0; MAGIC.len()];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid("Not an inotify recording"));
+        }
+
+        Ok(Replayer {
+            reader,
+            fd: FdHandle::new(Arc::downgrade(&inotify.fd)),
+            watches: Vec::new(),
+            buffer: Vec::new(),
+            pending: VecDeque::new(),
+        })
+    }
+
+    /// Returns the events of the next recorded read
+    ///
+    /// Returns `None`, once the end of the recording has been reached.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error from reading from the underlying reader, or an
+    /// error with [`ErrorKind::InvalidData`], if the recording is corrupted.
+    /// Corrupted events within a read are only noticed while iterating, so
+    /// consider calling [`Events::checked`] on the returned iterator.
+    ///
+    /// [`ErrorKind::InvalidData`]: std::io::ErrorKind::InvalidData
+    pub fn next_events(&mut self) -> io::Result<Option<Events<'_>>> {
+        loop {
+            let mut header = [0; 5];
+            match self.reader.read_exact(&mut header) {
+                Ok(()) => {}
+                Err(error) if error.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+                Err(error) => return Err(error),
+            }
+            let len = u32::from_le_bytes([header[1], header[2], header[3], header[4]]) as usize;
+            if len > MAX_RECORD_LEN {
+                return Err(invalid("Corrupted inotify recording"));
+            }
+
+            self.buffer.resize(len, 0);
+            self.reader.read_exact(&mut self.buffer)?;
+
+            match header[0] {
+                RECORD_WATCH if len >= 4 => {
+                    let id = c_int::from_le_bytes([
+                        self.buffer[0],
+                        self.buffer[1],
+                        self.buffer[2],
+                        self.buffer[3],
+                    ]);
+                    let path = PathBuf::from(OsStr::from_bytes(&self.buffer[4..]));
+
+                    self.watches.retain(|(watched, _)| *watched != id);
+                    self.watches.push((id, path));
+                }
+                RECORD_READ => {
+                    return Ok(Some(Events::replayed(self.fd.clone(), &self.buffer, len)));
+                }
+                _ => return Err(invalid("Corrupted inotify recording")),
+            }
+        }
+    }
+
+    /// Returns the watches that have been recorded so far
+    ///
+    /// Watches are recorded along with the events, so this includes all
+    /// watches that had been added before the last read returned by
+    /// [`Replayer::next_events`].
+    pub fn watches(&self) -> impl Iterator<Item = (WatchDescriptor, &Path)> + '_ {
+        self.watches.iter().map(move |(id, path)| {
+            (
+                WatchDescriptor {
+                    id: *id,
+                    fd: self.fd.clone(),
+                },
+                path.as_path(),
+            )
+        })
+    }
+
+    /// Returns the path a watch has been recorded for
+    pub fn path(&self, wd: &WatchDescriptor) -> Option<&Path> {
+        self.watches
+            .iter()
+            .find(|(id, _)| *id == wd.id)
+            .map(|(_, path)| path.as_path())
+    }
+
+    fn next_event(&mut self) -> Option<io::Result<EventOwned>> {
+        while self.pending.is_empty() {
+            match self.next_events() {
+                Ok(Some(events)) => {
+                    let events = events
+                        .checked()
+                        .map(|event| event.map(|event| event.to_owned()))
+                        .collect::<Result<Vec<_>, _>>();
+                    match events {
+                        Ok(events) => self.pending.extend(events),
+                        Err(error) => return Some(Err(error.into())),
+                    }
+                }
+                Ok(None) => return None,
+                Err(error) => return Some(Err(error)),
+            }
+        }
+
+        self.pending.pop_front().map(Ok)
+    }
+}
+
+impl<R> Iterator for Replayer<R>
+where
+    R: Read,
+{
+    type Item = io::Result<EventOwned>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_event()
+    }
+}
+
+#[cfg(feature = "stream-core")]
+impl<R> Stream for Replayer<R>
+where
+    R: Read + Unpin,
+{
+    type Item = io::Result<EventOwned>;
+
+    fn poll_next(mut self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Poll::Ready(self.next_event())
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
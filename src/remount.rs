@@ -0,0 +1,346 @@
+use std::{
+    collections::VecDeque,
+    fs::{self, File},
+    io,
+    os::unix::{fs::MetadataExt, io::AsRawFd},
+    path::{Path, PathBuf},
+    thread,
+    time::Duration,
+};
+
+use crate::events::{EventMask, EventOwned};
+use crate::inotify::Inotify;
+use crate::util::{get_buffer_size_for_events, poll_priority};
+use crate::watches::{WatchDescriptor, WatchMask};
+
+/// How often to check for the path, if the mount table can't be watched
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Watches a path, and watches it again after its filesystem is mounted again
+///
+/// When the filesystem that a watched file or directory lives on is
+/// unmounted, the kernel sends [`EventMask::UNMOUNT`], followed by
+/// [`EventMask::IGNORED`], and the watch is gone for good. `RemountWatcher`
+/// remembers the path in that case, and returns [`RemountEvent::Unmounted`].
+/// Whenever the mount table changes afterwards, it checks whether something
+/// other than what was left behind by the unmount is at the path now. If so,
+/// it watches the path again, with the same mask, and returns
+/// [`RemountEvent::Reattached`]. This is useful for watching removable media
+/// or network mounts.
+///
+/// Changes to the mount table are noticed by polling `/proc/self/mountinfo`.
+/// If that isn't available, the path is checked once a second instead. Use
+/// [`RemountWatcher::poll_interval`] to check it periodically in any case, for
+/// example if the filesystem is mounted in another mount namespace.
+///
+/// `RemountWatcher` uses an [`Inotify`] instance of its own, and blocks while
+/// waiting for events. If the watch is removed for any other reason, for
+/// example because the path has been deleted, the watcher ends.
+///
+/// # Examples
+///
+/// ```no_run
+/// use inotify::{RemountEvent, RemountWatcher, WatchMask};
+///
+/// let watcher = RemountWatcher::new("/media/usb", WatchMask::CREATE)
+///     .expect("Failed to watch path");
+///
+/// for event in watcher {
+///     match event.expect("Error while watching path") {
+///         RemountEvent::Unmounted => println!("Drive removed"),
+///         RemountEvent::Reattached => println!("Drive is back"),
+///         RemountEvent::Event(event) => println!("{:?}", event),
+///     }
+/// }
+/// ```
+#[derive(Debug)]
+pub struct RemountWatcher {
+    inotify: Inotify,
+    path: PathBuf,
+    mask: WatchMask,
+    state: State,
+    mounts: Option<File>,
+    poll_interval: Option<Duration>,
+    pending: VecDeque<RemountEvent>,
+    buffer: Vec<u8>,
+}
+
+impl RemountWatcher {
+    /// Starts watching `path` with `mask`
+    ///
+    /// # Errors
+    ///
+    /// Returns the error from initializing inotify, or from adding the watch.
+    /// The path needs to exist initially.
+    pub fn new<P>(path: P, mask: WatchMask) -> io::Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref().to_path_buf();
+
+        let inotify = Inotify::init()?;
+        let wd = inotify.watches().add(&path, mask)?;
+
+        Ok(RemountWatcher {
+            inotify,
+            path,
+            mask,
+            state: State::Attached {
+                wd,
+                unmounted: false,
+            },
+            mounts: File::open("/proc/self/mountinfo").ok(),
+            poll_interval: None,
+            pending: VecDeque::new(),
+            buffer: vec![0; get_buffer_size_for_events(16)],
+        })
+    }
+
+    /// Checks for the path every `interval` too, while it is unmounted
+    pub fn poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = Some(interval);
+        self
+    }
+
+    /// Returns the watched path
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the current watch of the path, if it is being watched
+    ///
+    /// The watch descriptor changes, whenever the path is reattached.
+    pub fn wd(&self) -> Option<&WatchDescriptor> {
+        match &self.state {
+            State::Attached { wd, .. } => Some(wd),
+            _ => None,
+        }
+    }
+
+    /// Indicates whether the path is being watched
+    pub fn is_attached(&self) -> bool {
+        self.wd().is_some()
+    }
+
+    /// Waits for the next event
+    ///
+    /// Returns `None`, once the watch has been removed for any other reason
+    /// than its filesystem being unmounted.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error from reading events, from waiting for changes to the
+    /// mount table, or from adding the watch again. The watcher can be used
+    /// again afterwards.
+    pub fn next_event(&mut self) -> io::Result<Option<RemountEvent>> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return Ok(Some(event));
+            }
+
+            match self.state {
+                State::Attached { .. } => {
+                    let events = self
+                        .inotify
+                        .read_events_blocking(&mut self.buffer)?
+                        .map(|event| event.to_owned())
+                        .collect::<Vec<_>>();
+                    for event in events {
+                        self.handle(event)?;
+                    }
+                }
+                State::Unmounted { .. } => {
+                    self.wait_for_mounts()?;
+                    self.reattach()?;
+                }
+                State::Removed => return Ok(None),
+            }
+        }
+    }
+
+    fn handle(&mut self, event: EventOwned) -> io::Result<()> {
+        if event.is_queue_overflow() {
+            self.pending.push_back(RemountEvent::Event(event));
+            return Ok(());
+        }
+
+        let unmounted = match &mut self.state {
+            State::Attached { wd, unmounted } if *wd == event.wd => unmounted,
+            _ => return Ok(()),
+        };
+
+        if event.mask.contains(EventMask::UNMOUNT) {
+            *unmounted = true;
+        }
+
+        if !event.mask.contains(EventMask::IGNORED) {
+            self.pending.push_back(RemountEvent::Event(event));
+            return Ok(());
+        }
+
+        if *unmounted {
+            self.state = State::Unmounted {
+                left_behind: identity(&self.path),
+            };
+            self.pending.push_back(RemountEvent::Event(event));
+            self.pending.push_back(RemountEvent::Unmounted);
+
+            // The filesystem might have been mounted again already.
+            self.reattach()
+        } else {
+            self.state = State::Removed;
+            self.pending.push_back(RemountEvent::Event(event));
+            Ok(())
+        }
+    }
+
+    /// Watches the path again, if something new is at the path
+    fn reattach(&mut self) -> io::Result<()> {
+        let left_behind = match self.state {
+            State::Unmounted { left_behind } => left_behind,
+            _ => return Ok(()),
+        };
+
+        match identity(&self.path) {
+            Some(current) if Some(current) != left_behind => {}
+            _ => return Ok(()),
+        }
+
+        let wd = match self.inotify.watches().add(&self.path, self.mask) {
+            Ok(wd) => wd,
+            // Unmounted again in the meantime.
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(error) => return Err(error),
+        };
+        self.state = State::Attached {
+            wd,
+            unmounted: false,
+        };
+        self.pending.push_back(RemountEvent::Reattached);
+
+        Ok(())
+    }
+
+    fn wait_for_mounts(&self) -> io::Result<()> {
+        match &self.mounts {
+            Some(mounts) => poll_priority(mounts.as_raw_fd(), self.poll_interval).map(|_| ()),
+            None => {
+                thread::sleep(self.poll_interval.unwrap_or(DEFAULT_POLL_INTERVAL));
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Iterator for RemountWatcher {
+    type Item = io::Result<RemountEvent>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_event().transpose()
+    }
+}
+
+#[derive(Debug)]
+enum State {
+    Attached {
+        wd: WatchDescriptor,
+        /// [`EventMask::UNMOUNT`] has been received for the watch
+        unmounted: bool,
+    },
+    Unmounted {
+        /// What was at the path right after the unmount
+        left_behind: Option<(u64, u64)>,
+    },
+    Removed,
+}
+
+/// Returns the device and inode number of what is at `path`
+fn identity(path: &Path) -> Option<(u64, u64)> {
+    fs::metadata(path)
+        .ok()
+        .map(|metadata| (metadata.dev(), metadata.ino()))
+}
+
+/// An item returned by [`RemountWatcher`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RemountEvent {
+    /// The filesystem of the path has been unmounted
+    Unmounted,
+
+    /// The filesystem has been mounted again, and the path is watched again
+    Reattached,
+
+    /// An event for the path
+    Event(EventOwned),
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use tempfile::TempDir;
+
+    use super::{RemountEvent, RemountWatcher};
+    use crate::events::test_event;
+    use crate::{EventMask, WatchMask};
+
+    fn push(watcher: &mut RemountWatcher, mask: EventMask) {
+        let mut event = test_event(mask, 0, None);
+        event.wd = watcher.wd().unwrap().clone();
+        watcher.handle(event).unwrap();
+    }
+
+    #[test]
+    fn it_should_reattach_once_something_new_is_at_the_path() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("mnt");
+        fs::create_dir(&path).unwrap();
+
+        let mut watcher = RemountWatcher::new(&path, WatchMask::CREATE).unwrap();
+        push(&mut watcher, EventMask::UNMOUNT);
+        push(&mut watcher, EventMask::IGNORED);
+        assert!(!watcher.is_attached());
+
+        let masks = std::iter::from_fn(|| watcher.pending.pop_front())
+            .map(|event| match event {
+                RemountEvent::Event(event) => Some(event.mask),
+                RemountEvent::Unmounted => None,
+                event => panic!("Unexpected event: {:?}", event),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            masks,
+            vec![Some(EventMask::UNMOUNT), Some(EventMask::IGNORED), None]
+        );
+
+        // The directory that was left behind doesn't count.
+        watcher.reattach().unwrap();
+        assert!(!watcher.is_attached());
+
+        // Created before the removal, so it doesn't get the same inode.
+        let other = dir.path().join("other");
+        fs::create_dir(&other).unwrap();
+        fs::remove_dir(&path).unwrap();
+        watcher.reattach().unwrap();
+        assert!(!watcher.is_attached());
+
+        fs::rename(&other, &path).unwrap();
+        watcher.reattach().unwrap();
+        assert!(watcher.is_attached());
+        assert_eq!(watcher.pending.pop_front(), Some(RemountEvent::Reattached));
+    }
+
+    #[test]
+    fn it_should_end_if_the_watch_is_removed_otherwise() {
+        let dir = TempDir::new().unwrap();
+
+        let mut watcher = RemountWatcher::new(dir.path(), WatchMask::CREATE).unwrap();
+        push(&mut watcher, EventMask::IGNORED);
+
+        match watcher.next_event().unwrap() {
+            Some(RemountEvent::Event(event)) => assert_eq!(event.mask, EventMask::IGNORED),
+            event => panic!("Unexpected event: {:?}", event),
+        }
+        assert_eq!(watcher.next_event().unwrap(), None);
+    }
+}
@@ -0,0 +1,288 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use crate::events::{EventMask, EventOwned};
+
+/// Pairs `MOVED_FROM` and `MOVED_TO` events into renames
+///
+/// When a file is moved within the watched directories, inotify generates a
+/// [`MOVED_FROM`] and a [`MOVED_TO`] event with the same cookie. The two events
+/// usually directly follow each other, but they might end up in different
+/// reads. And if a file is moved out of or into the watched directories, only
+/// one of them is generated.
+///
+/// `RenameTracker` takes care of this. Push all events into it, using
+/// [`RenameTracker::push`], and take the results out of it, using
+/// [`RenameTracker::pop`]. A `MOVED_FROM` event is held back until either the
+/// matching `MOVED_TO` arrives, or the configured window has passed. The
+/// order of events is preserved, so events that arrive after a `MOVED_FROM`
+/// are held back too.
+///
+/// `RenameTracker` doesn't do any I/O, and doesn't look at the clock itself.
+/// The current time is passed to it instead. Use
+/// [`RenameTracker::next_deadline`] to find out how long to wait for new
+/// events, before calling [`RenameTracker::pop`] again.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::time::{Duration, Instant};
+///
+/// use inotify::{Inotify, RenameItem, RenameTracker, WatchMask};
+///
+/// let inotify = Inotify::init()
+///     .expect("Failed to initialize an inotify instance");
+/// inotify.watches().add("/tmp", WatchMask::MOVE)
+///     .expect("Failed to add watch");
+///
+/// let mut tracker = RenameTracker::new(Duration::from_millis(50));
+/// let mut buffer = [0; 4096];
+///
+/// loop {
+///     let events = inotify.read_events_blocking(&mut buffer)
+///         .expect("Error while reading events");
+///     for event in events {
+///         tracker.push(event.to_owned(), Instant::now());
+///     }
+///
+///     while let Some(item) = tracker.pop(Instant::now()) {
+///         match item {
+///             RenameItem::Rename { from, to, .. } => {
+///                 println!("{:?} -> {:?}", from.name, to.name);
+///             }
+///             RenameItem::Deleted(_) => unreachable!("Not enabled"),
+///             RenameItem::Event(event) => {
+///                 // Handle other event
+///             }
+///         }
+///     }
+/// }
+/// ```
+///
+/// [`MOVED_FROM`]: EventMask::MOVED_FROM
+/// [`MOVED_TO`]: EventMask::MOVED_TO
+#[derive(Debug)]
+pub struct RenameTracker {
+    window: Duration,
+    synthesize_deletes: bool,
+    queue: VecDeque<Slot>,
+}
+
+impl RenameTracker {
+    /// Creates a `RenameTracker`
+    ///
+    /// `window` is how long to wait for a `MOVED_TO` after a `MOVED_FROM`.
+    pub fn new(window: Duration) -> Self {
+        RenameTracker {
+            window,
+            synthesize_deletes: false,
+            queue: VecDeque::new(),
+        }
+    }
+
+    /// Configures whether unmatched `MOVED_FROM` events count as deletions
+    ///
+    /// If a file is moved out of the watched directories, only a `MOVED_FROM`
+    /// event is generated. For anyone keeping track of the watched files, this
+    /// is the same as if the file had been deleted. If enabled, such events
+    /// are returned as [`RenameItem::Deleted`], once the window has passed.
+    ///
+    /// This is disabled by default.
+    pub fn synthesize_deletes(mut self, enabled: bool) -> Self {
+        self.synthesize_deletes = enabled;
+        self
+    }
+
+    /// Passes an event to the tracker
+    ///
+    /// `now` is the time at which the event was received.
+    pub fn push(&mut self, event: EventOwned, now: Instant) {
+        if event.mask.contains(EventMask::MOVED_TO) && event.cookie != 0 {
+            let pending = self.queue.iter_mut().find(|slot| match slot {
+                Slot::Pending { from, .. } => from.cookie == event.cookie,
+                Slot::Ready(_) => false,
+            });
+
+            if let Some(slot) = pending {
+                let from = match slot {
+                    Slot::Pending { from, .. } => from.clone(),
+                    Slot::Ready(_) => unreachable!("Only pending slots are matched"),
+                };
+                let cookie = event.cookie;
+
+                *slot = Slot::Ready(RenameItem::Rename {
+                    from,
+                    to: event,
+                    cookie,
+                });
+                return;
+            }
+        }
+
+        if event.mask.contains(EventMask::MOVED_FROM) && event.cookie != 0 {
+            self.queue.push_back(Slot::Pending {
+                from: event,
+                deadline: now + self.window,
+            });
+            return;
+        }
+
+        self.queue.push_back(Slot::Ready(RenameItem::Event(event)));
+    }
+
+    /// Returns the next item, if one is ready
+    ///
+    /// `now` is the current time. A `MOVED_FROM` event whose window has passed
+    /// by then is returned on its own.
+    pub fn pop(&mut self, now: Instant) -> Option<RenameItem> {
+        match self.queue.pop_front()? {
+            Slot::Ready(item) => Some(item),
+            Slot::Pending { from, deadline } if deadline > now => {
+                self.queue.push_front(Slot::Pending { from, deadline });
+                None
+            }
+            Slot::Pending { from, .. } if self.synthesize_deletes => {
+                Some(RenameItem::Deleted(from))
+            }
+            Slot::Pending { from, .. } => Some(RenameItem::Event(from)),
+        }
+    }
+
+    /// Returns all remaining items, without waiting for any windows to pass
+    ///
+    /// This is useful when shutting down. `MOVED_FROM` events that are still
+    /// waiting for a match are returned as [`RenameItem::Event`], as it is not
+    /// known yet whether they will be matched.
+    pub fn flush(&mut self) -> impl Iterator<Item = RenameItem> + '_ {
+        self.queue.drain(..).map(|slot| match slot {
+            Slot::Ready(item) => item,
+            Slot::Pending { from, .. } => RenameItem::Event(from),
+        })
+    }
+
+    /// Returns the time at which the next held back item becomes ready
+    ///
+    /// Returns `None`, if no item is held back. If an item is ready already,
+    /// the returned time is in the past.
+    pub fn next_deadline(&self) -> Option<Instant> {
+        match self.queue.front()? {
+            Slot::Pending { deadline, .. } => Some(*deadline),
+            Slot::Ready(_) => Some(Instant::now()),
+        }
+    }
+
+    /// Indicates whether the tracker holds no events
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+}
+
+/// An item returned by [`RenameTracker`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RenameItem {
+    /// A `MOVED_FROM` and a `MOVED_TO` event with the same cookie
+    Rename {
+        /// The `MOVED_FROM` event
+        from: EventOwned,
+
+        /// The `MOVED_TO` event
+        to: EventOwned,
+
+        /// The cookie that connects both events
+        cookie: u32,
+    },
+
+    /// A `MOVED_FROM` event that wasn't matched within the window
+    ///
+    /// The file was presumably moved out of the watched directories. Only
+    /// returned, if enabled via [`RenameTracker::synthesize_deletes`].
+    Deleted(EventOwned),
+
+    /// Any other event
+    ///
+    /// This includes `MOVED_FROM` and `MOVED_TO` events that couldn't be
+    /// paired, unless [`RenameItem::Deleted`] is returned for them instead.
+    Event(EventOwned),
+}
+
+#[derive(Debug)]
+enum Slot {
+    Ready(RenameItem),
+    Pending { from: EventOwned, deadline: Instant },
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::{RenameItem, RenameTracker};
+    use crate::events::test_event;
+    use crate::EventMask;
+
+    #[test]
+    fn it_should_pair_moves_with_the_same_cookie() {
+        let now = Instant::now();
+        let mut tracker = RenameTracker::new(Duration::from_secs(1));
+
+        tracker.push(test_event(EventMask::MOVED_FROM, 7, Some("a")), now);
+        tracker.push(test_event(EventMask::CREATE, 0, Some("b")), now);
+        assert_eq!(tracker.pop(now), None);
+
+        tracker.push(test_event(EventMask::MOVED_TO, 7, Some("c")), now);
+        match tracker.pop(now) {
+            Some(RenameItem::Rename { from, to, cookie }) => {
+                assert_eq!(from.name.as_deref(), Some("a".as_ref()));
+                assert_eq!(to.name.as_deref(), Some("c".as_ref()));
+                assert_eq!(cookie, 7);
+            }
+            item => panic!("Unexpected item: {:?}", item),
+        }
+        match tracker.pop(now) {
+            Some(RenameItem::Event(event)) => assert_eq!(event.mask, EventMask::CREATE),
+            item => panic!("Unexpected item: {:?}", item),
+        }
+        assert!(tracker.is_empty());
+    }
+
+    #[test]
+    fn it_should_return_unmatched_moves_after_the_window() {
+        let now = Instant::now();
+        let window = Duration::from_secs(1);
+        let mut tracker = RenameTracker::new(window);
+
+        tracker.push(test_event(EventMask::MOVED_FROM, 7, Some("a")), now);
+        assert_eq!(tracker.next_deadline(), Some(now + window));
+        assert_eq!(tracker.pop(now), None);
+
+        match tracker.pop(now + window) {
+            Some(RenameItem::Event(event)) => assert_eq!(event.mask, EventMask::MOVED_FROM),
+            item => panic!("Unexpected item: {:?}", item),
+        }
+
+        // A late `MOVED_TO` is returned on its own.
+        tracker.push(test_event(EventMask::MOVED_TO, 7, Some("b")), now + window);
+        match tracker.pop(now + window) {
+            Some(RenameItem::Event(event)) => assert_eq!(event.mask, EventMask::MOVED_TO),
+            item => panic!("Unexpected item: {:?}", item),
+        }
+    }
+
+    #[test]
+    fn it_should_synthesize_deletes_for_unmatched_moves_if_enabled() {
+        let now = Instant::now();
+        let window = Duration::from_secs(1);
+        let mut tracker = RenameTracker::new(window).synthesize_deletes(true);
+
+        tracker.push(test_event(EventMask::MOVED_FROM, 7, Some("a")), now);
+        assert_eq!(tracker.pop(now), None);
+
+        match tracker.pop(now + window) {
+            Some(RenameItem::Deleted(event)) => {
+                assert_eq!(event.name.as_deref(), Some("a".as_ref()))
+            }
+            item => panic!("Unexpected item: {:?}", item),
+        }
+    }
+}
@@ -0,0 +1,303 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    os::raw::c_int,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use crate::events::{EventMask, EventOwned};
+use crate::rename::{RenameItem, RenameTracker};
+use crate::watches::WatchDescriptor;
+
+/// Resolves events to full paths, including both sides of renames
+///
+/// Events only carry a watch descriptor and a name. To find out which file
+/// they are about, the path each watch has been added for needs to be known.
+/// `PathResolver` keeps track of that, for example for the watches returned
+/// by [`Watches::add_recursive`], and returns each event with its full path.
+///
+/// A file that is moved from one watched directory to another generates a
+/// [`MOVED_FROM`] and a [`MOVED_TO`] event for different watches. These are
+/// paired by a [`RenameTracker`], and returned as a single
+/// [`PathItem::Renamed`] with both paths. If a watched directory is renamed,
+/// the paths of all watches within it are updated.
+///
+/// Like `RenameTracker`, `PathResolver` doesn't do any I/O, and doesn't look at
+/// the clock itself. Push all events into it using [`PathResolver::push`],
+/// and take the results out of it using [`PathResolver::pop`].
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::time::{Duration, Instant};
+///
+/// use inotify::{Inotify, PathItem, PathResolver, WatchMask};
+///
+/// let inotify = Inotify::init()
+///     .expect("Failed to initialize an inotify instance");
+/// let watched = inotify.watches()
+///     .add_recursive("/home/user/project", WatchMask::MOVE, |_| true)
+///     .expect("Failed to add watches");
+///
+/// let mut resolver = PathResolver::new(Duration::from_millis(50));
+/// resolver.add_watches(watched);
+///
+/// let mut buffer = [0; 4096];
+/// loop {
+///     let events = inotify.read_events_blocking(&mut buffer)
+///         .expect("Error while reading events");
+///     for event in events {
+///         resolver.push(event.to_owned(), Instant::now());
+///     }
+///
+///     while let Some(item) = resolver.pop(Instant::now()) {
+///         match item {
+///             PathItem::Renamed { from, to, .. } => {
+///                 println!("{} -> {}", from.display(), to.display());
+///             }
+///             PathItem::Event { path, event } => {
+///                 // Handle other event
+///             }
+///         }
+///     }
+/// }
+/// ```
+///
+/// [`Watches::add_recursive`]: crate::Watches::add_recursive
+/// [`MOVED_FROM`]: EventMask::MOVED_FROM
+/// [`MOVED_TO`]: EventMask::MOVED_TO
+#[derive(Debug)]
+pub struct PathResolver {
+    tracker: RenameTracker,
+    paths: HashMap<c_int, PathBuf>,
+    ready: VecDeque<PathItem>,
+}
+
+impl PathResolver {
+    /// Creates a `PathResolver`
+    ///
+    /// `window` is how long to wait for a `MOVED_TO` after a `MOVED_FROM`.
+    /// See [`RenameTracker::new`].
+    pub fn new(window: Duration) -> Self {
+        PathResolver {
+            tracker: RenameTracker::new(window),
+            paths: HashMap::new(),
+            ready: VecDeque::new(),
+        }
+    }
+
+    /// Tells the resolver which path a watch has been added for
+    pub fn add_watch<P>(&mut self, wd: &WatchDescriptor, path: P)
+    where
+        P: AsRef<Path>,
+    {
+        self.paths.insert(wd.id, path.as_ref().to_path_buf());
+    }
+
+    /// Tells the resolver about multiple watches
+    ///
+    /// Accepts the return value of [`Watches::add_recursive`].
+    ///
+    /// [`Watches::add_recursive`]: crate::Watches::add_recursive
+    pub fn add_watches<I>(&mut self, watches: I)
+    where
+        I: IntoIterator<Item = (WatchDescriptor, PathBuf)>,
+    {
+        for (wd, path) in watches {
+            self.paths.insert(wd.id, path);
+        }
+    }
+
+    /// Returns the path a watch has been added for
+    ///
+    /// This reflects renames of watched directories that have been returned
+    /// from [`PathResolver::pop`] so far.
+    pub fn path(&self, wd: &WatchDescriptor) -> Option<&Path> {
+        self.paths.get(&wd.id).map(PathBuf::as_path)
+    }
+
+    /// Passes an event to the resolver
+    ///
+    /// `now` is the time at which the event was received.
+    pub fn push(&mut self, event: EventOwned, now: Instant) {
+        self.tracker.push(event, now);
+    }
+
+    /// Returns the next item, if one is ready
+    ///
+    /// `now` is the current time. See [`RenameTracker::pop`].
+    pub fn pop(&mut self, now: Instant) -> Option<PathItem> {
+        if let Some(item) = self.ready.pop_front() {
+            return Some(item);
+        }
+
+        let item = self.tracker.pop(now)?;
+        Some(self.resolve(item))
+    }
+
+    /// Returns all remaining items, without waiting for any windows to pass
+    ///
+    /// See [`RenameTracker::flush`].
+    pub fn flush(&mut self) -> impl Iterator<Item = PathItem> + '_ {
+        let items = self.tracker.flush().collect::<Vec<_>>();
+        for item in items {
+            let item = self.resolve(item);
+            self.ready.push_back(item);
+        }
+
+        self.ready.drain(..)
+    }
+
+    /// Returns the time at which the next held back item becomes ready
+    ///
+    /// See [`RenameTracker::next_deadline`].
+    pub fn next_deadline(&self) -> Option<Instant> {
+        if !self.ready.is_empty() {
+            return Some(Instant::now());
+        }
+
+        self.tracker.next_deadline()
+    }
+
+    /// Indicates whether the resolver holds no events
+    pub fn is_empty(&self) -> bool {
+        self.ready.is_empty() && self.tracker.is_empty()
+    }
+
+    fn resolve(&mut self, item: RenameItem) -> PathItem {
+        let (from, to) = match item {
+            RenameItem::Rename { from, to, .. } => (from, to),
+            RenameItem::Deleted(event) | RenameItem::Event(event) => return self.event(event),
+        };
+
+        let (from_path, to_path) = match (self.full_path(&from), self.full_path(&to)) {
+            (Some(from_path), Some(to_path)) => (from_path, to_path),
+            _ => {
+                let to = self.event(to);
+                self.ready.push_back(to);
+                return self.event(from);
+            }
+        };
+
+        let is_dir = to.mask.contains(EventMask::ISDIR);
+        if is_dir {
+            for path in self.paths.values_mut() {
+                if let Ok(relative) = path.strip_prefix(&from_path) {
+                    *path = to_path.join(relative);
+                }
+            }
+        }
+
+        PathItem::Renamed {
+            from: from_path,
+            to: to_path,
+            is_dir,
+        }
+    }
+
+    fn event(&mut self, event: EventOwned) -> PathItem {
+        let path = self.full_path(&event);
+        if event.mask.contains(EventMask::IGNORED) {
+            self.paths.remove(&event.wd.id);
+        }
+
+        PathItem::Event { path, event }
+    }
+
+    fn full_path(&self, event: &EventOwned) -> Option<PathBuf> {
+        // Keyed by id instead of watch descriptor, so this also works for
+        // events that don't belong to any instance, like those from
+        // `Events::from_bytes`.
+        let path = self.paths.get(&event.wd.id)?;
+        Some(match &event.name {
+            Some(name) => path.join(name),
+            None => path.clone(),
+        })
+    }
+}
+
+/// An item returned by [`PathResolver`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PathItem {
+    /// A file or directory has been renamed
+    ///
+    /// Both paths are within the watched directories, but not necessarily in
+    /// the same one.
+    Renamed {
+        /// The path before the rename
+        from: PathBuf,
+
+        /// The path after the rename
+        to: PathBuf,
+
+        /// Whether a directory has been renamed
+        is_dir: bool,
+    },
+
+    /// Any other event
+    ///
+    /// `path` is `None`, if the path of the watch isn't known, or if the event
+    /// doesn't refer to a file, like a queue overflow.
+    Event {
+        /// The full path of the file the event is about
+        path: Option<PathBuf>,
+
+        /// The event
+        event: EventOwned,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        path::{Path, PathBuf},
+        time::{Duration, Instant},
+    };
+
+    use super::{PathItem, PathResolver};
+    use crate::events::test_event;
+    use crate::fd_guard::FdHandle;
+    use crate::{EventMask, WatchDescriptor};
+
+    #[test]
+    fn it_should_resolve_renames_between_directories() {
+        let now = Instant::now();
+        let mut resolver = PathResolver::new(Duration::from_secs(1));
+
+        let wd = |id| WatchDescriptor {
+            id,
+            fd: FdHandle::default(),
+        };
+        resolver.add_watch(&wd(1), "/project/a");
+        resolver.add_watch(&wd(2), "/project/z");
+        resolver.add_watch(&wd(3), "/project/a/b");
+
+        let from = test_event(EventMask::MOVED_FROM | EventMask::ISDIR, 7, Some("b"));
+        let mut to = test_event(EventMask::MOVED_TO | EventMask::ISDIR, 7, Some("c"));
+        to.wd = wd(2);
+        resolver.push(from, now);
+        resolver.push(to, now);
+
+        assert_eq!(
+            resolver.pop(now),
+            Some(PathItem::Renamed {
+                from: PathBuf::from("/project/a/b"),
+                to: PathBuf::from("/project/z/c"),
+                is_dir: true,
+            })
+        );
+        assert_eq!(resolver.path(&wd(1)), Some(Path::new("/project/a")));
+        assert_eq!(resolver.path(&wd(3)), Some(Path::new("/project/z/c")));
+
+        let mut event = test_event(EventMask::CREATE, 0, Some("file"));
+        event.wd = wd(3);
+        resolver.push(event, now);
+        match resolver.pop(now) {
+            Some(PathItem::Event { path, .. }) => {
+                assert_eq!(path, Some(PathBuf::from("/project/z/c/file")));
+            }
+            item => panic!("Unexpected item: {:?}", item),
+        }
+        assert!(resolver.is_empty());
+    }
+}
@@ -0,0 +1,285 @@
+use std::{
+    collections::HashMap,
+    ffi::OsString,
+    fs::{self, Metadata},
+    io,
+    os::unix::fs::MetadataExt,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use crate::events::{EventMask, EventOwned};
+use crate::watches::WatchDescriptor;
+
+/// Recovers from queue overflows, by comparing snapshots of a directory
+///
+/// If the event queue overflows, events are lost, and anyone keeping track
+/// of a directory's contents can't know what happened. `Resync` keeps a
+/// snapshot of a watched directory (the names of its entries and some of
+/// their metadata). After an overflow, [`Resync::resync`] lists the directory
+/// again, and returns synthetic [`CREATE`], [`DELETE`] and [`MODIFY`] events
+/// for the differences. These can be handled like any other event.
+///
+/// Pass all events for the directory to [`Resync::observe`], to keep the
+/// snapshot up to date. Otherwise, `resync` reports changes that have been
+/// reported by events already.
+///
+/// # Examples
+///
+/// ```no_run
+/// use inotify::{Inotify, Resync, WatchMask};
+///
+/// let inotify = Inotify::init()
+///     .expect("Failed to initialize an inotify instance");
+/// let wd = inotify.watches().add("/srv/files", WatchMask::ALL_EVENTS)
+///     .expect("Failed to add watch");
+///
+/// let mut resync = Resync::new("/srv/files", wd)
+///     .expect("Failed to take snapshot");
+/// let mut buffer = [0; 4096];
+///
+/// loop {
+///     let events = inotify.read_events_blocking(&mut buffer)
+///         .expect("Error while reading events");
+///     for event in events {
+///         let event = event.to_owned();
+///
+///         if event.is_queue_overflow() {
+///             for event in resync.resync().expect("Failed to resync") {
+///                 // Handle synthetic event
+///             }
+///             continue;
+///         }
+///
+///         resync.observe(&event).expect("Failed to update snapshot");
+///         // Handle event
+///     }
+/// }
+/// ```
+///
+/// [`CREATE`]: EventMask::CREATE
+/// [`DELETE`]: EventMask::DELETE
+/// [`MODIFY`]: EventMask::MODIFY
+#[derive(Debug)]
+pub struct Resync {
+    path: PathBuf,
+    wd: WatchDescriptor,
+    entries: HashMap<OsString, Entry>,
+}
+
+impl Resync {
+    /// Takes a snapshot of the directory at `path`
+    ///
+    /// `wd` is the watch for the directory. It is used for the synthetic
+    /// events. Add the watch before taking the snapshot, so no change is
+    /// missed in between.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error from listing the directory.
+    pub fn new<P>(path: P, wd: WatchDescriptor) -> io::Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref().to_path_buf();
+        let entries = scan(&path)?;
+
+        Ok(Resync { path, wd, entries })
+    }
+
+    /// Updates the snapshot according to an event
+    ///
+    /// Events for other watches are ignored.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error from looking up the entry the event refers to.
+    pub fn observe(&mut self, event: &EventOwned) -> io::Result<()> {
+        if event.wd != self.wd {
+            return Ok(());
+        }
+        let name = match &event.name {
+            Some(name) => name,
+            None => return Ok(()),
+        };
+
+        if event
+            .mask
+            .intersects(EventMask::DELETE | EventMask::MOVED_FROM)
+        {
+            self.entries.remove(name);
+            return Ok(());
+        }
+
+        match fs::symlink_metadata(self.path.join(name)) {
+            Ok(metadata) => {
+                self.entries.insert(name.clone(), Entry::new(&metadata));
+            }
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {
+                self.entries.remove(name);
+            }
+            Err(error) => return Err(error),
+        }
+
+        Ok(())
+    }
+
+    /// Lists the directory again, and returns events for the differences
+    ///
+    /// Entries that have been added are reported as [`CREATE`], entries that
+    /// have been removed as [`DELETE`], and entries whose size or
+    /// modification time has changed as [`MODIFY`]. An entry that has been
+    /// replaced by another file is reported as `DELETE`, followed by
+    /// `CREATE`. Events for directories include [`ISDIR`]. The snapshot is
+    /// updated afterwards.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error from listing the directory. The snapshot is not
+    /// changed in that case.
+    ///
+    /// [`CREATE`]: EventMask::CREATE
+    /// [`DELETE`]: EventMask::DELETE
+    /// [`MODIFY`]: EventMask::MODIFY
+    /// [`ISDIR`]: EventMask::ISDIR
+    pub fn resync(&mut self) -> io::Result<Vec<EventOwned>> {
+        let entries = scan(&self.path)?;
+        let mut events = Vec::new();
+
+        for (name, old) in &self.entries {
+            match entries.get(name) {
+                Some(new) if new.ino == old.ino => {
+                    if new.len != old.len || new.modified != old.modified {
+                        events.push(self.event(EventMask::MODIFY, old, name));
+                    }
+                }
+                Some(new) => {
+                    events.push(self.event(EventMask::DELETE, old, name));
+                    events.push(self.event(EventMask::CREATE, new, name));
+                }
+                None => events.push(self.event(EventMask::DELETE, old, name)),
+            }
+        }
+        for (name, new) in &entries {
+            if !self.entries.contains_key(name) {
+                events.push(self.event(EventMask::CREATE, new, name));
+            }
+        }
+
+        self.entries = entries;
+        Ok(events)
+    }
+
+    fn event(&self, mask: EventMask, entry: &Entry, name: &OsString) -> EventOwned {
+        let mask = if entry.is_dir {
+            mask | EventMask::ISDIR
+        } else {
+            mask
+        };
+
+        EventOwned::synthetic(self.wd.clone(), mask, Some(name.clone()))
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct Entry {
+    ino: u64,
+    len: u64,
+    modified: Option<SystemTime>,
+    is_dir: bool,
+}
+
+impl Entry {
+    fn new(metadata: &Metadata) -> Self {
+        Entry {
+            ino: metadata.ino(),
+            len: metadata.len(),
+            modified: metadata.modified().ok(),
+            is_dir: metadata.is_dir(),
+        }
+    }
+}
+
+fn scan(path: &Path) -> io::Result<HashMap<OsString, Entry>> {
+    let mut entries = HashMap::new();
+
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        match entry.metadata() {
+            Ok(metadata) => {
+                entries.insert(entry.file_name(), Entry::new(&metadata));
+            }
+            // Removed in the meantime.
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {}
+            Err(error) => return Err(error),
+        }
+    }
+
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use tempfile::TempDir;
+
+    use super::Resync;
+    use crate::events::test_event;
+    use crate::fd_guard::FdHandle;
+    use crate::EventMask;
+
+    #[test]
+    fn it_should_report_the_differences_to_the_snapshot() {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join("a"), "a").unwrap();
+        fs::write(dir.path().join("b"), "b").unwrap();
+
+        let mut resync =
+            Resync::new(dir.path(), test_event(EventMask::empty(), 0, None).wd).unwrap();
+
+        fs::write(dir.path().join("a"), "changed").unwrap();
+        fs::remove_file(dir.path().join("b")).unwrap();
+        fs::create_dir(dir.path().join("c")).unwrap();
+
+        let mut events = resync
+            .resync()
+            .unwrap()
+            .into_iter()
+            .map(|event| (event.name.unwrap().into_string().unwrap(), event.mask))
+            .collect::<Vec<_>>();
+        events.sort();
+        assert_eq!(
+            events,
+            vec![
+                ("a".to_string(), EventMask::MODIFY),
+                ("b".to_string(), EventMask::DELETE),
+                ("c".to_string(), EventMask::CREATE | EventMask::ISDIR),
+            ]
+        );
+
+        assert_eq!(resync.resync().unwrap(), vec![]);
+    }
+
+    #[test]
+    fn it_should_only_observe_events_of_its_watch() {
+        let dir = TempDir::new().unwrap();
+        let event = test_event(EventMask::CREATE, 0, Some("a"));
+        let mut resync = Resync::new(dir.path(), event.wd.clone()).unwrap();
+
+        fs::write(dir.path().join("a"), "a").unwrap();
+        resync.observe(&event).unwrap();
+        assert_eq!(resync.resync().unwrap(), vec![]);
+
+        // Same watch id, but another instance.
+        let mut other = test_event(EventMask::CREATE, 0, Some("b"));
+        other.wd.fd = FdHandle::default();
+        fs::write(dir.path().join("b"), "b").unwrap();
+        resync.observe(&other).unwrap();
+
+        let events = resync.resync().unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].name.as_deref(), Some("b".as_ref()));
+        assert_eq!(events[0].mask, EventMask::CREATE);
+    }
+}
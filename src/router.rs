@@ -0,0 +1,233 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    io,
+    os::raw::c_int,
+    pin::Pin,
+    sync::{Arc, Mutex, MutexGuard},
+    task::{Context, Poll, Waker},
+};
+
+use futures_core::Stream;
+
+use crate::events::EventOwned;
+use crate::watches::WatchDescriptor;
+
+/// Splits a stream of events into one stream per watch
+///
+/// Returned by [`EventStream::split_by_watch`]. Use [`WatchRouter::watch`] to
+/// get a [`WatchStream`] that only yields the events of a single watch, and
+/// [`WatchRouter::rest`] to get one for all other events. This is useful if
+/// each watched directory is handled by its own task.
+///
+/// There's no task that reads the underlying stream. Whichever `WatchStream`
+/// is polled reads it, and passes events for other watches on to their
+/// streams. Events are buffered until the stream they belong to is polled, so
+/// all streams should be polled regularly. Events for which no stream exists
+/// are discarded. Queue overflow events are passed to all streams.
+///
+/// # Examples
+///
+/// ```no_run
+/// use futures_util::StreamExt;
+/// use inotify::{Inotify, WatchMask};
+///
+/// # async fn example() -> std::io::Result<()> {
+/// let inotify = Inotify::init()?;
+/// let logs = inotify.watches().add("/var/log", WatchMask::CREATE)?;
+///
+/// let router = inotify.into_event_stream_with_capacity(0)?.split_by_watch();
+/// let mut logs = router.watch(&logs);
+///
+/// tokio::spawn(async move {
+///     while let Some(_event) = logs.next().await {
+///         // Handle new log file
+///     }
+/// });
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`EventStream::split_by_watch`]: crate::EventStream::split_by_watch
+#[derive(Debug)]
+pub struct WatchRouter<S> {
+    shared: Arc<Mutex<Shared<S>>>,
+}
+
+impl<S> WatchRouter<S> {
+    pub(crate) fn new(stream: S) -> Self {
+        WatchRouter {
+            shared: Arc::new(Mutex::new(Shared {
+                stream,
+                done: false,
+                next_id: 0,
+                slots: HashMap::new(),
+            })),
+        }
+    }
+
+    /// Returns a stream of the events for the watch `wd`
+    ///
+    /// If a stream for this watch exists already, that one ends.
+    pub fn watch(&self, wd: &WatchDescriptor) -> WatchStream<S> {
+        self.add_stream(Key::Watch(wd.id))
+    }
+
+    /// Returns a stream of all events for which no other stream exists
+    ///
+    /// If such a stream exists already, that one ends.
+    pub fn rest(&self) -> WatchStream<S> {
+        self.add_stream(Key::Rest)
+    }
+
+    fn add_stream(&self, key: Key) -> WatchStream<S> {
+        let mut shared = lock(&self.shared);
+
+        let id = shared.next_id;
+        shared.next_id += 1;
+
+        let previous = shared.slots.insert(
+            key,
+            Slot {
+                id,
+                events: VecDeque::new(),
+                waker: None,
+            },
+        );
+        if let Some(waker) = previous.and_then(|slot| slot.waker) {
+            waker.wake();
+        }
+
+        WatchStream {
+            shared: self.shared.clone(),
+            key,
+            id,
+        }
+    }
+}
+
+/// Stream of the events for a single watch
+///
+/// Returned by [`WatchRouter::watch`] and [`WatchRouter::rest`].
+#[derive(Debug)]
+pub struct WatchStream<S> {
+    shared: Arc<Mutex<Shared<S>>>,
+    key: Key,
+    id: u64,
+}
+
+impl<S> Stream for WatchStream<S>
+where
+    S: Stream<Item = io::Result<EventOwned>> + Unpin,
+{
+    type Item = io::Result<EventOwned>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut guard = lock(&self.shared);
+        let shared = &mut *guard;
+
+        loop {
+            let slot = match shared.slots.get_mut(&self.key) {
+                Some(slot) if slot.id == self.id => slot,
+                // Replaced by another stream for the same watch.
+                _ => return Poll::Ready(None),
+            };
+
+            if let Some(event) = slot.events.pop_front() {
+                return Poll::Ready(Some(Ok(event)));
+            }
+            if shared.done {
+                return Poll::Ready(None);
+            }
+
+            // Only the task that polled the underlying stream last is woken
+            // up, so this one needs to route events to everyone else.
+            slot.waker = Some(cx.waker().clone());
+
+            match Pin::new(&mut shared.stream).poll_next(cx) {
+                Poll::Ready(Some(Ok(event))) => shared.route(event),
+                Poll::Ready(Some(Err(error))) => return Poll::Ready(Some(Err(error))),
+                Poll::Ready(None) => {
+                    shared.done = true;
+                    shared.wake_all();
+                }
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+impl<S> Drop for WatchStream<S> {
+    fn drop(&mut self) {
+        let mut shared = lock(&self.shared);
+
+        if shared.slots.get(&self.key).map(|slot| slot.id) == Some(self.id) {
+            shared.slots.remove(&self.key);
+        }
+
+        // This stream might have been the one to be woken up for new events,
+        // so another one needs to take over.
+        shared.wake_all();
+    }
+}
+
+#[derive(Debug)]
+struct Shared<S> {
+    stream: S,
+    done: bool,
+    next_id: u64,
+    slots: HashMap<Key, Slot>,
+}
+
+impl<S> Shared<S> {
+    fn route(&mut self, event: EventOwned) {
+        if event.is_queue_overflow() {
+            for slot in self.slots.values_mut() {
+                slot.push(event.clone());
+            }
+            return;
+        }
+
+        let key = Key::Watch(event.wd.id);
+        let slot = match self.slots.get_mut(&key) {
+            Some(slot) => Some(slot),
+            None => self.slots.get_mut(&Key::Rest),
+        };
+        if let Some(slot) = slot {
+            slot.push(event);
+        }
+    }
+
+    fn wake_all(&mut self) {
+        for slot in self.slots.values_mut() {
+            if let Some(waker) = slot.waker.take() {
+                waker.wake();
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum Key {
+    Watch(c_int),
+    Rest,
+}
+
+#[derive(Debug)]
+struct Slot {
+    id: u64,
+    events: VecDeque<EventOwned>,
+    waker: Option<Waker>,
+}
+
+impl Slot {
+    fn push(&mut self, event: EventOwned) {
+        self.events.push_back(event);
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+fn lock<S>(shared: &Mutex<Shared<S>>) -> MutexGuard<'_, Shared<S>> {
+    shared.lock().unwrap_or_else(|error| error.into_inner())
+}
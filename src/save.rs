@@ -0,0 +1,311 @@
+use std::{
+    collections::VecDeque,
+    ffi::OsString,
+    time::{Duration, Instant},
+};
+
+use crate::events::{EventMask, EventOwned};
+use crate::watches::WatchDescriptor;
+
+/// Recognizes files being saved by writing a temporary file and renaming it
+///
+/// Many editors and tools save a file atomically: They create a temporary
+/// file next to it, write the new contents to that, and then rename the
+/// temporary file over the original one. The events for this are a
+/// [`CREATE`] and a [`CLOSE_WRITE`] for the temporary name, followed by a
+/// [`MOVED_FROM`] for the temporary name and a [`MOVED_TO`] for the target.
+/// Anyone watching the target file itself won't see any of that, as the file
+/// is replaced by another one.
+///
+/// `SaveDetector` watches for this pattern in the events of a directory
+/// watch. Events for a newly created file are held back, until the file is
+/// renamed, or the configured window has passed without further events for
+/// it. If the file is renamed, all of its events are replaced by a single
+/// [`SaveItem::Replaced`]. Otherwise, they are returned as they are. The
+/// order of events is preserved, so events that arrive after the `CREATE`
+/// are held back too.
+///
+/// Like [`RenameTracker`], `SaveDetector` doesn't do any I/O, and doesn't look
+/// at the clock itself.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::time::{Duration, Instant};
+///
+/// use inotify::{Inotify, SaveDetector, SaveItem, WatchMask};
+///
+/// let inotify = Inotify::init()
+///     .expect("Failed to initialize an inotify instance");
+/// inotify.watches().add("/etc/app", WatchMask::CREATE | WatchMask::CLOSE_WRITE | WatchMask::MOVE)
+///     .expect("Failed to add watch");
+///
+/// let mut detector = SaveDetector::new(Duration::from_millis(100));
+/// let mut buffer = [0; 4096];
+///
+/// loop {
+///     let events = inotify.read_events_blocking(&mut buffer)
+///         .expect("Error while reading events");
+///     for event in events {
+///         detector.push(event.to_owned(), Instant::now());
+///     }
+///
+///     while let Some(item) = detector.pop(Instant::now()) {
+///         match item {
+///             SaveItem::Replaced(event) => println!("Saved: {:?}", event.name),
+///             SaveItem::Event(_event) => {
+///                 // Handle other event
+///             }
+///         }
+///     }
+/// }
+/// ```
+///
+/// [`CREATE`]: EventMask::CREATE
+/// [`CLOSE_WRITE`]: EventMask::CLOSE_WRITE
+/// [`MOVED_FROM`]: EventMask::MOVED_FROM
+/// [`MOVED_TO`]: EventMask::MOVED_TO
+/// [`RenameTracker`]: crate::RenameTracker
+#[derive(Debug)]
+pub struct SaveDetector {
+    window: Duration,
+    queue: VecDeque<Slot>,
+}
+
+impl SaveDetector {
+    /// Creates a `SaveDetector`
+    ///
+    /// `window` is how long to wait for the next event for a newly created
+    /// file, before its events are returned as they are.
+    pub fn new(window: Duration) -> Self {
+        SaveDetector {
+            window,
+            queue: VecDeque::new(),
+        }
+    }
+
+    /// Passes an event to the detector
+    ///
+    /// `now` is the time at which the event was received.
+    pub fn push(&mut self, event: EventOwned, now: Instant) {
+        if event.is_queue_overflow() {
+            // The rest of the pattern might have been lost.
+            for slot in &mut self.queue {
+                if let Slot::Held(held) = slot {
+                    held.released = true;
+                }
+            }
+            self.queue.push_back(Slot::Ready(SaveItem::Event(event)));
+            return;
+        }
+
+        if event.mask.contains(EventMask::MOVED_TO) && event.cookie != 0 {
+            let held = self.queue.iter().position(|slot| match slot {
+                Slot::Held(held) => !held.released && held.cookie == Some(event.cookie),
+                Slot::Ready(_) => false,
+            });
+            if let Some(index) = held {
+                self.queue[index] = Slot::Ready(SaveItem::Replaced(event));
+                return;
+            }
+        }
+
+        let held = self.queue.iter_mut().find_map(|slot| match slot {
+            Slot::Held(held)
+                if !held.released
+                    && held.cookie.is_none()
+                    && held.wd == event.wd
+                    && Some(&held.name) == event.name.as_ref() =>
+            {
+                Some(held)
+            }
+            _ => None,
+        });
+        if let Some(held) = held {
+            if event.mask.contains(EventMask::MOVED_FROM) && event.cookie != 0 {
+                held.cookie = Some(event.cookie);
+            } else if event.mask.contains(EventMask::DELETE) {
+                held.released = true;
+            }
+            held.deadline = now + self.window;
+            held.events.push_back(event);
+            return;
+        }
+
+        if event.mask.contains(EventMask::CREATE) && !event.mask.contains(EventMask::ISDIR) {
+            if let Some(name) = event.name.clone() {
+                self.queue.push_back(Slot::Held(Held {
+                    wd: event.wd.clone(),
+                    name,
+                    cookie: None,
+                    events: VecDeque::from(vec![event]),
+                    deadline: now + self.window,
+                    released: false,
+                }));
+                return;
+            }
+        }
+
+        self.queue.push_back(Slot::Ready(SaveItem::Event(event)));
+    }
+
+    /// Returns the next item, if one is ready
+    ///
+    /// `now` is the current time. The events of a file whose window has
+    /// passed by then are returned one by one.
+    pub fn pop(&mut self, now: Instant) -> Option<SaveItem> {
+        loop {
+            match self.queue.front_mut()? {
+                Slot::Ready(_) => match self.queue.pop_front() {
+                    Some(Slot::Ready(item)) => return Some(item),
+                    _ => unreachable!("Front slot is ready"),
+                },
+                Slot::Held(held) if held.released || held.deadline <= now => {
+                    match held.events.pop_front() {
+                        Some(event) => return Some(SaveItem::Event(event)),
+                        None => {
+                            self.queue.pop_front();
+                        }
+                    }
+                }
+                Slot::Held(_) => return None,
+            }
+        }
+    }
+
+    /// Returns all remaining items, without waiting for any windows to pass
+    ///
+    /// This is useful when shutting down. Events that are still held back are
+    /// returned as [`SaveItem::Event`].
+    pub fn flush(&mut self) -> impl Iterator<Item = SaveItem> + '_ {
+        self.queue.drain(..).flat_map(|slot| match slot {
+            Slot::Ready(item) => vec![item],
+            Slot::Held(held) => held.events.into_iter().map(SaveItem::Event).collect(),
+        })
+    }
+
+    /// Returns the time at which the next held back item becomes ready
+    ///
+    /// Returns `None`, if no item is held back. If an item is ready already,
+    /// the returned time is in the past.
+    pub fn next_deadline(&self) -> Option<Instant> {
+        match self.queue.front()? {
+            Slot::Held(held) if !held.released => Some(held.deadline),
+            _ => Some(Instant::now()),
+        }
+    }
+
+    /// Indicates whether the detector holds no events
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+}
+
+/// An item returned by [`SaveDetector`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SaveItem {
+    /// A file has been replaced by a temporary file
+    ///
+    /// Contains the `MOVED_TO` event for the replaced file. The events for
+    /// the temporary file are not returned.
+    Replaced(EventOwned),
+
+    /// Any other event
+    Event(EventOwned),
+}
+
+#[derive(Debug)]
+enum Slot {
+    Ready(SaveItem),
+    Held(Held),
+}
+
+#[derive(Debug)]
+struct Held {
+    wd: WatchDescriptor,
+    name: OsString,
+    cookie: Option<u32>,
+    events: VecDeque<EventOwned>,
+    deadline: Instant,
+    released: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::{SaveDetector, SaveItem};
+    use crate::events::test_event;
+    use crate::fd_guard::FdHandle;
+    use crate::EventMask;
+
+    #[test]
+    fn it_should_detect_atomic_saves() {
+        let now = Instant::now();
+        let mut detector = SaveDetector::new(Duration::from_secs(1));
+
+        detector.push(test_event(EventMask::CREATE, 0, Some("a.tmp")), now);
+        detector.push(test_event(EventMask::MODIFY, 0, Some("b")), now);
+        detector.push(test_event(EventMask::MODIFY, 0, Some("a.tmp")), now);
+        detector.push(test_event(EventMask::CLOSE_WRITE, 0, Some("a.tmp")), now);
+        assert_eq!(detector.pop(now), None);
+
+        detector.push(test_event(EventMask::MOVED_FROM, 7, Some("a.tmp")), now);
+        detector.push(test_event(EventMask::MOVED_TO, 7, Some("a")), now);
+        match detector.pop(now) {
+            Some(SaveItem::Replaced(event)) => {
+                assert_eq!(event.name.as_deref(), Some("a".as_ref()))
+            }
+            item => panic!("Unexpected item: {:?}", item),
+        }
+        match detector.pop(now) {
+            Some(SaveItem::Event(event)) => assert_eq!(event.mask, EventMask::MODIFY),
+            item => panic!("Unexpected item: {:?}", item),
+        }
+        assert!(detector.is_empty());
+    }
+
+    #[test]
+    fn it_should_return_events_of_files_that_are_not_renamed() {
+        let now = Instant::now();
+        let window = Duration::from_secs(1);
+        let mut detector = SaveDetector::new(window);
+
+        detector.push(test_event(EventMask::CREATE, 0, Some("a")), now);
+        detector.push(test_event(EventMask::CLOSE_WRITE, 0, Some("a")), now);
+        assert_eq!(detector.next_deadline(), Some(now + window));
+        assert_eq!(detector.pop(now), None);
+
+        let masks = std::iter::from_fn(|| detector.pop(now + window))
+            .map(|item| match item {
+                SaveItem::Event(event) => event.mask,
+                item => panic!("Unexpected item: {:?}", item),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(masks, vec![EventMask::CREATE, EventMask::CLOSE_WRITE]);
+        assert!(detector.is_empty());
+    }
+
+    #[test]
+    fn it_should_keep_files_of_different_instances_apart() {
+        let now = Instant::now();
+        let window = Duration::from_secs(1);
+        let mut detector = SaveDetector::new(window);
+
+        let mut other = test_event(EventMask::DELETE, 0, Some("a"));
+        // Doesn't belong to the instance of the created file, so it doesn't
+        // release that file.
+        other.wd.fd = FdHandle::default();
+        detector.push(test_event(EventMask::CREATE, 0, Some("a")), now);
+        detector.push(other, now);
+        assert_eq!(detector.pop(now), None);
+
+        let masks = std::iter::from_fn(|| detector.pop(now + window))
+            .map(|item| match item {
+                SaveItem::Event(event) => event.mask,
+                item => panic!("Unexpected item: {:?}", item),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(masks, vec![EventMask::CREATE, EventMask::DELETE]);
+    }
+}
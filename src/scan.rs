@@ -0,0 +1,238 @@
+use std::{
+    ffi::OsString,
+    fs::{self, Metadata},
+    io,
+    path::{Path, PathBuf},
+};
+
+use crate::usage::instance_watch_ids;
+use crate::watches::{WatchDescriptor, WatchMask, Watches};
+
+impl Watches {
+    /// Watches a directory, and lists the entries it already contains
+    ///
+    /// Anyone building an index of a directory needs its current contents, as
+    /// well as the changes from then on. Listing the directory first and
+    /// watching it afterwards misses everything that happens in between. This
+    /// method adds the watch first, and only then lists the directory, so no
+    /// change is missed.
+    ///
+    /// As a consequence, entries that are created while the directory is
+    /// being listed might be returned here, and reported by an event as
+    /// well. Entries that are removed while the directory is being listed are
+    /// not returned.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error from adding the watch, or from listing the
+    /// directory. In the latter case, the watch has been added already.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use inotify::{Inotify, WatchMask};
+    ///
+    /// let inotify = Inotify::init()
+    ///     .expect("Failed to initialize an inotify instance");
+    ///
+    /// let (_wd, existing) = inotify.watches()
+    ///     .add_and_scan("/srv/files", WatchMask::CREATE | WatchMask::DELETE)
+    ///     .expect("Failed to add watch");
+    /// for entry in existing {
+    ///     println!("{:?}: {} bytes", entry.path, entry.metadata.len());
+    /// }
+    ///
+    /// // Read events for changes from here on
+    /// ```
+    pub fn add_and_scan<P>(
+        &mut self,
+        path: P,
+        mask: WatchMask,
+    ) -> io::Result<(WatchDescriptor, Vec<Existing>)>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let wd = self.add(path, mask)?;
+
+        let mut existing = Vec::new();
+        for entry in fs::read_dir(path)? {
+            let entry = entry?;
+            let metadata = match entry.metadata() {
+                Ok(metadata) => metadata,
+                // Removed in the meantime. The event tells about that.
+                Err(error) if error.kind() == io::ErrorKind::NotFound => continue,
+                Err(error) => return Err(error),
+            };
+
+            existing.push(Existing {
+                wd: wd.clone(),
+                name: entry.file_name(),
+                path: entry.path(),
+                metadata,
+            });
+        }
+
+        Ok((wd, existing))
+    }
+
+    /// Watches a directory and all directories below it
+    ///
+    /// inotify doesn't support watching a directory tree, so this adds a
+    /// watch with `mask` for each directory. `filter` is called with the path
+    /// of each directory below `path`, relative to `path`. If it returns
+    /// `false`, that directory and everything below it is skipped. With the
+    /// `globset` feature, [`IgnoreRules`] can be used for that. Symbolic
+    /// links are not followed.
+    ///
+    /// Returns the watches that have been added, together with the paths of
+    /// their directories. Directories that are created later are not watched
+    /// automatically. Call this method for them, when their `CREATE` event
+    /// arrives.
+    ///
+    /// `filter` only decides which directories are watched. Events about the
+    /// skipped directories themselves, like their `CREATE` or `DELETE`, are
+    /// still reported by the watch of the directory containing them. Pass
+    /// those events through the same filter, or through
+    /// `IgnoreRules::is_ignored`, to ignore them too.
+    ///
+    /// Directories below `path` that can't be watched or listed because of
+    /// missing permissions, or that are removed or replaced while the tree is
+    /// being walked, are skipped.
+    ///
+    /// Directories that are watched already keep their watch, with `mask`
+    /// added to it, as if by [`WatchMask::MASK_ADD`].
+    ///
+    /// # Errors
+    ///
+    /// Returns the error from reading the existing watches from `/proc`, from
+    /// adding a watch, or from listing a directory. In the latter cases, the
+    /// watches that have been added up to that point are removed again.
+    /// Watches that existed before are left in place.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use inotify::{Inotify, WatchMask};
+    ///
+    /// let inotify = Inotify::init()
+    ///     .expect("Failed to initialize an inotify instance");
+    ///
+    /// let watched = inotify.watches()
+    ///     .add_recursive("/home/user/project", WatchMask::MODIFY, |path| {
+    ///         !path.ends_with("target") && !path.ends_with(".git")
+    ///     })
+    ///     .expect("Failed to add watches");
+    /// println!("Watching {} directories", watched.len());
+    /// ```
+    ///
+    /// [`IgnoreRules`]: crate::IgnoreRules
+    pub fn add_recursive<P, F>(
+        &mut self,
+        path: P,
+        mask: WatchMask,
+        mut filter: F,
+    ) -> io::Result<Vec<(WatchDescriptor, PathBuf)>>
+    where
+        P: AsRef<Path>,
+        F: FnMut(&Path) -> bool,
+    {
+        let root = path.as_ref();
+        let existing = instance_watch_ids(**self.fd)?;
+        let mut watched = Vec::new();
+
+        match self.walk(root, mask | WatchMask::MASK_ADD, &mut filter, &mut watched) {
+            Ok(()) => Ok(watched),
+            Err(error) => {
+                for (wd, _) in watched {
+                    if !existing.contains(&wd.id) {
+                        let _ = self.remove(wd);
+                    }
+                }
+                Err(error)
+            }
+        }
+    }
+
+    fn walk<F>(
+        &mut self,
+        root: &Path,
+        mask: WatchMask,
+        filter: &mut F,
+        watched: &mut Vec<(WatchDescriptor, PathBuf)>,
+    ) -> io::Result<()>
+    where
+        F: FnMut(&Path) -> bool,
+    {
+        let mut directories = vec![root.to_path_buf()];
+
+        while let Some(directory) = directories.pop() {
+            let skip = |error: &io::Error| directory != root && is_skippable(error);
+
+            let wd = match self.add(&directory, mask | WatchMask::ONLYDIR) {
+                Ok(wd) => wd,
+                Err(error) if skip(&error) => continue,
+                Err(error) => return Err(error),
+            };
+            watched.push((wd, directory.clone()));
+
+            let entries = match fs::read_dir(&directory) {
+                Ok(entries) => entries,
+                Err(error) if skip(&error) => continue,
+                Err(error) => return Err(error),
+            };
+
+            for entry in entries {
+                let entry = match entry {
+                    Ok(entry) => entry,
+                    Err(error) if skip(&error) => break,
+                    Err(error) => return Err(error),
+                };
+                let is_dir = match entry.file_type() {
+                    Ok(file_type) => file_type.is_dir(),
+                    Err(error) if error.kind() == io::ErrorKind::NotFound => continue,
+                    Err(error) => return Err(error),
+                };
+                if !is_dir {
+                    continue;
+                }
+
+                let path = entry.path();
+                let relative = path.strip_prefix(root).unwrap_or(&path);
+                if filter(relative) {
+                    directories.push(path);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Indicates whether a directory below the root is skipped after `error`
+fn is_skippable(error: &io::Error) -> bool {
+    matches!(
+        error.kind(),
+        io::ErrorKind::NotFound | io::ErrorKind::PermissionDenied
+    ) || error.raw_os_error() == Some(libc::ENOTDIR)
+}
+
+/// An entry that existed when a directory started being watched
+///
+/// Returned by [`Watches::add_and_scan`].
+#[derive(Clone, Debug)]
+pub struct Existing {
+    /// The watch of the directory that contains the entry
+    pub wd: WatchDescriptor,
+
+    /// The name of the entry, as it would be reported by an event
+    pub name: OsString,
+
+    /// The path of the entry
+    pub path: PathBuf,
+
+    /// The metadata of the entry
+    ///
+    /// Symbolic links are not followed.
+    pub metadata: Metadata,
+}
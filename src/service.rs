@@ -0,0 +1,265 @@
+use std::{
+    future::{self, Future},
+    io,
+    path::{Path, PathBuf},
+    pin::{pin, Pin},
+    task::Poll,
+};
+
+use futures_core::Stream;
+use tokio::sync::{mpsc, oneshot};
+
+use crate::events::EventOwned;
+use crate::inotify::Inotify;
+use crate::stream::EventStream;
+use crate::watches::{WatchDescriptor, WatchMask};
+
+/// A task that owns an inotify instance, and is controlled through messages
+///
+/// Many applications put inotify into a task of its own, and talk to that
+/// task through channels. `WatcherService` is such a task. Create one using
+/// [`WatcherService::spawn`], which returns a [`ServiceHandle`] to add and
+/// remove watches, and a receiver for the events.
+///
+/// The service stops, once [`ServiceHandle::shutdown`] is called, all
+/// handles or the receiver have been dropped, or the inotify instance can't
+/// be read anymore, because it has been closed. Other errors from reading
+/// events are sent to the receiver, and the service keeps running.
+///
+/// Commands are processed while the service waits for room in a full
+/// receiver. Awaiting [`ServiceHandle::add`] before receiving the events
+/// doesn't block the service therefore.
+///
+/// # Examples
+///
+/// ```no_run
+/// use inotify::{WatchMask, WatcherService};
+///
+/// # async fn example() -> std::io::Result<()> {
+/// let (handle, mut events) = WatcherService::spawn(16)?;
+/// handle.add("/tmp", WatchMask::CREATE).await?;
+///
+/// while let Some(event) = events.recv().await {
+///     println!("{:?}", event?);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct WatcherService {
+    stream: EventStream<Vec<u8>>,
+    commands: mpsc::Receiver<Command>,
+    events: mpsc::Sender<io::Result<EventOwned>>,
+}
+
+impl WatcherService {
+    /// Creates a service, without starting it
+    ///
+    /// `capacity` is the number of events the receiver can hold. If it is
+    /// full, the service stops reading events until there is room. Start the
+    /// service by polling [`WatcherService::run`].
+    ///
+    /// # Errors
+    ///
+    /// Returns the error from initializing inotify.
+    pub fn new(capacity: usize) -> io::Result<(Self, ServiceHandle, ServiceEvents)> {
+        let stream = Inotify::init()?.into_event_stream_with_capacity(0)?;
+        let (commands_tx, commands) = mpsc::channel(8);
+        let (events, events_rx) = mpsc::channel(capacity.max(1));
+
+        let service = WatcherService {
+            stream,
+            commands,
+            events,
+        };
+        let handle = ServiceHandle {
+            commands: commands_tx,
+        };
+
+        Ok((service, handle, events_rx))
+    }
+
+    /// Creates a service, and spawns it onto the current Tokio runtime
+    ///
+    /// See [`WatcherService::new`].
+    ///
+    /// # Panics
+    ///
+    /// Panics, if called outside of a Tokio runtime.
+    pub fn spawn(capacity: usize) -> io::Result<(ServiceHandle, ServiceEvents)> {
+        let (service, handle, events) = Self::new(capacity)?;
+        tokio::spawn(service.run());
+        Ok((handle, events))
+    }
+
+    /// Runs the service, until it stops
+    pub async fn run(mut self) {
+        // The event that waits for room in the receiver, if any.
+        let mut pending = None;
+        let mut stopping = false;
+
+        loop {
+            let next = match pending.take() {
+                None => {
+                    let commands = &mut self.commands;
+                    let stream = &mut self.stream;
+                    let mut closed = pin!(self.events.closed());
+
+                    future::poll_fn(|cx| {
+                        if let Poll::Ready(command) = commands.poll_recv(cx) {
+                            return Poll::Ready(Next::Command(command));
+                        }
+                        if closed.as_mut().poll(cx).is_ready() {
+                            return Poll::Ready(Next::Closed);
+                        }
+                        Pin::new(&mut *stream).poll_next(cx).map(Next::Event)
+                    })
+                    .await
+                }
+                Some(event) => {
+                    let mut event = Some(event);
+                    let commands = &mut self.commands;
+                    let mut reserve = pin!(self.events.reserve());
+
+                    let next = future::poll_fn(|cx| {
+                        if let Poll::Ready(command) = commands.poll_recv(cx) {
+                            return Poll::Ready(Next::Command(command));
+                        }
+                        reserve.as_mut().poll(cx).map(|permit| match permit {
+                            Ok(permit) => {
+                                permit.send(event.take().expect("Only sent once"));
+                                Next::Sent
+                            }
+                            Err(_) => Next::Closed,
+                        })
+                    })
+                    .await;
+
+                    pending = event;
+                    next
+                }
+            };
+
+            match next {
+                Next::Command(Some(Command::Add { path, mask, reply })) => {
+                    let _ = reply.send(self.stream.watches().add(path, mask));
+                }
+                Next::Command(Some(Command::Remove { wd, reply })) => {
+                    let _ = reply.send(self.stream.watches().remove(wd));
+                }
+                Next::Command(Some(Command::Shutdown)) | Next::Command(None) => break,
+                Next::Event(Some(event)) => {
+                    stopping = is_fatal(&event);
+                    pending = Some(event);
+                }
+                Next::Event(None) => break,
+                Next::Sent => {
+                    if stopping {
+                        break;
+                    }
+                }
+                Next::Closed => break,
+            }
+        }
+    }
+}
+
+/// Receives the events of a [`WatcherService`]
+pub type ServiceEvents = mpsc::Receiver<io::Result<EventOwned>>;
+
+/// Handle to control a [`WatcherService`]
+///
+/// Can be cloned, to control the service from several tasks.
+#[derive(Clone, Debug)]
+pub struct ServiceHandle {
+    commands: mpsc::Sender<Command>,
+}
+
+impl ServiceHandle {
+    /// Adds a watch
+    ///
+    /// See [`Watches::add`].
+    ///
+    /// # Errors
+    ///
+    /// Returns the error from adding the watch, or an error, if the service
+    /// has stopped.
+    ///
+    /// [`Watches::add`]: crate::Watches::add
+    pub async fn add<P>(&self, path: P, mask: WatchMask) -> io::Result<WatchDescriptor>
+    where
+        P: AsRef<Path>,
+    {
+        let (reply, response) = oneshot::channel();
+        self.send(Command::Add {
+            path: path.as_ref().to_path_buf(),
+            mask,
+            reply,
+        })
+        .await?;
+        response.await.map_err(|_| stopped())?
+    }
+
+    /// Removes a watch
+    ///
+    /// See [`Watches::remove`].
+    ///
+    /// # Errors
+    ///
+    /// Returns the error from removing the watch, or an error, if the service
+    /// has stopped.
+    ///
+    /// [`Watches::remove`]: crate::Watches::remove
+    pub async fn remove(&self, wd: WatchDescriptor) -> io::Result<()> {
+        let (reply, response) = oneshot::channel();
+        self.send(Command::Remove { wd, reply }).await?;
+        response.await.map_err(|_| stopped())?
+    }
+
+    /// Stops the service
+    ///
+    /// Events that have already been sent remain available from the
+    /// receiver. Does nothing, if the service has stopped already.
+    pub async fn shutdown(self) {
+        let _ = self.commands.send(Command::Shutdown).await;
+    }
+
+    async fn send(&self, command: Command) -> io::Result<()> {
+        self.commands.send(command).await.map_err(|_| stopped())
+    }
+}
+
+#[derive(Debug)]
+enum Command {
+    Add {
+        path: PathBuf,
+        mask: WatchMask,
+        reply: oneshot::Sender<io::Result<WatchDescriptor>>,
+    },
+    Remove {
+        wd: WatchDescriptor,
+        reply: oneshot::Sender<io::Result<()>>,
+    },
+    Shutdown,
+}
+
+enum Next {
+    Command(Option<Command>),
+    Event(Option<io::Result<EventOwned>>),
+    /// The pending event has been sent
+    Sent,
+    /// The receiver has been dropped
+    Closed,
+}
+
+/// Indicates whether no more events can be read after `event`
+fn is_fatal(event: &io::Result<EventOwned>) -> bool {
+    match event {
+        Ok(_) => false,
+        Err(error) => error.raw_os_error() == Some(libc::EBADF),
+    }
+}
+
+fn stopped() -> io::Error {
+    io::Error::new(io::ErrorKind::Other, "inotify watcher service has stopped")
+}
@@ -0,0 +1,248 @@
+use std::{ffi::OsStr, io, os::unix::io::AsRawFd, sync::Arc, time::Duration, vec};
+
+use crate::epoll::Epoll;
+use crate::events::{Event, Events};
+use crate::util::read;
+use crate::Inotify;
+
+/// A set of inotify instances that can be read from as one
+///
+/// Some applications spread their watches over multiple [`Inotify`] instances,
+/// for example to keep the event queues of different parts of the application
+/// separate, or to work around the per-instance queue limit. `InotifySet` owns
+/// any number of instances and uses epoll to wait for events from all of them
+/// at once.
+///
+/// # Examples
+///
+/// ```no_run
+/// use inotify::{Inotify, InotifySet, WatchMask};
+///
+/// let mut set = InotifySet::new()
+///     .expect("Failed to create inotify set");
+///
+/// for path in ["/tmp", "/var/tmp"] {
+///     let inotify = Inotify::init()
+///         .expect("Failed to initialize an inotify instance");
+///     inotify.watches().add(path, WatchMask::CREATE)
+///         .expect("Failed to add watch");
+///     set.insert(inotify)
+///         .expect("Failed to add instance to set");
+/// }
+///
+/// let mut buffer = [0; 4096];
+/// let events = set.read_events_blocking(&mut buffer)
+///     .expect("Error while reading events");
+///
+/// for (key, event) in events {
+///     // Handle event
+/// }
+/// ```
+#[derive(Debug)]
+pub struct InotifySet {
+    epoll: Epoll,
+    instances: Vec<Option<Inotify>>,
+}
+
+impl InotifySet {
+    /// Creates an empty `InotifySet`
+    ///
+    /// # Errors
+    ///
+    /// Directly returns the error from the call to [`epoll_create1`].
+    ///
+    /// [`epoll_create1`]: libc::epoll_create1
+    pub fn new() -> io::Result<Self> {
+        Ok(InotifySet {
+            epoll: Epoll::new()?,
+            instances: Vec::new(),
+        })
+    }
+
+    /// Adds an instance to the set
+    ///
+    /// Returns an [`InstanceKey`] that identifies the instance within this set.
+    /// Events read from the set are tagged with this key.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error from registering the instance with epoll. The instance
+    /// is dropped in that case.
+    pub fn insert(&mut self, inotify: Inotify) -> io::Result<InstanceKey> {
+        let index = match self.instances.iter().position(Option::is_none) {
+            Some(index) => index,
+            None => {
+                self.instances.push(None);
+                self.instances.len() - 1
+            }
+        };
+
+        self.epoll.add(inotify.as_raw_fd(), index as u64)?;
+        self.instances[index] = Some(inotify);
+
+        Ok(InstanceKey(index))
+    }
+
+    /// Removes an instance from the set and returns it
+    ///
+    /// Returns `None`, if there is no instance for `key` in this set.
+    pub fn remove(&mut self, key: InstanceKey) -> Option<Inotify> {
+        let inotify = self.instances.get_mut(key.0)?.take()?;
+
+        // This can only fail if the file descriptor is not registered, which
+        // would be a bug.
+        let _ = self.epoll.delete(inotify.as_raw_fd());
+
+        Some(inotify)
+    }
+
+    /// Returns a reference to the instance for `key`
+    pub fn get(&self, key: InstanceKey) -> Option<&Inotify> {
+        self.instances.get(key.0)?.as_ref()
+    }
+
+    /// Returns an iterator over all instances in the set, with their keys
+    pub fn iter(&self) -> impl Iterator<Item = (InstanceKey, &Inotify)> {
+        self.instances
+            .iter()
+            .enumerate()
+            .filter_map(|(index, inotify)| Some((InstanceKey(index), inotify.as_ref()?)))
+    }
+
+    /// Returns the number of instances in the set
+    pub fn len(&self) -> usize {
+        self.instances.iter().flatten().count()
+    }
+
+    /// Indicates whether the set contains no instances
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Waits until events are available from any instance, then returns them
+    ///
+    /// Behaves like [`InotifySet::read_events`], except that it blocks the
+    /// current thread until at least one event is available.
+    pub fn read_events_blocking<'a>(&self, buffer: &'a mut [u8]) -> io::Result<SetEvents<'a>> {
+        self.read(buffer, None)
+    }
+
+    /// Returns one buffer's worth of available events from all instances
+    ///
+    /// Reads events from every instance that has events available, filling
+    /// `buffer` one instance after the other. Instances that don't fit into
+    /// `buffer` anymore are read from on the next call.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error with [`ErrorKind::WouldBlock`], if no events are
+    /// available. Otherwise, this returns the same errors as
+    /// [`Inotify::read_events`], as well as any error from [`epoll_wait`].
+    ///
+    /// [`ErrorKind::WouldBlock`]: std::io::ErrorKind::WouldBlock
+    /// [`epoll_wait`]: libc::epoll_wait
+    pub fn read_events<'a>(&self, buffer: &'a mut [u8]) -> io::Result<SetEvents<'a>> {
+        self.read(buffer, Some(Duration::ZERO))
+    }
+
+    fn read<'a>(
+        &self,
+        buffer: &'a mut [u8],
+        timeout: Option<Duration>,
+    ) -> io::Result<SetEvents<'a>> {
+        let mut ready = vec![libc::epoll_event { events: 0, u64: 0 }; self.instances.len().max(1)];
+
+        // Each entry is the key, the start of the events in `buffer`, and the
+        // number of bytes read.
+        let mut reads = Vec::new();
+
+        while reads.is_empty() {
+            let num_ready = self.epoll.wait(&mut ready, timeout)?;
+            if num_ready == 0 {
+                return Err(io::ErrorKind::WouldBlock.into());
+            }
+
+            let mut pos = 0;
+            for event in &ready[..num_ready] {
+                let key = InstanceKey(event.u64 as usize);
+                let inotify = match self.get(key) {
+                    Some(inotify) => inotify,
+                    None => continue,
+                };
+                if pos == buffer.len() {
+                    break;
+                }
+
+                match read(inotify.as_raw_fd(), &mut buffer[pos..]) {
+                    Ok(num_bytes) => {
+                        reads.push((key, pos, num_bytes));
+                        pos += num_bytes;
+                    }
+                    // Another thread was faster.
+                    Err(error) if error.kind() == io::ErrorKind::WouldBlock => {}
+                    // The rest of the buffer is too small for the next event.
+                    // We'll get to this instance next time.
+                    Err(error)
+                        if error.kind() == io::ErrorKind::InvalidInput && !reads.is_empty() =>
+                    {
+                        break;
+                    }
+                    Err(error) => return Err(error),
+                }
+            }
+
+            if reads.is_empty() && timeout.is_some() {
+                return Err(io::ErrorKind::WouldBlock.into());
+            }
+        }
+
+        let buffer: &'a [u8] = buffer;
+        let batches = reads
+            .into_iter()
+            .map(|(key, pos, num_bytes)| {
+                let fd = &self.get(key).expect("Instance was just read from").fd;
+                (
+                    key,
+                    Events::new(Arc::downgrade(fd), &buffer[pos..pos + num_bytes], num_bytes),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        Ok(SetEvents {
+            batches: batches.into_iter(),
+            current: None,
+        })
+    }
+}
+
+/// Identifies an instance within an [`InotifySet`]
+///
+/// Returned by [`InotifySet::insert`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct InstanceKey(usize);
+
+/// Iterator over events read from an [`InotifySet`]
+///
+/// Yields each event together with the [`InstanceKey`] of the instance it
+/// was read from.
+#[derive(Debug)]
+pub struct SetEvents<'a> {
+    batches: vec::IntoIter<(InstanceKey, Events<'a>)>,
+    current: Option<(InstanceKey, Events<'a>)>,
+}
+
+impl<'a> Iterator for SetEvents<'a> {
+    type Item = (InstanceKey, Event<&'a OsStr>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((key, events)) = &mut self.current {
+                if let Some(event) = events.next() {
+                    return Some((*key, event));
+                }
+            }
+
+            self.current = Some(self.batches.next()?);
+        }
+    }
+}
@@ -0,0 +1,313 @@
+use std::{
+    collections::HashMap,
+    io,
+    os::raw::c_int,
+    path::Path,
+    sync::{mpsc, Arc, Mutex, MutexGuard, OnceLock},
+};
+
+use crate::dispatcher::{DispatcherHandle, Sink, StopSignal};
+use crate::events::{EventMask, EventOwned};
+use crate::fd_guard::FdHandle;
+use crate::inotify::Inotify;
+use crate::watches::{WatchDescriptor, WatchMask, Watches};
+
+/// The instance returned by [`SharedInotify::global`]
+static GLOBAL: OnceLock<SharedInotify> = OnceLock::new();
+
+/// An inotify instance that is shared by independent users
+///
+/// The number of inotify instances per user is limited (see
+/// `/proc/sys/fs/inotify/max_user_instances`). If every library in a process
+/// creates an instance of its own, that limit can be reached quickly.
+/// `SharedInotify` lets all of them use a single instance instead.
+///
+/// Each user calls [`SharedInotify::register`], and adds watches through the
+/// returned [`Registration`]. A thread reads all events, and passes each of
+/// them to the registrations that have added a watch for it. Queue overflow
+/// events are passed to all registrations.
+///
+/// # Examples
+///
+/// ```no_run
+/// use inotify::{SharedInotify, WatchMask};
+///
+/// let mut registration = SharedInotify::global()
+///     .expect("Failed to initialize the shared inotify instance")
+///     .register();
+///
+/// registration.add("/tmp", WatchMask::CREATE)
+///     .expect("Failed to add watch");
+///
+/// for event in registration.events().iter().take(10) {
+///     // Handle event
+/// }
+/// ```
+#[derive(Debug)]
+pub struct SharedInotify {
+    dispatcher: DispatcherHandle,
+    routes: Arc<Mutex<Routes>>,
+}
+
+impl SharedInotify {
+    /// Returns the instance that is shared by the whole process
+    ///
+    /// The instance is created on the first call, and lives until the process
+    /// exits.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error from initializing inotify, or from spawning the
+    /// thread that reads the events. In that case, the next call tries again.
+    pub fn global() -> io::Result<&'static SharedInotify> {
+        if let Some(shared) = GLOBAL.get() {
+            return Ok(shared);
+        }
+
+        // If another thread wins the race, this instance is dropped again.
+        let shared = SharedInotify::new()?;
+        Ok(GLOBAL.get_or_init(|| shared))
+    }
+
+    /// Creates an instance to be shared
+    ///
+    /// Use this instead of [`SharedInotify::global`] to share an instance
+    /// between a limited set of users.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error from initializing inotify, or from spawning the
+    /// thread that reads the events.
+    pub fn new() -> io::Result<Self> {
+        let routes = Arc::new(Mutex::new(Routes {
+            closed: false,
+            next_id: 0,
+            registrations: HashMap::new(),
+            watches: HashMap::new(),
+        }));
+        let dispatcher = DispatcherHandle::spawn(
+            Inotify::init()?.fd,
+            Router {
+                routes: routes.clone(),
+            },
+        )?;
+
+        Ok(SharedInotify { dispatcher, routes })
+    }
+
+    /// Registers a new user of this instance
+    ///
+    /// If the thread that reads events has stopped, because reading failed,
+    /// the channel of the returned registration is disconnected right away.
+    pub fn register(&self) -> Registration {
+        let (sender, receiver) = mpsc::channel();
+
+        let mut routes = lock(&self.routes);
+        let id = routes.next_id;
+        routes.next_id += 1;
+        if !routes.closed {
+            routes.registrations.insert(id, sender);
+        }
+
+        Registration {
+            id,
+            watches: self.dispatcher.watches(),
+            routes: self.routes.clone(),
+            events: receiver,
+        }
+    }
+}
+
+/// A user of a [`SharedInotify`]
+///
+/// Returned by [`SharedInotify::register`]. Receives the events for the
+/// watches that have been added through it.
+///
+/// Several registrations can watch the same file. The kernel only has a
+/// single watch for it then, and they get the same [`WatchDescriptor`]. Each
+/// registration only receives the events it has asked for, and the watch is
+/// only removed once no registration uses it anymore. Dropping the
+/// registration removes all of its watches.
+#[derive(Debug)]
+pub struct Registration {
+    id: u64,
+    watches: Watches,
+    routes: Arc<Mutex<Routes>>,
+    events: mpsc::Receiver<EventOwned>,
+}
+
+impl Registration {
+    /// Adds a watch for this registration
+    ///
+    /// If this registration is already watching the same file, `mask`
+    /// replaces the events it has asked for before. Watches of other
+    /// registrations are not affected.
+    ///
+    /// See [`Watches::add`].
+    ///
+    /// # Errors
+    ///
+    /// Returns the error from adding the watch.
+    pub fn add<P>(&mut self, path: P, mask: WatchMask) -> io::Result<WatchDescriptor>
+    where
+        P: AsRef<Path>,
+    {
+        // Keep the lock while adding the watch, so events for it are only
+        // routed after its route exists.
+        let mut routes = lock(&self.routes);
+
+        // Other registrations might be watching the same file, so the masks
+        // need to be combined.
+        let wd = self.watches.add(path, mask | WatchMask::MASK_ADD)?;
+
+        let users = routes.watches.entry(wd.id).or_default();
+        users.retain(|(id, _)| *id != self.id);
+        users.push((self.id, mask));
+
+        Ok(wd)
+    }
+
+    /// Removes a watch of this registration
+    ///
+    /// The watch is only removed from the kernel, if no other registration
+    /// uses it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error with [`ErrorKind::InvalidInput`], if the watch has not
+    /// been added through this registration, or the error from removing the
+    /// watch.
+    ///
+    /// [`ErrorKind::InvalidInput`]: std::io::ErrorKind::InvalidInput
+    pub fn remove(&mut self, wd: WatchDescriptor) -> io::Result<()> {
+        let mut routes = lock(&self.routes);
+
+        if !routes.unroute(wd.id, self.id) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Watch has not been added through this registration",
+            ));
+        }
+        if !routes.watches.contains_key(&wd.id) {
+            self.watches.remove(wd)?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the receiver for the events of this registration
+    pub fn events(&self) -> &mpsc::Receiver<EventOwned> {
+        &self.events
+    }
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        let mut routes = lock(&self.routes);
+
+        routes.registrations.remove(&self.id);
+
+        let ids = routes.watches.keys().copied().collect::<Vec<_>>();
+        for id in ids {
+            if routes.unroute(id, self.id) && !routes.watches.contains_key(&id) {
+                let wd = WatchDescriptor {
+                    id,
+                    fd: FdHandle::new(Arc::downgrade(&self.watches.fd)),
+                };
+                // There's nothing we could do about an error here anyway.
+                let _ = self.watches.remove(wd);
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Routes {
+    /// Set, once the thread that reads events has stopped
+    closed: bool,
+    next_id: u64,
+    registrations: HashMap<u64, mpsc::Sender<EventOwned>>,
+    /// The registrations using each watch, and what they have asked for
+    watches: HashMap<c_int, Vec<(u64, WatchMask)>>,
+}
+
+impl Routes {
+    /// Removes registration `id` from watch `wd`
+    ///
+    /// Returns `false`, if it wasn't using that watch.
+    fn unroute(&mut self, wd: c_int, id: u64) -> bool {
+        let users = match self.watches.get_mut(&wd) {
+            Some(users) => users,
+            None => return false,
+        };
+
+        let len = users.len();
+        users.retain(|(user, _)| *user != id);
+        let removed = users.len() < len;
+
+        if users.is_empty() {
+            self.watches.remove(&wd);
+        }
+
+        removed
+    }
+
+    fn send(&self, id: u64, event: EventOwned) {
+        if let Some(sender) = self.registrations.get(&id) {
+            // A registration that doesn't receive its events anymore is
+            // cleaned up, once it is dropped.
+            let _ = sender.send(event);
+        }
+    }
+}
+
+/// Passes events read by the dispatcher thread to the registrations
+struct Router {
+    routes: Arc<Mutex<Routes>>,
+}
+
+impl Sink for Router {
+    fn send(&mut self, event: EventOwned, _: &StopSignal) -> io::Result<bool> {
+        let mut routes = lock(&self.routes);
+
+        if event.is_queue_overflow() {
+            for id in routes.registrations.keys() {
+                routes.send(*id, event.clone());
+            }
+            return Ok(true);
+        }
+
+        // The kernel sends these regardless of the mask.
+        let always = EventMask::IGNORED | EventMask::UNMOUNT;
+
+        if let Some(users) = routes.watches.get(&event.wd.id) {
+            for (id, mask) in users {
+                let wanted = WatchMask::from_bits_truncate(event.mask.bits())
+                    .intersects(*mask & WatchMask::ALL_EVENTS);
+                if wanted || event.mask.intersects(always) {
+                    routes.send(*id, event.clone());
+                }
+            }
+        }
+
+        if event.mask.contains(EventMask::IGNORED) {
+            routes.watches.remove(&event.wd.id);
+        }
+
+        Ok(true)
+    }
+}
+
+impl Drop for Router {
+    fn drop(&mut self) {
+        // The dispatcher thread has stopped. Disconnect all channels, so
+        // registrations don't wait for events forever.
+        let mut routes = lock(&self.routes);
+        routes.closed = true;
+        routes.registrations.clear();
+    }
+}
+
+fn lock(routes: &Mutex<Routes>) -> MutexGuard<'_, Routes> {
+    routes.lock().unwrap_or_else(|error| error.into_inner())
+}
@@ -0,0 +1,55 @@
+use std::io;
+
+use crate::events::Events;
+use crate::inotify::Inotify;
+
+/// Something events can be read from
+///
+/// Implemented by [`Inotify`], and by [`MockInotify`], which returns events
+/// that have been pushed into it. Code that only reads events can be written
+/// against this trait, and then be tested using `MockInotify`, without
+/// touching the file system.
+///
+/// # Examples
+///
+/// ```
+/// use inotify::{EventMask, EventSource, MockInotify};
+///
+/// fn count_modifications(source: &impl EventSource) -> usize {
+///     let mut buffer = [0; 1024];
+///     source
+///         .read_events(&mut buffer)
+///         .map(|events| events.filter(|event| event.mask == EventMask::MODIFY).count())
+///         .unwrap_or(0)
+/// }
+///
+/// let mock = MockInotify::new().unwrap();
+/// let wd = mock.add_watch("/tmp/file");
+/// mock.push(&wd, EventMask::MODIFY, 0, None);
+/// mock.push(&wd, EventMask::CLOSE_WRITE, 0, None);
+///
+/// assert_eq!(count_modifications(&mock), 1);
+/// ```
+///
+/// [`MockInotify`]: crate::MockInotify
+pub trait EventSource {
+    /// Returns one buffer's worth of available events
+    ///
+    /// See [`Inotify::read_events`].
+    fn read_events<'a>(&self, buffer: &'a mut [u8]) -> io::Result<Events<'a>>;
+
+    /// Waits until events are available, then returns them
+    ///
+    /// See [`Inotify::read_events_blocking`].
+    fn read_events_blocking<'a>(&self, buffer: &'a mut [u8]) -> io::Result<Events<'a>>;
+}
+
+impl EventSource for Inotify {
+    fn read_events<'a>(&self, buffer: &'a mut [u8]) -> io::Result<Events<'a>> {
+        Inotify::read_events(self, buffer)
+    }
+
+    fn read_events_blocking<'a>(&self, buffer: &'a mut [u8]) -> io::Result<Events<'a>> {
+        Inotify::read_events_blocking(self, buffer)
+    }
+}
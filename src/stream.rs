@@ -1,114 +1,682 @@
+#[cfg(feature = "stream")]
+use std::time::Duration;
 use std::{
-    io,
-    os::unix::io::AsRawFd,
+    ffi::{OsStr, OsString},
+    fmt, future, io, mem,
+    os::unix::io::{AsFd, AsRawFd},
     pin::Pin,
-    sync::Arc,
-    task::{Context, Poll},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    task::{Context, Poll, Waker},
 };
 
-use futures_core::{ready, Stream};
-use tokio::io::unix::AsyncFd;
+use futures_core::{ready, FusedStream, Stream};
 
-use crate::events::{Event, EventOwned};
-use crate::fd_guard::FdGuard;
-use crate::util::read_into_buffer;
+#[cfg(feature = "stream")]
+use crate::batch::Batched;
+use crate::buffer_name::BufferName;
+use crate::coalesce::{CoalescedEvent, Coalescer};
+#[cfg(feature = "stream")]
+use crate::debounce::Debounced;
+use crate::driver::IoDriver;
+#[cfg(feature = "stream")]
+use crate::driver::TokioDriver;
+use crate::events::{
+    after_read, Event, EventBuffered, EventMask, EventOwned, Timestamp, TypedItem,
+};
+use crate::fd_guard::{FdGuard, FdHandle};
+use crate::router::WatchRouter;
+use crate::util::{read, read_drained};
 use crate::watches::Watches;
 use crate::Inotify;
 
 /// Stream of inotify events
 ///
 /// Allows for streaming events returned by [`Inotify::into_event_stream`].
+/// The stream is driven by the [`IoDriver`] `D`, which is tokio by default.
+#[cfg(feature = "stream")]
+#[derive(Debug)]
+pub struct EventStream<T, D = TokioDriver> {
+    // Declared before `fd`, so the driver is dropped before the file
+    // descriptor might be closed.
+    driver: D,
+    fd: Arc<FdGuard>,
+    buffer: T,
+    buffer_pos: usize,
+    unused_bytes: usize,
+    timestamp: Timestamp,
+    handle: FdHandle,
+    grow: Option<fn(&mut T) -> bool>,
+    terminated: bool,
+    close: Arc<CloseState>,
+    filter: EventFilter,
+    max_events_per_poll: usize,
+    events_since_yield: usize,
+    drain_reads: bool,
+}
+
+/// Stream of inotify events
+///
+/// Allows for streaming events returned by
+/// [`Inotify::into_event_stream_with_driver`]. The stream is driven by the
+/// [`IoDriver`] `D`.
+#[cfg(not(feature = "stream"))]
 #[derive(Debug)]
-pub struct EventStream<T> {
-    fd: AsyncFd<Arc<FdGuard>>,
+pub struct EventStream<T, D> {
+    // Declared before `fd`, so the driver is dropped before the file
+    // descriptor might be closed.
+    driver: D,
+    fd: Arc<FdGuard>,
     buffer: T,
     buffer_pos: usize,
     unused_bytes: usize,
+    timestamp: Timestamp,
+    handle: FdHandle,
+    grow: Option<fn(&mut T) -> bool>,
+    terminated: bool,
+    close: Arc<CloseState>,
+    filter: EventFilter,
+    max_events_per_poll: usize,
+    events_since_yield: usize,
+    drain_reads: bool,
 }
 
-impl<T> EventStream<T>
+impl<T, D> EventStream<T, D>
 where
     T: AsMut<[u8]> + AsRef<[u8]>,
+    D: IoDriver,
 {
-    /// Returns a new `EventStream` associated with the default reactor.
+    /// Returns a new `EventStream`, registered with the driver's reactor.
     pub(crate) fn new(fd: Arc<FdGuard>, buffer: T) -> io::Result<Self> {
         Ok(EventStream {
-            fd: AsyncFd::new(fd)?,
+            driver: D::register(fd.as_fd())?,
+            fd,
             buffer,
             buffer_pos: 0,
             unused_bytes: 0,
+            timestamp: Timestamp::now(),
+            handle: FdHandle::default(),
+            grow: None,
+            terminated: false,
+            close: Arc::new(CloseState::default()),
+            filter: EventFilter::default(),
+            max_events_per_poll: 0,
+            events_since_yield: 0,
+            drain_reads: false,
         })
     }
 
     /// Returns an instance of `Watches` to add and remove watches.
     /// See [`Watches::add`] and [`Watches::remove`].
     pub fn watches(&self) -> Watches {
-        Watches::new(self.fd.get_ref().clone())
+        Watches::new(self.fd.clone())
+    }
+
+    /// Returns a handle that can be used to end the stream
+    ///
+    /// Get the handle before moving the stream into a task. See
+    /// [`CloseHandle::close`].
+    pub fn close_handle(&self) -> CloseHandle {
+        CloseHandle {
+            state: self.close.clone(),
+        }
     }
 
     /// Consumes the `EventStream` instance and returns an `Inotify` using the original
     /// file descriptor that was passed from `Inotify` to create the `EventStream`.
     pub fn into_inotify(self) -> Inotify {
-        Inotify::from_file_descriptor(self.fd.into_inner())
+        Inotify::from_file_descriptor(self.fd)
+    }
+
+    /// Discards events whose mask doesn't intersect `mask`
+    ///
+    /// Rejected events are skipped in the buffer, before any allocation takes
+    /// place. Queue overflow events are never discarded. Calling this again
+    /// replaces the previous mask.
+    pub fn filter_events(mut self, mask: EventMask) -> Self {
+        self.filter.mask = Some(mask);
+        self
+    }
+
+    /// Discards events whose name is rejected by `predicate`
+    ///
+    /// Events without a name, which refer to the watched file or directory
+    /// itself, are never discarded. Like [`EventStream::filter_events`], this
+    /// happens before any allocation takes place. Calling this again replaces
+    /// the previous predicate.
+    pub fn filter_names<F>(mut self, predicate: F) -> Self
+    where
+        F: Fn(&OsStr) -> bool + Send + Sync + 'static,
+    {
+        self.filter.names = Some(Box::new(predicate));
+        self
+    }
+
+    /// Limits how many events are yielded in a row, without yielding to the
+    /// runtime
+    ///
+    /// If many events are queued, the stream can yield them for a long time
+    /// without ever returning [`Poll::Pending`], keeping other tasks from
+    /// running. With a limit, the stream returns `Poll::Pending` after `max`
+    /// events in a row, and wakes its task right away, so it is polled again
+    /// once others have had their turn.
+    ///
+    /// Pass `0` to remove the limit. There is no limit by default.
+    pub fn set_max_events_per_poll(&mut self, max: usize) {
+        self.max_events_per_poll = max;
+        self.events_since_yield = 0;
+    }
+
+    /// Keeps reading until no more events are available, once woken up
+    ///
+    /// By default, the stream reads once per wakeup. With a busy set of
+    /// watches, more events often arrive while the previous ones are being
+    /// read, each costing another wakeup. With draining enabled, the stream
+    /// keeps reading into the rest of its buffer, until a read would block or
+    /// the buffer is full. See [`Inotify::drain_events`].
+    ///
+    /// This is most effective with a large buffer. Disabled by default.
+    pub fn set_drain_reads(&mut self, drain: bool) {
+        self.drain_reads = drain;
+    }
+
+    /// Splits this stream into one stream per watch
+    ///
+    /// See [`WatchRouter`] for details.
+    pub fn split_by_watch(self) -> WatchRouter<Self> {
+        WatchRouter::new(self)
+    }
+
+    /// Converts this stream into one that yields [`TypedItem`]s
+    ///
+    /// Events whose mask can't be parsed are yielded as errors with
+    /// [`io::ErrorKind::InvalidData`], wrapping an [`EventMaskParseError`].
+    ///
+    /// [`EventMaskParseError`]: crate::EventMaskParseError
+    pub fn typed(self) -> TypedEventStream<T, D> {
+        TypedEventStream { inner: self }
+    }
+
+    /// Waits for the next event, borrowing it from the internal buffer
+    ///
+    /// Unlike the [`Stream`] implementation, this doesn't allocate an
+    /// [`EventOwned`] for every event. Returns `Ok(None)`, once the stream has
+    /// ended.
+    pub async fn next_borrowed(&mut self) -> io::Result<Option<Event<&OsStr>>> {
+        if !future::poll_fn(|cx| self.poll_fill_buffer(cx)).await? {
+            return Ok(None);
+        }
+
+        self.next_from_buffer().map(Some)
+    }
+
+    /// Waits for events and returns all events obtained from a single read
+    ///
+    /// If events from a previous read are still left in the buffer, those are
+    /// returned without reading again. Returns an empty `Vec`, once the stream
+    /// has ended.
+    pub async fn read_batch(&mut self) -> io::Result<Vec<EventOwned>> {
+        let mut events = Vec::new();
+
+        if future::poll_fn(|cx| self.poll_fill_buffer(cx)).await? {
+            while self.unused_bytes > 0 {
+                events.push(self.next_from_buffer()?.to_owned());
+                self.skip_filtered_events()?;
+            }
+        }
+
+        Ok(events)
+    }
+
+    /// Waits for events and returns all events from a single read, sharing a
+    /// buffer
+    ///
+    /// Like [`EventStream::read_batch`], but copies the events into a single
+    /// shared buffer, instead of allocating a name for each of them. See
+    /// [`BufferName`] for details.
+    pub async fn read_batch_buffered(&mut self) -> io::Result<Vec<EventBuffered>> {
+        let mut events = Vec::new();
+
+        if future::poll_fn(|cx| self.poll_fill_buffer(cx)).await? {
+            let base = self.buffer_pos;
+            let buffer: Arc<[u8]> =
+                Arc::from(&self.buffer.as_ref()[base..base + self.unused_bytes]);
+
+            while self.unused_bytes > 0 {
+                let start = self.buffer_pos - base + mem::size_of::<inotify_sys::inotify_event>();
+                let event = self.next_from_buffer()?;
+                events.push(
+                    event.map_name(|name| {
+                        BufferName::new(buffer.clone(), start, start + name.len())
+                    }),
+                );
+                self.skip_filtered_events()?;
+            }
+        }
+
+        Ok(events)
+    }
+
+    /// Waits for events and returns a summary per file of a single read
+    ///
+    /// Like [`EventStream::read_batch`], but merges all events for the same
+    /// file, as described for [`Coalescer`].
+    pub async fn read_coalesced(&mut self) -> io::Result<Vec<CoalescedEvent>> {
+        let mut coalescer = Coalescer::new();
+        for event in self.read_batch().await? {
+            coalescer.push(event);
+        }
+
+        Ok(coalescer.drain().collect())
+    }
+
+    /// Reads more events, if no accepted events are left in the buffer
+    ///
+    /// Events rejected by the filters are skipped. Returns `false`, if the
+    /// read signalled end-of-file.
+    fn poll_fill_buffer(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<bool>> {
+        if self.terminated {
+            return Poll::Ready(Ok(false));
+        }
+
+        self.close.register(cx.waker());
+        if self.close.closed.load(Ordering::Acquire) {
+            self.terminated = true;
+            return Poll::Ready(Ok(false));
+        }
+
+        loop {
+            if self.unused_bytes == 0 {
+                // Nothing usable in buffer. Need to reset and fill buffer.
+                self.buffer_pos = 0;
+                self.unused_bytes = loop {
+                    let fd = self.fd.as_raw_fd();
+                    let buffer = self.buffer.as_mut();
+                    let result = if self.drain_reads {
+                        self.driver.poll_read(cx, || read_drained(fd, buffer))
+                    } else {
+                        self.driver.poll_read(cx, || read(fd, buffer))
+                    };
+                    match ready!(result) {
+                        // The buffer is too small for the next event. If we
+                        // own it, we can make room and try again.
+                        Err(error)
+                            if error.raw_os_error() == Some(libc::EINVAL)
+                                && self.grow.is_some_and(|grow| grow(&mut self.buffer)) =>
+                        {
+                            continue;
+                        }
+                        // The read returned `0`, signalling end-of-file.
+                        Err(error) if error.kind() == io::ErrorKind::UnexpectedEof => break 0,
+                        result => break result?,
+                    }
+                };
+                self.timestamp = Timestamp::now();
+                self.handle = FdHandle::new(Arc::downgrade(&self.fd));
+                after_read(
+                    &self.fd,
+                    &self.handle,
+                    &self.buffer.as_ref()[..self.unused_bytes],
+                    self.timestamp,
+                );
+
+                if self.unused_bytes == 0 {
+                    // The read returned `0`, signalling end-of-file.
+                    self.terminated = true;
+                    return Poll::Ready(Ok(false));
+                }
+            }
+
+            self.skip_filtered_events()?;
+            if self.unused_bytes > 0 {
+                return Poll::Ready(Ok(true));
+            }
+        }
+    }
+
+    /// Skips the events at the front of the buffer that the filters reject
+    fn skip_filtered_events(&mut self) -> io::Result<()> {
+        if self.filter.is_empty() {
+            return Ok(());
+        }
+
+        while self.unused_bytes > 0 {
+            let result = Event::from_buffer(
+                FdHandle::default(),
+                &self.buffer.as_ref()[self.buffer_pos..self.buffer_pos + self.unused_bytes],
+                self.timestamp,
+            );
+            let bytes_consumed = match result {
+                Ok((_, event)) if self.filter.accepts(&event) => return Ok(()),
+                Ok((bytes_consumed, _)) => bytes_consumed,
+                Err(error) => {
+                    self.unused_bytes = 0;
+                    return Err(error.into());
+                }
+            };
+            self.buffer_pos += bytes_consumed;
+            self.unused_bytes -= bytes_consumed;
+        }
+
+        Ok(())
+    }
+
+    /// Takes the next event out of the buffer
+    ///
+    /// Must only be called, if [`EventStream::poll_fill_buffer`] has reported
+    /// an event in the buffer.
+    fn next_from_buffer(&mut self) -> io::Result<Event<&OsStr>> {
+        // We have bytes in the buffer. inotify doesn't put partial events in
+        // there, and we only take complete events out. That means we should
+        // have at least one event in there. If not, the rest of the buffer
+        // can't be trusted.
+        let result = Event::from_buffer(
+            self.handle.clone(),
+            &self.buffer.as_ref()[self.buffer_pos..self.buffer_pos + self.unused_bytes],
+            self.timestamp,
+        );
+        let (bytes_consumed, event) = match result {
+            Ok(result) => result,
+            Err(error) => {
+                self.unused_bytes = 0;
+                return Err(error.into());
+            }
+        };
+        self.buffer_pos += bytes_consumed;
+        self.unused_bytes -= bytes_consumed;
+
+        Ok(event)
     }
 }
 
-impl<T> Stream for EventStream<T>
+impl<T, D> Stream for EventStream<T, D>
 where
     T: AsMut<[u8]> + AsRef<[u8]>,
+    D: IoDriver,
 {
     type Item = io::Result<EventOwned>;
 
     fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        // Safety: safe because we never move out of `self_`.
-        let self_ = unsafe { self.get_unchecked_mut() };
+        let self_ = self.get_mut();
 
-        if self_.unused_bytes == 0 {
-            // Nothing usable in buffer. Need to reset and fill buffer.
-            self_.buffer_pos = 0;
-            self_.unused_bytes = ready!(read(&self_.fd, self_.buffer.as_mut(), cx))?;
+        if self_.max_events_per_poll > 0 && self_.events_since_yield >= self_.max_events_per_poll {
+            self_.events_since_yield = 0;
+            cx.waker().wake_by_ref();
+            return Poll::Pending;
         }
 
-        if self_.unused_bytes == 0 {
-            // The previous read returned `0` signalling end-of-file. Let's
-            // signal end-of-stream to the caller.
+        let has_event = match self_.poll_fill_buffer(cx) {
+            Poll::Ready(has_event) => has_event?,
+            Poll::Pending => {
+                self_.events_since_yield = 0;
+                return Poll::Pending;
+            }
+        };
+        if !has_event {
             return Poll::Ready(None);
         }
 
-        // We have bytes in the buffer. inotify doesn't put partial events in
-        // there, and we only take complete events out. That means we have at
-        // least one event in there and can call `from_buffer` to take it out.
-        let (bytes_consumed, event) = Event::from_buffer(
-            Arc::downgrade(self_.fd.get_ref()),
-            &self_.buffer.as_ref()[self_.buffer_pos..],
-        );
-        self_.buffer_pos += bytes_consumed;
-        self_.unused_bytes -= bytes_consumed;
+        self_.events_since_yield += 1;
+        Poll::Ready(Some(self_.next_from_buffer().map(|event| event.to_owned())))
+    }
+}
 
-        Poll::Ready(Some(Ok(event.to_owned())))
+impl<T, D> FusedStream for EventStream<T, D>
+where
+    T: AsMut<[u8]> + AsRef<[u8]>,
+    D: IoDriver,
+{
+    /// Returns `true`, once a read has signalled end-of-file
+    fn is_terminated(&self) -> bool {
+        self.terminated
     }
 }
 
-fn read(
-    fd: &AsyncFd<Arc<FdGuard>>,
-    buffer: &mut [u8],
-    cx: &mut Context,
-) -> Poll<io::Result<usize>> {
-    let mut guard = ready!(fd.poll_read_ready(cx))?;
-    let result = guard.try_io(|_| {
-        let read = read_into_buffer(fd.as_raw_fd(), buffer);
-        if read == -1 {
-            return Err(io::Error::last_os_error());
+// None of the fields are pinned. The buffer is only ever accessed through
+// `&mut`, so it doesn't matter whether it may be moved.
+impl<T, D> Unpin for EventStream<T, D> {}
+
+#[cfg(feature = "stream")]
+impl<T, D> EventStream<T, D>
+where
+    T: AsMut<[u8]> + AsRef<[u8]>,
+    D: IoDriver,
+{
+    /// Suppresses bursts of events for the same file
+    ///
+    /// Returns a stream that holds back events, until no further event for
+    /// the same watch descriptor and name has arrived for `quiet`, then
+    /// yields the last one. See [`Debouncer`] for details.
+    ///
+    /// Requires a tokio runtime with the time driver enabled.
+    ///
+    /// [`Debouncer`]: crate::Debouncer
+    pub fn debounce(self, quiet: Duration) -> Debounced<Self> {
+        Debounced::new(self, quiet)
+    }
+
+    /// Groups bursts of events into batches
+    ///
+    /// Returns a stream that collects events, until no further event has
+    /// arrived for `quiet`, or the first one has been held back for
+    /// `max_delay`, then yields all of them at once. See [`Batcher`] for
+    /// details.
+    ///
+    /// Requires a tokio runtime with the time driver enabled.
+    ///
+    /// [`Batcher`]: crate::Batcher
+    pub fn batch(self, quiet: Duration, max_delay: Duration) -> Batched<Self> {
+        Batched::new(self, quiet, max_delay)
+    }
+
+    /// Waits for the next event, but no longer than `timeout`
+    ///
+    /// Returns `Ok(None)`, if no event has arrived in time, or if the stream
+    /// has ended. Use [`FusedStream::is_terminated`] to tell the two apart.
+    ///
+    /// Requires a tokio runtime with the time driver enabled.
+    pub async fn next_timeout(&mut self, timeout: Duration) -> io::Result<Option<EventOwned>> {
+        let next = future::poll_fn(|cx| Pin::new(&mut *self).poll_next(cx));
+        match tokio::time::timeout(timeout, next).await {
+            Ok(event) => event.transpose(),
+            Err(_elapsed) => Ok(None),
         }
+    }
+}
 
-        Ok(read as usize)
-    });
+#[cfg(feature = "stream")]
+impl EventStream<Vec<u8>> {
+    /// Lets the stream grow its buffer, if it is too small for an event
+    pub(crate) fn with_growable_buffer(mut self) -> Self {
+        self.grow = Some(grow_buffer);
+        self
+    }
+}
 
-    match result {
-        Ok(result) => Poll::Ready(result),
-        Err(_would_block) => {
-            cx.waker().wake_by_ref();
-            Poll::Pending
+/// The size up to which an owned buffer is grown
+///
+/// Large enough for an event with a name of `PATH_MAX` bytes, which no file
+/// system exceeds.
+#[cfg(feature = "stream")]
+const MAX_GROWN_BUFFER_SIZE: usize =
+    std::mem::size_of::<inotify_sys::inotify_event>() + libc::PATH_MAX as usize;
+
+/// Doubles the size of `buffer`, unless it has reached the maximum size
+///
+/// Returns whether the buffer has been grown.
+#[cfg(feature = "stream")]
+fn grow_buffer(buffer: &mut Vec<u8>) -> bool {
+    if buffer.len() >= MAX_GROWN_BUFFER_SIZE {
+        return false;
+    }
+
+    let len = buffer
+        .len()
+        .saturating_mul(2)
+        .clamp(1, MAX_GROWN_BUFFER_SIZE);
+    buffer.resize(len, 0);
+    true
+}
+
+/// The filters configured for an [`EventStream`]
+#[derive(Default)]
+struct EventFilter {
+    mask: Option<EventMask>,
+    names: Option<NamePredicate>,
+}
+
+type NamePredicate = Box<dyn Fn(&OsStr) -> bool + Send + Sync>;
+
+impl EventFilter {
+    fn is_empty(&self) -> bool {
+        self.mask.is_none() && self.names.is_none()
+    }
+
+    fn accepts(&self, event: &Event<&OsStr>) -> bool {
+        if event.is_queue_overflow() {
+            return true;
+        }
+
+        let mask_accepted = self.mask.map_or(true, |mask| event.mask.intersects(mask));
+        let name_accepted = match (&self.names, event.name) {
+            (Some(predicate), Some(name)) => predicate(name),
+            _ => true,
+        };
+
+        mask_accepted && name_accepted
+    }
+}
+
+impl fmt::Debug for EventFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EventFilter")
+            .field("mask", &self.mask)
+            .field("names", &self.names.is_some())
+            .finish()
+    }
+}
+
+/// Handle to end an [`EventStream`]
+///
+/// Returned by [`EventStream::close_handle`].
+#[derive(Clone, Debug)]
+pub struct CloseHandle {
+    state: Arc<CloseState>,
+}
+
+impl CloseHandle {
+    /// Ends the stream
+    ///
+    /// The stream yields `None` the next time it is polled, even if events
+    /// are still left in its buffer. A task that is waiting for the stream is
+    /// woken up. The inotify file descriptor is closed once the stream is
+    /// dropped, as usual.
+    pub fn close(&self) {
+        self.state.closed.store(true, Ordering::Release);
+
+        let waker = self
+            .state
+            .waker
+            .lock()
+            .unwrap_or_else(|error| error.into_inner())
+            .take();
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+
+    /// Returns whether [`CloseHandle::close`] has been called
+    pub fn is_closed(&self) -> bool {
+        self.state.closed.load(Ordering::Acquire)
+    }
+}
+
+#[derive(Debug, Default)]
+struct CloseState {
+    closed: AtomicBool,
+    waker: Mutex<Option<Waker>>,
+}
+
+impl CloseState {
+    /// Remembers the waker of the task polling the stream
+    fn register(&self, waker: &Waker) {
+        let mut current = self.waker.lock().unwrap_or_else(|error| error.into_inner());
+        match &*current {
+            Some(current) if current.will_wake(waker) => {}
+            _ => *current = Some(waker.clone()),
         }
     }
 }
+
+/// Stream of inotify events, with parsed event masks
+///
+/// Returned by [`EventStream::typed`].
+#[cfg(feature = "stream")]
+#[derive(Debug)]
+pub struct TypedEventStream<T, D = TokioDriver> {
+    inner: EventStream<T, D>,
+}
+
+/// Stream of inotify events, with parsed event masks
+///
+/// Returned by [`EventStream::typed`].
+#[cfg(not(feature = "stream"))]
+#[derive(Debug)]
+pub struct TypedEventStream<T, D> {
+    inner: EventStream<T, D>,
+}
+
+impl<T, D> TypedEventStream<T, D> {
+    /// Returns the underlying [`EventStream`]
+    pub fn into_inner(self) -> EventStream<T, D> {
+        self.inner
+    }
+}
+
+impl<T, D> Stream for TypedEventStream<T, D>
+where
+    T: AsMut<[u8]> + AsRef<[u8]>,
+    D: IoDriver,
+{
+    type Item = io::Result<TypedItem<OsString>>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let event = ready!(Pin::new(&mut self.inner).poll_next(cx));
+        Poll::Ready(event.map(|event| Ok(event?.into_typed_item()?)))
+    }
+}
+
+impl<T, D> FusedStream for TypedEventStream<T, D>
+where
+    T: AsMut<[u8]> + AsRef<[u8]>,
+    D: IoDriver,
+{
+    fn is_terminated(&self) -> bool {
+        self.inner.is_terminated()
+    }
+}
+
+#[cfg(all(test, feature = "stream"))]
+mod tests {
+    use std::marker::PhantomPinned;
+
+    use super::{grow_buffer, EventStream, MAX_GROWN_BUFFER_SIZE};
+
+    #[test]
+    fn grow_buffer_should_stop_at_the_maximum_size() {
+        let mut buffer = vec![0; 16];
+        assert!(grow_buffer(&mut buffer));
+        assert_eq!(buffer.len(), 32);
+
+        while grow_buffer(&mut buffer) {}
+        assert_eq!(buffer.len(), MAX_GROWN_BUFFER_SIZE);
+    }
+
+    #[test]
+    fn event_stream_should_be_unpin_regardless_of_the_buffer() {
+        fn assert_unpin<T: Unpin>() {}
+        assert_unpin::<EventStream<PhantomPinned>>();
+    }
+}
@@ -0,0 +1,127 @@
+use std::{
+    env, fs, io,
+    os::unix::io::{FromRawFd, OwnedFd, RawFd},
+    sync::Mutex,
+};
+
+/// The first file descriptor passed by systemd
+///
+/// See [sd_listen_fds(3)](https://www.freedesktop.org/software/systemd/man/sd_listen_fds.html).
+const SD_LISTEN_FDS_START: RawFd = 3;
+
+/// File descriptors that have already been taken
+///
+/// Makes sure no file descriptor is wrapped twice, which would lead to it
+/// being closed twice.
+static TAKEN: Mutex<Vec<RawFd>> = Mutex::new(Vec::new());
+
+/// Takes ownership of an inotify file descriptor passed by systemd
+///
+/// If `name` is `None`, the first passed inotify file descriptor that hasn't
+/// been taken yet is returned. Otherwise, the file descriptor with that name
+/// (as given by `FileDescriptorName=` or `FDNAME=`) is returned.
+pub fn take_inotify_fd(name: Option<&str>) -> io::Result<OwnedFd> {
+    let fds = parse_listen_fds(
+        env::var("LISTEN_PID").ok().as_deref(),
+        env::var("LISTEN_FDS").ok().as_deref(),
+        env::var("LISTEN_FDNAMES").ok().as_deref(),
+        std::process::id(),
+    );
+
+    let mut taken = TAKEN.lock().unwrap_or_else(|error| error.into_inner());
+
+    for (fd, fd_name) in fds {
+        if taken.contains(&fd) {
+            continue;
+        }
+
+        if let Some(name) = name {
+            if fd_name.as_deref() != Some(name) {
+                continue;
+            }
+            if !is_inotify_fd(fd)? {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("File descriptor `{}` is not an inotify instance", name),
+                ));
+            }
+        } else if !is_inotify_fd(fd)? {
+            continue;
+        }
+
+        taken.push(fd);
+
+        // We've verified that the file descriptor is open and refers to an
+        // inotify instance. systemd passed it to us for our exclusive use,
+        // and we've recorded above that it has been taken.
+        return Ok(unsafe { OwnedFd::from_raw_fd(fd) });
+    }
+
+    Err(io::Error::new(
+        io::ErrorKind::NotFound,
+        "No inotify file descriptor was passed by systemd",
+    ))
+}
+
+/// Parses the environment variables set by systemd
+///
+/// Returns the passed file descriptors and their names. Returns nothing, if
+/// the variables are missing or invalid, or are meant for another process.
+fn parse_listen_fds(
+    listen_pid: Option<&str>,
+    listen_fds: Option<&str>,
+    listen_fdnames: Option<&str>,
+    pid: u32,
+) -> Vec<(RawFd, Option<String>)> {
+    let listen_pid = listen_pid.and_then(|pid| pid.parse::<u32>().ok());
+    if listen_pid != Some(pid) {
+        return Vec::new();
+    }
+
+    let listen_fds = match listen_fds.and_then(|fds| fds.parse::<RawFd>().ok()) {
+        Some(fds) if fds > 0 => fds,
+        _ => return Vec::new(),
+    };
+
+    let mut names = listen_fdnames
+        .map(|names| names.split(':').map(String::from).collect::<Vec<_>>())
+        .unwrap_or_default()
+        .into_iter();
+
+    (SD_LISTEN_FDS_START..SD_LISTEN_FDS_START.saturating_add(listen_fds))
+        .map(|fd| (fd, names.next()))
+        .collect()
+}
+
+/// Checks whether `fd` refers to an inotify instance
+fn is_inotify_fd(fd: RawFd) -> io::Result<bool> {
+    match fs::read_link(format!("/proc/self/fd/{}", fd)) {
+        Ok(target) => Ok(target.as_os_str() == "anon_inode:inotify"),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(false),
+        Err(error) => Err(error),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_listen_fds;
+
+    #[test]
+    fn parse_listen_fds_should_ignore_variables_meant_for_other_processes() {
+        assert!(parse_listen_fds(Some("1"), Some("2"), None, 2).is_empty());
+        assert!(parse_listen_fds(None, Some("2"), None, 2).is_empty());
+    }
+
+    #[test]
+    fn parse_listen_fds_should_pair_file_descriptors_with_names() {
+        let fds = parse_listen_fds(Some("2"), Some("3"), Some("a:b"), 2);
+        assert_eq!(
+            fds,
+            vec![
+                (3, Some("a".to_string())),
+                (4, Some("b".to_string())),
+                (5, None),
+            ]
+        );
+    }
+}
@@ -0,0 +1,197 @@
+use std::{
+    collections::HashMap,
+    ffi::OsStr,
+    io,
+    os::{
+        raw::c_int,
+        unix::io::{AsRawFd, FromRawFd, OwnedFd},
+    },
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use crate::events::{encode, EventMask};
+use crate::fd_guard::{FdGuard, FdHandle};
+use crate::stream::EventStream;
+use crate::util::get_buffer_size_for_events;
+use crate::watches::WatchDescriptor;
+
+impl EventStream<Vec<u8>> {
+    /// Creates a stream that returns the events pushed into an [`EventInjector`]
+    ///
+    /// This is meant for testing code that consumes an `EventStream`, without
+    /// touching the file system and waiting for the kernel. The stream ends,
+    /// once the injector has been dropped, and all events have been read.
+    ///
+    /// The stream isn't backed by an inotify instance, so adding watches
+    /// using [`EventStream::watches`] fails. Use [`EventInjector::add_watch`]
+    /// instead.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error from creating the channel between stream and
+    /// injector, or from registering the stream with the runtime.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use futures_util::StreamExt;
+    /// use inotify::{EventMask, EventStream};
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() -> std::io::Result<()> {
+    /// let (mut stream, injector) = EventStream::with_injector()?;
+    ///
+    /// let wd = injector.add_watch("/tmp");
+    /// injector.push(&wd, EventMask::CREATE, 0, Some("file".as_ref()))?;
+    /// injector.push_overflow()?;
+    /// drop(injector);
+    ///
+    /// let event = stream.next().await.unwrap()?;
+    /// assert_eq!(event.wd, wd);
+    /// assert!(stream.next().await.unwrap()?.is_queue_overflow());
+    /// assert!(stream.next().await.is_none());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_injector() -> io::Result<(Self, EventInjector)> {
+        let mut fds = [0; 2];
+        let result = unsafe {
+            libc::socketpair(
+                libc::AF_UNIX,
+                libc::SOCK_SEQPACKET | libc::SOCK_CLOEXEC,
+                0,
+                fds.as_mut_ptr(),
+            )
+        };
+        if result == -1 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let fd = Arc::new(unsafe { FdGuard::from_raw_fd(fds[0]) });
+        let socket = unsafe { OwnedFd::from_raw_fd(fds[1]) };
+
+        // The stream expects a non-blocking file descriptor, like the one of
+        // an inotify instance.
+        let flags = unsafe { libc::fcntl(fd.fd, libc::F_GETFL) };
+        if flags == -1
+            || unsafe { libc::fcntl(fd.fd, libc::F_SETFL, flags | libc::O_NONBLOCK) } == -1
+        {
+            return Err(io::Error::last_os_error());
+        }
+
+        let injector = EventInjector {
+            socket,
+            fd: FdHandle::new(Arc::downgrade(&fd)),
+            watches: Mutex::new(HashMap::new()),
+        };
+        // Each event is sent as a message of its own, and read on its own,
+        // so the buffer needs to hold only one.
+        let stream = EventStream::new(fd, vec![0; get_buffer_size_for_events(1)])?;
+
+        Ok((stream, injector))
+    }
+}
+
+/// Pushes events into a stream created by [`EventStream::with_injector`]
+///
+/// Events are returned by the stream in the order in which they have been
+/// pushed. Dropping the injector ends the stream.
+#[derive(Debug)]
+pub struct EventInjector {
+    socket: OwnedFd,
+    fd: FdHandle,
+    watches: Mutex<HashMap<PathBuf, c_int>>,
+}
+
+impl EventInjector {
+    /// Returns a watch descriptor for `path`
+    ///
+    /// Nothing is watched. Like [`Watches::add`], this returns the same watch
+    /// descriptor, if it is called for the same path again.
+    ///
+    /// [`Watches::add`]: crate::Watches::add
+    pub fn add_watch<P>(&self, path: P) -> WatchDescriptor
+    where
+        P: AsRef<Path>,
+    {
+        let mut watches = self
+            .watches
+            .lock()
+            .unwrap_or_else(|error| error.into_inner());
+
+        let next_id = watches.len() as c_int + 1;
+        let id = *watches
+            .entry(path.as_ref().to_path_buf())
+            .or_insert(next_id);
+
+        WatchDescriptor {
+            id,
+            fd: self.fd.clone(),
+        }
+    }
+
+    /// Pushes an event into the stream
+    ///
+    /// This can be any event, including [`EventMask::IGNORED`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error with [`ErrorKind::InvalidInput`], if `name` is longer
+    /// than a file name can be. Otherwise, returns the error from sending the
+    /// event to the stream, which fails with [`ErrorKind::BrokenPipe`], if
+    /// the stream has been dropped.
+    ///
+    /// [`ErrorKind::InvalidInput`]: std::io::ErrorKind::InvalidInput
+    /// [`ErrorKind::BrokenPipe`]: std::io::ErrorKind::BrokenPipe
+    pub fn push(
+        &self,
+        wd: &WatchDescriptor,
+        mask: EventMask,
+        cookie: u32,
+        name: Option<&OsStr>,
+    ) -> io::Result<()> {
+        if name.map_or(0, OsStr::len) > libc::NAME_MAX as usize {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Name is longer than NAME_MAX",
+            ));
+        }
+
+        self.send(wd.id, mask, cookie, name)
+    }
+
+    /// Pushes a queue overflow event into the stream
+    ///
+    /// # Errors
+    ///
+    /// See [`EventInjector::push`].
+    pub fn push_overflow(&self) -> io::Result<()> {
+        self.send(-1, EventMask::Q_OVERFLOW, 0, None)
+    }
+
+    fn send(
+        &self,
+        wd: c_int,
+        mask: EventMask,
+        cookie: u32,
+        name: Option<&OsStr>,
+    ) -> io::Result<()> {
+        let mut bytes = Vec::new();
+        encode(wd, mask, cookie, name, &mut bytes);
+
+        let result = unsafe {
+            libc::send(
+                self.socket.as_raw_fd(),
+                bytes.as_ptr() as *const _,
+                bytes.len(),
+                libc::MSG_NOSIGNAL,
+            )
+        };
+        if result == -1 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,207 @@
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    ffi::OsStr,
+    fs::{self, File},
+    hash::Hasher,
+    io::{self, Read},
+    os::{raw::c_int, unix::fs::MetadataExt},
+    path::{Path, PathBuf},
+};
+
+use crate::events::{Event, EventMask};
+use crate::watches::WatchDescriptor;
+
+/// Detects writes that didn't change a file
+///
+/// Formatters and code generators often rewrite files with identical
+/// content. Anything that reacts to [`CLOSE_WRITE`] events, like a build
+/// system, does unnecessary work then. `UnchangedFilter` remembers what each
+/// file looked like after it was last written, and tells you if a
+/// `CLOSE_WRITE` event didn't change anything.
+///
+/// Files are compared as configured by [`Compare`]. Since the filter only
+/// learns about a file from its events, the first write to each file is
+/// never considered unchanged, unless the file has been passed to
+/// [`UnchangedFilter::remember`] before.
+///
+/// # Examples
+///
+/// ```no_run
+/// use inotify::{Compare, Inotify, UnchangedFilter, WatchMask};
+///
+/// let inotify = Inotify::init()
+///     .expect("Failed to initialize an inotify instance");
+/// let wd = inotify.watches().add("src", WatchMask::CLOSE_WRITE)
+///     .expect("Failed to add watch");
+///
+/// let mut filter = UnchangedFilter::new(Compare::Content);
+/// filter.add_watch(&wd, "src");
+///
+/// let mut buffer = [0; 4096];
+/// loop {
+///     let events = inotify.read_events_blocking(&mut buffer)
+///         .expect("Error while reading events");
+///     for event in events {
+///         if filter.is_unchanged(&event) {
+///             continue;
+///         }
+///         // Rebuild
+///     }
+/// }
+/// ```
+///
+/// [`CLOSE_WRITE`]: EventMask::CLOSE_WRITE
+#[derive(Debug)]
+pub struct UnchangedFilter {
+    compare: Compare,
+    watches: HashMap<c_int, PathBuf>,
+    files: HashMap<PathBuf, Fingerprint>,
+}
+
+impl UnchangedFilter {
+    /// Creates an `UnchangedFilter`
+    pub fn new(compare: Compare) -> Self {
+        UnchangedFilter {
+            compare,
+            watches: HashMap::new(),
+            files: HashMap::new(),
+        }
+    }
+
+    /// Tells the filter which path a watch has been added for
+    ///
+    /// Events for watches that the filter doesn't know about are never
+    /// considered unchanged.
+    pub fn add_watch<P>(&mut self, wd: &WatchDescriptor, path: P)
+    where
+        P: AsRef<Path>,
+    {
+        self.watches.insert(wd.id, path.as_ref().to_path_buf());
+    }
+
+    /// Remembers what the file at `path` looks like now
+    ///
+    /// The next write to it is compared against this.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error from reading the file.
+    pub fn remember<P>(&mut self, path: P) -> io::Result<()>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let fingerprint = Fingerprint::of(path, self.compare)?;
+        self.files.insert(path.to_path_buf(), fingerprint);
+        Ok(())
+    }
+
+    /// Indicates whether an event is for a write that didn't change the file
+    ///
+    /// Returns `true` only for [`CLOSE_WRITE`] events. All other events are
+    /// used to forget about files that have been removed, and to forget about
+    /// watches that have been removed.
+    ///
+    /// If the file can't be read, it is considered changed.
+    ///
+    /// [`CLOSE_WRITE`]: EventMask::CLOSE_WRITE
+    pub fn is_unchanged<S>(&mut self, event: &Event<S>) -> bool
+    where
+        S: AsRef<OsStr>,
+    {
+        if event.mask.contains(EventMask::IGNORED) {
+            if let Some(dir) = self.watches.remove(&event.wd.id) {
+                self.files.retain(|path, _| !path.starts_with(&dir));
+            }
+            return false;
+        }
+
+        let path = match self.watches.get(&event.wd.id) {
+            Some(path) => match &event.name {
+                Some(name) => path.join(name.as_ref()),
+                None => path.clone(),
+            },
+            None => return false,
+        };
+
+        let removed = EventMask::DELETE
+            | EventMask::DELETE_SELF
+            | EventMask::MOVED_FROM
+            | EventMask::MOVE_SELF
+            | EventMask::MOVED_TO;
+        if event.mask.intersects(removed) {
+            // Whatever has been moved to this path is a different file.
+            self.files.remove(&path);
+            return false;
+        }
+
+        if !event.mask.contains(EventMask::CLOSE_WRITE) || event.mask.contains(EventMask::ISDIR) {
+            return false;
+        }
+
+        match Fingerprint::of(&path, self.compare) {
+            Ok(fingerprint) => self.files.insert(path, fingerprint) == Some(fingerprint),
+            Err(_) => {
+                self.files.remove(&path);
+                false
+            }
+        }
+    }
+}
+
+/// How [`UnchangedFilter`] compares files
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Compare {
+    /// Compare a hash of the content of the file
+    ///
+    /// This requires reading the whole file after each write. The hash is not
+    /// cryptographic, but changes that go unnoticed are very unlikely.
+    Content,
+
+    /// Compare the size and modification time of the file
+    ///
+    /// This is cheap, but only detects files that have been opened for
+    /// writing without writing anything. Rewriting a file with the same
+    /// content updates the modification time.
+    Metadata,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Fingerprint {
+    Content { len: u64, hash: u64 },
+    Metadata { len: u64, mtime: (i64, i64) },
+}
+
+impl Fingerprint {
+    fn of(path: &Path, compare: Compare) -> io::Result<Self> {
+        match compare {
+            Compare::Content => {
+                let mut file = File::open(path)?;
+                let mut hasher = DefaultHasher::new();
+                let mut buffer = [0; 8192];
+                let mut len = 0;
+
+                loop {
+                    let num_bytes = file.read(&mut buffer)?;
+                    if num_bytes == 0 {
+                        break;
+                    }
+                    hasher.write(&buffer[..num_bytes]);
+                    len += num_bytes as u64;
+                }
+
+                Ok(Fingerprint::Content {
+                    len,
+                    hash: hasher.finish(),
+                })
+            }
+            Compare::Metadata => {
+                let metadata = fs::metadata(path)?;
+                Ok(Fingerprint::Metadata {
+                    len: metadata.len(),
+                    mtime: (metadata.mtime(), metadata.mtime_nsec()),
+                })
+            }
+        }
+    }
+}
@@ -0,0 +1,267 @@
+use std::{
+    collections::HashSet,
+    fmt, fs, io,
+    os::unix::{fs::MetadataExt, io::RawFd},
+    sync::{Arc, Mutex},
+};
+
+use libc::c_int;
+
+use crate::util::max_user_watches;
+
+/// How many watches are in use, compared to the limit
+///
+/// Returned by [`Watches::usage`] and [`user_watch_usage`], and passed to the
+/// callback set with [`Inotify::set_usage_hook`].
+///
+/// [`Watches::usage`]: crate::Watches::usage
+/// [`Inotify::set_usage_hook`]: crate::Inotify::set_usage_hook
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct WatchUsage {
+    /// The number of watches
+    pub watches: usize,
+
+    /// The maximum number of watches per user
+    ///
+    /// See [`max_user_watches`](crate::max_user_watches).
+    pub limit: u64,
+}
+
+impl WatchUsage {
+    /// Returns the share of the limit that is in use, in percent
+    pub fn percent(&self) -> f64 {
+        if self.limit == 0 {
+            return 100.0;
+        }
+
+        self.watches as f64 * 100.0 / self.limit as f64
+    }
+}
+
+/// Returns the number of watches of all inotify instances of the current user
+///
+/// This scans the file descriptors of all processes of the current user in
+/// `/proc`, which is slow if there are many of them. Processes whose file
+/// descriptors can't be read are skipped, so the result can be lower than the
+/// number of watches the kernel accounts for.
+///
+/// # Errors
+///
+/// Returns the error from reading `/proc`, or from reading the limit.
+pub fn user_watch_usage() -> io::Result<WatchUsage> {
+    let uid = unsafe { libc::geteuid() };
+
+    let mut watches = 0;
+    for entry in fs::read_dir("/proc")? {
+        let entry = entry?;
+        let is_process = entry
+            .file_name()
+            .to_str()
+            .is_some_and(|name| name.bytes().all(|b| b.is_ascii_digit()));
+        if !is_process {
+            continue;
+        }
+
+        // Processes can exit at any time, so ignore all errors from here on.
+        match entry.metadata() {
+            Ok(metadata) if metadata.uid() == uid => {}
+            _ => continue,
+        }
+        let fds = match fs::read_dir(entry.path().join("fd")) {
+            Ok(fds) => fds,
+            Err(_) => continue,
+        };
+        for fd in fds.flatten() {
+            let is_inotify = fs::read_link(fd.path())
+                .is_ok_and(|target| target.as_os_str() == "anon_inode:inotify");
+            if !is_inotify {
+                continue;
+            }
+
+            let fdinfo = entry.path().join("fdinfo").join(fd.file_name());
+            if let Ok(fdinfo) = fs::read_to_string(fdinfo) {
+                watches += watch_ids(&fdinfo).count();
+            }
+        }
+    }
+
+    Ok(WatchUsage {
+        watches,
+        limit: max_user_watches()?,
+    })
+}
+
+/// Returns the number of watches of the inotify instance `fd`, and their
+/// highest ID
+pub(crate) fn instance_watches(fd: RawFd) -> io::Result<(usize, c_int)> {
+    let fdinfo = fs::read_to_string(format!("/proc/self/fdinfo/{}", fd))?;
+
+    Ok(watch_ids(&fdinfo).fold((0, 0), |(count, max), id| (count + 1, max.max(id))))
+}
+
+/// Returns the IDs of the watches of the inotify instance `fd`
+pub(crate) fn instance_watch_ids(fd: RawFd) -> io::Result<HashSet<c_int>> {
+    let fdinfo = fs::read_to_string(format!("/proc/self/fdinfo/{}", fd))?;
+
+    Ok(watch_ids(&fdinfo).collect())
+}
+
+/// Returns the IDs of the watches listed in the fdinfo of an inotify instance
+fn watch_ids(fdinfo: &str) -> impl Iterator<Item = c_int> + '_ {
+    fdinfo.lines().filter_map(|line| {
+        let wd = line.strip_prefix("inotify wd:")?;
+        let wd = wd.split_whitespace().next()?;
+        c_int::from_str_radix(wd, 16).ok()
+    })
+}
+
+/// Callback that is called when watch usage crosses a threshold
+///
+/// Lives in [`FdGuard`], so it sees watches that are added through any
+/// [`Watches`] of the same inotify instance.
+///
+/// To avoid reading `/proc` whenever a watch is added, the number of watches
+/// is tracked here. Watches that the kernel removes on its own, for example
+/// because the file has been deleted, can't be tracked, so the count is
+/// re-read from `/proc` before the callback is called.
+///
+/// [`FdGuard`]: crate::fd_guard::FdGuard
+/// [`Watches`]: crate::Watches
+#[derive(Default)]
+pub(crate) struct UsageHook(Mutex<Option<UsageState>>);
+
+struct UsageState {
+    hook: Arc<dyn Fn(WatchUsage) + Send + Sync>,
+    threshold: usize,
+    limit: u64,
+    watches: usize,
+    max_id: c_int,
+    fired: bool,
+}
+
+impl UsageHook {
+    pub(crate) fn set(
+        &self,
+        fd: RawFd,
+        percent: u8,
+        hook: Arc<dyn Fn(WatchUsage) + Send + Sync>,
+    ) -> io::Result<()> {
+        let limit = max_user_watches()?;
+        let (watches, max_id) = instance_watches(fd)?;
+
+        *self.lock() = Some(UsageState {
+            hook,
+            threshold: (limit.saturating_mul(u64::from(percent)) / 100) as usize,
+            limit,
+            watches,
+            max_id,
+            fired: false,
+        });
+
+        Ok(())
+    }
+
+    pub(crate) fn clear(&self) {
+        *self.lock() = None;
+    }
+
+    /// Called after [`Watches::add`] returned the watch `id`
+    ///
+    /// [`Watches::add`]: crate::Watches::add
+    pub(crate) fn watch_added(&self, fd: RawFd, id: c_int) {
+        let fire = {
+            let mut state = self.lock();
+            let state = match state.as_mut() {
+                Some(state) => state,
+                None => return,
+            };
+
+            // The kernel hands out increasing IDs, so a lower one means an
+            // existing watch has been updated.
+            if id <= state.max_id {
+                return;
+            }
+            state.max_id = id;
+            state.watches += 1;
+
+            if state.fired || state.watches < state.threshold {
+                return;
+            }
+            if let Ok((watches, _)) = instance_watches(fd) {
+                state.watches = watches;
+            }
+            if state.watches < state.threshold {
+                return;
+            }
+
+            state.fired = true;
+            (
+                state.hook.clone(),
+                WatchUsage {
+                    watches: state.watches,
+                    limit: state.limit,
+                },
+            )
+        };
+
+        // Don't hold the lock while calling the hook, so the hook can add
+        // watches itself.
+        let (hook, usage) = fire;
+        hook(usage);
+    }
+
+    /// Called after [`Watches::remove`] removed a watch
+    ///
+    /// [`Watches::remove`]: crate::Watches::remove
+    pub(crate) fn watch_removed(&self) {
+        if let Some(state) = self.lock().as_mut() {
+            state.watches = state.watches.saturating_sub(1);
+
+            // Call the hook again, once usage crosses the threshold again.
+            if state.watches < state.threshold {
+                state.fired = false;
+            }
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Option<UsageState>> {
+        self.0.lock().unwrap_or_else(|error| error.into_inner())
+    }
+}
+
+impl fmt::Debug for UsageHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = self.lock();
+
+        f.debug_struct("UsageHook")
+            .field("is_set", &state.is_some())
+            .field("watches", &state.as_ref().map(|state| state.watches))
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{watch_ids, WatchUsage};
+
+    #[test]
+    fn watch_ids_should_parse_fdinfo() {
+        let fdinfo = "pos:\t0\n\
+            flags:\t02004000\n\
+            mnt_id:\t15\n\
+            ino:\t1057\n\
+            inotify wd:1 ino:a0 sdev:fd00001 mask:2 ignored_mask:0 fhandle-bytes:8\n\
+            inotify wd:1f ino:a1 sdev:fd00001 mask:2 ignored_mask:0 fhandle-bytes:8\n";
+
+        assert_eq!(watch_ids(fdinfo).collect::<Vec<_>>(), vec![1, 0x1f]);
+    }
+
+    #[test]
+    fn percent_should_compare_watches_to_limit() {
+        let usage = WatchUsage {
+            watches: 25,
+            limit: 100,
+        };
+        assert_eq!(usage.percent(), 25.0);
+    }
+}
@@ -0,0 +1,99 @@
+use std::{convert::TryFrom, ffi::OsStr, io::Read, path::Path};
+
+use camino::{FromPathError, Utf8Path};
+
+use crate::events::Event;
+use crate::record::Replayer;
+use crate::resolve::PathResolver;
+use crate::watches::WatchDescriptor;
+
+impl<S> Event<S>
+where
+    S: AsRef<OsStr>,
+{
+    /// Returns the name of the event as a UTF-8 path
+    ///
+    /// Returns `Ok(None)`, if the event doesn't have a name.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error, if the name isn't valid UTF-8.
+    pub fn utf8_name(&self) -> Result<Option<&Utf8Path>, FromPathError> {
+        self.name
+            .as_ref()
+            .map(|name| <&Utf8Path>::try_from(Path::new(name)))
+            .transpose()
+    }
+}
+
+impl PathResolver {
+    /// Returns the path a watch has been added for as a UTF-8 path
+    ///
+    /// See [`PathResolver::path`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error, if the path isn't valid UTF-8.
+    pub fn utf8_path(&self, wd: &WatchDescriptor) -> Result<Option<&Utf8Path>, FromPathError> {
+        self.path(wd).map(<&Utf8Path>::try_from).transpose()
+    }
+}
+
+impl<R> Replayer<R>
+where
+    R: Read,
+{
+    /// Returns the path a watch has been recorded for as a UTF-8 path
+    ///
+    /// See [`Replayer::path`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error, if the path isn't valid UTF-8.
+    pub fn utf8_path(&self, wd: &WatchDescriptor) -> Result<Option<&Utf8Path>, FromPathError> {
+        self.path(wd).map(<&Utf8Path>::try_from).transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{ffi::OsStr, os::unix::ffi::OsStrExt, time::Duration};
+
+    use camino::Utf8Path;
+
+    use crate::events::test_event;
+    use crate::fd_guard::FdHandle;
+    use crate::{EventMask, PathResolver, WatchDescriptor};
+
+    #[test]
+    fn names_should_be_returned_as_utf8_paths() {
+        let event = test_event(EventMask::CREATE, 0, Some("app.log"));
+        assert_eq!(event.utf8_name().unwrap(), Some(Utf8Path::new("app.log")));
+
+        let event = test_event(EventMask::Q_OVERFLOW, 0, None);
+        assert_eq!(event.utf8_name().unwrap(), None);
+
+        let mut event = test_event(EventMask::CREATE, 0, None);
+        event.name = Some(OsStr::from_bytes(b"\xff.log").to_os_string());
+        assert!(event.utf8_name().is_err());
+    }
+
+    #[test]
+    fn tracked_paths_should_be_returned_as_utf8_paths() {
+        let mut resolver = PathResolver::new(Duration::from_secs(1));
+
+        let wd = |id| WatchDescriptor {
+            id,
+            fd: FdHandle::default(),
+        };
+        resolver.add_watch(&wd(1), "/project");
+        resolver.add_watch(&wd(2), OsStr::from_bytes(b"/\xff"));
+
+        assert_eq!(
+            resolver.utf8_path(&wd(1)).unwrap(),
+            Some(Utf8Path::new("/project"))
+        );
+        assert!(resolver.utf8_path(&wd(2)).is_err());
+        assert_eq!(resolver.utf8_path(&wd(3)).unwrap(), None);
+    }
+}
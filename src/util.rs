@@ -1,9 +1,15 @@
-use std::{io, mem, os::unix::io::RawFd, path::Path};
+use std::{
+    ffi::CString,
+    fs, io, mem,
+    os::unix::{ffi::OsStrExt, io::RawFd},
+    path::Path,
+    time::Duration,
+};
 
 use inotify_sys as ffi;
-use libc::{c_void, size_t};
+use libc::{c_int, c_short, c_void, pollfd, size_t, POLLIN, POLLPRI};
 
-const INOTIFY_EVENT_SIZE: usize = mem::size_of::<ffi::inotify_event>() + 257;
+pub(crate) const INOTIFY_EVENT_SIZE: usize = mem::size_of::<ffi::inotify_event>() + 257;
 
 pub fn read_into_buffer(fd: RawFd, buffer: &mut [u8]) -> isize {
     unsafe {
@@ -15,6 +21,173 @@ pub fn read_into_buffer(fd: RawFd, buffer: &mut [u8]) -> isize {
     }
 }
 
+/// Reads events from `fd` into `buffer` and returns the number of bytes read
+///
+/// Translates the return value of [`read`] into an [`io::Result`]. A return
+/// value of `0`, signaling end-of-file, is reported as an error with
+/// [`io::ErrorKind::UnexpectedEof`].
+///
+/// [`read`]: libc::read
+pub fn read(fd: RawFd, buffer: &mut [u8]) -> io::Result<usize> {
+    let num_bytes = read_into_buffer(fd, buffer);
+
+    match num_bytes {
+        0 => Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "`read` return `0`, signaling end-of-file",
+        )),
+        -1 => {
+            let error = io::Error::last_os_error();
+
+            #[cfg(feature = "log")]
+            if error.raw_os_error() == Some(libc::EINVAL) {
+                log::debug!(
+                    "Buffer of {} bytes is too small for the next event",
+                    buffer.len()
+                );
+            }
+
+            Err(error)
+        }
+        _ if num_bytes < 0 => {
+            panic!(
+                "{} {} {} {} {} {}",
+                "Unexpected return value from `read`. Received a negative",
+                "value that was not `-1`. According to the `read` man page",
+                "this shouldn't happen, as either `-1` is returned on",
+                "error, `0` on end-of-file, or a positive value for the",
+                "number of bytes read. Returned value:",
+                num_bytes,
+            );
+        }
+        _ => {
+            // The value returned by `read` should be `isize`. Let's quickly
+            // verify this with the following assignment, so we can be sure
+            // our cast below is valid.
+            let num_bytes: isize = num_bytes;
+
+            // The type returned by `read` is `isize`, and we've ruled out
+            // all negative values with the match arms above. This means we
+            // can safely cast to `usize`.
+            debug_assert!(num_bytes > 0);
+            Ok(num_bytes as usize)
+        }
+    }
+}
+
+/// Like [`read`], but keeps reading until no more events are available
+///
+/// Each read appends to the events already in `buffer`. Stops once a read
+/// returns [`io::ErrorKind::WouldBlock`], or once the rest of `buffer` is too
+/// small for the next event. Errors are only returned, if the first read
+/// fails, so events that have been read are never lost.
+pub fn read_drained(fd: RawFd, buffer: &mut [u8]) -> io::Result<usize> {
+    let mut num_bytes = read(fd, buffer)?;
+
+    while num_bytes < buffer.len() {
+        match read(fd, &mut buffer[num_bytes..]) {
+            Ok(n) => num_bytes += n,
+            Err(_) => break,
+        }
+    }
+
+    Ok(num_bytes)
+}
+
+/// Like [`read`], but waits until events are available
+///
+/// If another thread takes the events first, this goes back to waiting,
+/// instead of returning [`io::ErrorKind::WouldBlock`].
+pub fn read_blocking(fd: RawFd, buffer: &mut [u8]) -> io::Result<usize> {
+    loop {
+        wait_until_readable(fd)?;
+
+        match read(fd, buffer) {
+            Err(error) if error.kind() == io::ErrorKind::WouldBlock => continue,
+            result => return result,
+        }
+    }
+}
+
+/// Like [`read_drained`], but waits until events are available
+pub fn read_drained_blocking(fd: RawFd, buffer: &mut [u8]) -> io::Result<usize> {
+    loop {
+        wait_until_readable(fd)?;
+
+        match read_drained(fd, buffer) {
+            Err(error) if error.kind() == io::ErrorKind::WouldBlock => continue,
+            result => return result,
+        }
+    }
+}
+
+/// Blocks until `fd` is readable
+///
+/// This uses [`poll`] instead of switching the file descriptor into blocking
+/// mode, so other threads that read from the same file descriptor at the same
+/// time are not affected.
+///
+/// [`poll`]: libc::poll
+pub fn wait_until_readable(fd: RawFd) -> io::Result<()> {
+    poll_readable([fd], None).map(|_| ())
+}
+
+/// Waits until any of `fds` is readable, or until `timeout` has elapsed
+///
+/// Returns which of the file descriptors are readable. If the timeout elapsed,
+/// all of them are `false`. A `timeout` of `None` waits indefinitely.
+///
+/// Hang-ups and errors on a file descriptor are reported as readable, so the
+/// caller's next `read` can report them.
+pub fn poll_readable<const N: usize>(
+    fds: [RawFd; N],
+    timeout: Option<Duration>,
+) -> io::Result<[bool; N]> {
+    poll(fds, POLLIN, timeout)
+}
+
+/// Waits until `fd` signals an exceptional condition, or until `timeout` has
+/// elapsed
+///
+/// This is how the kernel reports changes to the mount table, when polling
+/// `/proc/self/mountinfo`. Returns `false`, if the timeout elapsed.
+pub(crate) fn poll_priority(fd: RawFd, timeout: Option<Duration>) -> io::Result<bool> {
+    poll([fd], POLLPRI, timeout).map(|[ready]| ready)
+}
+
+fn poll<const N: usize>(
+    fds: [RawFd; N],
+    events: c_short,
+    timeout: Option<Duration>,
+) -> io::Result<[bool; N]> {
+    let mut pollfds = fds.map(|fd| pollfd {
+        fd,
+        events,
+        revents: 0,
+    });
+
+    let timeout = match timeout {
+        // Round up, so we never wake up before the timeout has elapsed.
+        Some(timeout) => {
+            ((timeout.as_nanos() + 999_999) / 1_000_000).min(c_int::MAX as u128) as c_int
+        }
+        None => -1,
+    };
+
+    loop {
+        match unsafe { libc::poll(pollfds.as_mut_ptr(), N as libc::nfds_t, timeout) } {
+            -1 => {
+                let error = io::Error::last_os_error();
+                if error.kind() == io::ErrorKind::Interrupted {
+                    continue;
+                }
+                return Err(error);
+            }
+            _ => return Ok(pollfds.map(|pollfd| pollfd.revents != 0)),
+        }
+    }
+}
+
 /// Get the inotify event buffer size
 ///
 /// The maximum size of an inotify event and thus the buffer size to hold it
@@ -59,3 +232,114 @@ pub fn get_absolute_path_buffer_size(path: &Path) -> usize {
         0
     }
 }
+
+/// Get the inotify event buffer size, based on the file system of `path`
+///
+/// Unlike [`get_buffer_size`], which assumes that file names are at most 255
+/// bytes long, this asks the file system that contains `path` for its actual
+/// limit, using `pathconf(_PC_NAME_MAX)`. The result is large enough to hold
+/// any single event for files within `path`.
+///
+/// # Errors
+///
+/// Returns an error, if `path` contains a null byte, or if the call to
+/// [`pathconf`] fails.
+///
+/// [`pathconf`]: libc::pathconf
+pub fn get_buffer_size_for_path(path: &Path) -> io::Result<usize> {
+    let path = CString::new(path.as_os_str().as_bytes())
+        .map_err(|error| io::Error::new(io::ErrorKind::InvalidInput, error))?;
+
+    // `pathconf` returns -1 both on error and if there is no limit. Only in
+    // the first case is `errno` set, so we need to reset it beforehand.
+    unsafe { *libc::__errno_location() = 0 };
+    let name_max = unsafe { libc::pathconf(path.as_ptr(), libc::_PC_NAME_MAX) };
+
+    let name_max = if name_max == -1 {
+        let error = io::Error::last_os_error();
+        if error.raw_os_error() != Some(0) {
+            return Err(error);
+        }
+
+        // There's no limit. Fall back to the common one, as the kernel won't
+        // send longer names anyway.
+        libc::NAME_MAX as usize
+    } else {
+        name_max as usize
+    };
+
+    Ok(mem::size_of::<ffi::inotify_event>() + name_max + 1)
+}
+
+/// Get the buffer size required to hold `num_events` events of maximum size
+///
+/// Like [`get_buffer_size`], this assumes that file names are at most 255
+/// bytes long.
+///
+/// This is a `const fn`, so it can be used to size arrays. See
+/// [`FixedEventBuffer`] for a buffer type that makes use of that.
+///
+/// ```
+/// use inotify::get_buffer_size_for_events;
+///
+/// const BUFFER_SIZE: usize = get_buffer_size_for_events(16);
+/// let buffer = [0u8; BUFFER_SIZE];
+/// ```
+///
+/// [`FixedEventBuffer`]: crate::FixedEventBuffer
+pub const fn get_buffer_size_for_events(num_events: usize) -> usize {
+    num_events.saturating_mul(INOTIFY_EVENT_SIZE)
+}
+
+/// Get the maximum number of watches per user
+///
+/// Reads `/proc/sys/fs/inotify/max_user_watches`. Adding a watch fails with
+/// `ENOSPC`, once all inotify instances of a user have that many watches.
+///
+/// # Errors
+///
+/// Returns the error from reading the file, or an error with
+/// [`ErrorKind::InvalidData`], if it doesn't contain a number.
+///
+/// [`ErrorKind::InvalidData`]: std::io::ErrorKind::InvalidData
+pub fn max_user_watches() -> io::Result<u64> {
+    read_limit("max_user_watches")
+}
+
+/// Get the maximum number of inotify instances per user
+///
+/// Reads `/proc/sys/fs/inotify/max_user_instances`. Creating an inotify
+/// instance fails with `EMFILE`, once a user has that many.
+///
+/// # Errors
+///
+/// See [`max_user_watches`].
+pub fn max_user_instances() -> io::Result<u64> {
+    read_limit("max_user_instances")
+}
+
+/// Get the maximum number of events in the queue of an inotify instance
+///
+/// Reads `/proc/sys/fs/inotify/max_queued_events`. Once the queue is full,
+/// further events are dropped, and an [`EventMask::Q_OVERFLOW`] event is
+/// generated.
+///
+/// # Errors
+///
+/// See [`max_user_watches`].
+///
+/// [`EventMask::Q_OVERFLOW`]: crate::EventMask::Q_OVERFLOW
+pub fn max_queued_events() -> io::Result<u64> {
+    read_limit("max_queued_events")
+}
+
+fn read_limit(name: &str) -> io::Result<u64> {
+    let limit = fs::read_to_string(Path::new("/proc/sys/fs/inotify").join(name))?;
+
+    limit.trim().parse().map_err(|error| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Invalid value in {}: {}", name, error),
+        )
+    })
+}
@@ -0,0 +1,104 @@
+use std::{
+    io,
+    path::Path,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures_core::{ready, FusedStream, Stream};
+
+use crate::events::{EventMask, ParsedEventMask};
+use crate::inotify::Inotify;
+use crate::stream::EventStream;
+use crate::watches::WatchMask;
+
+/// Watches a single file or directory
+///
+/// This is a shortcut for the common case of waiting for changes to a single
+/// path. It creates an inotify instance, adds a watch for `path`, and returns
+/// a stream of the masks of the events for it. Use [`Inotify`] directly, if
+/// you need more control.
+///
+/// The stream ends after the watch has been removed, for example because the
+/// file has been deleted. The last mask it returns contains
+/// [`EventAuxiliaryFlags::IGNORED`] then. If the event queue overflows, the
+/// stream returns an error.
+///
+/// Must be called from within a tokio runtime.
+///
+/// # Errors
+///
+/// Returns the error from initializing inotify, or from adding the watch.
+///
+/// # Examples
+///
+/// ```no_run
+/// use futures_util::StreamExt;
+/// use inotify::{watch_file, EventKind, WatchMask};
+///
+/// # async fn example() -> std::io::Result<()> {
+/// let mut events = watch_file("/etc/hosts", WatchMask::MODIFY)?;
+///
+/// while let Some(mask) = events.next().await {
+///     if mask?.kind == Some(EventKind::Modify) {
+///         println!("/etc/hosts has been modified");
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`EventAuxiliaryFlags::IGNORED`]: crate::EventAuxiliaryFlags::IGNORED
+pub fn watch_file<P>(path: P, mask: WatchMask) -> io::Result<FileEvents>
+where
+    P: AsRef<Path>,
+{
+    let inotify = Inotify::init()?;
+    inotify.watches().add(path, mask)?;
+
+    Ok(FileEvents {
+        stream: inotify.into_event_stream_with_capacity(0)?,
+        done: false,
+    })
+}
+
+/// Stream of the events for a single file or directory
+///
+/// Returned by [`watch_file`].
+#[derive(Debug)]
+pub struct FileEvents {
+    stream: EventStream<Vec<u8>>,
+    done: bool,
+}
+
+impl Stream for FileEvents {
+    type Item = io::Result<ParsedEventMask>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if self.done {
+            return Poll::Ready(None);
+        }
+
+        let event = match ready!(Pin::new(&mut self.stream).poll_next(cx)) {
+            Some(Ok(event)) => event,
+            Some(Err(error)) => return Poll::Ready(Some(Err(error))),
+            None => {
+                self.done = true;
+                return Poll::Ready(None);
+            }
+        };
+
+        // There is only one watch, so nothing follows once it is gone.
+        if event.mask.contains(EventMask::IGNORED) {
+            self.done = true;
+        }
+
+        Poll::Ready(Some(event.mask.parse().map_err(Into::into)))
+    }
+}
+
+impl FusedStream for FileEvents {
+    fn is_terminated(&self) -> bool {
+        self.done
+    }
+}
@@ -0,0 +1,174 @@
+use std::{collections::HashMap, ffi::OsStr, fmt, io, os::raw::c_int, path::Path};
+
+use crate::events::{Event, EventMask};
+use crate::inotify::Inotify;
+use crate::util::get_buffer_size_for_events;
+use crate::watches::{WatchDescriptor, WatchMask};
+
+type Callback = Box<dyn FnMut(&Event<&OsStr>)>;
+
+/// Calls closures for the events of the paths they have been registered for
+///
+/// Instead of matching the watch descriptors of events against the ones
+/// returned when adding watches, register a closure for each path using
+/// [`Watcher::on`]. The closure is called for each event of the path that
+/// matches the mask it has been registered with. Several closures can be
+/// registered for the same path.
+///
+/// Events are only read and dispatched while [`Watcher::run`] or
+/// [`Watcher::poll`] is called.
+///
+/// # Examples
+///
+/// ```no_run
+/// use inotify::{Watcher, WatchMask};
+///
+/// let mut watcher = Watcher::new()
+///     .expect("Failed to initialize an inotify instance");
+///
+/// watcher.on("/etc/foo.conf", WatchMask::CLOSE_WRITE, |_event| {
+///     println!("Reloading foo");
+/// })
+/// .expect("Failed to add watch");
+/// watcher.on("/etc/bar.conf", WatchMask::CLOSE_WRITE, |_event| {
+///     println!("Reloading bar");
+/// })
+/// .expect("Failed to add watch");
+///
+/// watcher.run().expect("Error while watching");
+/// ```
+pub struct Watcher {
+    inotify: Inotify,
+    callbacks: HashMap<c_int, Vec<(EventMask, Callback)>>,
+    buffer: Vec<u8>,
+}
+
+impl Watcher {
+    /// Creates a `Watcher` without any watches
+    ///
+    /// # Errors
+    ///
+    /// Returns the error from initializing inotify.
+    pub fn new() -> io::Result<Self> {
+        Ok(Watcher {
+            inotify: Inotify::init()?,
+            callbacks: HashMap::new(),
+            buffer: vec![0; get_buffer_size_for_events(16)],
+        })
+    }
+
+    /// Calls `callback` for the events of `path` that match `mask`
+    ///
+    /// Queue overflows are passed to all closures, regardless of their mask.
+    /// So is the [`IGNORED`] event for the path, after which the closures for
+    /// the path are removed. If closures are registered for the same path
+    /// several times, the watch is extended to cover all of their masks.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error from adding the watch.
+    ///
+    /// [`IGNORED`]: EventMask::IGNORED
+    pub fn on<P, F>(&mut self, path: P, mask: WatchMask, callback: F) -> io::Result<WatchDescriptor>
+    where
+        P: AsRef<Path>,
+        F: FnMut(&Event<&OsStr>) + 'static,
+    {
+        let wd = self
+            .inotify
+            .watches()
+            .add(path, mask | WatchMask::MASK_ADD)?;
+
+        let events = EventMask::from_bits_truncate(mask.bits());
+        self.callbacks
+            .entry(wd.id)
+            .or_default()
+            .push((events, Box::new(callback)));
+
+        Ok(wd)
+    }
+
+    /// Removes a watch, and all closures that have been registered for it
+    ///
+    /// # Errors
+    ///
+    /// Returns the error from removing the watch.
+    pub fn remove(&mut self, wd: WatchDescriptor) -> io::Result<()> {
+        self.callbacks.remove(&wd.id);
+        self.inotify.watches().remove(wd)
+    }
+
+    /// Dispatches the events that are available, without blocking
+    ///
+    /// Returns the number of events that have been read.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error from reading events.
+    pub fn poll(&mut self) -> io::Result<usize> {
+        match self.inotify.read_events(&mut self.buffer) {
+            Ok(events) => Ok(dispatch(&mut self.callbacks, events)),
+            Err(error) if error.kind() == io::ErrorKind::WouldBlock => Ok(0),
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Dispatches events, until no watches are left
+    ///
+    /// # Errors
+    ///
+    /// Returns the error from reading events.
+    pub fn run(&mut self) -> io::Result<()> {
+        while !self.callbacks.is_empty() {
+            let events = self.inotify.read_events_blocking(&mut self.buffer)?;
+            dispatch(&mut self.callbacks, events);
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Watcher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Watcher")
+            .field("inotify", &self.inotify)
+            .field("watches", &self.callbacks.len())
+            .finish()
+    }
+}
+
+fn dispatch<'a>(
+    callbacks: &mut HashMap<c_int, Vec<(EventMask, Callback)>>,
+    events: impl Iterator<Item = Event<&'a OsStr>>,
+) -> usize {
+    let mut count = 0;
+
+    for event in events {
+        count += 1;
+
+        if event.is_queue_overflow() {
+            for (_, callback) in callbacks.values_mut().flatten() {
+                callback(&event);
+            }
+            continue;
+        }
+
+        let registered = match callbacks.get_mut(&event.wd.id) {
+            Some(registered) => registered,
+            None => continue,
+        };
+
+        let ignored = event.mask.contains(EventMask::IGNORED);
+        for (mask, callback) in registered.iter_mut() {
+            if ignored || mask.intersects(event.mask) {
+                callback(&event);
+            }
+        }
+
+        if ignored {
+            callbacks.remove(&event.wd.id);
+        }
+    }
+
+    count
+}
@@ -1,17 +1,24 @@
 use std::{
     cmp::Ordering,
+    error::Error,
     ffi::CString,
+    fmt,
     hash::{Hash, Hasher},
     io,
+    ops::BitOr,
     os::raw::c_int,
     os::unix::ffi::OsStrExt,
-    path::Path,
-    sync::{Arc, Weak},
+    path::{Path, PathBuf},
+    sync::Arc,
 };
 
 use inotify_sys as ffi;
 
-use crate::fd_guard::FdGuard;
+use crate::events::EventKind;
+use crate::fd_guard::{FdGuard, FdHandle};
+use crate::oneshot::{OneshotEvent, WaitFor};
+use crate::usage::{instance_watches, WatchUsage};
+use crate::util::max_user_watches;
 
 bitflags! {
     /// Describes a file system watch
@@ -205,6 +212,15 @@ bitflags! {
         /// See [`inotify_sys::IN_MASK_ADD`].
         const MASK_ADD = ffi::IN_MASK_ADD;
 
+        /// Only add a new watch, fail with `EEXIST` if one exists already
+        ///
+        /// Requires Linux 4.18. Older kernels ignore this flag. See
+        /// [`kernel_supports`](crate::kernel_supports) to find out whether it
+        /// is supported.
+        ///
+        /// See [`inotify_sys::IN_MASK_CREATE`].
+        const MASK_CREATE = ffi::IN_MASK_CREATE;
+
         /// Only receive one event, then remove the watch
         ///
         /// See [`inotify_sys::IN_ONESHOT`].
@@ -228,6 +244,177 @@ impl WatchMask {
     pub unsafe fn from_bits_unchecked(bits: u32) -> Self {
         Self::from_bits_retain(bits)
     }
+
+    /// Creates a mask that watches for all of the given kinds of events
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use inotify::{EventKind, WatchMask};
+    ///
+    /// let mask = WatchMask::from_kinds([EventKind::Create, EventKind::Delete]);
+    /// assert_eq!(mask, WatchMask::CREATE | WatchMask::DELETE);
+    /// ```
+    pub fn from_kinds<I>(kinds: I) -> Self
+    where
+        I: IntoIterator<Item = EventKind>,
+    {
+        kinds.into_iter().map(WatchMask::from).collect()
+    }
+}
+
+impl From<EventKind> for WatchMask {
+    fn from(kind: EventKind) -> Self {
+        WatchMask::from_bits_retain(kind.bit())
+    }
+}
+
+bitflags! {
+    /// The events a watch is triggered by
+    ///
+    /// Contains only the event bits of [`WatchMask`], without the flags that
+    /// change how a watch is added, which are in [`WatchFlags`]. Using these
+    /// two types instead of a [`WatchMask`] makes it impossible to pass a flag
+    /// like [`WatchFlags::ONESHOT`] where an event is expected, or the other
+    /// way around.
+    ///
+    /// Combining `WatchEvents` and [`WatchFlags`] with `|` results in a
+    /// [`WatchMask`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use inotify::{WatchEvents, WatchFlags, WatchMask};
+    ///
+    /// let mask = WatchEvents::CREATE | WatchEvents::DELETE | WatchFlags::ONLYDIR;
+    /// assert_eq!(mask, WatchMask::CREATE | WatchMask::DELETE | WatchMask::ONLYDIR);
+    /// assert_eq!(mask.events(), WatchEvents::CREATE | WatchEvents::DELETE);
+    /// assert_eq!(mask.flags(), WatchFlags::ONLYDIR);
+    /// ```
+    #[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Clone, Copy)]
+    pub struct WatchEvents: u32 {
+        /// See [`WatchMask::ACCESS`].
+        const ACCESS = ffi::IN_ACCESS;
+
+        /// See [`WatchMask::ATTRIB`].
+        const ATTRIB = ffi::IN_ATTRIB;
+
+        /// See [`WatchMask::CLOSE_WRITE`].
+        const CLOSE_WRITE = ffi::IN_CLOSE_WRITE;
+
+        /// See [`WatchMask::CLOSE_NOWRITE`].
+        const CLOSE_NOWRITE = ffi::IN_CLOSE_NOWRITE;
+
+        /// See [`WatchMask::CREATE`].
+        const CREATE = ffi::IN_CREATE;
+
+        /// See [`WatchMask::DELETE`].
+        const DELETE = ffi::IN_DELETE;
+
+        /// See [`WatchMask::DELETE_SELF`].
+        const DELETE_SELF = ffi::IN_DELETE_SELF;
+
+        /// See [`WatchMask::MODIFY`].
+        const MODIFY = ffi::IN_MODIFY;
+
+        /// See [`WatchMask::MOVE_SELF`].
+        const MOVE_SELF = ffi::IN_MOVE_SELF;
+
+        /// See [`WatchMask::MOVED_FROM`].
+        const MOVED_FROM = ffi::IN_MOVED_FROM;
+
+        /// See [`WatchMask::MOVED_TO`].
+        const MOVED_TO = ffi::IN_MOVED_TO;
+
+        /// See [`WatchMask::OPEN`].
+        const OPEN = ffi::IN_OPEN;
+
+        /// See [`WatchMask::ALL_EVENTS`].
+        const ALL_EVENTS = ffi::IN_ALL_EVENTS;
+
+        /// See [`WatchMask::MOVE`].
+        const MOVE = ffi::IN_MOVE;
+
+        /// See [`WatchMask::CLOSE`].
+        const CLOSE = ffi::IN_CLOSE;
+    }
+}
+
+bitflags! {
+    /// Flags that change how a watch is added
+    ///
+    /// Contains only the flag bits of [`WatchMask`]. See [`WatchEvents`].
+    #[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Clone, Copy)]
+    pub struct WatchFlags: u32 {
+        /// See [`WatchMask::DONT_FOLLOW`].
+        const DONT_FOLLOW = ffi::IN_DONT_FOLLOW;
+
+        /// See [`WatchMask::EXCL_UNLINK`].
+        const EXCL_UNLINK = ffi::IN_EXCL_UNLINK;
+
+        /// See [`WatchMask::MASK_ADD`].
+        const MASK_ADD = ffi::IN_MASK_ADD;
+
+        /// See [`WatchMask::MASK_CREATE`].
+        const MASK_CREATE = ffi::IN_MASK_CREATE;
+
+        /// See [`WatchMask::ONESHOT`].
+        const ONESHOT = ffi::IN_ONESHOT;
+
+        /// See [`WatchMask::ONLYDIR`].
+        const ONLYDIR = ffi::IN_ONLYDIR;
+    }
+}
+
+impl WatchMask {
+    /// Combines events and flags into a mask
+    pub fn new(events: WatchEvents, flags: WatchFlags) -> Self {
+        WatchMask::from_bits_retain(events.bits() | flags.bits())
+    }
+
+    /// Returns the events of the mask, without the flags
+    pub fn events(&self) -> WatchEvents {
+        WatchEvents::from_bits_truncate(self.bits())
+    }
+
+    /// Returns the flags of the mask, without the events
+    pub fn flags(&self) -> WatchFlags {
+        WatchFlags::from_bits_truncate(self.bits())
+    }
+}
+
+impl From<WatchEvents> for WatchMask {
+    fn from(events: WatchEvents) -> Self {
+        WatchMask::new(events, WatchFlags::empty())
+    }
+}
+
+impl From<WatchFlags> for WatchMask {
+    fn from(flags: WatchFlags) -> Self {
+        WatchMask::new(WatchEvents::empty(), flags)
+    }
+}
+
+impl From<EventKind> for WatchEvents {
+    fn from(kind: EventKind) -> Self {
+        WatchEvents::from_bits_retain(kind.bit())
+    }
+}
+
+impl BitOr<WatchFlags> for WatchEvents {
+    type Output = WatchMask;
+
+    fn bitor(self, flags: WatchFlags) -> WatchMask {
+        WatchMask::new(self, flags)
+    }
+}
+
+impl BitOr<WatchEvents> for WatchFlags {
+    type Output = WatchMask;
+
+    fn bitor(self, events: WatchEvents) -> WatchMask {
+        WatchMask::new(events, self)
+    }
 }
 
 impl WatchDescriptor {
@@ -240,7 +427,12 @@ impl WatchDescriptor {
 }
 
 /// Interface for adding and removing watches
-#[derive(Clone, Debug)]
+///
+/// The `Debug` output lists the watches of the inotify instance, if
+/// [`Inotify::enable_bookkeeping`] has been called.
+///
+/// [`Inotify::enable_bookkeeping`]: crate::Inotify::enable_bookkeeping
+#[derive(Clone)]
 pub struct Watches {
     pub(crate) fd: Arc<FdGuard>,
 }
@@ -318,18 +510,211 @@ impl Watches {
     where
         P: AsRef<Path>,
     {
-        let path = CString::new(path.as_ref().as_os_str().as_bytes())?;
+        let path = path.as_ref();
+        let c_path = CString::new(path.as_os_str().as_bytes())?;
 
         let wd =
-            unsafe { ffi::inotify_add_watch(**self.fd, path.as_ptr() as *const _, mask.bits()) };
+            unsafe { ffi::inotify_add_watch(**self.fd, c_path.as_ptr() as *const _, mask.bits()) };
 
-        match wd {
-            -1 => Err(io::Error::last_os_error()),
-            _ => Ok(WatchDescriptor {
-                id: wd,
-                fd: Arc::downgrade(&self.fd),
-            }),
+        if wd == -1 {
+            return Err(io::Error::last_os_error());
         }
+        self.fd.usage_hook.watch_added(**self.fd, wd);
+        self.fd.bookkeeping.watch_added(wd, path, mask);
+
+        Ok(WatchDescriptor {
+            id: wd,
+            fd: FdHandle::new(Arc::downgrade(&self.fd)),
+        })
+    }
+
+    /// Adds or updates a watch, with events and flags passed separately
+    ///
+    /// Works like [`Watches::add`], but takes the events to watch for and the
+    /// flags that change how the watch is added as separate arguments. See
+    /// [`WatchEvents`].
+    ///
+    /// # Errors
+    ///
+    /// See [`Watches::add`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use inotify::{Inotify, WatchEvents, WatchFlags};
+    ///
+    /// let inotify = Inotify::init()
+    ///     .expect("Failed to initialize an inotify instance");
+    ///
+    /// inotify
+    ///     .watches()
+    ///     .add_with_flags("/tmp", WatchEvents::CREATE, WatchFlags::ONLYDIR)
+    ///     .expect("Failed to add watch");
+    /// ```
+    pub fn add_with_flags<P>(
+        &mut self,
+        path: P,
+        events: WatchEvents,
+        flags: WatchFlags,
+    ) -> io::Result<WatchDescriptor>
+    where
+        P: AsRef<Path>,
+    {
+        self.add(path, WatchMask::new(events, flags))
+    }
+
+    /// Adds a watch that is removed after its first event, and returns that event
+    ///
+    /// Adds the watch like [`Watches::add`], with [`WatchMask::ONESHOT`] added
+    /// to `mask`. The returned [`OneshotEvent`] resolves with the first event
+    /// of the watch, once that event has been read from the instance. This
+    /// saves looking for that event among all the others.
+    ///
+    /// If the same file is watched already, the existing watch is changed
+    /// into a oneshot watch, like it would be by [`Watches::add`].
+    ///
+    /// # Errors
+    ///
+    /// See [`Watches::add`].
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use inotify::{Inotify, WatchMask};
+    ///
+    /// let inotify = Inotify::init()
+    ///     .expect("Failed to initialize an inotify instance");
+    ///
+    /// let created = inotify
+    ///     .watches()
+    ///     .add_oneshot("/tmp", WatchMask::CREATE)
+    ///     .expect("Failed to add watch");
+    ///
+    /// let mut buffer = [0; 1024];
+    /// inotify
+    ///     .read_events_blocking(&mut buffer)
+    ///     .expect("Error while reading events");
+    ///
+    /// if let Ok(event) = created.recv() {
+    ///     println!("{:?} has been created", event.name);
+    /// }
+    /// ```
+    pub fn add_oneshot<P>(&mut self, path: P, mask: WatchMask) -> io::Result<OneshotEvent>
+    where
+        P: AsRef<Path>,
+    {
+        let fd = self.fd.clone();
+        let adding = fd.oneshots.start_adding();
+
+        let wd = self.add(path, mask | WatchMask::ONESHOT)?;
+        Ok(adding.finish(wd))
+    }
+
+    /// Waits for the first event that matches `mask` for a file or directory
+    ///
+    /// Adds a oneshot watch right away, like [`Watches::add_oneshot`], so the
+    /// kernel removes it after the first event. The returned future resolves
+    /// with that event. This is useful for waiting until a file has been
+    /// created in a directory, or until a file has been closed after writing.
+    ///
+    /// Like with [`Watches::add_oneshot`], the event needs to be read from the
+    /// instance for the future to resolve, for example by an [`EventStream`]
+    /// that is polled elsewhere.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error from adding the watch, like [`Watches::add`]. The
+    /// returned [`WaitFor`] resolves with an error with
+    /// [`ErrorKind::NotFound`], if the watch has been removed before a
+    /// matching event occurred, for example because the file has been
+    /// deleted, and with an error with [`ErrorKind::Other`], if the event
+    /// queue overflowed in the meantime.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::thread;
+    ///
+    /// use inotify::{Inotify, WatchMask};
+    ///
+    /// # async fn example() -> std::io::Result<()> {
+    /// let inotify = Inotify::init()?;
+    /// let created = inotify.watches().wait_for("/var/run", WatchMask::CREATE)?;
+    ///
+    /// thread::spawn(move || {
+    ///     let mut buffer = [0; 1024];
+    ///     while inotify.read_events_blocking(&mut buffer).is_ok() {}
+    /// });
+    ///
+    /// let event = created.await?;
+    /// println!("{:?} has been created", event.name);
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`EventStream`]: crate::EventStream
+    /// [`ErrorKind::NotFound`]: std::io::ErrorKind::NotFound
+    /// [`ErrorKind::Other`]: std::io::ErrorKind::Other
+    pub fn wait_for<P>(&mut self, path: P, mask: WatchMask) -> io::Result<WaitFor>
+    where
+        P: AsRef<Path>,
+    {
+        self.add_oneshot(path, mask).map(WaitFor::new)
+    }
+
+    /// Adds or updates a watch, returning a typed error on failure
+    ///
+    /// Works like [`Watches::add`], but turns the most common errors into
+    /// variants of [`AddWatchError`]. Most importantly, this tells the watch
+    /// limit apart from a full disk, which both are reported as `ENOSPC`.
+    ///
+    /// # Errors
+    ///
+    /// See [`AddWatchError`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use inotify::{AddWatchError, Inotify, WatchMask};
+    ///
+    /// let inotify = Inotify::init()
+    ///     .expect("Failed to initialize an inotify instance");
+    ///
+    /// match inotify.watches().try_add("/does/not/exist", WatchMask::MODIFY) {
+    ///     Err(AddWatchError::PathNotFound { path }) => {
+    ///         println!("{} doesn't exist", path.display());
+    ///     }
+    ///     Err(AddWatchError::WatchLimitReached { limit }) => {
+    ///         println!("Watch limit ({:?}) reached", limit);
+    ///     }
+    ///     result => {
+    ///         result.expect("Failed to add watch");
+    ///     }
+    /// }
+    /// ```
+    pub fn try_add<P>(&mut self, path: P, mask: WatchMask) -> Result<WatchDescriptor, AddWatchError>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+
+        self.add(path, mask)
+            .map_err(|error| match error.raw_os_error() {
+                Some(libc::ENOSPC) => AddWatchError::WatchLimitReached {
+                    limit: max_user_watches().ok(),
+                },
+                Some(libc::ENOENT) => AddWatchError::PathNotFound {
+                    path: path.to_owned(),
+                },
+                Some(libc::EACCES) => AddWatchError::PermissionDenied {
+                    path: path.to_owned(),
+                },
+                Some(libc::ENOTDIR) => AddWatchError::NotADirectory {
+                    path: path.to_owned(),
+                },
+                Some(libc::EINVAL) => AddWatchError::InvalidMask { mask },
+                _ => AddWatchError::Other(error),
+            })
     }
 
     /// Stops watching a file
@@ -349,7 +734,7 @@ impl Watches {
     /// ```
     /// use inotify::Inotify;
     ///
-    /// let mut inotify = Inotify::init()
+    /// let inotify = Inotify::init()
     ///     .expect("Failed to initialize an inotify instance");
     ///
     /// # // Create a temporary file, so `Watches::add` won't return an error.
@@ -383,7 +768,7 @@ impl Watches {
     /// [`io::Error`]: std::io::Error
     /// [`ErrorKind`]: std::io::ErrorKind
     pub fn remove(&mut self, wd: WatchDescriptor) -> io::Result<()> {
-        if wd.fd.upgrade().as_ref() != Some(&self.fd) {
+        if wd.fd.instance() != self.fd.instance {
             return Err(io::Error::new(
                 io::ErrorKind::InvalidInput,
                 "Invalid WatchDescriptor",
@@ -392,11 +777,46 @@ impl Watches {
 
         let result = unsafe { ffi::inotify_rm_watch(**self.fd, wd.id) };
         match result {
-            0 => Ok(()),
+            0 => {
+                self.fd.usage_hook.watch_removed();
+                self.fd.bookkeeping.watch_removed(wd.id);
+                self.fd.oneshots.watch_removed(wd.id);
+                Ok(())
+            }
             -1 => Err(io::Error::last_os_error()),
             _ => panic!("unexpected return code from inotify_rm_watch ({})", result),
         }
     }
+
+    /// Returns how many watches this inotify instance has
+    ///
+    /// The number of watches is read from `/proc/self/fdinfo`, so it is
+    /// accurate, even if the kernel has removed watches on its own. Use
+    /// [`user_watch_usage`] to get the number of watches of all instances of
+    /// the current user, which is what the limit applies to.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error from reading `/proc`.
+    ///
+    /// [`user_watch_usage`]: crate::user_watch_usage
+    pub fn usage(&self) -> io::Result<WatchUsage> {
+        let (watches, _) = instance_watches(**self.fd)?;
+
+        Ok(WatchUsage {
+            watches,
+            limit: max_user_watches()?,
+        })
+    }
+}
+
+impl fmt::Debug for Watches {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut f = f.debug_struct("Watches");
+        f.field("fd", &**self.fd);
+        self.fd.bookkeeping.debug_fields(&mut f);
+        f.finish()
+    }
 }
 
 /// Represents a watch on an inode
@@ -405,21 +825,27 @@ impl Watches {
 /// descriptor can be used to get inotify to stop watching an inode by passing
 /// it to [`Watches::remove`].
 ///
+/// Two watch descriptors are equal, if they refer to the same watch of the
+/// same [`Inotify`] instance. They stay equal after the instance has been
+/// closed. Watch descriptors that don't belong to any instance, like those of
+/// events created by [`Events::from_bytes`], are never equal to anything.
+///
 /// [`Event`]: crate::Event
+/// [`Inotify`]: crate::Inotify
+/// [`Events::from_bytes`]: crate::Events::from_bytes
 #[derive(Clone, Debug)]
 pub struct WatchDescriptor {
     pub(crate) id: c_int,
-    pub(crate) fd: Weak<FdGuard>,
+    pub(crate) fd: FdHandle,
 }
 
 impl Eq for WatchDescriptor {}
 
 impl PartialEq for WatchDescriptor {
     fn eq(&self, other: &Self) -> bool {
-        let self_fd = self.fd.upgrade();
-        let other_fd = other.fd.upgrade();
-
-        self.id == other.id && self_fd.is_some() && self_fd == other_fd
+        // Descriptors that don't belong to any instance aren't equal to
+        // anything, not even to each other.
+        self.id == other.id && self.fd.instance() != 0 && self.fd.instance() == other.fd.instance()
     }
 }
 
@@ -437,13 +863,124 @@ impl PartialOrd for WatchDescriptor {
 
 impl Hash for WatchDescriptor {
     fn hash<H: Hasher>(&self, state: &mut H) {
-        // This function only takes `self.id` into account, as `self.fd` is a
-        // weak pointer that might no longer be available. Since neither
-        // panicking nor changing the hash depending on whether it's available
-        // is acceptable, we just don't look at it at all.
-        // I don't think that this influences storage in a `HashMap` or
-        // `HashSet` negatively, as storing `WatchDescriptor`s from different
-        // `Inotify` instances seems like something of an anti-pattern anyway.
+        // The instance id is cached in the handle, so this doesn't have to
+        // look at the weak pointer, which might no longer be available.
         self.id.hash(state);
+        self.fd.instance().hash(state);
+    }
+}
+
+/// An error that occurred while adding a watch
+///
+/// Returned by [`Watches::try_add`]. Can be converted into an [`io::Error`],
+/// which has the same [`ErrorKind`] as the one [`Watches::add`] would have
+/// returned.
+///
+/// [`ErrorKind`]: std::io::ErrorKind
+#[derive(Debug)]
+pub enum AddWatchError {
+    /// The user has reached the maximum number of watches
+    ///
+    /// Reported by the kernel as `ENOSPC`, which is easily mistaken for a full
+    /// disk. The limit can be raised through the `fs.inotify.max_user_watches`
+    /// sysctl.
+    WatchLimitReached {
+        /// The limit, if it could be read
+        ///
+        /// See [`max_user_watches`](crate::max_user_watches).
+        limit: Option<u64>,
+    },
+
+    /// The path, or one of its parent directories, doesn't exist
+    PathNotFound {
+        /// The path of the watch
+        path: PathBuf,
+    },
+
+    /// The path isn't readable
+    PermissionDenied {
+        /// The path of the watch
+        path: PathBuf,
+    },
+
+    /// The path isn't a directory
+    ///
+    /// Returned if [`WatchMask::ONLYDIR`] has been passed for a file, or if
+    /// one of the parent directories in the path is a file.
+    NotADirectory {
+        /// The path of the watch
+        path: PathBuf,
+    },
+
+    /// The mask doesn't contain any events, or contains invalid flags
+    InvalidMask {
+        /// The mask of the watch
+        mask: WatchMask,
+    },
+
+    /// Any other error
+    Other(io::Error),
+}
+
+impl AddWatchError {
+    pub(crate) fn raw_os_error(&self) -> Option<c_int> {
+        match self {
+            AddWatchError::WatchLimitReached { .. } => Some(libc::ENOSPC),
+            AddWatchError::PathNotFound { .. } => Some(libc::ENOENT),
+            AddWatchError::PermissionDenied { .. } => Some(libc::EACCES),
+            AddWatchError::NotADirectory { .. } => Some(libc::ENOTDIR),
+            AddWatchError::InvalidMask { .. } => Some(libc::EINVAL),
+            AddWatchError::Other(error) => error.raw_os_error(),
+        }
+    }
+}
+
+impl fmt::Display for AddWatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AddWatchError::WatchLimitReached { limit: Some(limit) } => write!(
+                f,
+                "inotify watch limit reached ({} watches, see fs.inotify.max_user_watches)",
+                limit,
+            ),
+            AddWatchError::WatchLimitReached { limit: None } => write!(
+                f,
+                "inotify watch limit reached (see fs.inotify.max_user_watches)"
+            ),
+            AddWatchError::PathNotFound { path } => {
+                write!(f, "path to watch not found: {}", path.display())
+            }
+            AddWatchError::PermissionDenied { path } => {
+                write!(f, "permission denied to watch {}", path.display())
+            }
+            AddWatchError::NotADirectory { path } => {
+                write!(f, "path to watch is not a directory: {}", path.display())
+            }
+            AddWatchError::InvalidMask { mask } => write!(f, "invalid watch mask: {}", mask),
+            AddWatchError::Other(error) => write!(f, "failed to add watch: {}", error),
+        }
+    }
+}
+
+impl Error for AddWatchError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            AddWatchError::Other(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<AddWatchError> for io::Error {
+    fn from(error: AddWatchError) -> Self {
+        let kind = match error.raw_os_error() {
+            Some(errno) => io::Error::from_raw_os_error(errno).kind(),
+            None => io::ErrorKind::Other,
+        };
+
+        match error {
+            AddWatchError::Other(error) => error,
+            error => io::Error::new(kind, error),
+        }
     }
 }
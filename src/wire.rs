@@ -0,0 +1,487 @@
+use std::{
+    ffi::{OsStr, OsString},
+    io::{self, Read, Write},
+    os::{raw::c_int, unix::ffi::OsStrExt},
+    path::{Path, PathBuf},
+};
+
+use crate::events::{Event, EventMask, EventOwned};
+use crate::fd_guard::FdHandle;
+use crate::resolve::PathItem;
+use crate::watches::WatchDescriptor;
+
+/// Identifies a stream of wire items, and the version of its format
+const MAGIC: &[u8; 8] = b"INOTWIR1";
+
+/// The maximum size of the payload of an item
+///
+/// Far larger than any event or path, but small enough to be allocated
+/// without a second thought, when reading a corrupted stream.
+const MAX_ITEM_LEN: usize = 16 * 1024 * 1024;
+
+const ITEM_EVENT: u8 = b'E';
+const ITEM_RENAMED: u8 = b'M';
+const ITEM_PATH_EVENT: u8 = b'P';
+
+/// Writes events in a compact binary format
+///
+/// Meant for sending events to another process, for example over a Unix
+/// socket, where a [`WireReader`] reads them back. This allows splitting an
+/// application into a privileged process that watches files, and an
+/// unprivileged one that handles the events.
+///
+/// Besides events, [`PathItem`]s from a [`PathResolver`] can be written, so
+/// the receiving side doesn't need to know the paths of the watches.
+///
+/// The format is stable. Each item is written as a kind byte, followed by
+/// the length of its payload as a little-endian `u32`, and the payload
+/// itself. Integers in the payload are little-endian, names and paths are
+/// written as raw bytes. The time at which an event has been received is not
+/// written.
+///
+/// Available with the `wire` feature.
+///
+/// # Examples
+///
+/// ```
+/// use inotify::{Inotify, WireItem, WireReader, WireWriter};
+///
+/// # let inotify = Inotify::init().unwrap();
+/// # let mut buffer = [0; 1024];
+/// let mut writer = WireWriter::new(Vec::new())
+///     .expect("Failed to write header");
+/// # let _ = || {
+/// for event in inotify.read_events_blocking(&mut buffer).unwrap() {
+///     writer.write_event(&event).expect("Failed to write event");
+/// }
+/// # };
+/// let bytes = writer.into_inner().expect("Failed to flush");
+///
+/// let mut reader = WireReader::new(&bytes[..])
+///     .expect("Failed to read header");
+/// while let Some(item) = reader.read_item().expect("Failed to read item") {
+///     if let WireItem::Event(event) = item {
+///         // Handle event
+///     }
+/// }
+/// ```
+///
+/// [`PathResolver`]: crate::PathResolver
+#[derive(Debug)]
+pub struct WireWriter<W> {
+    writer: W,
+    payload: Vec<u8>,
+}
+
+impl<W> WireWriter<W>
+where
+    W: Write,
+{
+    /// Starts a stream of items
+    ///
+    /// # Errors
+    ///
+    /// Returns the error from writing to `writer`.
+    pub fn new(mut writer: W) -> io::Result<Self> {
+        writer.write_all(MAGIC)?;
+
+        Ok(WireWriter {
+            writer,
+            payload: Vec::new(),
+        })
+    }
+
+    /// Writes an event
+    ///
+    /// # Errors
+    ///
+    /// Returns the error from writing to the underlying writer.
+    pub fn write_event<S>(&mut self, event: &Event<S>) -> io::Result<()>
+    where
+        S: AsRef<OsStr>,
+    {
+        self.payload.clear();
+        encode_event(event, &mut self.payload);
+
+        self.write_item(ITEM_EVENT)
+    }
+
+    /// Writes an item returned by a [`PathResolver`]
+    ///
+    /// # Errors
+    ///
+    /// Returns the error from writing to the underlying writer.
+    ///
+    /// [`PathResolver`]: crate::PathResolver
+    pub fn write_path_item(&mut self, item: &PathItem) -> io::Result<()> {
+        self.payload.clear();
+
+        let kind = match item {
+            PathItem::Renamed { from, to, is_dir } => {
+                self.payload.push(u8::from(*is_dir));
+                encode_path(Some(from), &mut self.payload);
+                encode_path(Some(to), &mut self.payload);
+                ITEM_RENAMED
+            }
+            PathItem::Event { path, event } => {
+                encode_path(path.as_deref(), &mut self.payload);
+                encode_event(event, &mut self.payload);
+                ITEM_PATH_EVENT
+            }
+        };
+
+        self.write_item(kind)
+    }
+
+    /// Flushes the underlying writer
+    ///
+    /// # Errors
+    ///
+    /// Returns the error from flushing the writer.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    /// Flushes the underlying writer, and returns it
+    ///
+    /// # Errors
+    ///
+    /// Returns the error from flushing the writer.
+    pub fn into_inner(mut self) -> io::Result<W> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+
+    fn write_item(&mut self, kind: u8) -> io::Result<()> {
+        if self.payload.len() > MAX_ITEM_LEN {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Item too large",
+            ));
+        }
+        let len = self.payload.len() as u32;
+
+        self.writer.write_all(&[kind])?;
+        self.writer.write_all(&len.to_le_bytes())?;
+        self.writer.write_all(&self.payload)
+    }
+}
+
+/// Reads events that have been written by a [`WireWriter`]
+///
+/// The watch descriptors of the events aren't associated with any inotify
+/// instance. Descriptors read by the same `WireReader` compare equal, if they
+/// refer to the same watch, so they can still be used as keys in a map.
+///
+/// Available with the `wire` feature.
+#[derive(Debug)]
+pub struct WireReader<R> {
+    reader: R,
+    fd: FdHandle,
+    buffer: Vec<u8>,
+}
+
+impl<R> WireReader<R>
+where
+    R: Read,
+{
+    /// Opens a stream of items
+    ///
+    /// # Errors
+    ///
+    /// Returns the error from reading from `reader`, or an error with
+    /// [`ErrorKind::InvalidData`], if it wasn't written by a [`WireWriter`].
+    ///
+    /// [`ErrorKind::InvalidData`]: std::io::ErrorKind::InvalidData
+    pub fn new(mut reader: R) -> io::Result<Self> {
+        let mut magic = [0; MAGIC.len()];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid("Not a stream of inotify events"));
+        }
+
+        Ok(WireReader {
+            reader,
+            fd: FdHandle::detached(),
+            buffer: Vec::new(),
+        })
+    }
+
+    /// Reads the next item
+    ///
+    /// Returns `None`, once the end of the stream has been reached.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error from reading from the underlying reader, an error
+    /// with [`ErrorKind::InvalidData`], if the stream is corrupted, or one
+    /// with [`ErrorKind::UnexpectedEof`], if it ends in the middle of an item.
+    ///
+    /// [`ErrorKind::InvalidData`]: std::io::ErrorKind::InvalidData
+    /// [`ErrorKind::UnexpectedEof`]: std::io::ErrorKind::UnexpectedEof
+    pub fn read_item(&mut self) -> io::Result<Option<WireItem>> {
+        // Only the end of the stream before an item is a clean one.
+        let mut header = [0; 5];
+        let mut num_read = 0;
+        while num_read < header.len() {
+            match self.reader.read(&mut header[num_read..]) {
+                Ok(0) if num_read == 0 => return Ok(None),
+                Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+                Ok(n) => num_read += n,
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => {}
+                Err(error) => return Err(error),
+            }
+        }
+        let len = u32::from_le_bytes([header[1], header[2], header[3], header[4]]) as usize;
+        if len > MAX_ITEM_LEN {
+            return Err(corrupted());
+        }
+
+        self.buffer.resize(len, 0);
+        self.reader.read_exact(&mut self.buffer)?;
+
+        let mut payload = Payload(&self.buffer);
+        let item = match header[0] {
+            ITEM_EVENT => WireItem::Event(payload.event(&self.fd)?),
+            ITEM_RENAMED => {
+                let is_dir = payload.u8()? != 0;
+                let from = payload.path()?;
+                let to = payload.path()?;
+
+                match (from, to) {
+                    (Some(from), Some(to)) => {
+                        WireItem::Path(PathItem::Renamed { from, to, is_dir })
+                    }
+                    _ => return Err(corrupted()),
+                }
+            }
+            ITEM_PATH_EVENT => {
+                let path = payload.path()?;
+                let event = payload.event(&self.fd)?;
+
+                WireItem::Path(PathItem::Event { path, event })
+            }
+            _ => return Err(corrupted()),
+        };
+
+        Ok(Some(item))
+    }
+}
+
+impl<R> Iterator for WireReader<R>
+where
+    R: Read,
+{
+    type Item = io::Result<WireItem>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_item().transpose()
+    }
+}
+
+/// An item read by a [`WireReader`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum WireItem {
+    /// An event written by [`WireWriter::write_event`]
+    Event(EventOwned),
+
+    /// An item written by [`WireWriter::write_path_item`]
+    Path(PathItem),
+}
+
+fn encode_event<S>(event: &Event<S>, out: &mut Vec<u8>)
+where
+    S: AsRef<OsStr>,
+{
+    out.extend_from_slice(&event.wd.id.to_le_bytes());
+    out.extend_from_slice(&event.mask.bits().to_le_bytes());
+    out.extend_from_slice(&event.cookie.to_le_bytes());
+
+    // The name comes last, so it doesn't need a length.
+    match &event.name {
+        Some(name) => {
+            out.push(1);
+            out.extend_from_slice(name.as_ref().as_bytes());
+        }
+        None => out.push(0),
+    }
+}
+
+fn encode_path(path: Option<&Path>, out: &mut Vec<u8>) {
+    match path {
+        Some(path) => {
+            let path = path.as_os_str().as_bytes();
+
+            out.push(1);
+            out.extend_from_slice(&(path.len() as u32).to_le_bytes());
+            out.extend_from_slice(path);
+        }
+        None => out.push(0),
+    }
+}
+
+/// The rest of the payload of an item, that hasn't been decoded yet
+struct Payload<'a>(&'a [u8]);
+
+impl<'a> Payload<'a> {
+    fn bytes(&mut self, len: usize) -> io::Result<&'a [u8]> {
+        if self.0.len() < len {
+            return Err(corrupted());
+        }
+
+        let (bytes, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> io::Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        let bytes = self.bytes(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn path(&mut self) -> io::Result<Option<PathBuf>> {
+        if self.u8()? == 0 {
+            return Ok(None);
+        }
+
+        let len = self.u32()? as usize;
+        Ok(Some(PathBuf::from(OsStr::from_bytes(self.bytes(len)?))))
+    }
+
+    fn event(&mut self, fd: &FdHandle) -> io::Result<EventOwned> {
+        let id = self.u32()? as c_int;
+        let mask = EventMask::from_bits_retain(self.u32()?);
+        let cookie = self.u32()?;
+
+        let name = match self.u8()? {
+            0 if self.0.is_empty() => None,
+            1 => Some(OsString::from(OsStr::from_bytes(self.bytes(self.0.len())?))),
+            _ => return Err(corrupted()),
+        };
+
+        let wd = WatchDescriptor { id, fd: fd.clone() };
+
+        let mut event = EventOwned::synthetic(wd, mask, name);
+        event.cookie = cookie;
+        Ok(event)
+    }
+}
+
+fn corrupted() -> io::Error {
+    invalid("Corrupted stream of inotify events")
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io, path::PathBuf};
+
+    use super::{WireItem, WireReader, WireWriter, MAGIC};
+    use crate::events::test_event;
+    use crate::{EventMask, PathItem};
+
+    #[test]
+    fn items_should_round_trip() {
+        let create = test_event(EventMask::CREATE | EventMask::ISDIR, 0, Some("dir"));
+        let overflow = test_event(EventMask::Q_OVERFLOW, 0, None);
+        let renamed = PathItem::Renamed {
+            from: PathBuf::from("/project/a"),
+            to: PathBuf::from("/project/b"),
+            is_dir: false,
+        };
+        let moved = PathItem::Event {
+            path: Some(PathBuf::from("/project/c")),
+            event: test_event(EventMask::MOVED_TO, 7, Some("c")),
+        };
+
+        let mut writer = WireWriter::new(Vec::new()).unwrap();
+        writer.write_event(&create).unwrap();
+        writer.write_event(&overflow).unwrap();
+        writer.write_path_item(&renamed).unwrap();
+        writer.write_path_item(&moved).unwrap();
+        let bytes = writer.into_inner().unwrap();
+
+        let items = WireReader::new(&bytes[..])
+            .unwrap()
+            .collect::<io::Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(items.len(), 4);
+
+        let event = match &items[0] {
+            WireItem::Event(event) => event,
+            item => panic!("Unexpected item: {:?}", item),
+        };
+        assert_eq!(event.mask, create.mask);
+        assert_eq!(event.name.as_deref(), Some("dir".as_ref()));
+
+        // Descriptors from the same reader are equal, even though they don't
+        // belong to an instance.
+        match (&items[0], &items[1]) {
+            (WireItem::Event(create), WireItem::Event(overflow)) => {
+                assert_eq!(overflow.name, None);
+                assert_eq!(overflow.wd, create.wd);
+            }
+            items => panic!("Unexpected items: {:?}", items),
+        }
+
+        assert_eq!(items[2], WireItem::Path(renamed));
+        match &items[3] {
+            WireItem::Path(PathItem::Event { path, event }) => {
+                assert_eq!(path.as_deref(), Some("/project/c".as_ref()));
+                assert_eq!(event.cookie, 7);
+                assert_eq!(event.name.as_deref(), Some("c".as_ref()));
+            }
+            item => panic!("Unexpected item: {:?}", item),
+        }
+    }
+
+    #[test]
+    fn corrupted_streams_should_be_rejected() {
+        assert!(WireReader::new(&b"INOTREC1"[..]).is_err());
+
+        let mut writer = WireWriter::new(Vec::new()).unwrap();
+        writer
+            .write_event(&test_event(EventMask::CREATE, 0, Some("a")))
+            .unwrap();
+        let mut bytes = writer.into_inner().unwrap();
+
+        // Claim that the event has no name, although there is one.
+        let name_flag = bytes.len() - 2;
+        bytes[name_flag] = 0;
+
+        let mut reader = WireReader::new(&bytes[..]).unwrap();
+        let error = reader.read_item().unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        // Claim a length that is too large to be allocated.
+        bytes[name_flag] = 1;
+        bytes[MAGIC.len() + 1..MAGIC.len() + 5].copy_from_slice(&u32::MAX.to_le_bytes());
+        let mut reader = WireReader::new(&bytes[..]).unwrap();
+        let error = reader.read_item().unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn truncated_items_should_be_rejected() {
+        let mut writer = WireWriter::new(Vec::new()).unwrap();
+        writer
+            .write_event(&test_event(EventMask::CREATE, 0, None))
+            .unwrap();
+        let bytes = writer.into_inner().unwrap();
+
+        let mut reader = WireReader::new(&bytes[..MAGIC.len() + 3]).unwrap();
+        let error = reader.read_item().unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+
+        let mut reader = WireReader::new(&bytes[..]).unwrap();
+        assert!(reader.read_item().unwrap().is_some());
+        assert!(reader.read_item().unwrap().is_none());
+    }
+}
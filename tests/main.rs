@@ -3,30 +3,40 @@
 // This test suite is incomplete and doesn't cover all available functionality.
 // Contributions to improve test coverage would be highly appreciated!
 
-use inotify::{Inotify, WatchMask};
+use inotify::{
+    AddWatchError, Compare, ConfigChange, ConfigWatcher, EventMask, Events, FileFollower,
+    FixedEventBuffer, FollowEvent, Inotify, InotifySet, PathEvent, PathWatcher, Recorder,
+    RemountEvent, RemountWatcher, Replayer, Resync, SharedInotify, TypedItem, UnchangedFilter,
+    WatchMask, Watcher,
+};
+#[cfg(feature = "stream")]
+use inotify::{Change, WatcherService};
 use std::fs::File;
-use std::io::{ErrorKind, Write};
-use std::os::unix::io::{AsRawFd, FromRawFd, IntoRawFd};
+use std::io::{ErrorKind, Read, Write};
+use std::os::unix::io::{AsFd, AsRawFd, FromRawFd, IntoRawFd};
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::TryRecvError;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
 use tempfile::TempDir;
 
 #[cfg(feature = "stream")]
 use futures_util::StreamExt;
 #[cfg(feature = "stream")]
-use inotify::EventMask;
-#[cfg(feature = "stream")]
 use maplit::hashmap;
 #[cfg(feature = "stream")]
 use rand::{prelude::SliceRandom, thread_rng};
 #[cfg(feature = "stream")]
-use std::sync::{Arc, Mutex};
+use std::sync::Mutex;
 
 #[test]
 fn it_should_watch_a_file() {
     let mut testdir = TestDir::new();
     let (path, mut file) = testdir.new_file();
 
-    let mut inotify = Inotify::init().unwrap();
+    let inotify = Inotify::init().unwrap();
     let watch = inotify.watches().add(&path, WatchMask::MODIFY).unwrap();
 
     write_to(&mut file);
@@ -113,270 +123,2373 @@ async fn it_should_watch_a_file_from_eventstream_watches() {
 
 #[cfg(feature = "stream")]
 #[tokio::test]
-async fn it_should_watch_a_file_after_converting_back_from_eventstream() {
+async fn it_should_watch_a_file_with_an_internal_stream_buffer() {
     let mut testdir = TestDir::new();
     let (path, mut file) = testdir.new_file();
 
     let inotify = Inotify::init().unwrap();
+    let watch = inotify.watches().add(&path, WatchMask::MODIFY).unwrap();
 
-    let mut buffer = [0; 1024];
-    let stream = inotify.into_event_stream(&mut buffer[..]).unwrap();
-    let mut inotify = stream.into_inotify();
+    let mut stream = inotify.into_event_stream_with_capacity(0).unwrap();
+    write_to(&mut file);
 
+    let event = stream.next().await.unwrap().unwrap();
+    assert_eq!(watch, event.wd);
+    assert!(!futures_util::stream::FusedStream::is_terminated(&stream));
+}
+
+#[cfg(feature = "stream")]
+#[tokio::test]
+async fn it_should_borrow_events_from_the_stream_buffer() {
+    let mut testdir = TestDir::new();
+    let (path, mut file) = testdir.new_file();
+
+    let inotify = Inotify::init().unwrap();
     let watch = inotify.watches().add(&path, WatchMask::MODIFY).unwrap();
 
+    let mut stream = inotify.into_event_stream_with_capacity(0).unwrap();
     write_to(&mut file);
 
-    let events = inotify.read_events_blocking(&mut buffer).unwrap();
-
-    let mut num_events = 0;
-    for event in events {
-        assert_eq!(watch, event.wd);
-        num_events += 1;
-    }
-    assert!(num_events > 0);
+    let event = stream.next_borrowed().await.unwrap().unwrap();
+    assert_eq!(watch, event.wd);
+    assert!(event.mask.contains(EventMask::MODIFY));
 }
 
-#[test]
-fn it_should_return_immediately_if_no_events_are_available() {
-    let mut inotify = Inotify::init().unwrap();
+#[cfg(feature = "stream")]
+#[tokio::test]
+async fn it_should_read_a_batch_of_events_from_the_stream() {
+    let mut testdir = TestDir::new();
+    let (path_1, mut file_1) = testdir.new_file();
+    let (path_2, mut file_2) = testdir.new_file();
 
-    let mut buffer = [0; 1024];
-    assert_eq!(
-        inotify.read_events(&mut buffer).unwrap_err().kind(),
-        ErrorKind::WouldBlock
-    );
+    let inotify = Inotify::init().unwrap();
+    let watch_1 = inotify.watches().add(&path_1, WatchMask::MODIFY).unwrap();
+    let watch_2 = inotify.watches().add(&path_2, WatchMask::MODIFY).unwrap();
+
+    let mut stream = inotify.into_event_stream_with_capacity(0).unwrap();
+    write_to(&mut file_1);
+    write_to(&mut file_2);
+
+    let events = stream.read_batch().await.unwrap();
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0].wd, watch_1);
+    assert_eq!(events[1].wd, watch_2);
 }
 
-#[test]
-fn it_should_convert_the_name_into_an_os_str() {
-    let mut testdir = TestDir::new();
-    let (path, mut file) = testdir.new_file();
+#[cfg(feature = "stream")]
+#[tokio::test]
+async fn it_should_coalesce_the_events_of_a_read() {
+    let testdir = TestDir::new();
 
-    let mut inotify = Inotify::init().unwrap();
+    let inotify = Inotify::init().unwrap();
     inotify
         .watches()
-        .add(path.parent().unwrap(), WatchMask::MODIFY)
+        .add(testdir.dir.path(), WatchMask::ALL_EVENTS)
         .unwrap();
 
+    let mut stream = inotify.into_event_stream_with_capacity(4096).unwrap();
+    let mut file = File::create(testdir.dir.path().join("file")).unwrap();
     write_to(&mut file);
+    drop(file);
 
-    let mut buffer = [0; 1024];
-    let mut events = inotify.read_events_blocking(&mut buffer).unwrap();
+    let events = stream.read_coalesced().await.unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].change, Change::Created);
+    assert!(events[0].mask.contains(EventMask::CLOSE_WRITE));
+}
 
-    if let Some(event) = events.next() {
-        assert_eq!(path.file_name(), event.name);
-    } else {
-        panic!("Expected inotify event");
+#[cfg(feature = "stream")]
+#[tokio::test]
+async fn it_should_filter_events_in_the_stream() {
+    let testdir = TestDir::new();
+
+    let inotify = Inotify::init().unwrap();
+    inotify
+        .watches()
+        .add(testdir.dir.path(), WatchMask::ALL_EVENTS)
+        .unwrap();
+
+    let mut stream = inotify
+        .into_event_stream_with_capacity(4096)
+        .unwrap()
+        .filter_events(EventMask::CLOSE_WRITE)
+        .filter_names(|name| name != "ignored");
+    for name in ["ignored", "kept"] {
+        let mut file = File::create(testdir.dir.path().join(name)).unwrap();
+        write_to(&mut file);
     }
+
+    let event = stream.next().await.unwrap().unwrap();
+    assert_eq!(event.mask, EventMask::CLOSE_WRITE);
+    assert_eq!(event.name.as_deref(), Some("kept".as_ref()));
 }
 
-#[test]
-fn it_should_set_name_to_none_if_it_is_empty() {
+#[cfg(feature = "stream")]
+#[tokio::test]
+async fn it_should_broadcast_events_to_subscribers() {
+    use inotify::Broadcaster;
+
     let mut testdir = TestDir::new();
-    let (path, mut file) = testdir.new_file();
+    let (path_1, mut file_1) = testdir.new_file();
+    let (path_2, mut file_2) = testdir.new_file();
 
-    let mut inotify = Inotify::init().unwrap();
-    inotify.watches().add(&path, WatchMask::MODIFY).unwrap();
+    let inotify = Inotify::init().unwrap();
+    let watch_1 = inotify.watches().add(&path_1, WatchMask::MODIFY).unwrap();
+    let watch_2 = inotify.watches().add(&path_2, WatchMask::MODIFY).unwrap();
+
+    let stream = inotify.into_event_stream_with_capacity(0).unwrap();
+    let close = stream.close_handle();
+    let broadcaster = Broadcaster::new(stream, 16);
+    let mut all = broadcaster.subscribe();
+    let mut only_2 = broadcaster.handle().subscribe_to(watch_2.clone());
+    let task = tokio::spawn(broadcaster.run());
+
+    write_to(&mut file_1);
+    write_to(&mut file_2);
+
+    assert_eq!(all.recv().await.unwrap().wd, watch_1);
+    assert_eq!(all.recv().await.unwrap().wd, watch_2);
+    assert_eq!(only_2.next().await.unwrap().wd, watch_2);
+
+    close.close();
+    task.await.unwrap().unwrap();
+    assert!(all.recv().await.is_none());
+    assert!(only_2.recv().await.is_none());
+}
 
-    write_to(&mut file);
+#[cfg(feature = "stream")]
+#[tokio::test]
+async fn it_should_split_the_stream_by_watch() {
+    let mut testdir = TestDir::new();
+    let (path_1, mut file_1) = testdir.new_file();
+    let (path_2, mut file_2) = testdir.new_file();
+    let (path_3, mut file_3) = testdir.new_file();
 
-    let mut buffer = [0; 1024];
-    let mut events = inotify.read_events_blocking(&mut buffer).unwrap();
+    let inotify = Inotify::init().unwrap();
+    let watch_1 = inotify.watches().add(&path_1, WatchMask::MODIFY).unwrap();
+    let watch_2 = inotify.watches().add(&path_2, WatchMask::MODIFY).unwrap();
+    let watch_3 = inotify.watches().add(&path_3, WatchMask::MODIFY).unwrap();
 
-    if let Some(event) = events.next() {
-        assert_eq!(event.name, None);
-    } else {
-        panic!("Expected inotify event");
-    }
+    let router = inotify
+        .into_event_stream_with_capacity(0)
+        .unwrap()
+        .split_by_watch();
+    let mut stream_2 = router.watch(&watch_2);
+    let mut rest = router.rest();
+
+    write_to(&mut file_1);
+    write_to(&mut file_2);
+    write_to(&mut file_3);
+
+    // Reading the second watch's stream routes the first event to the rest.
+    assert_eq!(stream_2.next().await.unwrap().unwrap().wd, watch_2);
+    assert_eq!(rest.next().await.unwrap().unwrap().wd, watch_1);
+    assert_eq!(rest.next().await.unwrap().unwrap().wd, watch_3);
 }
 
-#[test]
-fn it_should_not_accept_watchdescriptors_from_other_instances() {
+#[cfg(feature = "stream")]
+#[tokio::test]
+async fn it_should_yield_to_the_runtime_after_the_configured_number_of_events() {
+    use std::task::Poll;
+
     let mut testdir = TestDir::new();
-    let (path, _) = testdir.new_file();
+    let (path_1, mut file_1) = testdir.new_file();
+    let (path_2, mut file_2) = testdir.new_file();
 
     let inotify = Inotify::init().unwrap();
-    let _ = inotify.watches().add(&path, WatchMask::ACCESS).unwrap();
+    let watch_1 = inotify.watches().add(&path_1, WatchMask::MODIFY).unwrap();
+    let watch_2 = inotify.watches().add(&path_2, WatchMask::MODIFY).unwrap();
 
-    let second_inotify = Inotify::init().unwrap();
-    let wd2 = second_inotify
-        .watches()
-        .add(&path, WatchMask::ACCESS)
-        .unwrap();
+    let mut stream = inotify.into_event_stream_with_capacity(0).unwrap();
+    stream.set_max_events_per_poll(1);
+    write_to(&mut file_1);
+    write_to(&mut file_2);
 
-    assert_eq!(
-        inotify.watches().remove(wd2).unwrap_err().kind(),
-        ErrorKind::InvalidInput
-    );
+    assert_eq!(stream.next().await.unwrap().unwrap().wd, watch_1);
+
+    let poll = futures_util::poll!(stream.next());
+    assert!(poll.is_pending());
+
+    let event = match futures_util::poll!(stream.next()) {
+        Poll::Ready(event) => event.unwrap().unwrap(),
+        Poll::Pending => panic!("Stream should be ready after yielding"),
+    };
+    assert_eq!(event.wd, watch_2);
 }
 
-#[test]
-fn watch_descriptors_from_different_inotify_instances_should_not_be_equal() {
+#[cfg(feature = "stream")]
+#[tokio::test]
+async fn it_should_drain_available_events_when_woken_up() {
     let mut testdir = TestDir::new();
-    let (path, _) = testdir.new_file();
+    let (path_1, mut file_1) = testdir.new_file();
+    let (path_2, mut file_2) = testdir.new_file();
 
-    let inotify_1 = Inotify::init().unwrap();
-    let inotify_2 = Inotify::init().unwrap();
+    let inotify = Inotify::init().unwrap();
+    let watch_1 = inotify.watches().add(&path_1, WatchMask::MODIFY).unwrap();
+    let watch_2 = inotify.watches().add(&path_2, WatchMask::MODIFY).unwrap();
 
-    let wd_1 = inotify_1.watches().add(&path, WatchMask::ACCESS).unwrap();
-    let wd_2 = inotify_2.watches().add(&path, WatchMask::ACCESS).unwrap();
+    let mut stream = inotify.into_event_stream(vec![0; 4096]).unwrap();
+    stream.set_drain_reads(true);
+    write_to(&mut file_1);
+    write_to(&mut file_2);
 
-    // As far as inotify is concerned, watch descriptors are just integers that
-    // are scoped per inotify instance. This means that multiple instances will
-    // produce the same watch descriptor number, a case we want inotify-rs to
-    // detect.
-    assert!(wd_1 != wd_2);
+    let events = stream.read_batch().await.unwrap();
+    let wds = events.into_iter().map(|event| event.wd).collect::<Vec<_>>();
+    assert_eq!(wds, [watch_1, watch_2]);
 }
 
-#[test]
-fn watch_descriptor_equality_should_not_be_confused_by_reused_fds() {
+#[cfg(feature = "stream")]
+#[tokio::test]
+async fn it_should_read_batches_that_share_the_read_buffer() {
+    let testdir = TestDir::new();
+
+    let inotify = Inotify::init().unwrap();
+    inotify
+        .watches()
+        .add(testdir.dir.path(), WatchMask::CREATE)
+        .unwrap();
+    let mut stream = inotify.into_event_stream(vec![0; 4096]).unwrap();
+    File::create(testdir.dir.path().join("file-a")).unwrap();
+    File::create(testdir.dir.path().join("file-b")).unwrap();
+
+    let events = stream.read_batch_buffered().await.unwrap();
+    let names = events
+        .iter()
+        .map(|event| event.name.clone().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["file-a", "file-b"]);
+    assert_eq!(std::ffi::OsString::from(names[1].clone()), "file-b");
+}
+
+#[cfg(feature = "stream")]
+#[tokio::test]
+async fn it_should_time_out_waiting_for_the_next_event() {
     let mut testdir = TestDir::new();
-    let (path, _) = testdir.new_file();
+    let (path, mut file) = testdir.new_file();
 
-    // When a new inotify instance is created directly after closing another
-    // one, it is possible that the file descriptor is reused immediately, and
-    // we end up with a new instance that has the same file descriptor as the
-    // old one.
-    // This is quite likely, but it doesn't happen every time. Therefore we may
-    // need a few tries until we find two instances where that is the case.
-    let (wd_1, inotify_2) = loop {
-        let inotify_1 = Inotify::init().unwrap();
+    let inotify = Inotify::init().unwrap();
+    let watch = inotify.watches().add(&path, WatchMask::MODIFY).unwrap();
 
-        let wd_1 = inotify_1.watches().add(&path, WatchMask::ACCESS).unwrap();
-        let fd_1 = inotify_1.as_raw_fd();
+    let mut stream = inotify.into_event_stream_with_capacity(0).unwrap();
+    let timeout = Duration::from_millis(50);
+    assert!(stream.next_timeout(timeout).await.unwrap().is_none());
 
-        inotify_1.close().unwrap();
-        let inotify_2 = Inotify::init().unwrap();
+    write_to(&mut file);
+    let event = stream.next_timeout(timeout).await.unwrap().unwrap();
+    assert_eq!(event.wd, watch);
+}
 
-        if fd_1 == inotify_2.as_raw_fd() {
-            break (wd_1, inotify_2);
-        }
-    };
+#[cfg(feature = "stream")]
+#[tokio::test]
+async fn it_should_end_the_stream_when_closed() {
+    let mut testdir = TestDir::new();
+    let (path, _) = testdir.new_file();
 
-    let wd_2 = inotify_2.watches().add(&path, WatchMask::ACCESS).unwrap();
+    let inotify = Inotify::init().unwrap();
+    inotify.watches().add(&path, WatchMask::MODIFY).unwrap();
 
-    // The way we engineered this situation, both `WatchDescriptor` instances
-    // have the same fields. They still come from different inotify instances
-    // though, so they shouldn't be equal.
-    assert!(wd_1 != wd_2);
+    let mut stream = inotify.into_event_stream_with_capacity(0).unwrap();
+    let handle = stream.close_handle();
 
-    inotify_2.close().unwrap();
+    let task = tokio::spawn(async move { stream.next().await.is_none() });
+    tokio::task::yield_now().await;
+    handle.close();
 
-    // A little extra gotcha: If both inotify instances are closed, and the `Eq`
-    // implementation naively compares the weak pointers, both will be `None`,
-    // making them equal. Let's make sure this isn't the case.
-    assert!(wd_1 != wd_2);
+    assert!(handle.is_closed());
+    assert!(task.await.unwrap());
 }
 
-#[test]
-fn it_should_implement_raw_fd_traits_correctly() {
-    let fd = Inotify::init()
-        .expect("Failed to initialize inotify instance")
-        .into_raw_fd();
+#[cfg(feature = "stream")]
+#[tokio::test]
+async fn it_should_debounce_bursts_of_events() {
+    let mut testdir = TestDir::new();
+    let (path, mut file) = testdir.new_file();
 
-    // If `IntoRawFd` has been implemented naively, `Inotify`'s `Drop`
-    // implementation will have closed the inotify instance at this point. Let's
-    // make sure this didn't happen.
-    let mut inotify = unsafe { <Inotify as FromRawFd>::from_raw_fd(fd) };
+    let inotify = Inotify::init().unwrap();
+    let watch = inotify.watches().add(&path, WatchMask::MODIFY).unwrap();
 
-    let mut buffer = [0; 1024];
-    if let Err(error) = inotify.read_events(&mut buffer) {
-        if error.kind() != ErrorKind::WouldBlock {
-            panic!("Failed to add watch: {}", error);
-        }
+    let mut stream = inotify
+        .into_event_stream_with_capacity(0)
+        .unwrap()
+        .debounce(Duration::from_millis(50));
+    for _ in 0..5 {
+        write_to(&mut file);
     }
+
+    let event = stream.next().await.unwrap().unwrap();
+    assert_eq!(watch, event.wd);
+
+    let next = tokio::time::timeout(Duration::from_millis(200), stream.next()).await;
+    assert!(next.is_err());
 }
 
-#[test]
-fn it_should_watch_correctly_with_a_watches_clone() {
+#[cfg(feature = "stream")]
+#[tokio::test]
+async fn it_should_batch_bursts_of_events() {
     let mut testdir = TestDir::new();
-    let (path, mut file) = testdir.new_file();
-
-    let mut inotify = Inotify::init().unwrap();
-    let mut watches1 = inotify.watches();
-    let mut watches2 = watches1.clone();
-    let watch1 = watches1.add(&path, WatchMask::MODIFY).unwrap();
-    let watch2 = watches2.add(&path, WatchMask::MODIFY).unwrap();
 
-    // same path and same Inotify should return same descriptor
-    assert_eq!(watch1, watch2);
+    let inotify = Inotify::init().unwrap();
+    inotify
+        .watches()
+        .add(testdir.dir.path(), WatchMask::CREATE)
+        .unwrap();
 
-    write_to(&mut file);
+    let mut stream = inotify
+        .into_event_stream_with_capacity(0)
+        .unwrap()
+        .batch(Duration::from_millis(50), Duration::from_secs(1));
+    for _ in 0..5 {
+        testdir.new_file();
+    }
 
-    let mut buffer = [0; 1024];
-    let events = inotify.read_events_blocking(&mut buffer).unwrap();
+    let batch = stream.next().await.unwrap().unwrap();
+    assert_eq!(batch.len(), 5);
 
-    let mut num_events = 0;
-    for event in events {
-        assert_eq!(watch2, event.wd);
-        num_events += 1;
-    }
-    assert!(num_events > 0);
+    let next = tokio::time::timeout(Duration::from_millis(200), stream.next()).await;
+    assert!(next.is_err());
 }
 
 #[cfg(feature = "stream")]
 #[tokio::test]
-/// Testing if two files with the same name but different directories
-/// (e.g. "file_a" and "another_dir/file_a") are distinguished when _randomly_
-/// triggering a DELETE_SELF for the two files.
-async fn it_should_distinguish_event_for_files_with_same_name() {
+async fn it_should_return_the_events_of_a_single_file() {
+    use inotify::{watch_file, EventAuxiliaryFlags, EventKind};
+
     let mut testdir = TestDir::new();
-    let testdir_path = testdir.dir.path().to_owned();
-    let file_order = Arc::new(Mutex::new(vec!["file_a", "another_dir/file_a"]));
-    file_order.lock().unwrap().shuffle(&mut thread_rng());
-    let file_order_clone = file_order.clone();
+    let (path, mut file) = testdir.new_file();
 
-    let inotify = Inotify::init().expect("Failed to initialize inotify instance");
+    let mut events = watch_file(&path, WatchMask::MODIFY | WatchMask::DELETE_SELF).unwrap();
 
-    // creating file_a inside `TestDir.dir`
-    let (path_1, _) = testdir.new_file_with_name("file_a");
-    // creating a directory inside `TestDir.dir`
-    testdir.new_directory_with_name("another_dir");
-    // creating a file inside `TestDir.dir/another_dir`
-    let (path_2, _) = testdir.new_file_in_directory_with_name("another_dir", "file_a");
+    write_to(&mut file);
+    let mask = events.next().await.unwrap().unwrap();
+    assert_eq!(mask.kind, Some(EventKind::Modify));
+
+    drop(file);
+    std::fs::remove_file(&path).unwrap();
+    let mask = events.next().await.unwrap().unwrap();
+    assert_eq!(mask.kind, Some(EventKind::DeleteSelf));
+    let mask = events.next().await.unwrap().unwrap();
+    assert!(mask.auxiliary_flags.contains(EventAuxiliaryFlags::IGNORED));
+    assert!(events.next().await.is_none());
+}
 
-    // watching both files for `DELETE_SELF`
-    let wd_1 = inotify
-        .watches()
-        .add(&path_1, WatchMask::DELETE_SELF)
-        .unwrap();
-    let wd_2 = inotify
+#[cfg(feature = "stream")]
+#[tokio::test]
+async fn it_should_wait_for_the_first_matching_event() {
+    let mut testdir = TestDir::new();
+
+    let inotify = Inotify::init().unwrap();
+    let created = inotify
         .watches()
-        .add(&path_2, WatchMask::DELETE_SELF)
+        .wait_for(testdir.dir.path(), WatchMask::CREATE)
         .unwrap();
 
-    let expected_ids = hashmap! {
-        wd_1.get_watch_descriptor_id() => "file_a",
-        wd_2.get_watch_descriptor_id() => "another_dir/file_a"
-    };
-    let mut buffer = [0; 1024];
+    let mut stream = inotify.into_event_stream([0; 1024]).unwrap();
+    tokio::spawn(async move { while stream.next().await.is_some() {} });
 
-    let file_removal_handler = tokio::spawn(async move {
-        for file in file_order.lock().unwrap().iter() {
+    let (path, _) = testdir.new_file();
+    let event = created.await.unwrap();
+    assert_eq!(event.mask, EventMask::CREATE);
+    assert_eq!(event.name.as_deref(), path.file_name());
+}
+
+#[cfg(feature = "stream")]
+#[tokio::test]
+async fn waiting_for_an_event_should_fail_if_the_watch_is_removed() {
+    let mut testdir = TestDir::new();
+    let (path, _) = testdir.new_file();
+
+    let inotify = Inotify::init().unwrap();
+    let modified = inotify
+        .watches()
+        .wait_for(&path, WatchMask::MODIFY)
+        .unwrap();
+
+    let mut stream = inotify.into_event_stream([0; 1024]).unwrap();
+    tokio::spawn(async move { while stream.next().await.is_some() {} });
+
+    std::fs::remove_file(&path).unwrap();
+    let error = modified.await.unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::NotFound);
+}
+
+#[cfg(feature = "async-io")]
+#[test]
+fn it_should_watch_a_file_with_the_async_io_driver() {
+    use futures_util::StreamExt;
+    use inotify::AsyncIoDriver;
+
+    let mut testdir = TestDir::new();
+    let (path, mut file) = testdir.new_file();
+
+    let inotify = Inotify::init().unwrap();
+    let watch = inotify.watches().add(&path, WatchMask::MODIFY).unwrap();
+
+    let mut buffer = [0; 1024];
+    let mut stream = inotify
+        .into_event_stream_with_driver::<AsyncIoDriver, _>(&mut buffer)
+        .unwrap();
+    write_to(&mut file);
+
+    let event = async_io::block_on(stream.next()).unwrap().unwrap();
+    assert_eq!(watch, event.wd);
+}
+
+#[cfg(feature = "stream")]
+#[tokio::test]
+async fn it_should_watch_a_file_after_converting_back_from_eventstream() {
+    let mut testdir = TestDir::new();
+    let (path, mut file) = testdir.new_file();
+
+    let inotify = Inotify::init().unwrap();
+
+    let mut buffer = [0; 1024];
+    let stream = inotify.into_event_stream(&mut buffer[..]).unwrap();
+    let inotify = stream.into_inotify();
+
+    let watch = inotify.watches().add(&path, WatchMask::MODIFY).unwrap();
+
+    write_to(&mut file);
+
+    let events = inotify.read_events_blocking(&mut buffer).unwrap();
+
+    let mut num_events = 0;
+    for event in events {
+        assert_eq!(watch, event.wd);
+        num_events += 1;
+    }
+    assert!(num_events > 0);
+}
+
+#[test]
+fn it_should_watch_a_file_with_a_blocking_iterator() {
+    let mut testdir = TestDir::new();
+    let (path, mut file) = testdir.new_file();
+
+    let inotify = Inotify::init().unwrap();
+    let watch = inotify.watches().add(&path, WatchMask::MODIFY).unwrap();
+
+    write_to(&mut file);
+
+    let mut events = inotify.into_blocking_iter([0; 1024]);
+    let event = events.next().unwrap().unwrap();
+    assert_eq!(watch, event.wd);
+
+    // Converting back must not close the file descriptor.
+    let inotify = events.into_inotify();
+    let mut buffer = [0; 1024];
+    assert_eq!(
+        inotify.read_events(&mut buffer).unwrap_err().kind(),
+        ErrorKind::WouldBlock
+    );
+}
+
+#[test]
+fn it_should_dispatch_events_over_a_channel() {
+    let mut testdir = TestDir::new();
+    let (path, mut file) = testdir.new_file();
+
+    let inotify = Inotify::init().unwrap();
+    let (events, dispatcher) = inotify.spawn_dispatcher().unwrap();
+
+    // Watches can still be added after the instance has been moved into the
+    // dispatcher.
+    let watch = dispatcher.watches().add(&path, WatchMask::MODIFY).unwrap();
+
+    write_to(&mut file);
+
+    let event = events.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert_eq!(watch, event.wd);
+
+    dispatcher.stop().unwrap();
+    assert!(events.recv().is_err());
+}
+
+#[cfg(feature = "crossbeam")]
+#[test]
+fn it_should_drop_the_oldest_event_if_the_crossbeam_channel_is_full() {
+    use inotify::FullChannelPolicy;
+
+    let mut testdir = TestDir::new();
+    let (path_1, _) = testdir.new_file();
+    let (path_2, _) = testdir.new_file();
+
+    let inotify = Inotify::init().unwrap();
+    let (events, dispatcher) = inotify
+        .spawn_crossbeam_dispatcher(1, FullChannelPolicy::DropOldest)
+        .unwrap();
+
+    let watches = dispatcher.watches();
+    watches
+        .clone()
+        .add(&path_1, WatchMask::DELETE_SELF)
+        .unwrap();
+    let watch_2 = watches
+        .clone()
+        .add(&path_2, WatchMask::DELETE_SELF)
+        .unwrap();
+
+    std::fs::remove_file(&path_1).unwrap();
+    std::fs::remove_file(&path_2).unwrap();
+
+    // Removing the files results in `DELETE_SELF` and `IGNORED` events for
+    // both. Only the last one fits into the channel.
+    let mut last = None;
+    while let Ok(event) = events.recv_timeout(Duration::from_millis(500)) {
+        last = Some(event);
+    }
+    let last = last.unwrap();
+    assert_eq!(watch_2, last.wd);
+    assert!(last.mask.contains(EventMask::IGNORED));
+
+    dispatcher.stop().unwrap();
+}
+
+#[cfg(feature = "crossbeam")]
+#[test]
+fn it_should_stop_a_crossbeam_dispatcher_that_waits_for_room() {
+    use inotify::FullChannelPolicy;
+
+    let mut testdir = TestDir::new();
+    let (path, _) = testdir.new_file();
+
+    let inotify = Inotify::init().unwrap();
+    let (events, dispatcher) = inotify
+        .spawn_crossbeam_dispatcher(1, FullChannelPolicy::Block)
+        .unwrap();
+    dispatcher
+        .watches()
+        .add(&path, WatchMask::DELETE_SELF)
+        .unwrap();
+
+    // `DELETE_SELF` fills the channel, so the dispatcher waits with `IGNORED`.
+    std::fs::remove_file(&path).unwrap();
+    while !events.is_full() {
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    dispatcher.stop().unwrap();
+    assert_eq!(events.len(), 1);
+}
+
+#[cfg(feature = "crossbeam")]
+#[test]
+fn it_should_not_spawn_a_crossbeam_dispatcher_without_capacity() {
+    use inotify::FullChannelPolicy;
+
+    let inotify = Inotify::init().unwrap();
+    let error = inotify
+        .spawn_crossbeam_dispatcher(0, FullChannelPolicy::DropOldest)
+        .unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidInput);
+}
+
+#[test]
+fn it_should_read_events_from_all_instances_in_a_set() {
+    let mut testdir = TestDir::new();
+    let (path_1, mut file_1) = testdir.new_file();
+    let (path_2, mut file_2) = testdir.new_file();
+
+    let inotify_1 = Inotify::init().unwrap();
+    let inotify_2 = Inotify::init().unwrap();
+    let watch_1 = inotify_1.watches().add(&path_1, WatchMask::MODIFY).unwrap();
+    let watch_2 = inotify_2.watches().add(&path_2, WatchMask::MODIFY).unwrap();
+
+    let mut set = InotifySet::new().unwrap();
+    let key_1 = set.insert(inotify_1).unwrap();
+    let key_2 = set.insert(inotify_2).unwrap();
+
+    let mut buffer = [0; 1024];
+    assert_eq!(
+        set.read_events(&mut buffer).unwrap_err().kind(),
+        ErrorKind::WouldBlock
+    );
+
+    write_to(&mut file_1);
+    write_to(&mut file_2);
+
+    let mut seen = Vec::new();
+    while seen.len() < 2 {
+        for (key, event) in set.read_events_blocking(&mut buffer).unwrap() {
+            seen.push((key, event.wd));
+        }
+    }
+    assert!(seen.contains(&(key_1, watch_1)));
+    assert!(seen.contains(&(key_2, watch_2)));
+
+    assert!(set.remove(key_1).is_some());
+    assert_eq!(set.len(), 1);
+}
+
+#[test]
+fn it_should_be_reported_as_readable_by_epoll() {
+    use std::os::unix::io::OwnedFd;
+
+    let mut testdir = TestDir::new();
+    let (path, mut file) = testdir.new_file();
+
+    let inotify = Inotify::init().unwrap();
+    inotify.watches().add(&path, WatchMask::MODIFY).unwrap();
+
+    let epfd = unsafe { libc::epoll_create1(libc::EPOLL_CLOEXEC) };
+    assert!(epfd >= 0);
+    let epfd = unsafe { OwnedFd::from_raw_fd(epfd) };
+    inotify.register_epoll(&epfd, 42).unwrap();
+
+    write_to(&mut file);
+
+    let mut events = [libc::epoll_event { events: 0, u64: 0 }; 1];
+    let num_events = unsafe { libc::epoll_wait(epfd.as_raw_fd(), events.as_mut_ptr(), 1, 5000) };
+    assert_eq!(num_events, 1);
+    let token = events[0].u64;
+    assert_eq!(token, 42);
+
+    inotify.deregister_epoll(&epfd).unwrap();
+    let num_events = unsafe { libc::epoll_wait(epfd.as_raw_fd(), events.as_mut_ptr(), 1, 0) };
+    assert_eq!(num_events, 0);
+}
+
+#[test]
+fn it_should_control_the_close_on_exec_flag() {
+    let inotify = Inotify::init().unwrap();
+    assert!(inotify.is_cloexec().unwrap());
+
+    inotify.set_cloexec(false).unwrap();
+    assert!(!inotify.is_cloexec().unwrap());
+
+    inotify.set_cloexec(true).unwrap();
+    assert!(inotify.is_cloexec().unwrap());
+
+    let fd = inotify.into_inheritable_fd().unwrap();
+    let flags = unsafe { libc::fcntl(fd.as_raw_fd(), libc::F_GETFD) };
+    assert_eq!(flags & libc::FD_CLOEXEC, 0);
+
+    // The file descriptor must still refer to a working inotify instance.
+    let inotify = Inotify::from(fd);
+    let mut buffer = [0; 1024];
+    assert_eq!(
+        inotify.read_events(&mut buffer).unwrap_err().kind(),
+        ErrorKind::WouldBlock
+    );
+}
+
+#[cfg(feature = "timestamps")]
+#[test]
+fn it_should_stamp_events_with_the_time_they_were_read() {
+    let mut testdir = TestDir::new();
+    let (path, mut file) = testdir.new_file();
+
+    let inotify = Inotify::init().unwrap();
+    inotify.watches().add(&path, WatchMask::MODIFY).unwrap();
+
+    let before = std::time::Instant::now();
+    write_to(&mut file);
+
+    let mut buffer = [0; 1024];
+    let events = inotify.read_events_blocking(&mut buffer).unwrap();
+    let after = std::time::Instant::now();
+
+    let mut num_events = 0;
+    for event in events {
+        assert!(before <= event.received_at());
+        assert!(event.received_at() <= after);
+        num_events += 1;
+    }
+    assert!(num_events > 0);
+}
+
+#[test]
+fn it_should_parse_events_read_by_the_caller() {
+    let mut testdir = TestDir::new();
+    let (path, mut file) = testdir.new_file();
+
+    let inotify = Inotify::init().unwrap();
+    let watch = inotify.watches().add(&path, WatchMask::MODIFY).unwrap();
+
+    write_to(&mut file);
+
+    let mut fd = File::from(inotify.as_fd().try_clone_to_owned().unwrap());
+    let mut buffer = [0; 1024];
+    let num_bytes = fd.read(&mut buffer).unwrap();
+
+    let mut num_events = 0;
+    for event in Events::from_bytes(&buffer[..num_bytes]).checked() {
+        assert_eq!(event.unwrap().mask, EventMask::MODIFY);
+        num_events += 1;
+    }
+    assert!(num_events > 0);
+
+    let events = inotify.events_from_bytes(&buffer[..num_bytes]);
+    assert_eq!(events.len(), num_events);
+
+    for event in events {
+        assert_eq!(watch, event.wd);
+    }
+}
+
+#[cfg(feature = "codec")]
+#[tokio::test]
+async fn it_should_decode_events_with_the_codec() {
+    use futures_util::StreamExt;
+    use tokio_util::codec::FramedRead;
+
+    let mut testdir = TestDir::new();
+    let (path_1, mut file_1) = testdir.new_file();
+    let (path_2, mut file_2) = testdir.new_file();
+
+    let inotify = Inotify::init().unwrap();
+    let watch_1 = inotify.watches().add(&path_1, WatchMask::MODIFY).unwrap();
+    let watch_2 = inotify.watches().add(&path_2, WatchMask::MODIFY).unwrap();
+
+    write_to(&mut file_1);
+    write_to(&mut file_2);
+
+    let mut fd = File::from(inotify.as_fd().try_clone_to_owned().unwrap());
+    let mut buffer = [0; 1024];
+    let num_bytes = fd.read(&mut buffer).unwrap();
+
+    let events = FramedRead::with_capacity(&buffer[..num_bytes], inotify.codec(), 7)
+        .collect::<Vec<_>>()
+        .await;
+
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0].as_ref().unwrap().wd, watch_1);
+    assert_eq!(events[1].as_ref().unwrap().wd, watch_2);
+}
+
+#[cfg(feature = "log")]
+#[test]
+fn it_should_log_removed_watches_once_per_read() {
+    use std::{
+        sync::Mutex,
+        thread::{self, ThreadId},
+    };
+
+    struct TestLogger(Mutex<Vec<(ThreadId, String)>>);
+
+    impl log::Log for TestLogger {
+        fn enabled(&self, _: &log::Metadata) -> bool {
+            true
+        }
+
+        fn log(&self, record: &log::Record) {
+            self.0
+                .lock()
+                .unwrap()
+                .push((thread::current().id(), record.args().to_string()));
+        }
+
+        fn flush(&self) {}
+    }
+
+    static LOGGER: TestLogger = TestLogger(Mutex::new(Vec::new()));
+    log::set_logger(&LOGGER).unwrap();
+    log::set_max_level(log::LevelFilter::Debug);
+
+    // Other tests run in parallel, so only count what this thread logged.
+    let logged = |message: &str| {
+        LOGGER
+            .0
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(thread, logged)| *thread == thread::current().id() && logged == message)
+            .count()
+    };
+
+    let mut testdir = TestDir::new();
+    let (path, _) = testdir.new_file();
+
+    let inotify = Inotify::init().unwrap();
+    let mut watches = inotify.watches();
+    let wd = watches.add(&path, WatchMask::MODIFY).unwrap();
+    let message = format!(
+        "inotify watch {} has been removed",
+        wd.get_watch_descriptor_id()
+    );
+    watches.remove(wd).unwrap();
+
+    let mut buffer = [0; 1024];
+    inotify.read_events_blocking(&mut buffer).unwrap().count();
+    assert_eq!(logged(&message), 1);
+
+    // Events that haven't just been read aren't logged. The event has no
+    // name, so it consists of the 16 bytes of its header.
+    Events::from_bytes(&buffer[..16]).count();
+    assert_eq!(logged(&message), 1);
+
+    let wd = watches.add(&path, WatchMask::MODIFY).unwrap();
+    let message = format!(
+        "inotify watch {} has been removed",
+        wd.get_watch_descriptor_id()
+    );
+    let before = logged(&message);
+    watches.remove(wd).unwrap();
+
+    inotify
+        .into_blocking_iter([0; 1024])
+        .next()
+        .unwrap()
+        .unwrap();
+    assert_eq!(logged(&message), before + 1);
+}
+
+#[test]
+fn it_should_detect_kernel_features() {
+    use inotify::{kernel_supports, Feature};
+
+    // Every kernel this crate is tested on supports these.
+    assert!(kernel_supports(Feature::MaskCreate).unwrap());
+    assert!(kernel_supports(Feature::ExclUnlink).unwrap());
+
+    match kernel_supports(Feature::FanotifyReportFid) {
+        Ok(supported) => assert!(supported),
+        // fanotify requires privileges that the tests might not have.
+        Err(error) if error.kind() == ErrorKind::PermissionDenied => {}
+        Err(error) => panic!("Failed to detect fanotify feature: {}", error),
+    }
+}
+
+#[test]
+fn it_should_read_the_inotify_limits() {
+    assert!(inotify::max_user_watches().unwrap() > 0);
+    assert!(inotify::max_user_instances().unwrap() > 0);
+    assert!(inotify::max_queued_events().unwrap() > 0);
+}
+
+#[test]
+fn it_should_return_typed_errors_when_adding_watches() {
+    let mut testdir = TestDir::new();
+    let (path, _) = testdir.new_file();
+    let missing = testdir.dir.path().join("missing");
+
+    let inotify = Inotify::init().unwrap();
+    let mut watches = inotify.watches();
+
+    match watches.try_add(&missing, WatchMask::MODIFY) {
+        Err(AddWatchError::PathNotFound { path }) => assert_eq!(path, missing),
+        result => panic!("Unexpected result: {:?}", result),
+    }
+    assert!(matches!(
+        watches.try_add(&path, WatchMask::MODIFY | WatchMask::ONLYDIR),
+        Err(AddWatchError::NotADirectory { .. })
+    ));
+    assert!(matches!(
+        watches.try_add(&path, WatchMask::empty()),
+        Err(AddWatchError::InvalidMask { .. })
+    ));
+    assert!(watches.try_add(&path, WatchMask::MODIFY).is_ok());
+
+    // Converting back results in the same kind of error as `add`.
+    let error: std::io::Error = watches
+        .try_add(&missing, WatchMask::MODIFY)
+        .unwrap_err()
+        .into();
+    assert_eq!(error.kind(), ErrorKind::NotFound);
+}
+
+#[test]
+fn it_should_monitor_the_fill_level_of_the_event_queue() {
+    let mut testdir = TestDir::new();
+
+    let inotify = Inotify::init().unwrap();
+    inotify
+        .watches()
+        .add(testdir.dir.path(), WatchMask::CREATE)
+        .unwrap();
+    let mut monitor = inotify.queue_monitor(0).unwrap();
+
+    assert_eq!(inotify.queued_bytes().unwrap(), 0);
+
+    testdir.new_file();
+    testdir.new_file();
+
+    let level = monitor.check().unwrap().unwrap();
+    assert!(level.events() >= 2);
+    assert_eq!(level.bytes, inotify.queued_bytes().unwrap());
+    assert_eq!(level.limit, inotify::max_queued_events().unwrap());
+
+    // A full queue is only reported once.
+    assert_eq!(monitor.check().unwrap(), None);
+}
+
+#[test]
+fn it_should_report_watch_usage() {
+    let mut testdir = TestDir::new();
+    let (path_a, _) = testdir.new_file();
+    let (path_b, _) = testdir.new_file();
+
+    let inotify = Inotify::init().unwrap();
+    let mut watches = inotify.watches();
+
+    let crossed = Arc::new(std::sync::Mutex::new(Vec::new()));
+    inotify
+        .set_usage_hook(0, {
+            let crossed = crossed.clone();
+            move |usage| crossed.lock().unwrap().push(usage.watches)
+        })
+        .unwrap();
+
+    let wd = watches.add(&path_a, WatchMask::MODIFY).unwrap();
+    watches.add(&path_a, WatchMask::ATTRIB).unwrap();
+    watches.add(&path_b, WatchMask::MODIFY).unwrap();
+
+    let usage = watches.usage().unwrap();
+    assert_eq!(usage.watches, 2);
+    assert_eq!(usage.limit, inotify::max_user_watches().unwrap());
+    assert!(inotify::user_watch_usage().unwrap().watches >= 2);
+
+    // Updating a watch doesn't count as adding one, and the hook is only
+    // called again after usage has dropped below the threshold.
+    assert_eq!(*crossed.lock().unwrap(), vec![1]);
+
+    watches.remove(wd).unwrap();
+    assert_eq!(watches.usage().unwrap().watches, 1);
+}
+
+#[test]
+fn it_should_read_events_into_a_fixed_size_buffer() {
+    const BUFFER_SIZE: usize = inotify::get_buffer_size_for_events(4);
+
+    let mut testdir = TestDir::new();
+    let (path, mut file) = testdir.new_file();
+
+    let inotify = Inotify::init().unwrap();
+    inotify.watches().add(&path, WatchMask::MODIFY).unwrap();
+
+    write_to(&mut file);
+
+    let mut buffer = FixedEventBuffer::<BUFFER_SIZE>::new();
+    assert_eq!(buffer.len(), BUFFER_SIZE);
+    assert_eq!(buffer.as_ptr() as usize % 4, 0);
+
+    let events = inotify.read_events_blocking(&mut buffer).unwrap();
+    assert_eq!(events.count(), 1);
+}
+
+#[test]
+fn it_should_return_events_that_share_the_read_buffer() {
+    let testdir = TestDir::new();
+
+    let inotify = Inotify::init().unwrap();
+    inotify
+        .watches()
+        .add(testdir.dir.path(), WatchMask::CREATE)
+        .unwrap();
+    File::create(testdir.dir.path().join("file-a")).unwrap();
+    File::create(testdir.dir.path().join("file-b")).unwrap();
+
+    let mut buffer = [0; 1024];
+    let events = inotify.read_events_blocking(&mut buffer).unwrap();
+    let events = events.into_buffered();
+    assert_eq!(events.len(), 2);
+
+    let names = events
+        .map(|event| event.name.unwrap())
+        .collect::<std::collections::HashSet<_>>();
+    assert!(names.contains(std::ffi::OsStr::new("file-a")));
+    assert!(names.contains(std::ffi::OsStr::new("file-b")));
+}
+
+#[test]
+fn it_should_append_events_to_a_vec() {
+    let mut testdir = TestDir::new();
+    let (path, mut file) = testdir.new_file();
+
+    let inotify = Inotify::init().unwrap();
+    let wd = inotify.watches().add(&path, WatchMask::MODIFY).unwrap();
+
+    let mut events = Vec::with_capacity(16);
+    let error = inotify.read_events_into(&mut events).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::WouldBlock);
+
+    write_to(&mut file);
+    assert_eq!(inotify.read_events_into(&mut events).unwrap(), 1);
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].wd, wd);
+    assert!(events[0].mask.contains(EventMask::MODIFY));
+
+    // The events are appended, without touching the ones already there.
+    write_to(&mut file);
+    assert_eq!(inotify.read_events_into(&mut events).unwrap(), 1);
+    assert_eq!(events.len(), 2);
+    assert_eq!(events.capacity(), 16);
+}
+
+#[test]
+fn it_should_drain_all_available_events() {
+    let mut testdir = TestDir::new();
+    let (path_1, mut file_1) = testdir.new_file();
+    let (path_2, mut file_2) = testdir.new_file();
+
+    let inotify = Inotify::init().unwrap();
+    inotify.watches().add(&path_1, WatchMask::MODIFY).unwrap();
+    inotify.watches().add(&path_2, WatchMask::MODIFY).unwrap();
+
+    let mut buffer = [0; 1024];
+    let error = inotify.drain_events(&mut buffer).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::WouldBlock);
+
+    write_to(&mut file_1);
+    write_to(&mut file_2);
+    assert_eq!(
+        inotify.drain_events_blocking(&mut buffer).unwrap().count(),
+        2
+    );
+
+    let error = inotify.read_events(&mut buffer).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::WouldBlock);
+}
+
+#[test]
+fn it_should_print_bookkept_watches_in_debug_output() {
+    let mut testdir = TestDir::new();
+    let (path, _) = testdir.new_file();
+
+    let inotify = Inotify::init().unwrap();
+    let fd = inotify.as_raw_fd();
+    assert_eq!(
+        format!("{:?}", inotify),
+        format!("Inotify {{ fd: {} }}", fd)
+    );
+
+    inotify.enable_bookkeeping();
+    let mut watches = inotify.watches();
+    let wd = watches.add(&path, WatchMask::MODIFY).unwrap();
+    let expected = format!(
+        "fd: {}, num_watches: 1, watches: {{{}: {} (MODIFY)}}",
+        fd,
+        wd.get_watch_descriptor_id(),
+        path.display()
+    );
+    assert_eq!(
+        format!("{:?}", inotify),
+        format!("Inotify {{ {} }}", expected)
+    );
+    assert_eq!(
+        format!("{:?}", watches),
+        format!("Watches {{ {} }}", expected)
+    );
+
+    // The kernel removes the watch, once the file is gone.
+    std::fs::remove_file(&path).unwrap();
+    let mut buffer = [0; 1024];
+    let mut events = inotify.read_events_blocking(&mut buffer).unwrap();
+    assert!(events.any(|event| event.mask.contains(EventMask::IGNORED)));
+    assert!(format!("{:?}", inotify).contains("num_watches: 0"));
+
+    inotify.disable_bookkeeping();
+    assert_eq!(
+        format!("{:?}", watches),
+        format!("Watches {{ fd: {} }}", fd)
+    );
+}
+
+#[test]
+fn it_should_size_buffers_for_the_file_system_of_a_path() {
+    let testdir = TestDir::new();
+
+    let size = inotify::get_buffer_size_for_path(testdir.dir.path()).unwrap();
+    assert!(size > 256);
+
+    let mut buffer = vec![0; size];
+    let inotify = Inotify::init().unwrap();
+    inotify
+        .watches()
+        .add(testdir.dir.path(), WatchMask::CREATE)
+        .unwrap();
+    File::create(testdir.dir.path().join("a".repeat(255))).unwrap();
+    assert_eq!(
+        inotify.read_events_blocking(&mut buffer).unwrap().count(),
+        1
+    );
+
+    assert_eq!(
+        inotify::get_buffer_size_for_events(4),
+        4 * inotify::get_buffer_size_for_events(1)
+    );
+}
+
+#[test]
+fn it_should_report_queue_overflows() {
+    let inotify = Inotify::init().unwrap();
+
+    let overflows = Arc::new(AtomicUsize::new(0));
+    inotify.set_overflow_hook({
+        let overflows = overflows.clone();
+        move || {
+            overflows.fetch_add(1, Ordering::SeqCst);
+        }
+    });
+
+    // This is what the kernel sends, if the queue overflows.
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(&(-1i32).to_ne_bytes());
+    bytes.extend_from_slice(&EventMask::Q_OVERFLOW.bits().to_ne_bytes());
+    bytes.extend_from_slice(&0u32.to_ne_bytes());
+    bytes.extend_from_slice(&0u32.to_ne_bytes());
+
+    let mut events = inotify.events_from_bytes(&bytes).typed();
+    assert_eq!(overflows.load(Ordering::SeqCst), 1);
+    assert!(matches!(events.next(), Some(Ok(TypedItem::QueueOverflow))));
+
+    inotify.clear_overflow_hook();
+    inotify.events_from_bytes(&bytes);
+    assert_eq!(overflows.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn it_should_add_watches_through_an_event() {
+    let testdir = TestDir::new();
+
+    let inotify = Inotify::init().unwrap();
+    inotify
+        .watches()
+        .add(testdir.dir.path(), WatchMask::CREATE)
+        .unwrap();
+
+    let subdir = testdir.dir.path().join("subdir");
+    std::fs::create_dir(&subdir).unwrap();
+
+    let mut buffer = [0; 1024];
+    let event = inotify
+        .read_events_blocking(&mut buffer)
+        .unwrap()
+        .next()
+        .unwrap()
+        .to_owned();
+    let watch = event
+        .watches()
+        .unwrap()
+        .add(&subdir, WatchMask::CREATE)
+        .unwrap();
+
+    File::create(subdir.join("file")).unwrap();
+    let event = inotify
+        .read_events_blocking(&mut buffer)
+        .unwrap()
+        .next()
+        .unwrap();
+    assert_eq!(event.wd, watch);
+
+    drop(inotify);
+    assert!(event.watches().is_none());
+}
+
+#[test]
+fn it_should_return_immediately_if_no_events_are_available() {
+    let inotify = Inotify::init().unwrap();
+
+    let mut buffer = [0; 1024];
+    assert_eq!(
+        inotify.read_events(&mut buffer).unwrap_err().kind(),
+        ErrorKind::WouldBlock
+    );
+}
+
+#[test]
+fn it_should_convert_the_name_into_an_os_str() {
+    let mut testdir = TestDir::new();
+    let (path, mut file) = testdir.new_file();
+
+    let inotify = Inotify::init().unwrap();
+    inotify
+        .watches()
+        .add(path.parent().unwrap(), WatchMask::MODIFY)
+        .unwrap();
+
+    write_to(&mut file);
+
+    let mut buffer = [0; 1024];
+    let mut events = inotify.read_events_blocking(&mut buffer).unwrap();
+
+    if let Some(event) = events.next() {
+        assert_eq!(path.file_name(), event.name);
+    } else {
+        panic!("Expected inotify event");
+    }
+}
+
+#[test]
+fn it_should_set_name_to_none_if_it_is_empty() {
+    let mut testdir = TestDir::new();
+    let (path, mut file) = testdir.new_file();
+
+    let inotify = Inotify::init().unwrap();
+    inotify.watches().add(&path, WatchMask::MODIFY).unwrap();
+
+    write_to(&mut file);
+
+    let mut buffer = [0; 1024];
+    let mut events = inotify.read_events_blocking(&mut buffer).unwrap();
+
+    if let Some(event) = events.next() {
+        assert_eq!(event.name, None);
+    } else {
+        panic!("Expected inotify event");
+    }
+}
+
+#[test]
+fn it_should_not_accept_watchdescriptors_from_other_instances() {
+    let mut testdir = TestDir::new();
+    let (path, _) = testdir.new_file();
+
+    let inotify = Inotify::init().unwrap();
+    let _ = inotify.watches().add(&path, WatchMask::ACCESS).unwrap();
+
+    let second_inotify = Inotify::init().unwrap();
+    let wd2 = second_inotify
+        .watches()
+        .add(&path, WatchMask::ACCESS)
+        .unwrap();
+
+    assert_eq!(
+        inotify.watches().remove(wd2).unwrap_err().kind(),
+        ErrorKind::InvalidInput
+    );
+}
+
+#[test]
+fn it_should_return_the_event_of_a_oneshot_watch() {
+    let mut testdir = TestDir::new();
+    let (path_1, mut file_1) = testdir.new_file();
+    let (path_2, mut file_2) = testdir.new_file();
+
+    let inotify = Inotify::init().unwrap();
+    let mut watches = inotify.watches();
+    watches.add(&path_1, WatchMask::MODIFY).unwrap();
+    let mut oneshot = watches.add_oneshot(&path_2, WatchMask::MODIFY).unwrap();
+    assert_eq!(oneshot.try_recv(), Err(TryRecvError::Empty));
+
+    write_to(&mut file_1);
+    write_to(&mut file_2);
+    let mut buffer = [0; 1024];
+    let events = inotify.read_events_blocking(&mut buffer).unwrap();
+    assert!(events.count() >= 2);
+
+    let event = oneshot.try_recv().unwrap();
+    assert_eq!(&event.wd, oneshot.wd());
+    assert_eq!(event.mask, EventMask::MODIFY);
+    assert_eq!(oneshot.try_recv(), Err(TryRecvError::Disconnected));
+}
+
+#[test]
+fn it_should_add_oneshot_watches_from_the_usage_hook() {
+    let mut testdir = TestDir::new();
+    let (path_a, _) = testdir.new_file();
+    let (path_b, mut file_b) = testdir.new_file();
+
+    let inotify = Inotify::init().unwrap();
+    let mut watches = inotify.watches();
+
+    let oneshot = Arc::new(std::sync::Mutex::new(None));
+    let hook_watches = std::sync::Mutex::new(Some(inotify.watches()));
+    inotify
+        .set_usage_hook(0, {
+            let oneshot = oneshot.clone();
+            let path_b = path_b.clone();
+            move |_| {
+                // Taken, so the hook doesn't keep the instance alive.
+                if let Some(mut watches) = hook_watches.lock().unwrap().take() {
+                    *oneshot.lock().unwrap() =
+                        Some(watches.add_oneshot(&path_b, WatchMask::MODIFY));
+                }
+            }
+        })
+        .unwrap();
+
+    watches.add(&path_a, WatchMask::MODIFY).unwrap();
+    let oneshot = oneshot.lock().unwrap().take().unwrap().unwrap();
+
+    write_to(&mut file_b);
+    let mut buffer = [0; 1024];
+    inotify.read_events_blocking(&mut buffer).unwrap();
+    assert_eq!(oneshot.recv().unwrap().mask, EventMask::MODIFY);
+}
+
+#[test]
+fn oneshot_watches_should_be_closed_when_removed() {
+    let mut testdir = TestDir::new();
+    let (path, _) = testdir.new_file();
+
+    let inotify = Inotify::init().unwrap();
+    let mut watches = inotify.watches();
+    let oneshot = watches.add_oneshot(&path, WatchMask::MODIFY).unwrap();
+
+    watches.remove(oneshot.wd().clone()).unwrap();
+    assert!(oneshot.recv().is_err());
+}
+
+#[cfg(feature = "stream")]
+#[tokio::test]
+async fn it_should_resolve_oneshot_watches_from_a_stream() {
+    let mut testdir = TestDir::new();
+    let (path, mut file) = testdir.new_file();
+
+    let inotify = Inotify::init().unwrap();
+    let oneshot = inotify
+        .watches()
+        .add_oneshot(&path, WatchMask::MODIFY)
+        .unwrap();
+
+    let mut stream = inotify.into_event_stream([0; 1024]).unwrap();
+    tokio::spawn(async move { while stream.next().await.is_some() {} });
+
+    write_to(&mut file);
+    let event = oneshot.await.unwrap();
+    assert_eq!(event.mask, EventMask::MODIFY);
+}
+
+#[test]
+fn watch_descriptors_from_different_inotify_instances_should_not_be_equal() {
+    let mut testdir = TestDir::new();
+    let (path, _) = testdir.new_file();
+
+    let inotify_1 = Inotify::init().unwrap();
+    let inotify_2 = Inotify::init().unwrap();
+
+    let wd_1 = inotify_1.watches().add(&path, WatchMask::ACCESS).unwrap();
+    let wd_2 = inotify_2.watches().add(&path, WatchMask::ACCESS).unwrap();
+
+    // As far as inotify is concerned, watch descriptors are just integers that
+    // are scoped per inotify instance. This means that multiple instances will
+    // produce the same watch descriptor number, a case we want inotify-rs to
+    // detect.
+    assert!(wd_1 != wd_2);
+}
+
+#[test]
+fn watch_descriptor_equality_should_not_be_confused_by_reused_fds() {
+    let mut testdir = TestDir::new();
+    let (path, _) = testdir.new_file();
+
+    // When a new inotify instance is created directly after closing another
+    // one, it is possible that the file descriptor is reused immediately, and
+    // we end up with a new instance that has the same file descriptor as the
+    // old one.
+    // This is quite likely, but it doesn't happen every time. Therefore we may
+    // need a few tries until we find two instances where that is the case.
+    let (wd_1, inotify_2) = loop {
+        let inotify_1 = Inotify::init().unwrap();
+
+        let wd_1 = inotify_1.watches().add(&path, WatchMask::ACCESS).unwrap();
+        let fd_1 = inotify_1.as_raw_fd();
+
+        inotify_1.close().unwrap();
+        let inotify_2 = Inotify::init().unwrap();
+
+        if fd_1 == inotify_2.as_raw_fd() {
+            break (wd_1, inotify_2);
+        }
+    };
+
+    let wd_2 = inotify_2.watches().add(&path, WatchMask::ACCESS).unwrap();
+
+    // The way we engineered this situation, both `WatchDescriptor` instances
+    // have the same fields. They still come from different inotify instances
+    // though, so they shouldn't be equal.
+    assert!(wd_1 != wd_2);
+
+    inotify_2.close().unwrap();
+
+    // A little extra gotcha: If both inotify instances are closed, and the `Eq`
+    // implementation naively compares the weak pointers, both will be `None`,
+    // making them equal. Let's make sure this isn't the case.
+    assert!(wd_1 != wd_2);
+}
+
+#[test]
+fn watch_descriptors_should_stay_equal_after_the_instance_is_closed() {
+    let mut testdir = TestDir::new();
+    let (path, _) = testdir.new_file();
+
+    let inotify = Inotify::init().unwrap();
+    let wd = inotify.watches().add(&path, WatchMask::ACCESS).unwrap();
+    let wd_clone = wd.clone();
+
+    // Descriptors that are used as keys in a `HashMap` must still be found
+    // after the instance has gone away.
+    inotify.close().unwrap();
+    assert!(wd == wd_clone);
+}
+
+#[test]
+fn it_should_implement_raw_fd_traits_correctly() {
+    let fd = Inotify::init()
+        .expect("Failed to initialize inotify instance")
+        .into_raw_fd();
+
+    // If `IntoRawFd` has been implemented naively, `Inotify`'s `Drop`
+    // implementation will have closed the inotify instance at this point. Let's
+    // make sure this didn't happen.
+    let inotify = unsafe { <Inotify as FromRawFd>::from_raw_fd(fd) };
+
+    let mut buffer = [0; 1024];
+    if let Err(error) = inotify.read_events(&mut buffer) {
+        if error.kind() != ErrorKind::WouldBlock {
+            panic!("Failed to add watch: {}", error);
+        }
+    }
+}
+
+#[test]
+fn it_should_watch_correctly_with_a_watches_clone() {
+    let mut testdir = TestDir::new();
+    let (path, mut file) = testdir.new_file();
+
+    let inotify = Inotify::init().unwrap();
+    let mut watches1 = inotify.watches();
+    let mut watches2 = watches1.clone();
+    let watch1 = watches1.add(&path, WatchMask::MODIFY).unwrap();
+    let watch2 = watches2.add(&path, WatchMask::MODIFY).unwrap();
+
+    // same path and same Inotify should return same descriptor
+    assert_eq!(watch1, watch2);
+
+    write_to(&mut file);
+
+    let mut buffer = [0; 1024];
+    let events = inotify.read_events_blocking(&mut buffer).unwrap();
+
+    let mut num_events = 0;
+    for event in events {
+        assert_eq!(watch2, event.wd);
+        num_events += 1;
+    }
+    assert!(num_events > 0);
+}
+
+#[test]
+fn it_should_read_events_through_a_shared_reference_from_another_thread() {
+    let mut testdir = TestDir::new();
+    let (path, mut file) = testdir.new_file();
+
+    let inotify = Arc::new(Inotify::init().unwrap());
+    let watch = inotify.watches().add(&path, WatchMask::MODIFY).unwrap();
+
+    let reader = {
+        let inotify = inotify.clone();
+        thread::spawn(move || {
+            let mut buffer = [0; 1024];
+            inotify
+                .read_events_blocking(&mut buffer)
+                .unwrap()
+                .map(|event| event.wd)
+                .collect::<Vec<_>>()
+        })
+    };
+
+    write_to(&mut file);
+
+    let wds = reader.join().unwrap();
+    assert!(!wds.is_empty());
+    for wd in wds {
+        assert_eq!(watch, wd);
+    }
+}
+
+#[cfg(feature = "stream")]
+#[tokio::test]
+/// Testing if two files with the same name but different directories
+/// (e.g. "file_a" and "another_dir/file_a") are distinguished when _randomly_
+/// triggering a DELETE_SELF for the two files.
+async fn it_should_distinguish_event_for_files_with_same_name() {
+    let mut testdir = TestDir::new();
+    let testdir_path = testdir.dir.path().to_owned();
+    let file_order = Arc::new(Mutex::new(vec!["file_a", "another_dir/file_a"]));
+    file_order.lock().unwrap().shuffle(&mut thread_rng());
+    let file_order_clone = file_order.clone();
+
+    let inotify = Inotify::init().expect("Failed to initialize inotify instance");
+
+    // creating file_a inside `TestDir.dir`
+    let (path_1, _) = testdir.new_file_with_name("file_a");
+    // creating a directory inside `TestDir.dir`
+    testdir.new_directory_with_name("another_dir");
+    // creating a file inside `TestDir.dir/another_dir`
+    let (path_2, _) = testdir.new_file_in_directory_with_name("another_dir", "file_a");
+
+    // watching both files for `DELETE_SELF`
+    let wd_1 = inotify
+        .watches()
+        .add(&path_1, WatchMask::DELETE_SELF)
+        .unwrap();
+    let wd_2 = inotify
+        .watches()
+        .add(&path_2, WatchMask::DELETE_SELF)
+        .unwrap();
+
+    let expected_ids = hashmap! {
+        wd_1.get_watch_descriptor_id() => "file_a",
+        wd_2.get_watch_descriptor_id() => "another_dir/file_a"
+    };
+    let mut buffer = [0; 1024];
+
+    let file_removal_handler = tokio::spawn(async move {
+        for file in file_order.lock().unwrap().iter() {
             testdir.delete_file(file);
         }
-    });
+    });
+
+    let event_handle = tokio::spawn(async move {
+        let mut events = inotify.into_event_stream(&mut buffer).unwrap();
+        while let Some(Ok(event)) = events.next().await {
+            if event.mask == EventMask::DELETE_SELF {
+                let id = event.wd.get_watch_descriptor_id();
+                let file = expected_ids.get(&id).unwrap();
+                let full_path = testdir_path.join(*file);
+                println!("file {:?} was deleted", full_path);
+                file_order_clone.lock().unwrap().retain(|&x| x != *file);
+
+                if file_order_clone.lock().unwrap().is_empty() {
+                    break;
+                }
+            }
+        }
+    });
+
+    let () = event_handle.await.unwrap();
+    let () = file_removal_handler.await.unwrap();
+}
+
+#[test]
+fn it_should_follow_a_file_across_rotation() {
+    let testdir = TestDir::new();
+    let path = testdir.dir.path().join("log");
+    std::fs::write(&path, "old\n").unwrap();
+
+    let mut follower = FileFollower::new(&path).unwrap();
+
+    let mut file = std::fs::OpenOptions::new()
+        .append(true)
+        .open(&path)
+        .unwrap();
+    file.write_all(b"first\n").unwrap();
+    assert_eq!(
+        follower.next_event().unwrap(),
+        FollowEvent::Appended(b"first\n".to_vec())
+    );
+
+    file.set_len(0).unwrap();
+    file.write_all(b"second\n").unwrap();
+    assert_eq!(follower.next_event().unwrap(), FollowEvent::Truncated);
+    assert_eq!(
+        follower.next_event().unwrap(),
+        FollowEvent::Appended(b"second\n".to_vec())
+    );
+
+    std::fs::rename(&path, testdir.dir.path().join("log.1")).unwrap();
+    std::fs::write(&path, "third\n").unwrap();
+    assert_eq!(follower.next_event().unwrap(), FollowEvent::Removed);
+    assert_eq!(follower.next_event().unwrap(), FollowEvent::Reopened);
+    assert_eq!(
+        follower.next_event().unwrap(),
+        FollowEvent::Appended(b"third\n".to_vec())
+    );
+}
+
+#[test]
+fn it_should_notice_when_a_followed_file_is_deleted() {
+    let testdir = TestDir::new();
+    let path = testdir.dir.path().join("log");
+    std::fs::write(&path, "old\n").unwrap();
+
+    let mut follower = FileFollower::new(&path).unwrap();
+
+    let mut file = std::fs::OpenOptions::new()
+        .append(true)
+        .open(&path)
+        .unwrap();
+    file.write_all(b"first\n").unwrap();
+    drop(file);
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(
+        follower.next_event().unwrap(),
+        FollowEvent::Appended(b"first\n".to_vec())
+    );
+    assert_eq!(follower.next_event().unwrap(), FollowEvent::Removed);
+
+    std::fs::write(&path, "second\n").unwrap();
+    assert_eq!(follower.next_event().unwrap(), FollowEvent::Reopened);
+    assert_eq!(
+        follower.next_event().unwrap(),
+        FollowEvent::Appended(b"second\n".to_vec())
+    );
+}
+
+#[test]
+fn it_should_notify_once_per_config_change() {
+    let testdir = TestDir::new();
+    let path = testdir.dir.path().join("config.toml");
+    std::fs::write(&path, "a = 1").unwrap();
+
+    let mut watcher = ConfigWatcher::new(&path)
+        .unwrap()
+        .quiet_period(Duration::from_millis(50));
+
+    // Save through a temporary file, like editors do.
+    let temp = testdir.dir.path().join(".config.toml.swp");
+    std::fs::write(&temp, "a = 2").unwrap();
+    std::fs::rename(&temp, &path).unwrap();
+    std::fs::write(&path, "a = 3").unwrap();
+    assert_eq!(watcher.wait().unwrap(), ConfigChange::Changed);
+
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(watcher.wait().unwrap(), ConfigChange::Removed);
+
+    // Unrelated files are ignored.
+    std::fs::write(testdir.dir.path().join("other"), "").unwrap();
+    std::fs::write(&path, "a = 4").unwrap();
+    assert_eq!(watcher.wait().unwrap(), ConfigChange::Changed);
+}
+
+#[test]
+fn it_should_notify_about_a_removed_config_directory() {
+    let testdir = TestDir::new();
+    let dir = testdir.dir.path().join("app");
+    let path = dir.join("config.toml");
+    std::fs::create_dir(&dir).unwrap();
+    std::fs::write(&path, "a = 1").unwrap();
+
+    let mut watcher = ConfigWatcher::new(&path)
+        .unwrap()
+        .quiet_period(Duration::from_millis(50));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(watcher.wait().unwrap(), ConfigChange::Removed);
+    assert_eq!(watcher.wait().unwrap_err().kind(), ErrorKind::NotFound);
+    assert!(watcher.next().is_none());
+}
+
+#[test]
+fn it_should_list_existing_entries_when_adding_a_watch() {
+    let mut testdir = TestDir::new();
+    let (path, _) = testdir.new_file();
+
+    let inotify = Inotify::init().unwrap();
+    let (wd, existing) = inotify
+        .watches()
+        .add_and_scan(testdir.dir.path(), WatchMask::CREATE)
+        .unwrap();
+
+    assert_eq!(existing.len(), 1);
+    assert_eq!(existing[0].wd, wd);
+    assert_eq!(existing[0].path, path);
+    assert_eq!(existing[0].name, path.file_name().unwrap());
+    assert!(existing[0].metadata.is_file());
+}
+
+#[test]
+fn it_should_report_differences_when_resyncing() {
+    let mut testdir = TestDir::new();
+    let (kept, _) = testdir.new_file();
+    let (modified, _) = testdir.new_file();
+    let (removed, _) = testdir.new_file();
+
+    let inotify = Inotify::init().unwrap();
+    let wd = inotify
+        .watches()
+        .add(testdir.dir.path(), WatchMask::ALL_EVENTS)
+        .unwrap();
+    let mut resync = Resync::new(testdir.dir.path(), wd.clone()).unwrap();
+
+    std::fs::write(&modified, "changed").unwrap();
+    std::fs::remove_file(&removed).unwrap();
+    let (created, _) = testdir.new_file();
+
+    let mut changes = resync
+        .resync()
+        .unwrap()
+        .into_iter()
+        .map(|event| {
+            assert_eq!(event.wd, wd);
+            (event.name.unwrap(), event.mask)
+        })
+        .collect::<Vec<_>>();
+    changes.sort();
+    let mut expected = vec![
+        (modified.file_name().unwrap().to_owned(), EventMask::MODIFY),
+        (removed.file_name().unwrap().to_owned(), EventMask::DELETE),
+        (created.file_name().unwrap().to_owned(), EventMask::CREATE),
+    ];
+    expected.sort();
+    assert_eq!(changes, expected);
+    assert!(!changes
+        .iter()
+        .any(|(name, _)| name == kept.file_name().unwrap()));
+
+    assert!(resync.resync().unwrap().is_empty());
+}
+
+#[test]
+fn it_should_watch_a_path_before_it_exists() {
+    let testdir = TestDir::new();
+    let path = testdir.dir.path().join("a/b/c");
+
+    let mut watcher = PathWatcher::new(&path, WatchMask::CLOSE_WRITE).unwrap();
+    assert!(!watcher.is_attached());
+
+    std::fs::create_dir_all(testdir.dir.path().join("a/b")).unwrap();
+    File::create(&path).unwrap();
+    assert_eq!(watcher.next_event().unwrap(), PathEvent::Attached);
+    assert!(watcher.is_attached());
+
+    write_to(&mut File::create(&path).unwrap());
+    match watcher.next_event().unwrap() {
+        PathEvent::Event(event) => assert_eq!(event.mask, EventMask::CLOSE_WRITE),
+        event => panic!("Unexpected event: {:?}", event),
+    }
+
+    std::fs::remove_dir_all(testdir.dir.path().join("a")).unwrap();
+    assert_eq!(watcher.next_event().unwrap(), PathEvent::Detached);
+    assert!(!watcher.is_attached());
+}
+
+#[test]
+fn it_should_end_remount_watching_once_the_path_is_deleted() {
+    let testdir = TestDir::new();
+    let path = testdir.dir.path().join("mnt");
+    std::fs::create_dir(&path).unwrap();
+
+    let mut watcher = RemountWatcher::new(&path, WatchMask::CREATE).unwrap();
+    assert!(watcher.is_attached());
+
+    File::create(path.join("file")).unwrap();
+    match watcher.next_event().unwrap() {
+        Some(RemountEvent::Event(event)) => assert_eq!(event.mask, EventMask::CREATE),
+        event => panic!("Unexpected event: {:?}", event),
+    }
+
+    std::fs::remove_dir_all(&path).unwrap();
+    let masks = watcher
+        .map(|event| match event.unwrap() {
+            RemountEvent::Event(event) => event.mask,
+            event => panic!("Unexpected event: {:?}", event),
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(masks, vec![EventMask::IGNORED]);
+}
+
+#[test]
+fn it_should_watch_a_directory_tree() {
+    let testdir = TestDir::new();
+    let root = testdir.dir.path();
+    std::fs::create_dir_all(root.join("src/nested")).unwrap();
+    std::fs::create_dir_all(root.join("target/debug")).unwrap();
+
+    let inotify = Inotify::init().unwrap();
+    let mut watched = inotify
+        .watches()
+        .add_recursive(root, WatchMask::CREATE, |path| !path.ends_with("target"))
+        .unwrap()
+        .into_iter()
+        .map(|(_, path)| path)
+        .collect::<Vec<_>>();
+    watched.sort();
+
+    assert_eq!(
+        watched,
+        vec![
+            root.to_path_buf(),
+            root.join("src"),
+            root.join("src/nested")
+        ]
+    );
+}
+
+#[test]
+fn it_should_skip_directories_it_may_not_watch() {
+    use std::os::unix::fs::PermissionsExt;
+
+    // Permissions don't apply to root.
+    if unsafe { libc::geteuid() } == 0 {
+        return;
+    }
+
+    let testdir = TestDir::new();
+    let root = testdir.dir.path();
+    std::fs::create_dir_all(root.join("private/nested")).unwrap();
+    std::fs::create_dir(root.join("public")).unwrap();
+    std::fs::set_permissions(root.join("private"), std::fs::Permissions::from_mode(0o000)).unwrap();
+
+    let inotify = Inotify::init().unwrap();
+    let watched = inotify
+        .watches()
+        .add_recursive(root, WatchMask::CREATE, |_| true)
+        .map(|watched| {
+            watched
+                .into_iter()
+                .map(|(_, path)| path)
+                .collect::<Vec<_>>()
+        });
+
+    std::fs::set_permissions(root.join("private"), std::fs::Permissions::from_mode(0o755)).unwrap();
+
+    let mut watched = watched.unwrap();
+    watched.sort();
+    assert_eq!(watched, vec![root.to_path_buf(), root.join("public")]);
+}
+
+#[test]
+fn it_should_keep_existing_watches_if_adding_recursively_fails() {
+    let testdir = TestDir::new();
+    let root = testdir.dir.path();
+    std::fs::create_dir_all(root.join("watched/loop")).unwrap();
+    let mut file = File::create(root.join("watched/file")).unwrap();
+
+    let inotify = Inotify::init().unwrap();
+    let mut watches = inotify.watches();
+    let wd = watches
+        .add(root.join("watched"), WatchMask::MODIFY)
+        .unwrap();
+
+    let error = watches
+        .add_recursive(root, WatchMask::CREATE, |relative| {
+            // Turn the directory into a symlink loop, so watching it fails.
+            if relative.ends_with("loop") {
+                let path = root.join(relative);
+                std::fs::remove_dir(&path).unwrap();
+                std::os::unix::fs::symlink("loop", &path).unwrap();
+            }
+            true
+        })
+        .unwrap_err();
+    assert_eq!(error.raw_os_error(), Some(libc::ELOOP));
+
+    write_to(&mut file);
+    let mut buffer = [0; 1024];
+    let events = inotify
+        .read_events_blocking(&mut buffer)
+        .unwrap()
+        .map(|event| (event.wd, event.mask))
+        .collect::<Vec<_>>();
+    assert!(events.contains(&(wd, EventMask::MODIFY)));
+}
+
+#[test]
+fn it_should_call_the_closures_registered_for_a_path() {
+    let mut testdir = TestDir::new();
+    let (path, mut file) = testdir.new_file();
+
+    let modified = Arc::new(AtomicUsize::new(0));
+    let mut watcher = Watcher::new().unwrap();
+    let wd = watcher
+        .on(&path, WatchMask::MODIFY, {
+            let modified = modified.clone();
+            move |event| {
+                assert_eq!(event.mask, EventMask::MODIFY);
+                modified.fetch_add(1, Ordering::SeqCst);
+            }
+        })
+        .unwrap();
+    watcher
+        .on(&path, WatchMask::DELETE_SELF, |_| {
+            panic!("Unexpected event")
+        })
+        .unwrap();
+
+    write_to(&mut file);
+    assert_eq!(watcher.poll().unwrap(), 1);
+    assert_eq!(modified.load(Ordering::SeqCst), 1);
+
+    watcher.remove(wd).unwrap();
+    assert_eq!(watcher.poll().unwrap(), 1);
+    watcher.run().unwrap();
+}
+
+#[cfg(feature = "fanotify")]
+#[test]
+fn it_should_read_fanotify_events() {
+    use inotify::fanotify::{Fanotify, InitFlags, MarkMask};
+
+    let mut testdir = TestDir::new();
+    let (path, mut file) = testdir.new_file();
+
+    let fanotify = match Fanotify::init(InitFlags::CLASS_NOTIF) {
+        Ok(fanotify) => fanotify,
+        // fanotify requires privileges that the tests might not have.
+        Err(error) if error.kind() == ErrorKind::PermissionDenied => return,
+        Err(error) => panic!("Failed to initialize fanotify: {}", error),
+    };
+    fanotify.marks().add(&path, MarkMask::MODIFY).unwrap();
+
+    write_to(&mut file);
+
+    let mut buffer = [0; 4096];
+    let mut events = fanotify.read_events_blocking(&mut buffer).unwrap();
+    let event = events.next().unwrap();
+    assert!(event.mask.contains(inotify::fanotify::EventMask::MODIFY));
+    assert_eq!(event.pid as u32, std::process::id());
+    assert_eq!(event.path().unwrap(), path.canonicalize().unwrap());
+}
+
+#[cfg(feature = "fanotify")]
+#[test]
+fn it_should_report_the_process_that_caused_a_fanotify_event() {
+    use inotify::fanotify::{Fanotify, InitFlags, MarkMask};
+
+    let mut testdir = TestDir::new();
+    let (path, mut file) = testdir.new_file();
+
+    let fanotify = match Fanotify::init(InitFlags::CLASS_NOTIF | InitFlags::REPORT_PIDFD) {
+        Ok(fanotify) => fanotify,
+        // fanotify requires privileges that the tests might not have.
+        Err(error) if error.kind() == ErrorKind::PermissionDenied => return,
+        Err(error) => panic!("Failed to initialize fanotify: {}", error),
+    };
+    fanotify.marks().add(&path, MarkMask::MODIFY).unwrap();
+
+    write_to(&mut file);
+
+    let mut buffer = [0; 4096];
+    let event = fanotify
+        .read_events_blocking(&mut buffer)
+        .unwrap()
+        .next()
+        .unwrap();
+    assert_eq!(event.pid as u32, std::process::id());
+    assert!(event.pidfd().is_some());
+    assert_eq!(event.uid().unwrap(), unsafe { libc::getuid() });
+}
+
+#[cfg(feature = "fanotify")]
+#[test]
+fn it_should_read_fanotify_events_for_a_whole_mount() {
+    use inotify::fanotify::{EventMask, Fanotify, InitFlags, MarkMask};
+
+    let mut testdir = TestDir::new();
+    let (path, mut file) = testdir.new_file();
+    let path = path.canonicalize().unwrap();
+
+    let fanotify = match Fanotify::init(InitFlags::CLASS_NOTIF) {
+        Ok(fanotify) => fanotify,
+        // fanotify requires privileges that the tests might not have.
+        Err(error) if error.kind() == ErrorKind::PermissionDenied => return,
+        Err(error) => panic!("Failed to initialize fanotify: {}", error),
+    };
+    fanotify
+        .marks()
+        .add_mount(testdir.dir.path(), MarkMask::MODIFY)
+        .unwrap();
+
+    write_to(&mut file);
+
+    // Other tests modify files on the same mount, so skip their events.
+    let mut buffer = [0; 4096];
+    loop {
+        let events = fanotify.read_events_blocking(&mut buffer).unwrap();
+        let found = events.into_iter().any(|event| {
+            event.mask.contains(EventMask::MODIFY) && event.path().ok().as_ref() == Some(&path)
+        });
+        if found {
+            break;
+        }
+    }
 
-    let event_handle = tokio::spawn(async move {
-        let mut events = inotify.into_event_stream(&mut buffer).unwrap();
-        while let Some(Ok(event)) = events.next().await {
-            if event.mask == EventMask::DELETE_SELF {
-                let id = event.wd.get_watch_descriptor_id();
-                let file = expected_ids.get(&id).unwrap();
-                let full_path = testdir_path.join(*file);
-                println!("file {:?} was deleted", full_path);
-                file_order_clone.lock().unwrap().retain(|&x| x != *file);
+    fanotify.marks().flush_mounts().unwrap();
+}
 
-                if file_order_clone.lock().unwrap().is_empty() {
-                    break;
-                }
+#[cfg(feature = "fanotify")]
+#[test]
+fn it_should_not_report_ignored_fanotify_events() {
+    use inotify::fanotify::{EventMask, Fanotify, InitFlags, MarkMask};
+
+    let mut testdir = TestDir::new();
+    let (ignored, mut ignored_file) = testdir.new_file();
+    let (path, mut file) = testdir.new_file();
+    let ignored = ignored.canonicalize().unwrap();
+    let path = path.canonicalize().unwrap();
+
+    let fanotify = match Fanotify::init(InitFlags::CLASS_NOTIF) {
+        Ok(fanotify) => fanotify,
+        // fanotify requires privileges that the tests might not have.
+        Err(error) if error.kind() == ErrorKind::PermissionDenied => return,
+        Err(error) => panic!("Failed to initialize fanotify: {}", error),
+    };
+    let marks = fanotify.marks();
+    marks
+        .add_mount(testdir.dir.path(), MarkMask::MODIFY)
+        .unwrap();
+    marks.ignore(&ignored, MarkMask::MODIFY).unwrap();
+
+    write_to(&mut ignored_file);
+    write_to(&mut file);
+
+    // Other tests modify files on the same mount, so skip their events.
+    let mut buffer = [0; 4096];
+    'outer: loop {
+        for event in fanotify.read_events_blocking(&mut buffer).unwrap() {
+            if !event.mask.contains(EventMask::MODIFY) {
+                continue;
+            }
+            let event_path = event.path().ok();
+            assert_ne!(event_path.as_ref(), Some(&ignored));
+            if event_path.as_ref() == Some(&path) {
+                break 'outer;
             }
         }
+    }
+}
+
+#[cfg(feature = "fanotify")]
+#[test]
+fn it_should_report_fanotify_file_identifiers() {
+    use inotify::fanotify::{EventMask, Fanotify, FidKind, InitFlags, MarkMask};
+    use std::os::unix::io::AsFd;
+
+    let mut testdir = TestDir::new();
+
+    let fanotify = match Fanotify::init(InitFlags::CLASS_NOTIF | InitFlags::REPORT_DFID_NAME) {
+        Ok(fanotify) => fanotify,
+        // fanotify requires privileges that the tests might not have.
+        Err(error) if error.kind() == ErrorKind::PermissionDenied => return,
+        Err(error) => panic!("Failed to initialize fanotify: {}", error),
+    };
+    fanotify
+        .marks()
+        .add(testdir.dir.path(), MarkMask::CREATE)
+        .unwrap();
+
+    let (path, _) = testdir.new_file();
+
+    let mut buffer = [0; 4096];
+    let event = fanotify
+        .read_events_blocking(&mut buffer)
+        .unwrap()
+        .next()
+        .unwrap();
+    assert!(event.mask.contains(EventMask::CREATE));
+    assert!(event.fd().is_none());
+
+    let fid = &event.fids()[0];
+    assert_eq!(fid.kind, FidKind::DfidName);
+    assert_eq!(fid.name.as_deref(), path.file_name());
+
+    let mount = File::open(testdir.dir.path()).unwrap();
+    assert_eq!(
+        fid.path(mount.as_fd()).unwrap(),
+        path.canonicalize().unwrap()
+    );
+}
+
+#[cfg(feature = "fanotify")]
+#[test]
+fn it_should_answer_fanotify_permission_requests() {
+    use inotify::fanotify::{Fanotify, InitFlags, MarkMask, Response};
+
+    let mut testdir = TestDir::new();
+    let (path, _) = testdir.new_file();
+
+    let fanotify = match Fanotify::init(InitFlags::CLASS_CONTENT) {
+        Ok(fanotify) => fanotify,
+        // fanotify requires privileges that the tests might not have.
+        Err(error) if error.kind() == ErrorKind::PermissionDenied => return,
+        Err(error) => panic!("Failed to initialize fanotify: {}", error),
+    };
+    fanotify.marks().add(&path, MarkMask::OPEN_PERM).unwrap();
+
+    // Opening the file blocks until the request has been answered.
+    let open = std::thread::spawn({
+        let path = path.clone();
+        move || File::open(path).map(|_| ())
     });
 
-    let () = event_handle.await.unwrap();
-    let () = file_removal_handler.await.unwrap();
+    let mut buffer = [0; 4096];
+    let event = fanotify
+        .read_events_blocking(&mut buffer)
+        .unwrap()
+        .next()
+        .unwrap();
+    assert!(event.is_permission_request());
+    fanotify.respond(&event, Response::Deny).unwrap();
+
+    let error = open.join().unwrap().unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::PermissionDenied);
+}
+
+#[cfg(feature = "fanotify")]
+#[test]
+fn it_should_answer_dropped_fanotify_permission_requests() {
+    use inotify::fanotify::{Fanotify, InitFlags, MarkMask, Response};
+
+    let mut testdir = TestDir::new();
+    let (path, _) = testdir.new_file();
+
+    let mut fanotify = match Fanotify::init(InitFlags::CLASS_CONTENT) {
+        Ok(fanotify) => fanotify,
+        // fanotify requires privileges that the tests might not have.
+        Err(error) if error.kind() == ErrorKind::PermissionDenied => return,
+        Err(error) => panic!("Failed to initialize fanotify: {}", error),
+    };
+    fanotify.set_default_response(Response::Deny);
+    fanotify.marks().add(&path, MarkMask::OPEN_PERM).unwrap();
+
+    let open = std::thread::spawn({
+        let path = path.clone();
+        move || File::open(path).map(|_| ())
+    });
+
+    let mut buffer = [0; 4096];
+    drop(fanotify.read_events_blocking(&mut buffer).unwrap());
+
+    let error = open.join().unwrap().unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::PermissionDenied);
+}
+
+#[test]
+fn it_should_add_watches_inside_a_mount_namespace() {
+    let mut testdir = TestDir::new();
+    let (path, mut file) = testdir.new_file();
+
+    let inotify = Inotify::init().unwrap();
+
+    // Use the namespace of this process, which is the only one the test can
+    // rely on.
+    let ns = File::open("/proc/self/ns/mnt").unwrap();
+    let mut watches = match inotify.watches_in_namespace(ns) {
+        Ok(watches) => watches,
+        // Entering a namespace requires privileges that the tests might not
+        // have.
+        Err(error) if error.kind() == ErrorKind::PermissionDenied => return,
+        Err(error) => panic!("Failed to enter namespace: {}", error),
+    };
+    let wd = watches.add(&path, WatchMask::MODIFY).unwrap();
+
+    write_to(&mut file);
+
+    let mut buffer = [0; 1024];
+    let event = inotify
+        .read_events_blocking(&mut buffer)
+        .unwrap()
+        .next()
+        .unwrap();
+    assert_eq!(event.wd, wd);
+    assert_eq!(event.mask, EventMask::MODIFY);
+
+    watches.remove(wd).unwrap();
+}
+
+#[test]
+fn it_should_detect_writes_that_did_not_change_a_file() {
+    let mut testdir = TestDir::new();
+    let (path, _) = testdir.new_file();
+    std::fs::write(&path, "content").unwrap();
+
+    let inotify = Inotify::init().unwrap();
+    let wd = inotify
+        .watches()
+        .add(testdir.dir.path(), WatchMask::CLOSE_WRITE)
+        .unwrap();
+
+    let mut filter = UnchangedFilter::new(Compare::Content);
+    filter.add_watch(&wd, testdir.dir.path());
+    filter.remember(&path).unwrap();
+
+    let mut buffer = [0; 1024];
+    for (content, unchanged) in [("content", true), ("changed", false), ("changed", true)] {
+        std::fs::write(&path, content).unwrap();
+
+        let events = inotify.read_events_blocking(&mut buffer).unwrap();
+        let event = events.last().unwrap();
+        assert_eq!(filter.is_unchanged(&event), unchanged);
+    }
+}
+
+#[test]
+fn it_should_route_events_of_a_shared_instance_to_its_users() {
+    let mut testdir = TestDir::new();
+    let (path, mut file) = testdir.new_file();
+
+    let shared = SharedInotify::global().unwrap();
+    let mut modifications = shared.register();
+    let mut deletions = shared.register();
+
+    let wd = modifications.add(&path, WatchMask::MODIFY).unwrap();
+    assert_eq!(deletions.add(&path, WatchMask::DELETE_SELF).unwrap(), wd);
+
+    write_to(&mut file);
+    let event = modifications
+        .events()
+        .recv_timeout(Duration::from_secs(1))
+        .unwrap();
+    assert_eq!(event.wd, wd);
+    assert_eq!(event.mask, EventMask::MODIFY);
+
+    // The watch is still used by the other registration.
+    modifications.remove(wd.clone()).unwrap();
+    assert!(modifications.remove(wd).is_err());
+
+    drop(file);
+    std::fs::remove_file(&path).unwrap();
+    let event = deletions
+        .events()
+        .recv_timeout(Duration::from_secs(1))
+        .unwrap();
+    assert_eq!(event.mask, EventMask::DELETE_SELF);
+
+    assert!(modifications.events().try_recv().is_err());
+}
+
+#[cfg(feature = "stream")]
+#[tokio::test]
+async fn it_should_control_a_watcher_service_through_its_handle() {
+    let mut testdir = TestDir::new();
+
+    let (handle, mut events) = WatcherService::spawn(16).unwrap();
+    let wd = handle
+        .add(testdir.dir.path(), WatchMask::CREATE)
+        .await
+        .unwrap();
+
+    testdir.new_file();
+    let event = events.recv().await.unwrap().unwrap();
+    assert_eq!(event.wd, wd);
+    assert_eq!(event.mask, EventMask::CREATE);
+
+    handle.clone().shutdown().await;
+    assert!(events.recv().await.is_none());
+    assert!(handle.remove(wd).await.is_err());
+}
+
+#[cfg(feature = "stream")]
+#[tokio::test]
+async fn it_should_stop_a_watcher_service_once_its_receiver_is_dropped() {
+    let testdir = TestDir::new();
+
+    let (service, handle, events) = WatcherService::new(16).unwrap();
+    let service = tokio::spawn(service.run());
+    handle
+        .add(testdir.dir.path(), WatchMask::CREATE)
+        .await
+        .unwrap();
+
+    drop(events);
+    tokio::time::timeout(Duration::from_secs(1), service)
+        .await
+        .expect("Service didn't stop")
+        .unwrap();
+}
+
+#[cfg(feature = "stream")]
+#[tokio::test]
+async fn it_should_process_watcher_service_commands_while_the_receiver_is_full() {
+    let mut testdir = TestDir::new();
+    let other = TestDir::new();
+
+    let (handle, mut events) = WatcherService::spawn(1).unwrap();
+    handle
+        .add(testdir.dir.path(), WatchMask::CREATE)
+        .await
+        .unwrap();
+
+    testdir.new_file();
+    testdir.new_file();
+    testdir.new_file();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let wd = tokio::time::timeout(
+        Duration::from_secs(1),
+        handle.add(other.dir.path(), WatchMask::CREATE),
+    )
+    .await
+    .expect("Service didn't process the command")
+    .unwrap();
+
+    for _ in 0..3 {
+        let event = events.recv().await.unwrap().unwrap();
+        assert_eq!(event.mask, EventMask::CREATE);
+        assert_ne!(event.wd, wd);
+    }
+}
+
+#[test]
+fn it_should_replay_recorded_events() {
+    let mut testdir = TestDir::new();
+    let (path, mut file) = testdir.new_file();
+
+    let inotify = Inotify::init().unwrap();
+    let wd = inotify.watches().add(&path, WatchMask::MODIFY).unwrap();
+
+    let mut recorder = Recorder::new(Vec::new()).unwrap();
+    recorder.record_watch(&wd, &path).unwrap();
+    write_to(&mut file);
+    let mut buffer = [0; 1024];
+    let recorded = recorder
+        .read_events_blocking(&inotify, &mut buffer)
+        .unwrap()
+        .map(|event| event.to_owned())
+        .collect::<Vec<_>>();
+    let recording = recorder.into_inner().unwrap();
+
+    let replay = Inotify::init().unwrap();
+    let mut replayer = Replayer::new(&recording[..], &replay).unwrap();
+    let events = replayer.next_events().unwrap().unwrap();
+    assert_eq!(events.len(), recorded.len());
+    let event = events.last().unwrap().to_owned();
+    assert_eq!(event.mask, EventMask::MODIFY);
+    assert_eq!(replayer.path(&event.wd), Some(path.as_path()));
+
+    let (replayed_wd, replayed_path) = replayer.watches().next().unwrap();
+    assert_eq!(replayed_path, path);
+    assert!(replayer.next_events().unwrap().is_none());
+
+    let replayed =
+        Iterator::collect::<Result<Vec<_>, _>>(Replayer::new(&recording[..], &replay).unwrap())
+            .unwrap();
+    assert_eq!(replayed.len(), recorded.len());
+    assert_eq!(replayed[0].wd, replayed_wd);
+}
+
+#[test]
+fn replaying_should_not_affect_the_instance() {
+    let mut testdir = TestDir::new();
+    let (path, mut file) = testdir.new_file();
+
+    let inotify = Inotify::init().unwrap();
+    let wd = inotify.watches().add(&path, WatchMask::MODIFY).unwrap();
+    let mut recorder = Recorder::new(Vec::new()).unwrap();
+    write_to(&mut file);
+    let mut buffer = [0; 1024];
+    recorder
+        .read_events_blocking(&inotify, &mut buffer)
+        .unwrap()
+        .for_each(drop);
+    let recording = recorder.into_inner().unwrap();
+
+    // Both instances are new, so the watches have the same id.
+    let replay = Inotify::init().unwrap();
+    let mut oneshot = replay
+        .watches()
+        .add_oneshot(&path, WatchMask::MODIFY)
+        .unwrap();
+    assert_eq!(
+        oneshot.wd().get_watch_descriptor_id(),
+        wd.get_watch_descriptor_id()
+    );
+
+    let replayed =
+        Iterator::collect::<Result<Vec<_>, _>>(Replayer::new(&recording[..], &replay).unwrap())
+            .unwrap();
+    assert_eq!(&replayed[0].wd, oneshot.wd());
+    assert_eq!(oneshot.try_recv(), Err(TryRecvError::Empty));
+}
+
+#[test]
+fn it_should_reject_corrupted_recordings() {
+    let replay = Inotify::init().unwrap();
+
+    let mut recording = b"INOTREC1R".to_vec();
+    recording.extend_from_slice(&u32::MAX.to_le_bytes());
+    let mut replayer = Replayer::new(&recording[..], &replay).unwrap();
+    let error = replayer.next_events().unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidData);
+
+    let mut recording = b"INOTREC1R".to_vec();
+    recording.extend_from_slice(&3u32.to_le_bytes());
+    recording.extend_from_slice(&[1, 2, 3]);
+    let mut replayer = Replayer::new(&recording[..], &replay).unwrap();
+    let error = Iterator::next(&mut replayer).unwrap().unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidData);
 }
 
 struct TestDir {